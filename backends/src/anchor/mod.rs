@@ -1,19 +1,24 @@
 use super::{ts, OutputAccessors, OutputStrippedOfAnsiScapes, ParseAdapter, ParseHigh, RunHigh};
 use anyhow::{anyhow, Context, Result};
-use log::debug;
 use necessist_core::{
+    __Backup as Backup, __Rewriter as Rewriter,
+    candidates::Candidates,
+    config::MochaReporter,
     framework::{Interface, Postprocess, SourceFileSpanTestMap},
-    LightContext, SourceFile, Span, __Backup as Backup, __Rewriter as Rewriter,
+    Exec, LightContext, SourceFile, Span,
 };
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
     fs::{read_to_string, write},
     path::{Path, PathBuf},
     process::Command,
+    sync::atomic::{AtomicBool, Ordering},
 };
-use subprocess::Exec;
 use toml_edit::{DocumentMut, Value};
+use tracing::debug;
 
 pub struct Anchor {
     mocha_adapter: ParseAdapter<ts::mocha::Mocha>,
@@ -21,6 +26,9 @@ pub struct Anchor {
     document: DocumentMut,
     prefix: String,
     suffix: String,
+    /// Whether an `anchor test` command has succeeded, and hence, whether the programs have been
+    /// built
+    built: AtomicBool,
 }
 
 impl Anchor {
@@ -44,6 +52,7 @@ impl Anchor {
             document,
             prefix,
             suffix,
+            built: AtomicBool::new(false),
         })
     }
 }
@@ -56,8 +65,10 @@ impl ParseHigh for Anchor {
         context: &LightContext,
         config: &necessist_core::config::Toml,
         source_files: &[&Path],
+        candidates: &mut Candidates,
     ) -> Result<(usize, SourceFileSpanTestMap)> {
-        self.mocha_adapter.parse(context, config, source_files)
+        self.mocha_adapter
+            .parse(context, config, source_files, candidates)
     }
 }
 
@@ -67,13 +78,21 @@ impl RunHigh for Anchor {
 
         self.check(context, source_file)?;
 
-        let _backup: Backup = self.patch_anchor_toml(source_file, false)?;
+        let _backup: Backup = self.patch_anchor_toml(context, source_file, false)?;
 
-        let command = command_to_run_test(context);
+        let command = self.command_to_run_test(context, source_file)?;
 
         self.mocha_adapter.0.dry_run(context, source_file, command)
     }
 
+    fn skipped_tests(
+        &self,
+        _context: &LightContext,
+        source_file: &Path,
+    ) -> Result<BTreeSet<String>> {
+        Ok(self.mocha_adapter.0.skipped_tests(source_file))
+    }
+
     fn instrument_source_file(
         &self,
         _context: &LightContext,
@@ -103,9 +122,9 @@ impl RunHigh for Anchor {
             return Ok(None);
         }
 
-        let backup = self.patch_anchor_toml(&span.source_file, false)?;
+        let backup = self.patch_anchor_toml(context, &span.source_file, false)?;
 
-        let command = command_to_run_test(context);
+        let command = self.command_to_run_test(context, &span.source_file)?;
 
         let exec_and_postprocess = self
             .mocha_adapter
@@ -129,31 +148,73 @@ impl RunHigh for Anchor {
 
 impl Anchor {
     fn check(&self, context: &LightContext, source_file: &Path) -> Result<()> {
-        let _backup: Backup = self.patch_anchor_toml(source_file, true)?;
+        let _backup: Backup = self.patch_anchor_toml(context, source_file, true)?;
 
-        let mut command = command_to_run_test(context);
+        let mut command = self.command_to_run_test(context, source_file)?;
 
         debug!("{:?}", command);
 
-        let output = command.output_stripped_of_ansi_escapes()?;
+        let output = command.output_stripped_of_ansi_escapes(context)?;
         if !output.status().success() {
             return Err(output.into());
         };
+
+        self.built.store(true, Ordering::SeqCst);
+
         Ok(())
     }
 
-    fn patch_anchor_toml(&self, source_file: &Path, check: bool) -> Result<Backup> {
+    fn command_to_run_test(&self, context: &LightContext, source_file: &Path) -> Result<Command> {
+        let mut command = Command::new("anchor");
+        command.arg("test");
+        // smoelius: Removals are made only from TypeScript test files, so once the programs have
+        // been built, there is no need to rebuild or redeploy them.
+        if self.built.load(Ordering::SeqCst) && source_file.extension() == Some(OsStr::new("ts")) {
+            if context.config.anchor.skip_build() {
+                command.arg("--skip-build");
+            }
+            if context.config.anchor.skip_deploy {
+                command.arg("--skip-deploy");
+            }
+        }
+        command.args(&context.opts.args);
+        command.current_dir(context.root.as_path());
+        ts::utils::set_node_options(context, context.root, &mut command)?;
+
+        Ok(command)
+    }
+
+    fn patch_anchor_toml(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+        check: bool,
+    ) -> Result<Backup> {
         let backup = Backup::new(&self.anchor_toml)?;
 
         let mut document = self.document.clone();
 
+        let test_files = self
+            .mocha_adapter
+            .0
+            .test_files(context, source_file)
+            .iter()
+            .map(|test_file| test_file.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
         edit_test_script(&mut document, |test| {
             *test = Value::from(format!(
                 "{}{}{}{}",
                 self.prefix,
-                source_file.to_string_lossy(),
+                test_files.join(" "),
                 self.suffix,
-                if check { " --dry-run" } else { "" }
+                if check {
+                    " --dry-run"
+                } else if context.config.mocha.reporter == MochaReporter::Json {
+                    " --reporter json"
+                } else {
+                    ""
+                }
             ));
             Ok(())
         })
@@ -203,12 +264,3 @@ fn parse_test_value(test_value: &mut Value) -> Result<(String, String)> {
     assert_eq!(3, captures.len());
     Ok((captures[1].to_string(), captures[2].to_string()))
 }
-
-fn command_to_run_test(context: &LightContext) -> Command {
-    let mut command = Command::new("anchor");
-    command.arg("test");
-    command.args(&context.opts.args);
-    command.current_dir(context.root.as_path());
-
-    command
-}
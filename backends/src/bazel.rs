@@ -0,0 +1,156 @@
+//! Support for Go and Rust projects built with Bazel. Tests are still found by parsing source
+//! files, but they are built with `bazel build` and run with `bazel test`.
+
+use crate::utils;
+use anyhow::{anyhow, bail, Result};
+use necessist_core::{util, LightContext};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{fs::read_to_string, path::Path, process::Command};
+
+const BUILD_FILES: &[&str] = &["BUILD.bazel", "BUILD"];
+
+const WORKSPACE_FILES: &[&str] = &["MODULE.bazel", "WORKSPACE.bazel", "WORKSPACE"];
+
+static RULE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^([A-Za-z_][0-9A-Za-z_]*)\(").unwrap());
+
+static NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\bname\s*=\s*"([^"]*)""#).unwrap());
+
+static SRCS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bsrcs\s*=\s*\[([^\]]*)\]").unwrap());
+
+pub(crate) fn enabled(context: &LightContext) -> bool {
+    context.config.bazel.enabled
+}
+
+/// Returns a command to build the target containing `source_file`.
+pub(crate) fn build_command(context: &LightContext, source_file: &Path) -> Command {
+    let mut command = Command::new("bazel");
+    command.current_dir(context.root.as_path());
+    command.arg("build");
+    command.arg(target_or_panic(context, source_file));
+    command
+}
+
+/// Returns a command to run the tests of the target containing `source_file`. The test logs are
+/// output so that they can be checked for evidence that a test ran.
+pub(crate) fn test_command(context: &LightContext, source_file: &Path) -> Command {
+    let mut command = Command::new("bazel");
+    command.current_dir(context.root.as_path());
+    command.arg("test");
+    command.arg(target_or_panic(context, source_file));
+    command.arg("--test_output=all");
+    // smoelius: Bazel does not pass the client's environment to tests by default.
+    command.arg("--test_env=NECESSIST_REMOVAL");
+    command
+}
+
+fn target_or_panic(context: &LightContext, source_file: &Path) -> String {
+    #[allow(clippy::panic)]
+    target(context, source_file).unwrap_or_else(|error| panic!("{error:?}"))
+}
+
+/// Returns the label of the target containing `source_file`, either as configured in
+/// `bazel.targets`, or as inferred from the nearest BUILD file.
+pub(crate) fn target(context: &LightContext, source_file: &Path) -> Result<String> {
+    let relative_path = utils::to_slash_string(util::strip_prefix(source_file, context.root)?);
+
+    if let Some(target) = context.config.bazel.targets.get(&relative_path) {
+        return Ok(target.clone());
+    }
+
+    infer_target(context.root, source_file)
+}
+
+fn infer_target(root: &Path, source_file: &Path) -> Result<String> {
+    let workspace = root
+        .ancestors()
+        .find(|dir| WORKSPACE_FILES.iter().any(|file| dir.join(file).is_file()))
+        .ok_or_else(|| anyhow!("Failed to find Bazel workspace containing {root:?}"))?;
+
+    // smoelius: The nearest BUILD file determines the source file's package.
+    for dir in source_file.ancestors().skip(1) {
+        if !dir.starts_with(workspace) {
+            break;
+        }
+        let Some(build_file) = BUILD_FILES
+            .iter()
+            .map(|file| dir.join(file))
+            .find(|path| path.is_file())
+        else {
+            continue;
+        };
+        let package = utils::to_slash_string(util::strip_prefix(dir, workspace)?);
+        let src = utils::to_slash_string(util::strip_prefix(source_file, dir)?);
+        let contents = read_to_string(&build_file)?;
+        let name = test_rule_name(&contents, &src).ok_or_else(|| {
+            anyhow!(
+                "Failed to find a test rule with {src:?} in its `srcs` in {build_file:?}; add an \
+                 entry for {src:?} to `bazel.targets` in necessist.toml"
+            )
+        })?;
+        return Ok(format!("//{package}:{name}"));
+    }
+
+    bail!("Failed to find BUILD file for {source_file:?}")
+}
+
+/// Returns the name of the first test rule (i.e., rule whose kind ends with `_test`) in a BUILD
+/// file's contents whose `srcs` include `src`. The parsing is heuristic: rules must begin at the
+/// start of a line, and `src` must appear literally in `srcs` (e.g., not via `glob`).
+fn test_rule_name(contents: &str, src: &str) -> Option<String> {
+    let quoted = format!("\"{src}\"");
+    let starts = RULE_RE
+        .captures_iter(contents)
+        .filter_map(|captures| {
+            let kind = captures.get(1)?;
+            Some((kind.as_str(), kind.start()))
+        })
+        .collect::<Vec<_>>();
+    starts.iter().enumerate().find_map(|(i, &(kind, start))| {
+        if !kind.ends_with("_test") {
+            return None;
+        }
+        let end = starts.get(i + 1).map_or(contents.len(), |&(_, end)| end);
+        let body = &contents[start..end];
+        let srcs = SRCS_RE.captures(body)?.get(1)?.as_str();
+        if !srcs.contains(&quoted) {
+            return None;
+        }
+        NAME_RE
+            .captures(body)
+            .and_then(|captures| captures.get(1))
+            .map(|name| name.as_str().to_owned())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::test_rule_name;
+
+    const BUILD: &str = r#"load("@io_bazel_rules_go//go:def.bzl", "go_library", "go_test")
+
+go_library(
+    name = "base64",
+    srcs = ["base64.go"],
+)
+
+go_test(
+    name = "base64_test",
+    srcs = [
+        "base64_test.go",
+        "example_test.go",
+    ],
+    embed = [":base64"],
+)
+"#;
+
+    #[test]
+    fn test_rule_name_is_inferred() {
+        assert_eq!(
+            Some("base64_test"),
+            test_rule_name(BUILD, "example_test.go").as_deref()
+        );
+        assert_eq!(None, test_rule_name(BUILD, "base64.go"));
+        assert_eq!(None, test_rule_name(BUILD, "missing_test.go"));
+    }
+}
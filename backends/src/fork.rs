@@ -0,0 +1,72 @@
+//! Environment variables that allow tests that fork a live chain to reuse RPC responses across
+//! removals.
+
+use necessist_core::LightContext;
+use std::process::Command;
+
+/// Sets environment variables on `command` according to the `[fork]` configuration table.
+///
+/// Foundry reads `FOUNDRY_FORK_BLOCK_NUMBER` itself, and caches the responses for a pinned block
+/// in `~/.foundry/cache/rpc`. Hardhat caches the responses for a pinned block in its cache
+/// directory, but has no equivalent environment variables. So the `NECESSIST_FORK_*` variables are
+/// provided for a Hardhat config file to read.
+pub(crate) fn set_env(context: &LightContext, command: &mut Command) {
+    let fork = &context.config.fork;
+
+    if let Some(block_number) = fork.block_number {
+        command.env("FOUNDRY_FORK_BLOCK_NUMBER", block_number.to_string());
+        command.env("NECESSIST_FORK_BLOCK_NUMBER", block_number.to_string());
+    }
+
+    // smoelius: A relative cache directory is resolved against the original root so that, with
+    // `--isolate worktree`, the cache outlives the worktree.
+    if let Some(cache_dir) = &fork.cache_dir {
+        command.env(
+            "NECESSIST_FORK_CACHE_DIR",
+            context.original_root.join(cache_dir),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::set_env;
+    use crate::utils::light_context;
+    use necessist_core::{config, LightContext};
+    use std::{ffi::OsStr, path::PathBuf, process::Command, rc::Rc};
+
+    #[test]
+    fn fork_env() {
+        let root = Rc::new(PathBuf::from("worktree"));
+        let original_root = Rc::new(PathBuf::from("root"));
+        let mut config = config::Toml::default();
+        config.fork.block_number = Some(19_000_000);
+        config.fork.cache_dir = Some(PathBuf::from("fork-cache"));
+        let context = LightContext {
+            original_root: &original_root,
+            ..light_context(&root, &config)
+        };
+        let mut command = Command::new("forge");
+        set_env(&context, &mut command);
+        let envs = command.get_envs().collect::<Vec<_>>();
+        let cache_dir = original_root.join("fork-cache");
+        assert_eq!(
+            [
+                (
+                    OsStr::new("FOUNDRY_FORK_BLOCK_NUMBER"),
+                    Some(OsStr::new("19000000"))
+                ),
+                (
+                    OsStr::new("NECESSIST_FORK_BLOCK_NUMBER"),
+                    Some(OsStr::new("19000000"))
+                ),
+                (
+                    OsStr::new("NECESSIST_FORK_CACHE_DIR"),
+                    Some(cache_dir.as_os_str())
+                ),
+            ]
+            .as_slice(),
+            envs
+        );
+    }
+}
@@ -1,10 +1,11 @@
 use super::{
-    AbstractTypes, GenericVisitor, MaybeNamed, Named, ParseLow, ProcessLines, RunLow, Spanned,
-    WalkDirResult,
+    fork, utils, AbstractTypes, GenericVisitor, MaybeNamed, Named, ParseCache, ParseLow,
+    ProcessLines, RunLow, Spanned, WalkDirResult,
 };
 use anyhow::{anyhow, Result};
 use if_chain::if_chain;
 use necessist_core::{
+    config,
     framework::{SpanTestMaps, TestSet},
     util, LightContext, LineColumn, SourceFile, Span, __Rewriter as Rewriter,
 };
@@ -13,7 +14,7 @@ use solang_parser::pt::{
 };
 use std::{
     cell::RefCell, collections::BTreeMap, convert::Infallible, fs::read_to_string, hash::Hash,
-    path::Path, process::Command,
+    path::Path, process::Command, rc::Rc,
 };
 
 mod storage;
@@ -23,7 +24,9 @@ mod visitor;
 use visitor::{collect_local_functions, visit, Statements};
 
 #[derive(Debug)]
-pub struct Foundry;
+pub struct Foundry {
+    parse_cache: ParseCache<SourceUnit>,
+}
 
 impl Foundry {
     pub fn applicable(context: &LightContext) -> Result<bool> {
@@ -35,7 +38,9 @@ impl Foundry {
     }
 
     pub fn new() -> Self {
-        Self
+        Self {
+            parse_cache: ParseCache::default(),
+        }
     }
 }
 
@@ -104,7 +109,7 @@ pub struct Types;
 
 impl AbstractTypes for Types {
     type Storage<'ast> = Storage<'ast>;
-    type File = (String, SourceUnit);
+    type File = (String, Rc<SourceUnit>);
     type Test<'ast> = Test<'ast>;
     type LocalFunction<'ast> = LocalFunction<'ast>;
     type Statement<'ast> = WithContents<'ast, &'ast Statement>;
@@ -170,6 +175,10 @@ impl ParseLow for Foundry {
 
     const IGNORED_METHODS: Option<&'static [&'static str]> = Some(&[]);
 
+    fn ignored_overrides(config: &config::Toml) -> config::IgnoredOverrides<'_> {
+        config.foundry.ignored_overrides()
+    }
+
     fn walk_dir(&self, root: &Path) -> Box<dyn Iterator<Item = WalkDirResult>> {
         Box::new(
             walkdir::WalkDir::new(root.join("test"))
@@ -186,9 +195,8 @@ impl ParseLow for Foundry {
         source_file: &Path,
     ) -> Result<<Self::Types as AbstractTypes>::File> {
         let contents = read_to_string(source_file)?;
-        solang_parser::parse(&contents, 0)
-            .map(|(source_unit, _)| (contents, source_unit))
-            .map_err(|error| anyhow!(format!("{error:?}")))
+        let source_unit = self.parse_cache.parse(source_file, &contents, parse)?;
+        Ok((contents, source_unit))
     }
 
     fn storage_from_file<'ast>(
@@ -415,8 +423,7 @@ impl RunLow for Foundry {
     }
 
     fn command_to_build_source_file(&self, context: &LightContext, _source_file: &Path) -> Command {
-        let mut command = Command::new("forge");
-        command.current_dir(context.root.as_path());
+        let mut command = Self::forge_command(context);
         command.arg("build");
         command
     }
@@ -433,8 +440,7 @@ impl RunLow for Foundry {
         _test_name: &str,
         _span: &Span,
     ) -> Command {
-        let mut command = Command::new("forge");
-        command.current_dir(context.root.as_path());
+        let mut command = Self::forge_command(context);
         command.arg("build");
         command
     }
@@ -446,29 +452,69 @@ impl RunLow for Foundry {
         span: &Span,
     ) -> (Command, Vec<String>, Option<ProcessLines>) {
         let mut command = Self::test_command(context, &span.source_file);
-        command.args(["--match-test", test_name]);
+        command.args([
+            "--match-test",
+            &utils::forge_match_test_regex(test_name),
+            "--json",
+        ]);
 
-        let pat = format!(" {test_name}(");
+        let test_name = test_name.to_owned();
 
         (
             command,
             Vec::new(),
-            Some((false, Box::new(move |line| line.contains(&pat)))),
+            Some((false, Box::new(move |line| ran_only(line, &test_name)))),
         )
     }
 }
 
+fn parse(contents: &str) -> Result<SourceUnit> {
+    solang_parser::parse(contents, 0)
+        .map(|(source_unit, _)| source_unit)
+        .map_err(|error| anyhow!(format!("{error:?}")))
+}
+
+/// Returns true if `line` is a `forge test --json` report in which `test_name`, and only
+/// `test_name`, was executed.
+///
+/// `--match-test` is a regular expression. It is anchored (see [`utils::forge_match_test_regex`]),
+/// but if it nonetheless matches other tests, the exit status cannot be attributed to the targeted
+/// test.
+fn ran_only(line: &str, test_name: &str) -> bool {
+    let Ok(serde_json::Value::Object(suites)) = serde_json::from_str(line) else {
+        return false;
+    };
+
+    let mut names = suites
+        .values()
+        .filter_map(|suite| suite.get("test_results")?.as_object())
+        .flat_map(serde_json::Map::keys)
+        .peekable();
+
+    names.peek().is_some() && names.all(|signature| signature.split('(').next() == Some(test_name))
+}
+
 impl Foundry {
-    fn test_command(context: &LightContext, source_file: &Path) -> Command {
+    // smoelius: Fuzz tests are run once, with a fixed seed, so that a removal's outcome does not
+    // depend on the inputs that happened to be generated.
+    fn forge_command(context: &LightContext) -> Command {
         let mut command = Command::new("forge");
         command.current_dir(context.root.as_path());
         command.env("FOUNDRY_FUZZ_RUNS", "1");
+        command.env(
+            "FOUNDRY_FUZZ_SEED",
+            context.config.foundry.fuzz_seed().to_string(),
+        );
+        command
+    }
+
+    fn test_command(context: &LightContext, source_file: &Path) -> Command {
+        let mut command = Self::forge_command(context);
+        fork::set_env(context, &mut command);
         command.args([
             "test",
             "--match-path",
-            &util::strip_prefix(source_file, context.root)
-                .unwrap()
-                .to_string_lossy(),
+            &utils::to_slash_string(util::strip_prefix(source_file, context.root).unwrap()),
         ]);
         command
     }
@@ -566,6 +612,7 @@ pub fn offset_to_line_column(contents: &str, loc: usize) -> (usize, usize) {
 mod test {
     use cargo_metadata::{MetadataCommand, Package};
     use std::{
+        ffi::OsStr,
         fs::read_to_string,
         io::{Error, Write},
         path::{Path, PathBuf},
@@ -575,6 +622,66 @@ mod test {
 
     const PT_RS: &str = "src/pt.rs";
 
+    #[test]
+    fn collect_local_functions() {
+        const SOURCE: &str = r"
+function free() {}
+
+library Lib {
+    function helper() internal {}
+}
+
+contract C {
+    function f() public {}
+}
+";
+
+        let (source_unit, _) = solang_parser::parse(SOURCE, 0).unwrap();
+        let local_functions = super::collect_local_functions(&source_unit);
+        assert_eq!(
+            ["Lib.helper", "f", "free", "helper"].as_slice(),
+            local_functions
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_command() {
+        let root = std::rc::Rc::new(PathBuf::from("root"));
+        let mut config = necessist_core::config::Toml::default();
+        config.foundry.fuzz_seed = Some(42);
+        let context = crate::utils::light_context(&root, &config);
+        let source_file = root.join("test").join("Counter.t.sol");
+        let command = super::Foundry::test_command(&context, &source_file);
+        assert_eq!("forge", command.get_program());
+        assert_eq!(
+            ["test", "--match-path", "test/Counter.t.sol"].as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            [
+                (OsStr::new("FOUNDRY_FUZZ_RUNS"), Some(OsStr::new("1"))),
+                (OsStr::new("FOUNDRY_FUZZ_SEED"), Some(OsStr::new("42"))),
+            ]
+            .as_slice(),
+            command.get_envs().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn ran_only() {
+        const REPORT: &str = r#"{"test/Counter.t.sol:CounterTest":{"duration":"1ms","test_results":{"testIncrement()":{"status":"Success"}},"warnings":[]}}"#;
+        const AMBIGUOUS: &str = r#"{"test/Counter.t.sol:CounterTest":{"duration":"1ms","test_results":{"testIncrement()":{"status":"Success"},"testIncrementTwice()":{"status":"Failure"}},"warnings":[]}}"#;
+
+        assert!(super::ran_only(REPORT, "testIncrement"));
+        assert!(!super::ran_only(REPORT, "testIncrementTwice"));
+        assert!(!super::ran_only(AMBIGUOUS, "testIncrement"));
+        assert!(!super::ran_only("Compiling 1 files", "testIncrement"));
+        assert!(!super::ran_only("{}", "testIncrement"));
+    }
+
     #[test]
     fn check_pt_rs() {
         let tempdir = tempdir().unwrap();
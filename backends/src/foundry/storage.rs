@@ -1,11 +1,12 @@
 use solang_parser::pt::SourceUnit;
+use std::rc::Rc;
 
 pub struct Storage<'ast> {
     pub contents: &'ast str,
 }
 
 impl<'ast> Storage<'ast> {
-    pub fn new(file: &'ast (String, SourceUnit)) -> Self {
+    pub fn new(file: &'ast (String, Rc<SourceUnit>)) -> Self {
         Self { contents: &file.0 }
     }
 }
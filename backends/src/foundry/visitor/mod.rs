@@ -4,8 +4,15 @@ use super::{Foundry, FunctionCall, GenericVisitor, LocalFunction, Storage, Test,
 use anyhow::Result;
 use if_chain::if_chain;
 use necessist_core::framework::{SpanTestMaps, TestSet};
-use solang_parser::pt::{Expression, FunctionDefinition, Identifier, Loc, SourceUnit, Statement};
-use std::{cell::RefCell, collections::BTreeMap, convert::Infallible};
+use solang_parser::pt::{
+    ContractDefinition, ContractTy, Expression, FunctionDefinition, Identifier, Loc, SourceUnit,
+    SourceUnitPart, Statement,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    convert::Infallible,
+};
 
 mod visit;
 use visit::{self as visit_fns, Visitor as _};
@@ -14,13 +21,14 @@ use visit::{self as visit_fns, Visitor as _};
 static FAUX_CONTINUE: Statement = Statement::Continue(Loc::Builtin);
 
 /// Wraps a reference to a vector of `Statement`s so that uses of `filter_statements` and
-/// `is_prefix_cheatcode` can be restricted to this file.
+/// `is_prefix_cheatcode` can be restricted to this file. The `bool` is the value of
+/// `foundry.skip_before_expect_revert`.
 #[derive(Clone, Copy)]
-pub struct Statements<'ast>(&'ast Vec<Statement>);
+pub struct Statements<'ast>(&'ast Vec<Statement>, bool);
 
 impl<'ast> Statements<'ast> {
     pub fn get(self) -> impl Iterator<Item = &'ast Statement> {
-        filter_statements(self.0)
+        filter_statements(self.0, self.1)
     }
 }
 
@@ -32,25 +40,52 @@ pub(super) fn collect_local_functions(
     collector.function_definitions.split_off(&String::new())
 }
 
+/// Collects contract functions, library functions, and free (i.e., file-level) functions.
+///
+/// A library function is recorded under both its unqualified name (as used within the library)
+/// and its qualified name (e.g., `Lib.helper`, as used outside the library).
 #[derive(Default)]
 struct FunctionDefinitionCollector<'ast> {
     function_definitions: BTreeMap<String, Vec<LocalFunction<'ast>>>,
+    library: Option<&'ast Identifier>,
 }
 
 impl<'ast> visit_fns::Visitor<'ast> for FunctionDefinitionCollector<'ast> {
     type Error = Infallible;
 
+    fn visit_contract_definition(
+        &mut self,
+        contract_definition: &'ast ContractDefinition,
+    ) -> Result<(), Self::Error> {
+        let library = if matches!(contract_definition.ty, ContractTy::Library(_)) {
+            contract_definition.name.as_ref()
+        } else {
+            None
+        };
+        let prev = std::mem::replace(&mut self.library, library);
+        visit_fns::visit_contract_definition(self, contract_definition)?;
+        self.library = prev;
+        Ok(())
+    }
+
     fn visit_function_definition(
         &mut self,
         function_definition: &'ast FunctionDefinition,
     ) -> Result<(), Self::Error> {
         if let Some(name) = &function_definition.name {
+            let local_function = LocalFunction {
+                function_definition,
+            };
+            if let Some(library) = self.library {
+                self.function_definitions
+                    .entry(format!("{library}.{name}"))
+                    .or_default()
+                    .push(local_function);
+            }
             self.function_definitions
                 .entry(name.to_string())
                 .or_default()
-                .push(LocalFunction {
-                    function_definition,
-                });
+                .push(local_function);
         }
         Ok(())
     }
@@ -61,7 +96,11 @@ pub(super) fn visit<'ast>(
     storage: &RefCell<Storage<'ast>>,
     source_unit: &'ast SourceUnit,
 ) -> Result<(TestSet, SpanTestMaps)> {
-    let mut visitor = Visitor::new(generic_visitor, storage);
+    let test_contracts = test_contracts(
+        source_unit,
+        &generic_visitor.toml.foundry.test_base_contracts,
+    );
+    let mut visitor = Visitor::new(generic_visitor, storage, test_contracts);
     visitor.visit_source_unit(source_unit)?;
     while let Some(local_function) = visitor.generic_visitor.next_local_function() {
         visitor.visit_local_function(local_function)?;
@@ -69,9 +108,62 @@ pub(super) fn visit<'ast>(
     visitor.generic_visitor.results()
 }
 
+/// Returns the names of the contracts in `source_unit` that inherit, directly or through other
+/// contracts in `source_unit`, from a contract named in `test_base_contracts`. Returns `None` if
+/// `test_base_contracts` is empty, i.e., if every contract may contain tests.
+fn test_contracts<'ast>(
+    source_unit: &'ast SourceUnit,
+    test_base_contracts: &[String],
+) -> Option<BTreeSet<&'ast str>> {
+    if test_base_contracts.is_empty() {
+        return None;
+    }
+
+    let contract_definitions = source_unit
+        .0
+        .iter()
+        .filter_map(|part| {
+            if let SourceUnitPart::ContractDefinition(contract_definition) = part {
+                Some(contract_definition)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // smoelius: Add contracts until a fixpoint is reached, so that the order in which contracts
+    // are defined does not matter.
+    let mut test_contracts = BTreeSet::new();
+    loop {
+        let n = test_contracts.len();
+        for contract_definition in &contract_definitions {
+            let Some(Identifier { name, .. }) = &contract_definition.name else {
+                continue;
+            };
+            if contract_definition.base.iter().any(|base| {
+                base.name
+                    .identifiers
+                    .last()
+                    .is_some_and(|Identifier { name, .. }| {
+                        test_base_contracts.contains(name) || test_contracts.contains(name.as_str())
+                    })
+            }) {
+                test_contracts.insert(name.as_str());
+            }
+        }
+        if test_contracts.len() == n {
+            break;
+        }
+    }
+
+    Some(test_contracts)
+}
+
 struct Visitor<'context, 'config, 'backend, 'ast, 'storage> {
     generic_visitor: GenericVisitor<'context, 'config, 'backend, 'ast, Foundry>,
     storage: &'storage RefCell<Storage<'ast>>,
+    test_contracts: Option<BTreeSet<&'ast str>>,
+    in_test_contract: bool,
 }
 
 impl<'context, 'config, 'backend, 'ast, 'storage>
@@ -80,10 +172,15 @@ impl<'context, 'config, 'backend, 'ast, 'storage>
     fn new(
         generic_visitor: GenericVisitor<'context, 'config, 'backend, 'ast, Foundry>,
         storage: &'storage RefCell<Storage<'ast>>,
+        test_contracts: Option<BTreeSet<&'ast str>>,
     ) -> Self {
+        // smoelius: If `foundry.test_base_contracts` is set, free functions are not tests.
+        let in_test_contract = test_contracts.is_none();
         Self {
             generic_visitor,
             storage,
+            test_contracts,
+            in_test_contract,
         }
     }
 
@@ -97,11 +194,37 @@ impl<'context, 'config, 'backend, 'ast, 'storage>
 impl<'ast> visit_fns::Visitor<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
     type Error = Infallible;
 
+    fn visit_contract_definition(
+        &mut self,
+        contract_definition: &'ast ContractDefinition,
+    ) -> Result<(), Self::Error> {
+        let in_test_contract = self.test_contracts.as_ref().is_none_or(|test_contracts| {
+            contract_definition
+                .name
+                .as_ref()
+                .is_some_and(|Identifier { name, .. }| test_contracts.contains(name.as_str()))
+        });
+        let prev = std::mem::replace(&mut self.in_test_contract, in_test_contract);
+        visit_fns::visit_contract_definition(self, contract_definition)?;
+        self.in_test_contract = prev;
+        Ok(())
+    }
+
     fn visit_function_definition(
         &mut self,
         function_definition: &'ast FunctionDefinition,
     ) -> Result<(), Self::Error> {
-        if let Some(test) = is_test_function(function_definition) {
+        let skip_before_expect_revert = self
+            .generic_visitor
+            .toml
+            .foundry
+            .skip_before_expect_revert();
+
+        if !self.in_test_contract {
+            return Ok(());
+        }
+
+        if let Some(test) = is_test_function(function_definition, skip_before_expect_revert) {
             let walk = self.generic_visitor.visit_test(self.storage, test);
 
             if walk {
@@ -116,7 +239,13 @@ impl<'ast> visit_fns::Visitor<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
 
     fn visit_statement(&mut self, statement: &'ast Statement) -> Result<(), Self::Error> {
         if let Statement::Block { statements, .. } = statement {
-            for statement in filter_statements(statements) {
+            let skip_before_expect_revert = self
+                .generic_visitor
+                .toml
+                .foundry
+                .skip_before_expect_revert();
+
+            for statement in filter_statements(statements, skip_before_expect_revert) {
                 self.visit_statement(statement)?;
             }
 
@@ -170,7 +299,10 @@ impl<'ast> visit_fns::Visitor<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
     }
 }
 
-fn is_test_function(function_definition: &FunctionDefinition) -> Option<Test<'_>> {
+fn is_test_function(
+    function_definition: &FunctionDefinition,
+    skip_before_expect_revert: bool,
+) -> Option<Test<'_>> {
     if_chain! {
         if let Some(Identifier { name, .. }) = &function_definition.name;
         if name.starts_with("test");
@@ -178,7 +310,7 @@ fn is_test_function(function_definition: &FunctionDefinition) -> Option<Test<'_>
         then {
             Some(Test {
                 name,
-                statements: Statements(statements),
+                statements: Statements(statements, skip_before_expect_revert),
             })
         } else {
             None
@@ -186,43 +318,67 @@ fn is_test_function(function_definition: &FunctionDefinition) -> Option<Test<'_>
     }
 }
 
-fn filter_statements<'ast, I: IntoIterator<Item = &'ast Statement>>(
-    statements: I,
-) -> impl Iterator<Item = &'ast Statement> {
-    let mut prev = None;
-    statements.into_iter().map(move |statement| {
+fn filter_statements(
+    statements: &[Statement],
+    skip_before_expect_revert: bool,
+) -> impl Iterator<Item = &Statement> {
+    statements.iter().enumerate().map(move |(i, statement)| {
         // smoelius: If the previous statement was a "prefix cheatcode," then replace the current
         // statement with a `continue`, so that the statement is ignored.
-        let next = if prev.is_some_and(is_prefix_cheatcode) {
+        let prefixed = i
+            .checked_sub(1)
+            .is_some_and(|prev| is_prefix_cheatcode(&statements[prev]));
+        // smoelius: Similarly, if `skip_before_expect_revert` is set and the next statement is
+        // `vm.expectRevert`, ignore the current statement. Removing it tends to make the call that
+        // is expected to revert do so for some other reason, so that the test still passes.
+        let precedes_expect_revert =
+            skip_before_expect_revert && statements.get(i + 1).is_some_and(is_expect_revert);
+        if prefixed || precedes_expect_revert {
             &FAUX_CONTINUE
         } else {
             statement
-        };
-        prev = Some(statement);
-        next
+        }
     })
 }
 
 fn is_prefix_cheatcode(statement: &Statement) -> bool {
+    vm_cheatcode(statement).is_some_and(|method| method == "prank" || method.starts_with("expect"))
+}
+
+fn is_expect_revert(statement: &Statement) -> bool {
+    vm_cheatcode(statement)
+        .is_some_and(|method| matches!(method, "expectPartialRevert" | "expectRevert"))
+}
+
+/// If `statement` is a call to a cheatcode (e.g., `vm.prank(..);`), returns the cheatcode's name
+fn vm_cheatcode(statement: &Statement) -> Option<&str> {
     if_chain! {
         if let Statement::Expression(_, expression) = statement;
         if let Expression::FunctionCall(_loc, callee, _args) = expression;
         if let Expression::MemberAccess(_, base, Identifier { name: method, .. }) = &**callee;
         if let Expression::Variable(variable) = &**base;
         if variable.to_string() == "vm";
-        if method == "prank" || method.starts_with("expect");
         then {
-            true
+            Some(method)
         } else {
-            false
+            None
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Foundry;
-    use crate::ParseLow;
+    use super::{is_test_function, test_contracts, Foundry};
+    use crate::{utils::light_context, ParseAdapter, ParseHigh, ParseLow};
+    use necessist_core::{candidates::Candidates, config};
+    use solang_parser::pt::{ContractPart, SourceUnitPart, Statement};
+    use std::{
+        collections::BTreeSet,
+        fs::{create_dir, write},
+        path::{Path, PathBuf},
+        rc::Rc,
+    };
+    use tempfile::tempdir;
 
     #[cfg_attr(
         dylint_lib = "assert_eq_arg_misordering",
@@ -236,6 +392,203 @@ mod test {
         );
     }
 
+    #[test]
+    fn skip_before_expect_revert() {
+        const SOURCE: &str = r"
+contract C {
+    function test() public {
+        token.approve(spender, amount);
+        vm.expectRevert();
+        spender.transferFrom(owner, amount);
+    }
+}
+";
+
+        let (source_unit, _) = solang_parser::parse(SOURCE, 0).unwrap();
+        let SourceUnitPart::ContractDefinition(contract_definition) = &source_unit.0[0] else {
+            panic!("expected contract definition");
+        };
+        let ContractPart::FunctionDefinition(function_definition) = &contract_definition.parts[0]
+        else {
+            panic!("expected function definition");
+        };
+
+        for (skip_before_expect_revert, expected) in [
+            (false, ["Expression", "Expression", "Continue"]),
+            (true, ["Continue", "Expression", "Continue"]),
+        ] {
+            let test = is_test_function(function_definition, skip_before_expect_revert).unwrap();
+            let kinds = test
+                .statements
+                .get()
+                .map(|statement| {
+                    if matches!(statement, Statement::Continue(_)) {
+                        "Continue"
+                    } else {
+                        "Expression"
+                    }
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(expected.as_slice(), kinds);
+        }
+    }
+
+    #[test]
+    fn test_base_contracts() {
+        const SOURCE: &str = r"
+contract C is Base {}
+contract Base is Test {}
+contract D is StdInvariant, Ownable {}
+contract E is Ownable {}
+contract F {}
+";
+
+        let (source_unit, _) = solang_parser::parse(SOURCE, 0).unwrap();
+
+        assert_eq!(None, test_contracts(&source_unit, &[]));
+
+        let test_base_contracts = ["StdInvariant".to_owned(), "Test".to_owned()];
+        let test_contracts = test_contracts(&source_unit, &test_base_contracts).unwrap();
+        assert_eq!(
+            ["Base", "C", "D"].as_slice(),
+            test_contracts.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn contract_inheriting_test_base_is_walked() {
+        assert_eq!(
+            ["testIncrement"].as_slice(),
+            walked_tests(
+                r"
+contract CounterTest is Test {
+    function testIncrement() public {
+        counter.increment();
+        assertEq(counter.number(), 1);
+    }
+}
+"
+            )
+        );
+    }
+
+    #[test]
+    fn contract_not_inheriting_test_base_is_skipped() {
+        assert!(walked_tests(
+            r"
+contract Helper {
+    function testIncrement() public {
+        counter.increment();
+        assertEq(counter.number(), 1);
+    }
+}
+"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn nested_skip_before_expect_revert() {
+        let tempdir = tempdir().unwrap();
+        let root = Rc::new(tempdir.path().to_path_buf());
+        create_dir(root.join("test")).unwrap();
+        let source_file = root.join("test").join("Token.t.sol");
+        write(
+            &source_file,
+            r"
+contract TokenTest {
+    function testTransferFrom() public {
+        token.approve(spender, amount);
+        vm.expectRevert();
+        spender.transferFrom(owner, amount);
+    }
+}
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            [
+                "token.approve(spender, amount);",
+                ".approve(spender, amount)"
+            ]
+            .as_slice(),
+            candidate_source_texts(&root, &source_file)
+        );
+
+        write(
+            root.join("test").join("necessist.toml"),
+            "[foundry]\nskip_before_expect_revert = true\n",
+        )
+        .unwrap();
+
+        assert!(candidate_source_texts(&root, &source_file).is_empty());
+    }
+
+    /// Parses `source_file` with the default root configuration, and returns the source texts of
+    /// the statement and method call candidates
+    fn candidate_source_texts(root: &Rc<PathBuf>, source_file: &Path) -> Vec<String> {
+        let config = config::Toml::default();
+        let context = light_context(root, &config);
+
+        let (_, source_file_span_test_map) = ParseAdapter(Foundry::new())
+            .parse(
+                &context,
+                &config,
+                &[source_file],
+                &mut Candidates::default(),
+            )
+            .unwrap();
+
+        source_file_span_test_map
+            .values()
+            .flat_map(|span_test_maps| {
+                span_test_maps
+                    .statement
+                    .keys()
+                    .chain(span_test_maps.method_call.keys())
+            })
+            .map(|span| span.source_text().unwrap())
+            .collect()
+    }
+
+    /// Parses `source` with `foundry.test_base_contracts` set to `["Test"]`, and returns the names
+    /// of the tests from which candidates were collected
+    fn walked_tests(source: &str) -> Vec<String> {
+        let tempdir = tempdir().unwrap();
+        let root = Rc::new(tempdir.path().to_path_buf());
+        create_dir(root.join("test")).unwrap();
+        let source_file = root.join("test").join("Counter.t.sol");
+        write(&source_file, source).unwrap();
+
+        let mut config = config::Toml::default();
+        config.foundry.test_base_contracts = vec!["Test".to_owned()];
+        let context = light_context(&root, &config);
+
+        let (_, source_file_span_test_map) = ParseAdapter(Foundry::new())
+            .parse(
+                &context,
+                &config,
+                &[&source_file],
+                &mut Candidates::default(),
+            )
+            .unwrap();
+
+        source_file_span_test_map
+            .values()
+            .flat_map(|span_test_maps| {
+                span_test_maps
+                    .statement
+                    .values()
+                    .chain(span_test_maps.method_call.values())
+            })
+            .flatten()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
     fn sort<'a>(items: &'a [&str]) -> Vec<&'a str> {
         let mut items = items.to_vec();
         items.sort_unstable();
@@ -24,11 +24,13 @@ use anyhow::Result;
 use if_chain::if_chain;
 use indexmap::IndexMap;
 use necessist_core::{
-    config,
+    __ToConsoleString,
+    config::{self, CandidateKind},
     framework::{SpanKind, SpanTestMaps, TestSet},
-    warn, LightContext, SourceFile, Span, WarnFlags, Warning, __ToConsoleString,
+    warn, LightContext, SourceFile, Span, WarnFlags, Warning,
 };
 use paste::paste;
+use proc_macro2::LineColumn;
 use std::{
     cell::RefCell,
     collections::{BTreeMap, BTreeSet, HashSet},
@@ -37,11 +39,17 @@ use std::{
 pub struct GenericVisitor<'context, 'config, 'backend, 'ast, T: ParseLow> {
     pub context: &'context LightContext<'context>,
     pub config: &'config config::Compiled,
+    /// The configuration that applies to `source_file`, before it was compiled into `config`
+    pub toml: &'config config::Toml,
     pub backend: &'backend mut T,
     pub walkable_functions: BTreeMap<String, Vec<<T::Types as AbstractTypes>::LocalFunction<'ast>>>,
     pub source_file: SourceFile,
     pub test_names: BTreeSet<String>,
     pub last_statement_in_test: Option<<T::Types as AbstractTypes>::Statement<'ast>>,
+    /// End of the last call matching an `assertion_calls` pattern in the current test
+    pub last_assertion_end: Option<LineColumn>,
+    /// Spans registered while visiting the current test (but not the local functions it calls)
+    pub spans_in_test: Vec<Span>,
     pub n_statement_leaves_visited: usize,
     pub n_before: Vec<usize>,
     pub call_statement: Option<<T::Types as AbstractTypes>::Statement<'ast>>,
@@ -87,6 +95,7 @@ struct VisitMaybeMacroCallArgs<'ast, 'storage, 'span, T: ParseLow> {
     // smoelius: Maybe remove this `storage` field?
     _storage: &'storage RefCell<<T::Types as AbstractTypes>::Storage<'ast>>,
     span: &'span Span,
+    candidate_kind: CandidateKind,
     is_ignored_as_call: bool,
     is_method_call: bool,
     is_ignored_as_method_call: bool,
@@ -112,14 +121,18 @@ macro_rules! visit_maybe_macro_call {
                     if let Some(statement) = statement {
                         if !$args.is_ignored_as_call {
                             let span = statement.span(&$this.source_file);
-                            $this.register_span(span, SpanKind::Statement);
+                            $this.register_span(span, SpanKind::Statement, $args.candidate_kind);
                         }
                     }
 
                     // smoelius: If the entire call is ignored, then treat the method call as
                     // ignored as well.
                     if !$args.is_ignored_as_call && $args.is_method_call && !$args.is_ignored_as_method_call {
-                        $this.register_span($args.span.clone(), SpanKind::MethodCall);
+                        $this.register_span(
+                            $args.span.clone(),
+                            SpanKind::MethodCall,
+                            CandidateKind::MethodCall,
+                        );
                     }
 
                     // smoelius: Return false (i.e., don't descend into the call arguments) only if
@@ -204,6 +217,15 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
     ) {
         self.last_statement_in_test = None;
 
+        // smoelius: `None` compares less than any `Some(..)`. So if the test contains no
+        // assertions, every span in the test is considered post-assertion.
+        let last_assertion_end = self.last_assertion_end.take();
+        for span in self.spans_in_test.drain(..) {
+            if last_assertion_end <= Some(span.start) {
+                self.span_test_maps.post_assertion.insert(span);
+            }
+        }
+
         // smoelius: Check whether the test was ignored.
         if self.test_names.is_empty() {
             debug_assert!(self.config.is_ignored_test(&test.name()));
@@ -261,7 +283,7 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
             && !self.backend.statement_is_declaration(storage, statement)
         {
             let span = statement.span(&self.source_file);
-            self.register_span(span, SpanKind::Statement);
+            self.register_span(span, SpanKind::Statement, CandidateKind::Statement);
         }
     }
 
@@ -297,6 +319,14 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
             }
         }
 
+        if let Some(path) = self.callee_path(storage, call) {
+            self.visit_assertion_call(&path, &call.span(&self.source_file));
+        }
+
+        if let Some(is_ignored_as_call) = self.visit_diagnostic_call(storage, call) {
+            return is_ignored_as_call;
+        }
+
         let call_span = call.span(&self.source_file);
         if let Some((field, name)) = self.callee_is_named_field(storage, call) {
             let inner_most_call_info = self.call_info(storage, &call_span, field, &name, true);
@@ -307,6 +337,7 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
                 (VisitMaybeMacroCallArgs::<'_, '_, '_, T> {
                     _storage: storage,
                     span: &call_info.span,
+                    candidate_kind: CandidateKind::MethodCall,
                     is_ignored_as_call: (!inner_most_call_info.is_method && inner_most_call_info.is_ignored)
                         || (!inner_most_call_info.is_nested && call_info.is_ignored),
                     is_method_call: true,
@@ -314,14 +345,20 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
                 })
             }
         } else {
-            let is_ignored_as_call = call
-                .name()
-                .is_some_and(|name| self.config.is_ignored_function(&name));
+            let callee_span = self
+                .backend
+                .call_callee(storage, call)
+                .span(&self.source_file);
+            let is_ignored_as_call = call.name().is_some_and(|name| {
+                self.config.is_ignored_function(&name)
+                    || self.is_ignored_call(&name, &call_span, &callee_span)
+            });
             visit_maybe_macro_call! {
                 self,
                 (VisitMaybeMacroCallArgs::<'_, '_, '_, T> {
                     _storage: storage,
                     span: &call_span,
+                    candidate_kind: CandidateKind::Call,
                     is_ignored_as_call,
                     is_method_call: false,
                     is_ignored_as_method_call: false
@@ -348,12 +385,21 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
         macro_call: <T::Types as AbstractTypes>::MacroCall<'ast>,
     ) -> bool {
         let name = macro_call.name();
+        let macro_call_span = macro_call.span(&self.source_file);
+        let callee_span = self
+            .backend
+            .macro_call_callee(storage, macro_call)
+            .span(&self.source_file);
+        self.visit_assertion_call(&name, &macro_call_span);
+        let is_ignored_as_call = self.config.is_ignored_macro(&name)
+            || self.is_ignored_call(&name, &macro_call_span, &callee_span);
         visit_maybe_macro_call! {
             self,
             (VisitMaybeMacroCallArgs::<'_, '_, '_, T> {
                 _storage: storage,
-                span: &macro_call.span(&self.source_file),
-                is_ignored_as_call: self.config.is_ignored_macro(&name),
+                span: &macro_call_span,
+                candidate_kind: CandidateKind::Macro,
+                is_ignored_as_call,
                 is_method_call: false,
                 is_ignored_as_method_call: false
             })
@@ -416,11 +462,21 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
 
     // smoelius: `register_span` no longer takes a `test_name` argument. It now registers a span
     // using `self.test_names` (which must be non-empty).
-    fn register_span(&mut self, span: Span, kind: SpanKind) {
+    // smoelius: Spans excluded by `candidate_kinds` or `min_statement_lines` are not registered.
+    fn register_span(&mut self, span: Span, kind: SpanKind, candidate_kind: CandidateKind) {
+        if !self.config.is_candidate_kind(candidate_kind)
+            || (kind == SpanKind::Statement
+                && span.end.line - span.start.line + 1 < self.config.min_statement_lines())
+        {
+            return;
+        }
         let span_test_map = match kind {
             SpanKind::Statement => &mut self.span_test_maps.statement,
             SpanKind::MethodCall => &mut self.span_test_maps.method_call,
         };
+        if self.last_statement_in_test.is_some() {
+            self.spans_in_test.push(span.clone());
+        }
         let span_test_names = span_test_map.entry(span).or_default();
         assert!(!self.test_names.is_empty());
         for test_name in &self.test_names {
@@ -473,6 +529,8 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
         innermost: bool,
         recursed: bool,
     ) -> CallInfo {
+        let field_span = field.span(&self.source_file);
+
         let mut base = self.backend.field_base(storage, field);
 
         let mut path_rev = vec![name.to_owned()];
@@ -503,11 +561,17 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
                     )
                 } else {
                     let name = call.name();
-                    let is_ignored = name
-                        .as_ref()
-                        .is_some_and(|name| self.config.is_ignored_function(name));
+                    let call_span = call.span(&self.source_file);
+                    let callee_span = self
+                        .backend
+                        .call_callee(storage, call)
+                        .span(&self.source_file);
+                    let is_ignored = name.as_ref().is_some_and(|name| {
+                        self.config.is_ignored_function(name)
+                            || self.is_ignored_call(name, &call_span, &callee_span)
+                    });
                     CallInfo {
-                        span: call.span(&self.source_file),
+                        span: call_span,
                         is_method: false,
                         is_ignored,
                         is_nested: true,
@@ -517,9 +581,15 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
         } else if let Some(macro_call) = self.backend.expression_is_macro_call(storage, base) {
             if innermost {
                 let name = macro_call.name();
-                let is_ignored = self.config.is_ignored_macro(&name);
+                let macro_call_span = macro_call.span(&self.source_file);
+                let callee_span = self
+                    .backend
+                    .macro_call_callee(storage, macro_call)
+                    .span(&self.source_file);
+                let is_ignored = self.config.is_ignored_macro(&name)
+                    || self.is_ignored_call(&name, &macro_call_span, &callee_span);
                 return CallInfo {
-                    span: macro_call.span(&self.source_file),
+                    span: macro_call_span,
                     is_method: false,
                     is_ignored,
                     is_nested: recursed,
@@ -528,7 +598,8 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
         } else if let Some(name) = base.name() {
             if innermost {
                 let name = format!("{name}.{path}");
-                let is_ignored_as_function = self.config.is_ignored_function(&name);
+                let is_ignored_as_function = self.config.is_ignored_function(&name)
+                    || self.is_ignored_call(&name, call_span, &field_span);
                 let is_ignored_as_method = self.config.is_ignored_method(&path);
                 let is_ignored = match self.config.ignored_path_disambiguation() {
                     config::IgnoredPathDisambiguation::None => {
@@ -558,6 +629,80 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
         }
     }
 
+    /// If `call` is a statement-level call matching a `diagnostic_calls` pattern, visits it and
+    /// returns whether to descend into its arguments. Otherwise, returns `None`.
+    ///
+    /// Diagnostic calls are ignored unless `include_diagnostic_calls` is set, in which case their
+    /// statements are recorded so that their outcomes can be reported separately.
+    fn visit_diagnostic_call(
+        &mut self,
+        storage: &RefCell<<T::Types as AbstractTypes>::Storage<'ast>>,
+        call: <T::Types as AbstractTypes>::Call<'ast>,
+    ) -> Option<bool> {
+        let statement = self.call_statement?;
+        let path = self.callee_path(storage, call)?;
+        if !self.config.is_diagnostic_call(&path) {
+            return None;
+        }
+        let include = self.config.include_diagnostic_calls();
+        if include {
+            self.span_test_maps
+                .diagnostic
+                .insert(statement.span(&self.source_file));
+        }
+        let descend = {
+            visit_maybe_macro_call! {
+                self,
+                (VisitMaybeMacroCallArgs::<'_, '_, '_, T> {
+                    _storage: storage,
+                    span: &call.span(&self.source_file),
+                    candidate_kind: CandidateKind::Call,
+                    is_ignored_as_call: !include,
+                    is_method_call: false,
+                    is_ignored_as_method_call: false
+                })
+            }
+        };
+        Some(descend)
+    }
+
+    /// If `name` matches an `assertion_calls` pattern and the current test (rather than a local
+    /// function) is being visited, records the end of `span`.
+    fn visit_assertion_call(&mut self, name: &str, span: &Span) {
+        if self.last_statement_in_test.is_some()
+            && self.config.is_assertion_call(name)
+            && self.last_assertion_end < Some(span.end)
+        {
+            self.last_assertion_end = Some(span.end);
+        }
+    }
+
+    /// Returns the callee's path (e.g., `fmt.Println`) if the callee is a name or a sequence of
+    /// named fields of a name
+    fn callee_path(
+        &self,
+        storage: &RefCell<<T::Types as AbstractTypes>::Storage<'ast>>,
+        call: <T::Types as AbstractTypes>::Call<'ast>,
+    ) -> Option<String> {
+        let mut expression = self.backend.call_callee(storage, call);
+        let mut path_rev = Vec::new();
+        while let Some(field) = self.backend.expression_is_field(storage, expression) {
+            path_rev.push(field.name()?);
+            expression = self.backend.field_base(storage, field);
+        }
+        path_rev.push(expression.name()?);
+        path_rev.reverse();
+        Some(path_rev.join("."))
+    }
+
+    /// Returns true if the call to `name` spanned by `call_span` is ignored by an `ignored_calls`
+    /// entry. The call's arguments are assumed to follow `callee_span`.
+    fn is_ignored_call(&self, name: &str, call_span: &Span, callee_span: &Span) -> bool {
+        self.config.is_ignored_call(name, || {
+            call_span.with_start(callee_span.end()).source_text().ok()
+        })
+    }
+
     fn field_base_is_named_field(
         &self,
         storage: &RefCell<<T::Types as AbstractTypes>::Storage<'ast>>,
@@ -584,7 +729,13 @@ impl<'ast, T: ParseLow> GenericVisitor<'_, '_, '_, 'ast, T> {
         Vec<<T::Types as AbstractTypes>::LocalFunction<'ast>>,
     )> {
         let expression = self.backend.call_callee(storage, call);
-        let name = expression.name()?;
+        // smoelius: If the callee is not a simple name, try treating it as a qualified one, e.g.,
+        // a call to a Solidity library function `Lib.helper(..)`.
+        let name = expression.name().or_else(|| {
+            let field = self.backend.expression_is_field(storage, expression)?;
+            let base = self.backend.field_base(storage, field);
+            Some(format!("{}.{}", base.name()?, field.name()?))
+        })?;
         let walkable_functions = self.walkable_functions.get(&name)?;
         Some((name, walkable_functions.clone()))
     }
@@ -1,16 +1,22 @@
 use super::{
-    AbstractTypes, GenericVisitor, MaybeNamed, Named, ParseLow, ProcessLines, RunLow, Spanned,
-    WalkDirResult,
+    bazel, utils, AbstractTypes, GenericVisitor, MaybeNamed, Named, ParseCache, ParseLow,
+    ProcessLines, RunLow, Spanned, WalkDirResult,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use necessist_core::{
-    framework::{SpanTestMaps, TestSet},
+    config,
+    framework::{SpanTestMaps, StatementKind, TestSet},
     util, LightContext, LineColumn, SourceFile, Span, __Rewriter as Rewriter,
 };
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::{
-    collections::BTreeMap, convert::Infallible, fs::read_to_string, path::Path, process::Command,
+    collections::{BTreeMap, BTreeSet},
+    convert::Infallible,
+    fs::read_to_string,
+    path::Path,
+    process::Command,
+    rc::Rc,
 };
 use streaming_iterator::StreamingIterator;
 use tree_sitter::{
@@ -36,6 +42,22 @@ const BLOCK_STATEMENTS_SOURCE: &str = r#"
 ) @block
 "#;
 
+const BLOCK_DECLARED_VARIABLES_SOURCE: &str = r"
+(block
+    [
+        (short_var_declaration
+            left: (expression_list (identifier) @name)
+        )
+        (var_declaration
+            (var_spec name: (identifier) @name)
+        )
+        (var_declaration
+            (var_spec_list (var_spec name: (identifier) @name))
+        )
+    ] @declaration
+)
+";
+
 const EXPRESSION_STATEMENT_EXPRESSION_SOURCE: &str = r"
 (expression_statement
     (_expression) @expression
@@ -44,6 +66,8 @@ const EXPRESSION_STATEMENT_EXPRESSION_SOURCE: &str = r"
 
 static LANGUAGE: Lazy<Language> = Lazy::new(|| Language::from(tree_sitter_go::LANGUAGE));
 static BLOCK_STATEMENTS_QUERY: Lazy<Query> = Lazy::new(|| valid_query(BLOCK_STATEMENTS_SOURCE));
+static BLOCK_DECLARED_VARIABLES_QUERY: Lazy<Query> =
+    Lazy::new(|| valid_query(BLOCK_DECLARED_VARIABLES_SOURCE));
 static EXPRESSION_STATEMENT_EXPRESSION_QUERY: Lazy<Query> =
     Lazy::new(|| valid_query(EXPRESSION_STATEMENT_EXPRESSION_SOURCE));
 
@@ -66,6 +90,7 @@ static CALL_EXPRESSION_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("call_exp
 static CONST_DECLARATION_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("const_declaration"));
 static CONTINUE_STATEMENT_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("continue_statement"));
 static DEFER_STATEMENT_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("defer_statement"));
+static GO_STATEMENT_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("go_statement"));
 static IDENTIFIER_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("identifier"));
 static RETURN_STATEMENT_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("return_statement"));
 static SELECTOR_EXPRESSION_KIND: Lazy<u16> = Lazy::new(|| non_zero_kind_id("selector_expression"));
@@ -83,6 +108,7 @@ fn non_zero_kind_id(kind: &str) -> u16 {
 #[derive(Debug)]
 pub struct Go {
     os_name_map: std::cell::RefCell<BTreeMap<SourceFile, String>>,
+    parse_cache: ParseCache<Tree>,
 }
 
 impl Go {
@@ -93,6 +119,7 @@ impl Go {
     pub fn new() -> Self {
         Self {
             os_name_map: std::cell::RefCell::new(BTreeMap::new()),
+            parse_cache: ParseCache::default(),
         }
     }
 }
@@ -136,7 +163,7 @@ pub struct Types;
 
 impl AbstractTypes for Types {
     type Storage<'ast> = Storage<'ast>;
-    type File = (String, Tree);
+    type File = (String, Rc<Tree>);
     type Test<'ast> = Test<'ast>;
     type LocalFunction<'ast> = LocalFunction<'ast>;
     type Statement<'ast> = Statement<'ast>;
@@ -230,6 +257,10 @@ impl ParseLow for Go {
         "Parallel", "Skip", "Skipf", "SkipNow",
     ]);
 
+    fn ignored_overrides(config: &config::Toml) -> config::IgnoredOverrides<'_> {
+        config.go.ignored_overrides()
+    }
+
     fn walk_dir(&self, root: &Path) -> Box<dyn Iterator<Item = WalkDirResult>> {
         Box::new(
             walkdir::WalkDir::new(root)
@@ -246,15 +277,8 @@ impl ParseLow for Go {
         source_file: &Path,
     ) -> Result<<Self::Types as AbstractTypes>::File> {
         let text = read_to_string(source_file)?;
-        let mut parser = Parser::new();
-        parser
-            .set_language(&LANGUAGE)
-            .with_context(|| "Failed to load Go grammar")?;
-        // smoelius: https://github.com/tree-sitter/tree-sitter/issues/255
-        parser
-            .parse(&text, None)
-            .map(|tree| (text, tree))
-            .ok_or_else(|| anyhow!("Unspecified error"))
+        let tree = self.parse_cache.parse(source_file, &text, parse)?;
+        Ok((text, tree))
     }
 
     fn storage_from_file<'ast>(
@@ -439,8 +463,21 @@ impl ParseLow for Go {
 }
 
 impl RunLow for Go {
+    // smoelius: `go test` does not run benchmarks by default. So with `--include-go-benchmarks`,
+    // a dry run runs each of the package's benchmarks once.
     fn command_to_run_source_file(&self, context: &LightContext, source_file: &Path) -> Command {
-        Self::test_command(context, source_file)
+        if bazel::enabled(context) {
+            let mut command = Self::bazel_test_command(context, source_file);
+            if context.opts.include_go_benchmarks {
+                command.args(["--test_arg=-test.bench=.", "--test_arg=-test.benchtime=1x"]);
+            }
+            return command;
+        }
+        let mut command = Self::test_command(context, source_file);
+        if context.opts.include_go_benchmarks {
+            command.args(["-bench=.", "-benchtime=1x"]);
+        }
+        command
     }
 
     fn instrument_source_file(
@@ -491,7 +528,32 @@ impl RunLow for Go {
         ))
     }
 
+    // smoelius: Go has no way to allow unused variables. So each variable declared in a block is
+    // "used" immediately after its declaration with a blank assignment.
+    fn unused_tolerance_insertions(
+        &self,
+        _context: &LightContext,
+        source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        let contents = source_file.contents();
+        let tree = self.parse_cache.parse(source_file, contents, parse)?;
+        let declarations = declared_variables(contents, &tree)?;
+        Ok(declarations
+            .into_iter()
+            .map(|(end, names)| {
+                let insertion = names
+                    .iter()
+                    .map(|name| format!("; _ = {name}"))
+                    .collect::<String>();
+                (end.to_line_column(source_file), insertion)
+            })
+            .collect())
+    }
+
     fn command_to_build_source_file(&self, context: &LightContext, source_file: &Path) -> Command {
+        if bazel::enabled(context) {
+            return bazel::build_command(context, source_file);
+        }
         let mut command = Self::test_command(context, source_file);
         command.arg("-run=^$");
         command
@@ -503,6 +565,9 @@ impl RunLow for Go {
         _test_name: &str,
         span: &Span,
     ) -> Command {
+        if bazel::enabled(context) {
+            return bazel::build_command(context, &span.source_file);
+        }
         let mut command = Self::test_command(context, &span.source_file);
         command.arg("-run=^$");
         command
@@ -514,11 +579,48 @@ impl RunLow for Go {
         test_name: &str,
         span: &Span,
     ) -> (Command, Vec<String>, Option<ProcessLines>) {
-        let mut command = Self::test_command(context, &span.source_file);
-        command.args([format!("-run=^{test_name}$").as_ref(), "-v"]);
+        if is_benchmark(test_name) {
+            return Self::command_to_run_benchmark(context, test_name, span);
+        }
 
         let needle = format!("=== RUN   {test_name}");
 
+        // smoelius: rules_go passes `--test_filter` to the test binary as `-test.run`. A fuzz
+        // target run this way runs on its seed corpus only, like with `go test`.
+        if bazel::enabled(context) {
+            return (
+                Self::bazel_test_command(context, &span.source_file),
+                vec![
+                    format!("--test_filter={}", utils::go_run_regex(test_name)),
+                    "--test_arg=-test.v".to_owned(),
+                ],
+                Some((false, Box::new(move |line| line == needle))),
+            );
+        }
+
+        let mut command = Self::test_command(context, &span.source_file);
+        command.arg(format!("-run={}", utils::go_run_regex(test_name)));
+
+        // smoelius: An empty `-fuzz` pattern matches no fuzz targets, so the target is run on its
+        // seed corpus only, rather than fuzzed.
+        if is_fuzz_target(test_name) {
+            command.args(["-fuzz=", "-fuzztime=1x"]);
+        }
+
+        if context.config.go.json {
+            command.arg("-json");
+
+            let test_name = test_name.to_owned();
+
+            return (
+                command,
+                Vec::new(),
+                Some((false, Box::new(move |line| is_run_event(line, &test_name)))),
+            );
+        }
+
+        command.arg("-v");
+
         (
             command,
             Vec::new(),
@@ -562,6 +664,187 @@ fn imports_os(contents: &str) -> Option<&str> {
     }
 }
 
+fn is_benchmark(name: &str) -> bool {
+    name.starts_with("Benchmark")
+}
+
+fn is_fuzz_target(name: &str) -> bool {
+    name.starts_with("Fuzz")
+}
+
+/// Returns true if `line` is output by `go test` when it starts running the benchmark
+/// `benchmark_name`, e.g., `BenchmarkFoo-8   \t       1\t      1234 ns/op`. The suffix `-8` is
+/// `GOMAXPROCS`, which is omitted when it is 1.
+fn is_benchmark_line(line: &str, benchmark_name: &str) -> bool {
+    line.strip_prefix(benchmark_name).is_some_and(|suffix| {
+        suffix.is_empty() || suffix.starts_with('-') || suffix.starts_with(char::is_whitespace)
+    })
+}
+
+fn is_run_event(line: &str, test_name: &str) -> bool {
+    let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+        return false;
+    };
+    event.get("Action").and_then(serde_json::Value::as_str) == Some("run")
+        && event.get("Test").and_then(serde_json::Value::as_str) == Some(test_name)
+}
+
+fn parse(contents: &str) -> Result<Tree> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&LANGUAGE)
+        .with_context(|| "Failed to load Go grammar")?;
+    // smoelius: https://github.com/tree-sitter/tree-sitter/issues/255
+    parser
+        .parse(contents, None)
+        .ok_or_else(|| anyhow!("Unspecified error"))
+}
+
+/// Returns the end of each variable declaration statement in `contents`, whose syntax tree is
+/// `tree`, along with the names of the variables it declares. Blank identifiers are omitted.
+fn declared_variables(contents: &str, tree: &Tree) -> Result<Vec<(Point, Vec<String>)>> {
+    let mut declarations = BTreeMap::<_, (Point, Vec<String>)>::new();
+    let mut cursor = QueryCursor::new();
+    let mut query_matches = cursor.matches(
+        &BLOCK_DECLARED_VARIABLES_QUERY,
+        tree.root_node(),
+        contents.as_bytes(),
+    );
+    while let Some(query_match) = query_matches.next() {
+        let captures = sort_captures(query_match.captures);
+        assert_eq!(2, captures.len());
+        let name = captures[0].node.utf8_text(contents.as_bytes())?;
+        let declaration = captures[1].node;
+        let (_, names) = declarations
+            .entry(declaration.end_byte())
+            .or_insert_with(|| (declaration.end_position(), Vec::new()));
+        if name != "_" && !names.iter().any(|other| other == name) {
+            names.push(name.to_owned());
+        }
+    }
+    Ok(declarations
+        .into_values()
+        .filter(|(_, names)| !names.is_empty())
+        .collect())
+}
+
+/// Returns the spans in `spans` that contain every use of some variable declared in a block, i.e.,
+/// whose removal would cause a "declared and not used" error. Any occurrence of a variable's name
+/// after its declaration in the enclosing block is considered a use. So the analysis errs toward
+/// keeping spans, e.g., when a variable is shadowed or only assigned to.
+fn sole_use_spans<'a>(
+    tree: &Tree,
+    text: &str,
+    source_file: &SourceFile,
+    spans: impl Iterator<Item = &'a Span>,
+) -> Result<BTreeSet<Span>> {
+    let mut variables = Vec::new();
+    let mut cursor = QueryCursor::new();
+    let mut query_matches = cursor.matches(
+        &BLOCK_DECLARED_VARIABLES_QUERY,
+        tree.root_node(),
+        text.as_bytes(),
+    );
+    while let Some(query_match) = query_matches.next() {
+        let captures = sort_captures(query_match.captures);
+        assert_eq!(2, captures.len());
+        let name = captures[0].node.utf8_text(text.as_bytes())?;
+        let declaration = captures[1].node;
+        if name == "_" {
+            continue;
+        }
+        let Some(block) = declaration.parent() else {
+            continue;
+        };
+        let mut identifiers = Vec::new();
+        collect_identifiers(block, &mut identifiers);
+        let mut uses = Vec::new();
+        for identifier in identifiers {
+            if identifier.start_byte() >= declaration.end_byte()
+                && identifier.utf8_text(text.as_bytes())? == name
+            {
+                uses.push(identifier.range().to_internal_span(source_file));
+            }
+        }
+        if !uses.is_empty() {
+            variables.push((declaration.range().to_internal_span(source_file), uses));
+        }
+    }
+    Ok(spans
+        .filter(|span| {
+            variables.iter().any(|(declaration, uses)| {
+                !contains(span, declaration) && uses.iter().all(|used| contains(span, used))
+            })
+        })
+        .cloned()
+        .collect())
+}
+
+/// Returns the kinds of the spans in `spans` that belong to Go statements that start goroutines
+/// (`go f()`) or to calls that wait for them (calls to methods named `Wait`, e.g., `wg.Wait()`). A
+/// span belongs to such a statement or call if it is contained in it and ends where it does, e.g.,
+/// the method call `.Wait()` belongs to `wg.Wait()`.
+fn statement_kinds<'a>(
+    tree: &Tree,
+    text: &str,
+    source_file: &SourceFile,
+    spans: impl Iterator<Item = &'a Span>,
+) -> Result<BTreeMap<Span, StatementKind>> {
+    let mut nodes = Vec::new();
+    collect_statement_kinds(tree.root_node(), text, &mut nodes)?;
+    let ranges = nodes
+        .into_iter()
+        .map(|(node, kind)| (node.range().to_internal_span(source_file), kind))
+        .collect::<Vec<_>>();
+    Ok(spans
+        .filter_map(|span| {
+            ranges
+                .iter()
+                .find(|(range, _)| contains(range, span) && range.end == span.end)
+                .map(|&(_, kind)| (span.clone(), kind))
+        })
+        .collect())
+}
+
+fn collect_statement_kinds<'tree>(
+    node: Node<'tree>,
+    text: &str,
+    nodes: &mut Vec<(Node<'tree>, StatementKind)>,
+) -> Result<()> {
+    if node.kind_id() == *GO_STATEMENT_KIND {
+        nodes.push((node, StatementKind::Go));
+    } else if node.kind_id() == *CALL_EXPRESSION_KIND {
+        if let Some(field) = node
+            .child_by_field_id(*FUNCTION_FIELD)
+            .filter(|function| function.kind_id() == *SELECTOR_EXPRESSION_KIND)
+            .and_then(|function| function.child_by_field_id(*FIELD_FIELD))
+        {
+            if field.utf8_text(text.as_bytes())? == "Wait" {
+                nodes.push((node, StatementKind::Wait));
+            }
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_statement_kinds(child, text, nodes)?;
+    }
+    Ok(())
+}
+
+fn collect_identifiers<'tree>(node: Node<'tree>, identifiers: &mut Vec<Node<'tree>>) {
+    if node.kind_id() == *IDENTIFIER_KIND {
+        identifiers.push(node);
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_identifiers(child, identifiers);
+    }
+}
+
+fn contains(outer: &Span, inner: &Span) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
 fn package_line(contents: &str) -> Option<usize> {
     // smoelius: `+ 1` because `LineColumn` `line`s are one-based.
     contents
@@ -579,6 +862,51 @@ impl Go {
         command.current_dir(context.root.as_path());
         command.arg("test");
         command.arg(package_path);
+        if let Some(parallel) = context.config.go.parallel {
+            command.arg(format!("-parallel={parallel}"));
+        }
+        command.args(&context.config.go.test_flags);
+        command
+    }
+
+    // smoelius: Benchmarks do not produce `=== RUN` lines or `run` events. So `-v` and
+    // `go.json` are not used, and the benchmark's result line is looked for instead.
+    fn command_to_run_benchmark(
+        context: &LightContext,
+        benchmark_name: &str,
+        span: &Span,
+    ) -> (Command, Vec<String>, Option<ProcessLines>) {
+        let pattern = utils::go_run_regex(benchmark_name);
+
+        let benchmark_name = benchmark_name.to_owned();
+        let process_lines: ProcessLines = (
+            false,
+            Box::new(move |line| is_benchmark_line(line, &benchmark_name)),
+        );
+
+        if bazel::enabled(context) {
+            return (
+                Self::bazel_test_command(context, &span.source_file),
+                vec![
+                    "--test_arg=-test.run=^$".to_owned(),
+                    format!("--test_arg=-test.bench={pattern}"),
+                    "--test_arg=-test.benchtime=1x".to_owned(),
+                ],
+                Some(process_lines),
+            );
+        }
+
+        let mut command = Self::test_command(context, &span.source_file);
+        command.args(["-run=^$", &format!("-bench={pattern}"), "-benchtime=1x"]);
+
+        (command, Vec::new(), Some(process_lines))
+    }
+
+    fn bazel_test_command(context: &LightContext, source_file: &Path) -> Command {
+        let mut command = bazel::test_command(context, source_file);
+        if let Some(parallel) = context.config.go.parallel {
+            command.arg(format!("--test_arg=-test.parallel={parallel}"));
+        }
         command
     }
 }
@@ -590,7 +918,7 @@ fn source_file_package_path(context: &LightContext, source_file: &Path) -> Resul
 
     let stripped = util::strip_prefix(dir, context.root)?;
 
-    Ok(Path::new(".").join(stripped).to_string_lossy().to_string())
+    Ok(utils::to_slash_string(&Path::new(".").join(stripped)))
 }
 
 fn process_self_captures<'query, 'source, 'tree, T, U>(
@@ -680,6 +1008,286 @@ impl ToLineColumn for Point {
 
 #[cfg(test)]
 mod test {
+    use super::{Go, ParseLow, RunLow};
+    use crate::utils::light_context;
+    use necessist_core::{config, framework::StatementKind, LineColumn, SourceFile, Span};
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        fs::write,
+        path::PathBuf,
+        rc::Rc,
+    };
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_command() {
+        let root = Rc::new(PathBuf::from("root"));
+        let config = config::Toml::default();
+        let context = light_context(&root, &config);
+        let source_file = root.join("src").join("encoding").join("base64_test.go");
+        let command = Go::test_command(&context, &source_file);
+        assert_eq!("go", command.get_program());
+        assert_eq!(
+            ["test", "./src/encoding"].as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_command_with_parallel() {
+        let root = Rc::new(PathBuf::from("root"));
+        let mut config = config::Toml::default();
+        config.go.parallel = Some(2);
+        let context = light_context(&root, &config);
+        let source_file = root.join("src").join("encoding").join("base64_test.go");
+        let command = Go::test_command(&context, &source_file);
+        assert_eq!(
+            ["test", "./src/encoding", "-parallel=2"].as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_command_with_test_flags() {
+        let root = Rc::new(PathBuf::from("root"));
+        let mut config = config::Toml::default();
+        config.go.test_flags = vec!["-count=1".to_owned(), "-timeout=120s".to_owned()];
+        let context = light_context(&root, &config);
+        let source_file = root.join("src").join("encoding").join("base64_test.go");
+        let command = Go::test_command(&context, &source_file);
+        assert_eq!(
+            ["test", "./src/encoding", "-count=1", "-timeout=120s"].as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn benchmark_and_fuzz_target_commands() {
+        let tempdir = tempdir().unwrap();
+        let root = Rc::new(tempdir.path().to_path_buf());
+        let path = root.join("foo_test.go");
+        write(&path, "package foo\n").unwrap();
+        let source_file = SourceFile::new(root.clone(), path).unwrap();
+        let span = Span {
+            source_file,
+            start: LineColumn { line: 1, column: 0 },
+            end: LineColumn {
+                line: 1,
+                column: 11,
+            },
+        };
+        let config = config::Toml::default();
+        let context = light_context(&root, &config);
+
+        let (command, final_args, process_lines) =
+            Go::new().command_to_run_test(&context, "BenchmarkFoo", &span);
+        assert_eq!(
+            [
+                "test",
+                "./",
+                "-run=^$",
+                "-bench=^BenchmarkFoo$",
+                "-benchtime=1x"
+            ]
+            .as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+        assert!(final_args.is_empty());
+        let (init, f) = process_lines.unwrap();
+        assert!(!init);
+        assert!(f("BenchmarkFoo-8   \t       1\t      1234 ns/op"));
+        assert!(f("BenchmarkFoo"));
+        assert!(!f("BenchmarkFooBar-8 \t       1\t      1234 ns/op"));
+        assert!(!f("=== RUN   BenchmarkFoo"));
+
+        let (command, _, _) = Go::new().command_to_run_test(&context, "FuzzFoo", &span);
+        assert_eq!(
+            [
+                "test",
+                "./",
+                "-run=^FuzzFoo$",
+                "-fuzz=",
+                "-fuzztime=1x",
+                "-v"
+            ]
+            .as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn bazel_build_command() {
+        let root = Rc::new(PathBuf::from("root"));
+        let mut config = config::Toml::default();
+        config.bazel.enabled = true;
+        config.bazel.targets.insert(
+            "src/encoding/base64_test.go".to_owned(),
+            "//src/encoding:base64_test".to_owned(),
+        );
+        let context = light_context(&root, &config);
+        let source_file = root.join("src").join("encoding").join("base64_test.go");
+        let command = Go::new().command_to_build_source_file(&context, &source_file);
+        assert_eq!("bazel", command.get_program());
+        assert_eq!(
+            ["build", "//src/encoding:base64_test"].as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn is_run_event() {
+        const TESTS: &[(&str, bool)] = &[
+            ("", false),
+            ("=== RUN   TestFoo", false),
+            (r#"{"Action":"start","Package":"example.com/foo"}"#, false),
+            (
+                r#"{"Action":"run","Package":"example.com/foo","Test":"TestFoo"}"#,
+                true,
+            ),
+            (
+                r#"{"Action":"run","Package":"example.com/foo","Test":"TestFooBar"}"#,
+                false,
+            ),
+            (
+                r#"{"Action":"run","Package":"example.com/foo","Test":"TestFoo/sub"}"#,
+                false,
+            ),
+            (
+                r#"{"Action":"output","Package":"example.com/foo","Test":"TestFoo","Output":"=== RUN   TestFoo\n"}"#,
+                false,
+            ),
+        ];
+        for &(line, expected) in TESTS {
+            assert_eq!(expected, super::is_run_event(line, "TestFoo"), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn declared_variables() {
+        const CONTENTS: &str = r"package foo
+
+func TestFoo(t *testing.T) {
+	x, _ := f()
+	var y int
+	var (
+		z = 1
+		w = 2
+	)
+	if v := g(); v {
+		u := 3
+	}
+}
+";
+        let tree = super::parse(CONTENTS).unwrap();
+        let declarations = super::declared_variables(CONTENTS, &tree).unwrap();
+        assert_eq!(
+            [
+                (3, 12, vec!["x"]),
+                (4, 10, vec!["y"]),
+                (8, 2, vec!["z", "w"]),
+                (10, 8, vec!["u"]),
+            ]
+            .as_slice(),
+            declarations
+                .iter()
+                .map(|(end, names)| (
+                    end.row,
+                    end.column,
+                    names.iter().map(String::as_str).collect()
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn sole_use_spans() {
+        const CONTENTS: &str = r"package foo
+
+func TestFoo(t *testing.T) {
+	x := f()
+	g(x)
+	y := f()
+	g(y)
+	g(y)
+	if true {
+		z := f()
+		g(z)
+	}
+}
+";
+        let tempdir = tempdir().unwrap();
+        let root = Rc::new(tempdir.path().to_path_buf());
+        let path = root.join("foo_test.go");
+        write(&path, CONTENTS).unwrap();
+        let source_file = SourceFile::new(root, path).unwrap();
+        let (text, tree) = Go::new().parse_source_file(&source_file).unwrap();
+        let span = |start_line, start_column, end_line, end_column| Span {
+            source_file: source_file.clone(),
+            start: LineColumn {
+                line: start_line,
+                column: start_column,
+            },
+            end: LineColumn {
+                line: end_line,
+                column: end_column,
+            },
+        };
+        let g_x = span(5, 1, 5, 5);
+        let g_y = span(7, 1, 7, 5);
+        let g_z = span(11, 2, 11, 6);
+        let if_true = span(9, 1, 12, 2);
+        let spans = [g_x.clone(), g_y, g_z.clone(), if_true];
+        assert_eq!(
+            [g_x, g_z].into_iter().collect::<BTreeSet<_>>(),
+            super::sole_use_spans(&tree, &text, &source_file, spans.iter()).unwrap()
+        );
+    }
+
+    #[test]
+    fn statement_kinds() {
+        const CONTENTS: &str = r"package foo
+
+func TestFoo(t *testing.T) {
+	var wg sync.WaitGroup
+	wg.Add(1)
+	go f(&wg)
+	wg.Wait()
+}
+";
+        let tempdir = tempdir().unwrap();
+        let root = Rc::new(tempdir.path().to_path_buf());
+        let path = root.join("foo_test.go");
+        write(&path, CONTENTS).unwrap();
+        let source_file = SourceFile::new(root, path).unwrap();
+        let (text, tree) = Go::new().parse_source_file(&source_file).unwrap();
+        let span = |line, start_column, end_column| Span {
+            source_file: source_file.clone(),
+            start: LineColumn {
+                line,
+                column: start_column,
+            },
+            end: LineColumn {
+                line,
+                column: end_column,
+            },
+        };
+        let add = span(5, 3, 10);
+        let go_f = span(6, 1, 10);
+        let f = span(6, 4, 10);
+        let wait = span(7, 3, 10);
+        let spans = [add, go_f.clone(), f.clone(), wait.clone()];
+        assert_eq!(
+            [
+                (go_f, StatementKind::Go),
+                (f, StatementKind::Go),
+                (wait, StatementKind::Wait)
+            ]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>(),
+            super::statement_kinds(&tree, &text, &source_file, spans.iter()).unwrap()
+        );
+    }
+
     #[test]
     fn imports_os() {
         const TESTS: &[(&str, Option<&str>)] = &[
@@ -1,3 +1,4 @@
+use std::rc::Rc;
 use tree_sitter::Tree;
 
 pub struct Storage<'ast> {
@@ -5,7 +6,7 @@ pub struct Storage<'ast> {
 }
 
 impl<'ast> Storage<'ast> {
-    pub fn new(file: &'ast (String, Tree)) -> Self {
+    pub fn new(file: &'ast (String, Rc<Tree>)) -> Self {
         Self { text: &file.0 }
     }
 }
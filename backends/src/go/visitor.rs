@@ -1,11 +1,15 @@
 #![cfg_attr(dylint_lib = "general", allow(non_local_effect_before_error_return))]
 
 use super::{
-    bounded_cursor, process_self_captures, valid_query, Call, GenericVisitor, Go, LocalFunction,
-    Statement, Storage, Test, BLOCK_KIND, CALL_EXPRESSION_KIND,
+    bounded_cursor, is_benchmark, is_fuzz_target, process_self_captures, sole_use_spans,
+    statement_kinds, valid_query, Call, GenericVisitor, Go, LocalFunction, Statement, Storage,
+    Test, BLOCK_KIND, CALL_EXPRESSION_KIND,
 };
 use anyhow::Result;
-use necessist_core::framework::{SpanTestMaps, TestSet};
+use necessist_core::{
+    framework::{SpanTestMaps, TestSet},
+    Span,
+};
 use once_cell::sync::Lazy;
 use std::{cell::RefCell, collections::BTreeMap};
 use streaming_iterator::StreamingIterator;
@@ -13,10 +17,10 @@ use tree_sitter::{Query, QueryCursor, QueryMatch, Tree};
 
 macro_rules! trace {
     () => {
-        log::trace!("{}:{}", file!(), line!())
+        tracing::trace!("{}:{}", file!(), line!())
     };
     ($expr:expr) => {
-        log::trace!("{}:{}: {:?}", file!(), line!(), $expr)
+        tracing::trace!("{}:{}: {:?}", file!(), line!(), $expr)
     };
 }
 
@@ -31,7 +35,7 @@ const TEST_FUNCTION_DECLARATION_SOURCE: &str = r#"
 (function_declaration
     name: (
         (identifier) @name
-        (#match? @name "^Test")
+        (#match? @name "^(Test|Benchmark|Fuzz)")
     )
     body: (block) @body
 )
@@ -85,7 +89,43 @@ pub(super) fn visit<'ast>(
     while let Some(local_function) = visitor.generic_visitor.next_local_function() {
         visitor.visit_local_function(local_function)?;
     }
-    visitor.generic_visitor.results()
+    let context = visitor.generic_visitor.context;
+    let source_file = visitor.generic_visitor.source_file.clone();
+    let (test_set, mut span_test_maps) = visitor.generic_visitor.results()?;
+    // smoelius: With `--tolerate-unused`, the spans' removals build, so there is no need to skip
+    // them.
+    if context.config.go.skip_sole_uses && !context.opts.tolerate_unused {
+        let spans = sole_use_spans(
+            tree,
+            storage.borrow().text,
+            &source_file,
+            span_test_maps.iter().map(|(span, _, _)| span),
+        )?;
+        for span in &spans {
+            remove_span(&mut span_test_maps, span);
+        }
+    }
+    let statement_kinds = statement_kinds(
+        tree,
+        storage.borrow().text,
+        &source_file,
+        span_test_maps.iter().map(|(span, _, _)| span),
+    )?;
+    if context.opts.skip_go_statements {
+        for span in statement_kinds.keys() {
+            remove_span(&mut span_test_maps, span);
+        }
+    } else {
+        span_test_maps.statement_kind = statement_kinds;
+    }
+    Ok((test_set, span_test_maps))
+}
+
+fn remove_span(span_test_maps: &mut SpanTestMaps, span: &Span) {
+    span_test_maps.statement.remove(span);
+    span_test_maps.method_call.remove(span);
+    span_test_maps.diagnostic.remove(span);
+    span_test_maps.post_assertion.remove(span);
 }
 
 struct Visitor<'context, 'config, 'backend, 'ast, 'storage> {
@@ -145,6 +185,13 @@ impl<'context, 'config, 'backend, 'ast, 'storage>
             return Ok(());
         }
 
+        let opts = self.generic_visitor.context.opts;
+        if (is_benchmark(name) && !opts.include_go_benchmarks)
+            || (is_fuzz_target(name) && !opts.include_go_fuzz)
+        {
+            return Ok(());
+        }
+
         let body = query_match.nodes_for_capture_index(1).next().unwrap();
 
         let test = Test { name, body };
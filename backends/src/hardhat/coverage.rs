@@ -0,0 +1,195 @@
+//! Support for `hardhat.skip_uncovered`, which skips candidates that call contract functions that
+//! an lcov report (e.g., one written by solidity-coverage) shows were never executed
+
+use anyhow::{Context, Result};
+use necessist_core::{
+    framework::SourceFileSpanTestMap, source_warn, util, LightContext, WarnFlags, Warning,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::read_to_string,
+    path::Path,
+};
+
+static METHOD_CALL_RE: Lazy<Regex> = Lazy::new(|| {
+    #[allow(clippy::unwrap_used)]
+    Regex::new(r"\.\s*([A-Za-z_$][A-Za-z0-9_$]*)\s*\(").unwrap()
+});
+
+/// Removes from `source_file_span_test_map` the candidates that call a function that the lcov
+/// report `lcov_file` shows was never executed
+pub(super) fn skip_uncovered(
+    context: &LightContext,
+    lcov_file: &Path,
+    source_file_span_test_map: &mut SourceFileSpanTestMap,
+) -> Result<()> {
+    let contents = read_to_string(lcov_file).with_context(|| {
+        format!(
+            "Failed to read `{}`; run `npx hardhat coverage` to create it, or disable \
+             `hardhat.skip_uncovered`",
+            lcov_file.display()
+        )
+    })?;
+    let uncovered = uncovered_functions(&contents);
+    if uncovered.is_empty() {
+        return Ok(());
+    }
+
+    for (source_file, span_test_maps) in source_file_span_test_map.iter_mut() {
+        let mut n_skipped = 0;
+        let mut called = BTreeSet::new();
+        for span_test_map in [
+            &mut span_test_maps.statement,
+            &mut span_test_maps.method_call,
+        ] {
+            let mut error = None;
+            span_test_map.retain(|span, _| {
+                let text = match span.source_text() {
+                    Ok(text) => text,
+                    Err(err) => {
+                        error.get_or_insert(err);
+                        return true;
+                    }
+                };
+                let called_uncovered = called_methods(&text)
+                    .filter(|name| uncovered.contains(*name))
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>();
+                if called_uncovered.is_empty() {
+                    return true;
+                }
+                n_skipped += 1;
+                called.extend(called_uncovered);
+                false
+            });
+            if let Some(error) = error {
+                return Err(error);
+            }
+        }
+
+        if n_skipped == 0 {
+            continue;
+        }
+
+        source_warn(
+            context,
+            Warning::UncoveredCallSkipped,
+            source_file,
+            &format!(
+                "Skipping {n_skipped} candidate{} that call functions that `{}` shows were never \
+                 executed: {}",
+                if n_skipped == 1 { "" } else { "s" },
+                util::strip_prefix(lcov_file, context.original_root)
+                    .unwrap_or(lcov_file)
+                    .display(),
+                called
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            WarnFlags::empty(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the names of the functions in the lcov report `contents` that were never executed. A
+/// function is considered executed if any function with the same name was, since a test's calls
+/// cannot be attributed to a particular contract.
+fn uncovered_functions(contents: &str) -> BTreeSet<String> {
+    let mut hits = BTreeMap::<&str, u64>::new();
+    for line in contents.lines() {
+        let Some((n, name)) = line
+            .strip_prefix("FNDA:")
+            .and_then(|rest| rest.split_once(','))
+        else {
+            continue;
+        };
+        let Ok(n) = n.trim().parse::<u64>() else {
+            continue;
+        };
+        *hits.entry(unqualified(name)).or_default() += n;
+    }
+    hits.into_iter()
+        .filter(|&(_, n)| n == 0)
+        .map(|(name, _)| name.to_owned())
+        .collect()
+}
+
+/// Strips from `name` any contract qualifier (e.g., `Token.`) and parameter list (e.g.,
+/// `(address,uint256)`)
+fn unqualified(name: &str) -> &str {
+    let name = name.trim();
+    let name = name.split_once('(').map_or(name, |(name, _)| name);
+    name.rsplit_once('.').map_or(name, |(_, name)| name)
+}
+
+/// Returns the names of the methods called in `text`, e.g., `mint` in
+/// `await token.connect(alice).mint(100)`
+fn called_methods(text: &str) -> impl Iterator<Item = &str> {
+    METHOD_CALL_RE
+        .captures_iter(text)
+        .filter_map(|captures| captures.get(1))
+        .map(|m| m.as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{called_methods, uncovered_functions};
+
+    const LCOV: &str = "\
+TN:
+SF:contracts/Token.sol
+FN:10,mint
+FN:20,burn
+FN:30,Token.pause
+FNF:3
+FNH:1
+FNDA:3,mint
+FNDA:0,burn
+FNDA:0,Token.pause
+end_of_record
+TN:
+SF:contracts/Vault.sol
+FN:10,burn
+FNF:1
+FNH:0
+FNDA:0,burn
+end_of_record
+TN:
+SF:contracts/Other.sol
+FN:10,mint
+FNF:1
+FNH:0
+FNDA:0,mint
+end_of_record
+";
+
+    #[test]
+    fn uncovered() {
+        assert_eq!(
+            ["burn", "pause"]
+                .into_iter()
+                .map(ToOwned::to_owned)
+                .collect::<std::collections::BTreeSet<_>>(),
+            uncovered_functions(LCOV)
+        );
+    }
+
+    #[test]
+    fn called() {
+        assert_eq!(
+            vec!["connect", "mint"],
+            called_methods("await token.connect(alice).mint(100);").collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["burn"],
+            called_methods(".burn (1)").collect::<Vec<_>>()
+        );
+        assert!(called_methods("x = y;").next().is_none());
+    }
+}
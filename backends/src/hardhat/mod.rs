@@ -1,30 +1,82 @@
-use super::{ts, OutputAccessors, OutputStrippedOfAnsiScapes, ParseAdapter, ParseHigh, RunHigh};
-use anyhow::Result;
-use log::debug;
+use super::{fork, ts, OutputAccessors, OutputStrippedOfAnsiScapes, ParseAdapter, ParseHigh, RunHigh};
+use anyhow::{ensure, Result};
 use necessist_core::{
+    __Rewriter as Rewriter,
+    candidates::Candidates,
     framework::{Interface, Postprocess, SourceFileSpanTestMap},
-    LightContext, SourceFile, Span, __Rewriter as Rewriter,
+    Exec, LightContext, SourceFile, Span,
 };
-use std::path::Path;
-use subprocess::Exec;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+mod coverage;
+
+const CONFIG: &str = "hardhat.config.ts";
 
 pub struct Hardhat {
+    /// The directory (relative to the root) of the Hardhat package, e.g., `packages/contracts` in
+    /// a monorepo, or the empty path if the root is the Hardhat package
+    package: PathBuf,
     mocha_adapter: ParseAdapter<ts::mocha::Mocha>,
 }
 
 impl Hardhat {
+    /// Returns true if the root is a Hardhat package, or is a workspace with a Hardhat package
     pub fn applicable(context: &LightContext) -> Result<bool> {
-        context
-            .root
-            .join("hardhat.config.ts")
-            .try_exists()
-            .map_err(Into::into)
+        if context.root.join(CONFIG).try_exists()? {
+            return Ok(true);
+        }
+        ts::utils::workspace_packages_containing(context.root, CONFIG)
+            .map(|packages| !packages.is_empty())
+    }
+
+    pub fn new(context: &LightContext) -> Result<Self> {
+        let package = if context.root.join(CONFIG).try_exists()? {
+            PathBuf::new()
+        } else {
+            let mut packages = ts::utils::workspace_packages_containing(context.root, CONFIG)?;
+            ensure!(
+                packages.len() <= 1,
+                "Found multiple Hardhat packages: {}; please select one with --root <ROOT>",
+                packages
+                    .iter()
+                    .map(|package| package.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            // smoelius: If `--framework hardhat` was passed and no package was found, the root is
+            // treated as the package.
+            packages.pop().unwrap_or_default()
+        };
+        debug!("Hardhat package: {:?}", package);
+        Ok(Self {
+            mocha_adapter: ParseAdapter(ts::mocha::Mocha::new(package.join("test")).with_grep()),
+            package,
+        })
     }
 
-    pub fn new() -> Self {
-        Self {
-            mocha_adapter: ParseAdapter(ts::mocha::Mocha::new("test")),
+    /// Returns the directory in which Hardhat commands are run
+    fn dir(&self, context: &LightContext) -> PathBuf {
+        context.root.join(&self.package)
+    }
+
+    fn compile(&self, context: &LightContext) -> Result<()> {
+        let mut command = ts::utils::script("npx");
+        command.current_dir(self.dir(context));
+        command.args(["hardhat", "compile"]);
+        command.args(&context.opts.args);
+        ts::utils::set_node_options(context, &self.dir(context), &mut command)?;
+
+        debug!("{:?}", command);
+
+        let output = command.output_stripped_of_ansi_escapes(context)?;
+        if !output.status().success() {
+            return Err(output.into());
         }
+        Ok(())
     }
 }
 
@@ -36,8 +88,23 @@ impl ParseHigh for Hardhat {
         context: &LightContext,
         config: &necessist_core::config::Toml,
         source_files: &[&Path],
+        candidates: &mut Candidates,
     ) -> Result<(usize, SourceFileSpanTestMap)> {
-        self.mocha_adapter.parse(context, config, source_files)
+        let (n_tests, mut source_file_span_test_map) =
+            self.mocha_adapter
+                .parse(context, config, source_files, candidates)?;
+
+        // smoelius: The lcov report is read from the original root, since it is typically not
+        // committed, and so is absent from a worktree created by `--isolate worktree`.
+        if config.hardhat.skip_uncovered {
+            let lcov_file = context
+                .original_root
+                .join(&self.package)
+                .join(config.hardhat.lcov_file());
+            coverage::skip_uncovered(context, &lcov_file, &mut source_file_span_test_map)?;
+        }
+
+        Ok((n_tests, source_file_span_test_map))
     }
 }
 
@@ -45,16 +112,27 @@ impl RunHigh for Hardhat {
     fn dry_run(&self, context: &LightContext, source_file: &Path) -> Result<()> {
         ts::utils::install_node_modules(context)?;
 
-        compile(context)?;
+        self.compile(context)?;
 
         let mut command = ts::utils::script("npx");
-        command.current_dir(context.root.as_path());
-        command.args(["hardhat", "test", &source_file.to_string_lossy()]);
+        command.current_dir(self.dir(context));
+        command.args(["hardhat", "test"]);
+        command.args(self.mocha_adapter.0.test_files(context, source_file));
         command.args(&context.opts.args);
+        ts::utils::set_node_options(context, &self.dir(context), &mut command)?;
+        fork::set_env(context, &mut command);
 
         self.mocha_adapter.0.dry_run(context, source_file, command)
     }
 
+    fn skipped_tests(
+        &self,
+        _context: &LightContext,
+        source_file: &Path,
+    ) -> Result<BTreeSet<String>> {
+        Ok(self.mocha_adapter.0.skipped_tests(source_file))
+    }
+
     fn instrument_source_file(
         &self,
         _context: &LightContext,
@@ -70,7 +148,7 @@ impl RunHigh for Hardhat {
     }
 
     fn build_source_file(&self, context: &LightContext, _source_file: &Path) -> Result<()> {
-        compile(context)
+        self.compile(context)
     }
 
     fn exec(
@@ -79,33 +157,21 @@ impl RunHigh for Hardhat {
         test_name: &str,
         span: &Span,
     ) -> Result<Option<(Exec, Option<Box<Postprocess>>)>> {
-        if let Err(error) = compile(context) {
+        if let Err(error) = self.compile(context) {
             debug!("{}", error);
             return Ok(None);
         }
 
         let mut command = ts::utils::script("npx");
-        command.current_dir(context.root.as_path());
-        command.args(["hardhat", "test", &span.source_file.to_string_lossy()]);
+        command.current_dir(self.dir(context));
+        command.args(["hardhat", "test"]);
+        command.args(self.mocha_adapter.0.test_files(context, &span.source_file));
         command.args(&context.opts.args);
+        ts::utils::set_node_options(context, &self.dir(context), &mut command)?;
+        fork::set_env(context, &mut command);
 
         self.mocha_adapter
             .0
             .exec(context, test_name, span, &command)
     }
 }
-
-fn compile(context: &LightContext) -> Result<()> {
-    let mut command = ts::utils::script("npx");
-    command.current_dir(context.root.as_path());
-    command.args(["hardhat", "compile"]);
-    command.args(&context.opts.args);
-
-    debug!("{:?}", command);
-
-    let output = command.output_stripped_of_ansi_escapes()?;
-    if !output.status().success() {
-        return Err(output.into());
-    };
-    Ok(())
-}
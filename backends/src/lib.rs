@@ -3,14 +3,19 @@ use clap::ValueEnum;
 use heck::ToKebabCase;
 use necessist_core::{
     framework::{
-        Applicable, AsParse, AsRun, Interface, Parse as ParseHigh, Postprocess, Run as RunHigh,
-        ToImplementation,
+        Applicable, AsParse, AsRun, Interface, Parse as ParseHigh, Postprocess, RemovalStrategy,
+        Run as RunHigh, ToImplementation,
     },
-    LightContext, SourceFile, Span, __Rewriter as Rewriter,
+    Exec, LightContext, LineColumn, SourceFile, Span, __Rewriter as Rewriter,
+};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    path::Path,
+    process::{Command, Output},
+    rc::Rc,
 };
-use std::{cell::RefCell, path::Path, rc::Rc};
 use strum_macros::EnumIter;
-use subprocess::Exec;
 
 // Framework modules
 
@@ -31,8 +36,14 @@ use rust::Rust;
 
 // Other modules
 
+mod bazel;
+
+mod fork;
+
 mod parsing;
-use parsing::{AbstractTypes, MaybeNamed, Named, ParseAdapter, ParseLow, Spanned, WalkDirResult};
+use parsing::{
+    AbstractTypes, MaybeNamed, Named, ParseAdapter, ParseCache, ParseLow, Spanned, WalkDirResult,
+};
 
 mod generic_visitor;
 use generic_visitor::GenericVisitor;
@@ -68,6 +79,36 @@ impl Applicable for Identifier {
             Self::Rust => Rust::applicable(context),
         }
     }
+
+    fn marker(&self) -> Option<&'static str> {
+        Some(match *self {
+            Self::Anchor => "Anchor.toml",
+            Self::Foundry => "foundry.toml",
+            Self::Go => "go.mod",
+            Self::Hardhat => "hardhat.config.ts",
+            Self::Rust => "Cargo.toml",
+        })
+    }
+
+    fn config_tables(&self) -> &'static [&'static str] {
+        match *self {
+            Self::Anchor => &["anchor", "js", "mocha"],
+            Self::Foundry => &["fork", "foundry"],
+            Self::Go => &["bazel", "go"],
+            Self::Hardhat => &["fork", "hardhat", "js", "mocha"],
+            Self::Rust => &["bazel", "rust"],
+        }
+    }
+
+    fn toolchain(&self) -> &'static [&'static [&'static str]] {
+        match *self {
+            Self::Anchor => &[&["anchor", "--version"], &["node", "--version"]],
+            Self::Foundry => &[&["forge", "--version"]],
+            Self::Go => &[&["go", "version"]],
+            Self::Hardhat => &[&["node", "--version"]],
+            Self::Rust => &[&["cargo", "--version"]],
+        }
+    }
 }
 
 impl ToImplementation for Identifier {
@@ -88,7 +129,10 @@ impl ToImplementation for Identifier {
                 Go::new(),
             ))),
 
-            Self::Hardhat => Ok(Some(Box::new(Hardhat::new()))),
+            Self::Hardhat => {
+                let hardhat = Hardhat::new(context)?;
+                Ok(Some(Box::new(hardhat)))
+            }
 
             Self::Rust => Ok(Some(implementation_as_interface(ParseRunAdapter::new)(
                 Rust::new(),
@@ -111,9 +155,34 @@ fn implementation_as_interface<T, U: Interface + 'static>(
 }
 
 impl<T: RunHigh> RunHigh for ParseAdapter<T> {
+    fn removal_strategy(&self) -> RemovalStrategy {
+        self.0.removal_strategy()
+    }
     fn dry_run(&self, context: &LightContext, source_file: &Path) -> Result<()> {
         self.0.dry_run(context, source_file)
     }
+    fn dry_run_command(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Result<Option<Command>> {
+        self.0.dry_run_command(context, source_file)
+    }
+    fn check_dry_run_output(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+        output: Output,
+    ) -> Result<()> {
+        self.0.check_dry_run_output(context, source_file, output)
+    }
+    fn skipped_tests(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Result<BTreeSet<String>> {
+        self.0.skipped_tests(context, source_file)
+    }
     fn instrument_source_file(
         &self,
         context: &LightContext,
@@ -127,6 +196,13 @@ impl<T: RunHigh> RunHigh for ParseAdapter<T> {
     fn statement_prefix_and_suffix(&self, span: &Span) -> Result<(String, String)> {
         self.0.statement_prefix_and_suffix(span)
     }
+    fn unused_tolerance_insertions(
+        &self,
+        context: &LightContext,
+        source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        self.0.unused_tolerance_insertions(context, source_file)
+    }
     fn build_source_file(&self, context: &LightContext, source_file: &Path) -> Result<()> {
         self.0.build_source_file(context, source_file)
     }
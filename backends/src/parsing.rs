@@ -21,22 +21,25 @@
 //!   then forwards it on to the framework.
 
 use super::{GenericVisitor, ParseHigh};
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use heck::ToKebabCase;
 use indexmap::IndexMap;
 use necessist_core::{
+    candidates::{self, Candidates},
     config,
     framework::{SourceFileSpanTestMap, SpanTestMaps, TestSet},
-    util, warn, LightContext, SourceFile, Span, WarnFlags, Warning,
+    source_warn, util, warn, LightContext, SourceFile, Span, WarnFlags, Warning,
 };
 use paste::paste;
+use sha2::{Digest, Sha256};
 use std::{
     any::type_name,
     cell::RefCell,
-    collections::{BTreeMap, BTreeSet, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     convert::Infallible,
+    fmt::{Debug, Formatter},
     hash::Hash,
-    path::Path,
+    path::{Path, PathBuf},
     rc::Rc,
 };
 
@@ -88,6 +91,56 @@ pub trait AbstractTypes {
 
 pub type WalkDirResult = walkdir::Result<walkdir::DirEntry>;
 
+/// Parsed files keyed by path
+///
+/// A backend may need a file's syntax tree in more than one phase, e.g., while walking a crate's
+/// module tree, while visiting the file, and while computing insertions for `--tolerate-unused`.
+/// Parsing through a `ParseCache` ensures that a file is parsed again only if its contents have
+/// changed. Only the tree of a file's most recently parsed contents is kept, so a file that is
+/// modified (e.g., by a removal) replaces its entry rather than adding one.
+pub struct ParseCache<F> {
+    files: RefCell<HashMap<PathBuf, ([u8; 32], Rc<F>)>>,
+}
+
+impl<F> Default for ParseCache<F> {
+    fn default() -> Self {
+        Self {
+            files: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<F> Debug for ParseCache<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParseCache")
+            .field("len", &self.files.borrow().len())
+            .finish()
+    }
+}
+
+impl<F> ParseCache<F> {
+    /// Returns the result of `parse` applied to `contents`, the contents of `path`, calling `parse`
+    /// only if `path` was last parsed with different contents. Failures are not cached.
+    pub fn parse(
+        &self,
+        path: &Path,
+        contents: &str,
+        parse: impl FnOnce(&str) -> Result<F>,
+    ) -> Result<Rc<F>> {
+        let hash: [u8; 32] = Sha256::digest(contents).into();
+        if let Some((prev, file)) = self.files.borrow().get(path) {
+            if *prev == hash {
+                return Ok(file.clone());
+            }
+        }
+        let file = Rc::new(parse(contents)?);
+        self.files
+            .borrow_mut()
+            .insert(path.to_path_buf(), (hash, file.clone()));
+        Ok(file)
+    }
+}
+
 pub trait ParseLow: Sized {
     type Types: AbstractTypes;
 
@@ -100,7 +153,27 @@ pub trait ParseLow: Sized {
         let (_, type_name) = type_name::<Self>().rsplit_once("::").unwrap();
         type_name.to_kebab_case()
     }
+    /// Returns the additions to and removals from [`ParseLow::IGNORED_FUNCTIONS`],
+    /// [`ParseLow::IGNORED_MACROS`], and [`ParseLow::IGNORED_METHODS`] configured in `config`
+    fn ignored_overrides(_config: &config::Toml) -> config::IgnoredOverrides<'_> {
+        config::IgnoredOverrides::default()
+    }
     fn walk_dir(&self, root: &Path) -> Box<dyn Iterator<Item = WalkDirResult>>;
+    /// Returns the files, other than `source_file`, whose contents affect `source_file`'s
+    /// candidates. They are hashed along with `source_file`, so that its candidates are not reused
+    /// if they change.
+    fn dependencies(
+        &mut self,
+        _context: &LightContext,
+        _source_file: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+    /// Called instead of parsing `source_file` when its candidates are reused from an earlier
+    /// run. A backend that caches per-file state while parsing should populate that state here.
+    fn reuse_source_file(&mut self, _context: &LightContext, _source_file: &Path) -> Result<()> {
+        Ok(())
+    }
     fn parse_source_file(&self, source_file: &Path)
         -> Result<<Self::Types as AbstractTypes>::File>;
     fn storage_from_file<'ast>(
@@ -198,9 +271,18 @@ impl<T: ParseLow> ParseLow for Rc<RefCell<T>> {
     const IGNORED_FUNCTIONS: Option<&'static [&'static str]> = T::IGNORED_FUNCTIONS;
     const IGNORED_MACROS: Option<&'static [&'static str]> = T::IGNORED_MACROS;
     const IGNORED_METHODS: Option<&'static [&'static str]> = T::IGNORED_METHODS;
+    fn ignored_overrides(config: &config::Toml) -> config::IgnoredOverrides<'_> {
+        T::ignored_overrides(config)
+    }
     fn walk_dir(&self, root: &Path) -> Box<dyn Iterator<Item = WalkDirResult>> {
         self.borrow().walk_dir(root)
     }
+    fn dependencies(&mut self, context: &LightContext, source_file: &Path) -> Result<Vec<PathBuf>> {
+        self.borrow_mut().dependencies(context, source_file)
+    }
+    fn reuse_source_file(&mut self, context: &LightContext, source_file: &Path) -> Result<()> {
+        self.borrow_mut().reuse_source_file(context, source_file)
+    }
     fn parse_source_file(
         &self,
         source_file: &Path,
@@ -230,11 +312,14 @@ impl<T: ParseLow> ParseLow for Rc<RefCell<T>> {
         let GenericVisitor {
             context,
             config,
+            toml,
             backend,
             walkable_functions,
             source_file,
             test_names,
             last_statement_in_test,
+            last_assertion_end,
+            spans_in_test,
             n_statement_leaves_visited,
             n_before,
             call_statement,
@@ -248,11 +333,14 @@ impl<T: ParseLow> ParseLow for Rc<RefCell<T>> {
         let generic_visitor = GenericVisitor::<'_, '_, '_, 'ast, T> {
             context,
             config,
+            toml,
             backend: &mut backend,
             walkable_functions,
             source_file,
             test_names,
             last_statement_in_test,
+            last_assertion_end,
+            spans_in_test,
             n_before,
             n_statement_leaves_visited,
             call_statement,
@@ -359,13 +447,17 @@ impl<T: ParseLow> ParseLow for Rc<RefCell<T>> {
 pub struct ParseAdapter<T>(pub T);
 
 impl<T: ParseLow> ParseHigh for ParseAdapter<T> {
+    #[allow(clippy::too_many_lines)]
     fn parse(
         &mut self,
         context: &LightContext,
         config: &config::Toml,
         source_files: &[&Path],
+        candidates: &mut Candidates,
     ) -> Result<(usize, SourceFileSpanTestMap)> {
-        let config = Self::compile_config(context, config)?;
+        // smoelius: Nested configuration files can cause different directories to have different
+        // configurations. Each directory's configuration is compiled once.
+        let mut compiled_configs = BTreeMap::<PathBuf, (config::Toml, config::Compiled)>::new();
 
         let mut n_tests = 0;
         let mut source_file_span_test_map = SourceFileSpanTestMap::new();
@@ -376,6 +468,35 @@ impl<T: ParseLow> ParseHigh for ParseAdapter<T> {
             assert!(source_file.is_absolute());
             assert!(source_file.starts_with(context.root.as_path()));
 
+            if let Some(shard) = &context.opts.shard {
+                if !shard.contains(context.root, source_file)? {
+                    return Ok(());
+                }
+            }
+
+            let dir = source_file.parent().unwrap_or(source_file).to_path_buf();
+            if !compiled_configs.contains_key(&dir) {
+                let toml = config.for_source_file(context, source_file)?;
+                let compiled = Self::compile_config(context, &toml)?;
+                compiled_configs.insert(dir.clone(), (toml, compiled));
+            }
+            let (toml, config) = &compiled_configs[&dir];
+
+            // smoelius: The candidates of a source file that has not changed since it was last
+            // parsed are reused. Note that warnings emitted while parsing the file are not emitted
+            // again.
+            let dependencies = self.0.dependencies(context, source_file)?;
+            let hash = candidates::hash(context, toml, source_file, &dependencies)?;
+            if let Some((n_tests_reused, span_test_maps)) =
+                candidates.reuse(context, source_file, &hash)?
+            {
+                self.0.reuse_source_file(context, source_file)?;
+                let source_file = SourceFile::new(context.root.clone(), source_file.to_path_buf())?;
+                n_tests += n_tests_reused;
+                extend(&mut source_file_span_test_map, source_file, span_test_maps);
+                return Ok(());
+            }
+
             #[allow(clippy::unwrap_used)]
             let file = match self.0.parse_source_file(source_file) {
                 Ok(file) => file,
@@ -409,12 +530,15 @@ impl<T: ParseLow> ParseHigh for ParseAdapter<T> {
 
             let generic_visitor = GenericVisitor {
                 context,
-                config: &config,
+                config,
+                toml,
                 backend: &mut self.0,
                 walkable_functions,
                 source_file: source_file.clone(),
                 test_names: BTreeSet::default(),
                 last_statement_in_test: None,
+                last_assertion_end: None,
+                spans_in_test: Vec::new(),
                 n_statement_leaves_visited: 0,
                 n_before: Vec::new(),
                 call_statement: None,
@@ -425,7 +549,20 @@ impl<T: ParseLow> ParseHigh for ParseAdapter<T> {
                 local_functions_needing_warnings: BTreeSet::default(),
             };
 
-            let (test_set, span_test_map) = T::visit_file(generic_visitor, &storage, &file)?;
+            let (mut test_set, mut span_test_map) =
+                T::visit_file(generic_visitor, &storage, &file)?;
+
+            if let Some(max) = config.max_statements_per_test() {
+                skip_large_tests(
+                    context,
+                    &source_file,
+                    max,
+                    &mut test_set,
+                    &mut span_test_map,
+                )?;
+            }
+
+            candidates.record(&source_file, hash, test_set.len(), &span_test_map)?;
 
             n_tests += test_set.len();
             extend(&mut source_file_span_test_map, source_file, span_test_map);
@@ -478,6 +615,72 @@ fn extend(
         let test_names = span_test_maps.method_call.entry(span).or_default();
         test_names.extend(test_names_incoming);
     }
+    span_test_maps
+        .diagnostic
+        .extend(span_test_maps_incoming.diagnostic);
+    span_test_maps
+        .post_assertion
+        .extend(span_test_maps_incoming.post_assertion);
+    span_test_maps
+        .statement_kind
+        .extend(span_test_maps_incoming.statement_kind);
+}
+
+/// Removes from `test_set` and `span_test_maps` the tests with more than `max` statement
+/// candidates. Such tests usually consist of generated code.
+fn skip_large_tests(
+    context: &LightContext,
+    source_file: &SourceFile,
+    max: usize,
+    test_set: &mut TestSet,
+    span_test_maps: &mut SpanTestMaps,
+) -> Result<()> {
+    let mut n_statements = BTreeMap::<&str, usize>::new();
+    for test_names in span_test_maps.statement.values() {
+        for test_name in test_names {
+            *n_statements.entry(test_name).or_default() += 1;
+        }
+    }
+
+    let large_tests = n_statements
+        .into_iter()
+        .filter(|&(_, n)| n > max)
+        .map(|(test_name, n)| (test_name.to_owned(), n))
+        .collect::<Vec<_>>();
+
+    if large_tests.is_empty() {
+        return Ok(());
+    }
+
+    for (test_name, n) in &large_tests {
+        source_warn(
+            context,
+            Warning::TestSkipped,
+            source_file,
+            &format!(
+                "Skipping test `{test_name}`, which has {n} statements (more than \
+                 `max_statements_per_test = {max}`)",
+            ),
+            WarnFlags::empty(),
+        )?;
+        test_set.remove(test_name);
+    }
+
+    for span_test_map in [
+        &mut span_test_maps.statement,
+        &mut span_test_maps.method_call,
+    ] {
+        span_test_map.retain(|_, test_names| {
+            test_names.retain(|test_name| {
+                !large_tests
+                    .iter()
+                    .any(|(large_test_name, _)| large_test_name == test_name)
+            });
+            !test_names.is_empty()
+        });
+    }
+
+    Ok(())
 }
 
 macro_rules! check_config {
@@ -501,6 +704,42 @@ macro_rules! check_config {
     };
 }
 
+/// Returns `builtins` without the patterns in `removed`, followed by the patterns in `extra`
+fn builtins(
+    name: &str,
+    option: &str,
+    builtins: &[&str],
+    extra: &[String],
+    removed: &[String],
+) -> Result<Vec<String>> {
+    for pattern in removed {
+        ensure!(
+            builtins.contains(&pattern.as_str()),
+            "`{name}.{option}` contains `{pattern}`, which is not built into the {name} framework"
+        );
+    }
+    Ok(builtins
+        .iter()
+        .filter(|&&builtin| !removed.iter().any(|pattern| pattern == builtin))
+        .map(ToString::to_string)
+        .chain(extra.iter().cloned())
+        .collect())
+}
+
+macro_rules! builtins {
+    ($T:ty, $overrides:expr, $name:expr, $x:ident) => {
+        paste! {
+            builtins(
+                &$name,
+                stringify!([< removed_ignored_ $x:snake s >]),
+                $T::[< IGNORED_ $x:snake:upper S>].unwrap_or_default(),
+                $overrides.[< extra_ignored_ $x:snake s >],
+                $overrides.[< removed_ignored_ $x:snake s >],
+            )
+        }
+    };
+}
+
 impl<T: ParseLow> ParseAdapter<T> {
     fn compile_config(context: &LightContext, config: &config::Toml) -> Result<config::Compiled> {
         let name = T::name();
@@ -509,21 +748,11 @@ impl<T: ParseLow> ParseAdapter<T> {
         check_config!(T, context, config, name, macro);
         check_config!(T, context, config, name, method);
 
-        let ignored_functions = T::IGNORED_FUNCTIONS
-            .unwrap_or_default()
-            .iter()
-            .map(ToString::to_string)
-            .collect::<Vec<_>>();
-        let ignored_macros = T::IGNORED_MACROS
-            .unwrap_or_default()
-            .iter()
-            .map(ToString::to_string)
-            .collect::<Vec<_>>();
-        let ignored_methods = T::IGNORED_METHODS
-            .unwrap_or_default()
-            .iter()
-            .map(ToString::to_string)
-            .collect::<Vec<_>>();
+        let overrides = T::ignored_overrides(config);
+
+        let ignored_functions = builtins!(T, overrides, name, function)?;
+        let ignored_macros = builtins!(T, overrides, name, macro)?;
+        let ignored_methods = builtins!(T, overrides, name, method)?;
 
         let mut builtins = config::Toml {
             ignored_functions,
@@ -537,3 +766,47 @@ impl<T: ParseLow> ParseAdapter<T> {
         builtins.compile()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ParseCache;
+    use anyhow::anyhow;
+    use std::{cell::Cell, path::Path, rc::Rc};
+
+    #[test]
+    fn parse_cache_parses_each_contents_once() {
+        let parse_cache = ParseCache::default();
+        let n_parses = Cell::new(0);
+        let parse = |contents: &str| {
+            n_parses.set(n_parses.get() + 1);
+            if contents.is_empty() {
+                Err(anyhow!("Empty"))
+            } else {
+                Ok(contents.len())
+            }
+        };
+        let a = Path::new("a");
+        let b = Path::new("b");
+
+        let x = parse_cache.parse(a, "x", parse).unwrap();
+        let y = parse_cache.parse(a, "x", parse).unwrap();
+        assert!(Rc::ptr_eq(&x, &y));
+        assert_eq!(1, n_parses.get());
+
+        // smoelius: A file whose contents change replaces its entry.
+        assert_eq!(2, *parse_cache.parse(a, "xy", parse).unwrap());
+        assert_eq!(2, n_parses.get());
+        assert_eq!(1, parse_cache.files.borrow().len());
+        assert_eq!(1, *parse_cache.parse(a, "x", parse).unwrap());
+        assert_eq!(3, n_parses.get());
+
+        assert_eq!(1, *parse_cache.parse(b, "x", parse).unwrap());
+        assert_eq!(4, n_parses.get());
+        assert_eq!(2, parse_cache.files.borrow().len());
+
+        // smoelius: Failures are not cached.
+        assert!(parse_cache.parse(a, "", parse).is_err());
+        assert!(parse_cache.parse(a, "", parse).is_err());
+        assert_eq!(6, n_parses.get());
+    }
+}
@@ -2,10 +2,10 @@ use super::{rust, ts, OutputAccessors, OutputStrippedOfAnsiScapes, RunHigh};
 use anyhow::{anyhow, Error, Result};
 use assert_cmd::output::OutputError;
 use bstr::{io::BufReadExt, BStr};
-use log::debug;
 use necessist_core::{
-    framework::Postprocess, source_warn, util, LightContext, SourceFile, Span, WarnFlags, Warning,
-    __Rewriter as Rewriter,
+    __Rewriter as Rewriter, cause, dispatch,
+    framework::{Postprocess, RemovalStrategy},
+    source_warn, util, Exec, LightContext, LineColumn, SourceFile, Span, WarnFlags, Warning,
 };
 use std::{
     cell::RefCell,
@@ -16,7 +16,8 @@ use std::{
     process::{Command, ExitStatus as StdExitStatus, Output},
     rc::Rc,
 };
-use subprocess::{Exec, ExitStatus, NullFile, Redirection};
+use subprocess::{ExitStatus, NullFile, Redirection};
+use tracing::debug;
 
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
@@ -28,7 +29,17 @@ pub type ProcessLines = (bool, Box<dyn Fn(&str) -> bool>);
 
 pub trait RunLow {
     const REQUIRES_NODE_MODULES: bool = false;
+    const REMOVAL_STRATEGY: RemovalStrategy = RemovalStrategy::Guard;
     fn command_to_run_source_file(&self, context: &LightContext, source_file: &Path) -> Command;
+    /// Arguments to append to the command returned by `command_to_run_source_file` after the
+    /// user's arguments
+    fn final_args_to_run_source_file(
+        &self,
+        _context: &LightContext,
+        _source_file: &Path,
+    ) -> Vec<String> {
+        Vec::new()
+    }
     fn instrument_source_file(
         &self,
         context: &LightContext,
@@ -37,6 +48,13 @@ pub trait RunLow {
         n_instrumentable_statements: usize,
     ) -> Result<()>;
     fn statement_prefix_and_suffix(&self, span: &Span) -> Result<(String, String)>;
+    fn unused_tolerance_insertions(
+        &self,
+        _context: &LightContext,
+        _source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        Ok(Vec::new())
+    }
     fn command_to_build_source_file(&self, context: &LightContext, source_file: &Path) -> Command;
     fn command_to_build_test(
         &self,
@@ -50,14 +68,36 @@ pub trait RunLow {
         test_name: &str,
         span: &Span,
     ) -> (Command, Vec<String>, Option<ProcessLines>);
+    /// Like `command_to_run_test`, but given the standard output of the command returned by
+    /// `command_to_build_test`, e.g., so that a built test binary can be run directly. Returns
+    /// `None` if `command_to_run_test` should be used instead. Unlike with `command_to_run_test`,
+    /// the user's arguments are not appended to the returned command.
+    fn command_to_run_built_test(
+        &self,
+        _context: &LightContext,
+        _test_name: &str,
+        _span: &Span,
+        _build_stdout: &[u8],
+    ) -> Option<(Command, Vec<String>, Option<ProcessLines>)> {
+        None
+    }
 }
 
 impl<T: RunLow> RunLow for Rc<RefCell<T>> {
     const REQUIRES_NODE_MODULES: bool = T::REQUIRES_NODE_MODULES;
+    const REMOVAL_STRATEGY: RemovalStrategy = T::REMOVAL_STRATEGY;
     fn command_to_run_source_file(&self, context: &LightContext, source_file: &Path) -> Command {
         self.borrow()
             .command_to_run_source_file(context, source_file)
     }
+    fn final_args_to_run_source_file(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Vec<String> {
+        self.borrow()
+            .final_args_to_run_source_file(context, source_file)
+    }
     fn instrument_source_file(
         &self,
         context: &LightContext,
@@ -75,6 +115,14 @@ impl<T: RunLow> RunLow for Rc<RefCell<T>> {
     fn statement_prefix_and_suffix(&self, span: &Span) -> Result<(String, String)> {
         self.borrow().statement_prefix_and_suffix(span)
     }
+    fn unused_tolerance_insertions(
+        &self,
+        context: &LightContext,
+        source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        self.borrow()
+            .unused_tolerance_insertions(context, source_file)
+    }
     fn command_to_build_source_file(&self, context: &LightContext, source_file: &Path) -> Command {
         self.borrow()
             .command_to_build_source_file(context, source_file)
@@ -96,12 +144,22 @@ impl<T: RunLow> RunLow for Rc<RefCell<T>> {
     ) -> (Command, Vec<String>, Option<ProcessLines>) {
         self.borrow().command_to_run_test(context, test_name, span)
     }
+    fn command_to_run_built_test(
+        &self,
+        context: &LightContext,
+        test_name: &str,
+        span: &Span,
+        build_stdout: &[u8],
+    ) -> Option<(Command, Vec<String>, Option<ProcessLines>)> {
+        self.borrow()
+            .command_to_run_built_test(context, test_name, span, build_stdout)
+    }
 }
 
 pub struct RunAdapter<T>(pub T);
 
-impl<T: RunLow> RunHigh for RunAdapter<T> {
-    fn dry_run(&self, context: &LightContext, source_file: &Path) -> Result<()> {
+impl<T: RunLow> RunAdapter<T> {
+    fn command_to_dry_run(&self, context: &LightContext, source_file: &Path) -> Result<Command> {
         // smoelius: `REQUIRES_NODE_MODULES` is a hack. But at present, I don't know how it should
         // be generalized.
         if T::REQUIRES_NODE_MODULES && context.root.join("package.json").try_exists()? {
@@ -110,10 +168,46 @@ impl<T: RunLow> RunHigh for RunAdapter<T> {
 
         let mut command = self.0.command_to_run_source_file(context, source_file);
         command.args(&context.opts.args);
+        command.args(self.0.final_args_to_run_source_file(context, source_file));
+
+        Ok(command)
+    }
+}
+
+impl<T: RunLow> RunHigh for RunAdapter<T> {
+    fn removal_strategy(&self) -> RemovalStrategy {
+        T::REMOVAL_STRATEGY
+    }
+
+    fn dry_run(&self, context: &LightContext, source_file: &Path) -> Result<()> {
+        let mut command = self.command_to_dry_run(context, source_file)?;
 
         debug!("{:?}", command);
 
-        let output = command.output_stripped_of_ansi_escapes()?;
+        let output = command.output_stripped_of_ansi_escapes(context)?;
+        if !output.status().success() {
+            return Err(output.into());
+        }
+        Ok(())
+    }
+
+    fn dry_run_command(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Result<Option<Command>> {
+        self.command_to_dry_run(context, source_file).map(Some)
+    }
+
+    fn check_dry_run_output(
+        &self,
+        _context: &LightContext,
+        _source_file: &Path,
+        output: Output,
+    ) -> Result<()> {
+        // smoelius: The output was stripped of ANSI escapes and capped as it was read (see
+        // `truncation::output`).
+        let output = OutputError::new(output);
         if !output.status().success() {
             return Err(output.into());
         }
@@ -135,44 +229,76 @@ impl<T: RunLow> RunHigh for RunAdapter<T> {
         self.0.statement_prefix_and_suffix(span)
     }
 
+    fn unused_tolerance_insertions(
+        &self,
+        context: &LightContext,
+        source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        self.0.unused_tolerance_insertions(context, source_file)
+    }
+
     fn build_source_file(&self, context: &LightContext, source_file: &Path) -> Result<()> {
         let mut command = self.0.command_to_build_source_file(context, source_file);
         command.args(&context.opts.args);
 
         debug!("{:?}", command);
 
-        let output = command.output_stripped_of_ansi_escapes()?;
+        let output = command.output_stripped_of_ansi_escapes(context)?;
         if !output.status().success() {
             return Err(output.into());
         }
         Ok(())
     }
 
+    #[allow(clippy::too_many_lines)]
     fn exec(
         &self,
         context: &LightContext,
         test_name: &str,
         span: &Span,
     ) -> Result<Option<(Exec, Option<Box<Postprocess>>)>> {
-        {
+        let build_stdout = {
             let mut command = self.0.command_to_build_test(context, test_name, span);
             command.args(&context.opts.args);
 
             debug!("{:?}", command);
 
-            let output = command.output_stripped_of_ansi_escapes()?;
-            if !output.status().success() {
-                debug!("{}", output);
-                return Ok(None);
+            // smoelius: When explaining a span, the build output is shown as is.
+            if context.opts.explaining() {
+                let mut dispatched = dispatch::command(context, &command)?;
+                let status = dispatched.as_mut().unwrap_or(&mut command).status()?;
+                if !status.success() {
+                    return Ok(None);
+                }
+                None
+            } else {
+                let output = command.output_stripped_of_ansi_escapes(context)?;
+                if !output.status().success() {
+                    debug!("{}", output);
+                    return Ok(None);
+                }
+                Some(output.stdout().to_vec())
             }
-        }
+        };
 
-        let (mut command, final_args, init_f_test) =
-            self.0.command_to_run_test(context, test_name, span);
-        command.args(&context.opts.args);
+        let (mut command, final_args, init_f_test) = if let Some(run) =
+            build_stdout.and_then(|build_stdout| {
+                self.0
+                    .command_to_run_built_test(context, test_name, span, &build_stdout)
+            }) {
+            run
+        } else {
+            let (mut command, final_args, init_f_test) =
+                self.0.command_to_run_test(context, test_name, span);
+            command.args(&context.opts.args);
+            (command, final_args, init_f_test)
+        };
         command.args(final_args);
 
         let mut exec = util::exec_from_command(&command);
+        if context.opts.explaining() {
+            return Ok(Some((exec, None)));
+        }
         if init_f_test.is_some() {
             exec = exec.stdout(Redirection::Pipe);
             exec = exec.stderr(Redirection::Pipe);
@@ -193,6 +319,7 @@ impl<T: RunLow> RunHigh for RunAdapter<T> {
                         .take()
                         .ok_or_else(|| anyhow!("Failed to get stdout"))?;
                     let stdout = read_file_to_end(stdout_file)?;
+                    cause::observe(&stdout);
                     let run = stdout.byte_lines().try_fold(init, |prev, result| {
                         let buf = result?;
                         let line = match std::str::from_utf8(&buf) {
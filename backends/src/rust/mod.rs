@@ -1,24 +1,33 @@
 use super::{
-    AbstractTypes, GenericVisitor, MaybeNamed, Named, ParseLow, ProcessLines, RunLow, Spanned,
-    WalkDirResult,
+    bazel, AbstractTypes, GenericVisitor, MaybeNamed, Named, ParseCache, ParseLow, ProcessLines,
+    RunLow, Spanned, WalkDirResult,
 };
 use anyhow::Result;
-use cargo_metadata::{Metadata, Package, TargetKind};
+use cargo_metadata::{Message, Metadata, Package, TargetKind};
 use necessist_core::{
+    config,
     framework::{SpanTestMaps, TestSet},
-    LightContext, SourceFile, Span, ToInternalSpan, __Rewriter as Rewriter,
+    util, LightContext, LineColumn, SourceFile, Span, ToInternalSpan, __Rewriter as Rewriter,
 };
 use once_cell::sync::{Lazy, OnceCell};
 use quote::ToTokens;
 use std::{
     cell::RefCell,
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     ffi::OsStr,
     fs::read_to_string,
     path::{Path, PathBuf},
     process::Command,
+    rc::Rc,
     sync::RwLock,
 };
+use toml_edit::{DocumentMut, Item};
+
+mod module_tree;
+
+mod rustflags;
+
+mod shared_module;
 
 mod storage;
 use storage::{cached_source_file_package, Storage};
@@ -27,14 +36,20 @@ mod try_insert;
 use try_insert::TryInsert;
 
 mod visitor;
-use visitor::{collect_local_functions, visit};
+use visitor::{collect_local_functions, test_fn_starts, visit};
 
 #[derive(Debug)]
 pub struct Rust {
+    parse_cache: ParseCache<syn::File>,
     source_file_fs_module_path_cache: BTreeMap<PathBuf, Vec<String>>,
     source_file_package_cache: BTreeMap<PathBuf, Package>,
     directory_metadata_cache: BTreeMap<PathBuf, Metadata>,
     source_file_flags_cache: BTreeMap<PathBuf, Vec<String>>,
+    // smoelius: Maps source files whose test targets have `harness = false` to those targets'
+    // names.
+    source_file_custom_harness_cache: BTreeMap<PathBuf, Option<String>>,
+    // smoelius: Maps targets' crate roots to their module trees.
+    target_module_paths_cache: BTreeMap<PathBuf, BTreeMap<PathBuf, Vec<String>>>,
 }
 
 impl Rust {
@@ -48,10 +63,13 @@ impl Rust {
 
     pub fn new() -> Self {
         Self {
+            parse_cache: ParseCache::default(),
             source_file_fs_module_path_cache: BTreeMap::new(),
             source_file_package_cache: BTreeMap::new(),
             directory_metadata_cache: BTreeMap::new(),
             source_file_flags_cache: BTreeMap::new(),
+            source_file_custom_harness_cache: BTreeMap::new(),
+            target_module_paths_cache: BTreeMap::new(),
         }
     }
 }
@@ -59,12 +77,14 @@ impl Rust {
 #[derive(Clone, Copy)]
 pub struct Test<'ast> {
     test_path_id: usize,
-    item_fn: &'ast syn::ItemFn,
+    stmts: &'ast [syn::Stmt],
 }
 
 impl<'ast> Test<'ast> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         storage: &RefCell<Storage>,
+        parse_cache: &ParseCache<syn::File>,
         source_file_fs_module_path_cache: &mut BTreeMap<PathBuf, Vec<String>>,
         source_file_package_cache: &mut BTreeMap<PathBuf, Package>,
         directory_metadata_map: &mut BTreeMap<PathBuf, Metadata>,
@@ -75,6 +95,7 @@ impl<'ast> Test<'ast> {
         // `GenericVisitor` from walking the test.
         let test_name = item_fn.sig.ident.to_string();
         let result = storage.borrow_mut().test_path(
+            parse_cache,
             source_file_fs_module_path_cache,
             source_file_package_cache,
             directory_metadata_map,
@@ -96,9 +117,19 @@ impl<'ast> Test<'ast> {
         let test_path_id = reserve_test_path_id(test_path);
         Some(Self {
             test_path_id,
-            item_fn,
+            stmts: &item_fn.block.stmts,
         })
     }
+
+    /// Returns a test for a Criterion benchmark, whose name is the benchmark's ID. `stmts` are the
+    /// statements of the closure passed to `bench_function`, if its body is a block.
+    fn criterion(id: String, stmts: &'ast [syn::Stmt]) -> Self {
+        let test_path_id = reserve_test_path_id(vec![id]);
+        Self {
+            test_path_id,
+            stmts,
+        }
+    }
 }
 
 // smoelius: `TEST_PATH_ID_MAP` and `TEST_PATHS` cannot go in `Storage` because they are used by
@@ -179,7 +210,7 @@ pub struct Types;
 
 impl AbstractTypes for Types {
     type Storage<'ast> = Storage<'ast>;
-    type File = syn::File;
+    type File = Rc<syn::File>;
     type Test<'ast> = Test<'ast>;
     // smoelius: A "local function" is actually a `syn::Block` because it makes handling both
     // `syn:ItemFn` and `syn::ImplItemFn` easier.
@@ -390,6 +421,10 @@ impl ParseLow for Rust {
         "unwrap_err",
     ]);
 
+    fn ignored_overrides(config: &config::Toml) -> config::IgnoredOverrides<'_> {
+        config.rust.ignored_overrides()
+    }
+
     fn walk_dir(&self, root: &Path) -> Box<dyn Iterator<Item = WalkDirResult>> {
         Box::new(
             walkdir::WalkDir::new(root)
@@ -402,12 +437,67 @@ impl ParseLow for Rust {
         )
     }
 
+    // smoelius: A source file's module path is determined by the files above it in its target's
+    // module tree. And the helpers in a module shared by integration tests are attributed to the
+    // tests, in the other files of the targets that include the module, that call them. So those
+    // files, and the manifest listing the targets, affect the source file's candidates.
+    fn dependencies(&mut self, context: &LightContext, source_file: &Path) -> Result<Vec<PathBuf>> {
+        if bazel::enabled(context) {
+            return Ok(Vec::new());
+        }
+
+        let package = cached_source_file_package(
+            &mut self.source_file_package_cache,
+            &mut self.directory_metadata_cache,
+            source_file,
+        )?;
+
+        let mut dependencies = BTreeSet::from([package.manifest_path.clone().into_std_path_buf()]);
+        for target in &package.targets {
+            if target.kind.contains(&TargetKind::CustomBuild) {
+                continue;
+            }
+            let src_path = target.src_path.as_std_path();
+            let module_paths = self
+                .target_module_paths_cache
+                .entry(src_path.to_path_buf())
+                .or_insert_with(|| module_tree::module_paths(&self.parse_cache, src_path));
+            let Some(module_path) = module_paths.get(source_file) else {
+                continue;
+            };
+            let shared_module = target.kind == [TargetKind::Test] && src_path != source_file;
+            dependencies.extend(
+                module_paths
+                    .iter()
+                    .filter(|(_, other)| shared_module || module_path.starts_with(other))
+                    .map(|(path, _)| path.clone()),
+            );
+        }
+        dependencies.remove(source_file);
+
+        Ok(dependencies.into_iter().collect())
+    }
+
+    // smoelius: `visit` populates the caches used to build and run a source file's tests. So a
+    // source file whose candidates are reused must populate them here.
+    fn reuse_source_file(&mut self, context: &LightContext, source_file: &Path) -> Result<()> {
+        if bazel::enabled(context) {
+            self.source_file_fs_module_path_cache
+                .entry(source_file.to_path_buf())
+                .or_default();
+            return Ok(());
+        }
+        let _: Option<&String> = self.cached_source_file_custom_harness(source_file)?;
+        let _: &Vec<String> = self.cached_source_file_flags(source_file)?;
+        Ok(())
+    }
+
     fn parse_source_file(
         &self,
         source_file: &Path,
     ) -> Result<<Self::Types as AbstractTypes>::File> {
         let content = read_to_string(source_file)?;
-        syn::parse_file(&content).map_err(Into::into)
+        self.parse_cache.parse(source_file, &content, parse_file)
     }
 
     fn storage_from_file<'ast>(
@@ -438,12 +528,7 @@ impl ParseLow for Rust {
         _storage: &RefCell<<Self::Types as AbstractTypes>::Storage<'ast>>,
         test: <Self::Types as AbstractTypes>::Test<'ast>,
     ) -> Vec<<Self::Types as AbstractTypes>::Statement<'ast>> {
-        test.item_fn
-            .block
-            .stmts
-            .iter()
-            .map(|stmt| stmt as _)
-            .collect::<Vec<_>>()
+        test.stmts.iter().map(|stmt| stmt as _).collect::<Vec<_>>()
     }
 
     fn statement_is_removable(
@@ -605,7 +690,32 @@ include!(concat!(env!("OUT_DIR"), "/expression_with_block.rs"));
 
 impl RunLow for Rust {
     fn command_to_run_source_file(&self, context: &LightContext, source_file: &Path) -> Command {
-        self.test_command(context, source_file)
+        if bazel::enabled(context) {
+            return Self::bazel_test_command(context, source_file);
+        }
+        self.custom_harness_command(context, source_file)
+            .unwrap_or_else(|| self.test_command(context, source_file))
+    }
+
+    // smoelius: `bazel_test_command` passes `--include-ignored` itself, and a custom harness need
+    // not accept it.
+    fn final_args_to_run_source_file(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Vec<String> {
+        if bazel::enabled(context)
+            || self.custom_harness_command(context, source_file).is_some()
+            || !self.include_ignored(context, source_file)
+        {
+            return Vec::new();
+        }
+        // smoelius: The user's arguments may already include a `--`.
+        if context.opts.args.iter().any(|arg| arg == "--") {
+            vec!["--include-ignored".to_owned()]
+        } else {
+            vec!["--".to_owned(), "--include-ignored".to_owned()]
+        }
     }
 
     fn instrument_source_file(
@@ -628,7 +738,26 @@ impl RunLow for Rust {
         ))
     }
 
+    // smoelius: `#[allow(unused)]` is added to each test function. It takes precedence over a
+    // crate-level `#![deny(warnings)]`.
+    fn unused_tolerance_insertions(
+        &self,
+        context: &LightContext,
+        source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        let file = self
+            .parse_cache
+            .parse(source_file, source_file.contents(), parse_file)?;
+        Ok(test_fn_starts(&file, context.config.rust.include_benches)
+            .into_iter()
+            .map(|line_column| (line_column, "#[allow(unused)] ".to_owned()))
+            .collect())
+    }
+
     fn command_to_build_source_file(&self, context: &LightContext, source_file: &Path) -> Command {
+        if bazel::enabled(context) {
+            return bazel::build_command(context, source_file);
+        }
         let mut command = self.test_command(context, source_file);
         command.arg("--no-run");
         command
@@ -640,8 +769,16 @@ impl RunLow for Rust {
         _test_name: &str,
         span: &Span,
     ) -> Command {
+        if bazel::enabled(context) {
+            return bazel::build_command(context, &span.source_file);
+        }
         let mut command = self.test_command(context, &span.source_file);
         command.arg("--no-run");
+        // smoelius: The JSON messages name the test binary, which `command_to_run_built_test` then
+        // runs directly. When explaining a span, the build output is shown as is.
+        if !context.opts.explaining() {
+            command.arg("--message-format=json-render-diagnostics");
+        }
         command
     }
 
@@ -651,29 +788,271 @@ impl RunLow for Rust {
         test_name: &str,
         span: &Span,
     ) -> (Command, Vec<String>, Option<ProcessLines>) {
+        // smoelius: rules_rust passes `--test_filter` to the test binary as a filter argument.
+        if bazel::enabled(context) {
+            return (
+                Self::bazel_test_command(context, &span.source_file),
+                vec![
+                    format!("--test_filter={test_name}"),
+                    "--test_arg=--exact".to_owned(),
+                ],
+                Some((false, Box::new(|line| line == "running 1 test"))),
+            );
+        }
+        // smoelius: A custom harness cannot be asked to run just one test. So the whole target is
+        // run, and its exit status alone determines the outcome.
+        if let Some(command) = self.custom_harness_command(context, &span.source_file) {
+            return (command, Vec::new(), None);
+        }
+        // smoelius: Run without `--bench`, a Criterion benchmark runs once in "test mode" and
+        // prints `Testing <ID>`. Criterion also accepts `--exact`.
+        let is_running: Box<dyn Fn(&str) -> bool> = if self.is_bench_target(&span.source_file) {
+            let testing = format!("Testing {test_name}");
+            Box::new(move |line| line == "running 1 test" || line == testing)
+        } else {
+            Box::new(|line| line == "running 1 test")
+        };
+        let mut final_args = vec!["--".to_owned(), "--exact".to_owned()];
+        if self.include_ignored(context, &span.source_file) {
+            final_args.push("--include-ignored".to_owned());
+        }
+        final_args.push(test_name.to_owned());
         (
             self.test_command(context, &span.source_file),
-            vec!["--".to_owned(), "--exact".to_owned(), test_name.to_owned()],
-            Some((false, Box::new(|line| line == "running 1 test"))),
+            final_args,
+            Some((false, is_running)),
         )
     }
+
+    // smoelius: Running the test binary directly avoids a second `cargo` invocation per
+    // candidate, along with its locking and fingerprinting.
+    fn command_to_run_built_test(
+        &self,
+        context: &LightContext,
+        test_name: &str,
+        span: &Span,
+        build_stdout: &[u8],
+    ) -> Option<(Command, Vec<String>, Option<ProcessLines>)> {
+        // smoelius: With `--remote` or `--docker`, the binary's path is on another machine.
+        if context.opts.remote.is_some() || context.opts.docker.is_some() {
+            return None;
+        }
+        let source_file: &Path = &span.source_file;
+        if self
+            .source_file_custom_harness_cache
+            .get(source_file)
+            .is_some_and(Option::is_some)
+        {
+            return None;
+        }
+        let executables = Message::parse_stream(build_stdout)
+            .filter_map(|message| match message.ok()? {
+                Message::CompilerArtifact(artifact) if artifact.profile.test => artifact.executable,
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // smoelius: A unit test's source file can belong to several targets (e.g., a library and
+        // a binary), in which case it is not clear which binary to run.
+        let [executable] = executables.as_slice() else {
+            return None;
+        };
+        let package = self.source_file_package_cache.get(source_file)?;
+        let package_dir = package.manifest_path.parent()?;
+        let mut command = Command::new(executable);
+        // smoelius: `cargo test` runs a test binary from its package's directory, and sets these
+        // environment variables, which tests sometimes read at runtime.
+        command.current_dir(package_dir);
+        command.env("CARGO_MANIFEST_DIR", package_dir);
+        command.env("CARGO_PKG_NAME", &package.name);
+        command.env("CARGO_PKG_VERSION", package.version.to_string());
+        if let Some(test_threads) = context.config.rust.test_threads {
+            command.env("RUST_TEST_THREADS", test_threads.to_string());
+        }
+        // smoelius: The user's arguments following `--` are ones `cargo test` would pass to the
+        // test binary.
+        command.args(
+            context
+                .opts
+                .args
+                .iter()
+                .skip_while(|arg| *arg != "--")
+                .skip(1),
+        );
+        let mut final_args = vec!["--exact".to_owned()];
+        if self.include_ignored(context, source_file) {
+            final_args.push("--include-ignored".to_owned());
+        }
+        final_args.push(test_name.to_owned());
+        Some((
+            command,
+            final_args,
+            Some((false, Box::new(|line| line == "running 1 test"))),
+        ))
+    }
 }
 
 impl Rust {
-    fn test_command(&self, _context: &LightContext, source_file: &Path) -> Command {
-        #[allow(clippy::expect_used)]
+    fn test_command(&self, context: &LightContext, source_file: &Path) -> Command {
+        #[allow(clippy::panic)]
         let flags = self
             .source_file_flags_cache
             .get(source_file)
-            .expect("Flags are not cached");
+            .unwrap_or_else(|| panic!("Flags are not cached for {source_file:?}"));
         let mut command = Command::new("cargo");
         command.arg("test");
         command.args(flags);
+        command.args(&context.config.rust.features);
+        if context.config.rust.cap_lints {
+            rustflags::cap_lints(&mut command);
+        }
+        // smoelius: The number of test threads is set through the environment rather than with
+        // `--test-threads`, which would have to follow a `--` that the user's arguments might
+        // also contain.
+        if let Some(test_threads) = context.config.rust.test_threads {
+            command.env("RUST_TEST_THREADS", test_threads.to_string());
+        }
         command
     }
 
+    fn is_bench_target(&self, source_file: &Path) -> bool {
+        self.source_file_flags_cache
+            .get(source_file)
+            .is_some_and(|flags| flags.iter().any(|flag| flag == "--bench"))
+    }
+
+    /// Returns true if `--include-ignored` should be passed to `source_file`'s test binary.
+    /// Criterion benchmarks are excluded, as they do not use libtest.
+    fn include_ignored(&self, context: &LightContext, source_file: &Path) -> bool {
+        context.opts.include_ignored && !self.is_bench_target(source_file)
+    }
+
+    fn bazel_test_command(context: &LightContext, source_file: &Path) -> Command {
+        let mut command = bazel::test_command(context, source_file);
+        if let Some(test_threads) = context.config.rust.test_threads {
+            command.arg(format!("--test_env=RUST_TEST_THREADS={test_threads}"));
+        }
+        if context.opts.include_ignored {
+            command.arg("--test_arg=--include-ignored");
+        }
+        command
+    }
+
+    fn custom_harness_command(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Option<Command> {
+        let target = self
+            .source_file_custom_harness_cache
+            .get(source_file)?
+            .as_ref()?;
+        let (program, args) = context
+            .config
+            .rust
+            .custom_harness_commands
+            .get(target)?
+            .split_first()?;
+        let package = self.source_file_package_cache.get(source_file)?;
+        let mut command = Command::new(program);
+        command.args(args);
+        command.current_dir(package.manifest_path.parent()?);
+        Some(command)
+    }
+
+    /// Returns the name of `source_file`'s test target if the target has `harness = false`
+    fn cached_source_file_custom_harness(&mut self, source_file: &Path) -> Result<Option<&String>> {
+        self.source_file_custom_harness_cache
+            .entry(source_file.to_path_buf())
+            .or_try_insert_with(|| {
+                let package = cached_source_file_package(
+                    &mut self.source_file_package_cache,
+                    &mut self.directory_metadata_cache,
+                    source_file,
+                )?;
+
+                let Some(name) = source_file_target(package, source_file, &TargetKind::Test) else {
+                    return Ok(None);
+                };
+
+                let manifest = read_to_string(&package.manifest_path)?.parse::<DocumentMut>()?;
+
+                let harness_disabled = manifest
+                    .get("test")
+                    .and_then(Item::as_array_of_tables)
+                    .into_iter()
+                    .flatten()
+                    .any(|test| {
+                        test.get("name").and_then(Item::as_str) == Some(name)
+                            && test.get("harness").and_then(Item::as_bool) == Some(false)
+                    });
+
+                Ok(harness_disabled.then(|| name.clone()))
+            })
+            .map(|value| value.as_ref())
+    }
+
+    /// Returns the names and crate roots of the test targets that include `source_file` as a
+    /// module other than their crate root, e.g., the targets that declare `mod common;` for
+    /// `tests/common/mod.rs`
+    fn including_test_targets(&mut self, source_file: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let package = cached_source_file_package(
+            &mut self.source_file_package_cache,
+            &mut self.directory_metadata_cache,
+            source_file,
+        )?;
+
+        let mut including_test_targets = Vec::new();
+        for target in &package.targets {
+            let src_path = target.src_path.as_std_path();
+            if target.kind != [TargetKind::Test] || src_path == source_file {
+                continue;
+            }
+            let module_paths = self
+                .target_module_paths_cache
+                .entry(src_path.to_path_buf())
+                .or_insert_with(|| module_tree::module_paths(&self.parse_cache, src_path));
+            if module_paths.contains_key(source_file) {
+                including_test_targets.push((target.name.clone(), src_path.to_path_buf()));
+            }
+        }
+
+        Ok(including_test_targets)
+    }
+
+    /// Returns the names of the tests that call `helpers`, keyed by helper name, across the test
+    /// targets that include `source_file` (see [`Self::including_test_targets`])
+    fn shared_module_callers(
+        &mut self,
+        source_file: &Path,
+        helpers: &BTreeSet<String>,
+        include_should_panic_tests: bool,
+        include_ignored: bool,
+    ) -> Result<BTreeMap<String, BTreeSet<String>>> {
+        let mut callers = BTreeMap::<String, BTreeSet<String>>::new();
+        for (_, src_path) in self.including_test_targets(source_file)? {
+            let module_paths = &self.target_module_paths_cache[&src_path];
+            for (helper, test_names) in shared_module::callers(
+                &self.parse_cache,
+                module_paths,
+                source_file,
+                helpers,
+                include_should_panic_tests,
+                include_ignored,
+            ) {
+                callers.entry(helper).or_default().extend(test_names);
+            }
+        }
+        Ok(callers)
+    }
+
     #[cfg_attr(dylint_lib = "general", allow(non_local_effect_before_error_return))]
     fn cached_source_file_flags(&mut self, source_file: &Path) -> Result<&Vec<String>> {
+        if self.source_file_flags_cache.contains_key(source_file) {
+            return Ok(&self.source_file_flags_cache[source_file]);
+        }
+
+        let including_test_targets = self.including_test_targets(source_file)?;
+
         self.source_file_flags_cache
             .entry(source_file.to_path_buf())
             .or_try_insert_with(|| {
@@ -688,8 +1067,18 @@ impl Rust {
                     package.manifest_path.as_str().to_owned(),
                 ];
 
-                if let Some(name) = source_file_test(package, source_file) {
+                if let Some(name) = source_file_target(package, source_file, &TargetKind::Test) {
                     flags.extend(["--test".to_owned(), name.clone()]);
+                } else if !including_test_targets.is_empty() {
+                    // smoelius: The file is a module shared by integration tests. Its candidates'
+                    // tests could belong to any of the targets that include it.
+                    for (name, _) in including_test_targets {
+                        flags.extend(["--test".to_owned(), name]);
+                    }
+                } else if let Some(name) =
+                    source_file_target(package, source_file, &TargetKind::Bench)
+                {
+                    flags.extend(["--bench".to_owned(), name.clone()]);
                 } else {
                     // smoelius: Failed to find a test target with this file name. Assume it is a
                     // unit test.
@@ -716,12 +1105,20 @@ impl Rust {
     }
 }
 
-fn source_file_test<'a>(package: &'a Package, source_file: &Path) -> Option<&'a String> {
+fn parse_file(contents: &str) -> Result<syn::File> {
+    syn::parse_file(contents).map_err(Into::into)
+}
+
+fn source_file_target<'a>(
+    package: &'a Package,
+    source_file: &Path,
+    kind: &TargetKind,
+) -> Option<&'a String> {
     if let &[name] = package
         .targets
         .iter()
         .filter_map(|target| {
-            if target.kind == [TargetKind::Test] && target.src_path == source_file {
+            if target.kind == [kind.clone()] && target.src_path == source_file {
                 Some(&target.name)
             } else {
                 None
@@ -749,7 +1146,7 @@ pub(crate) fn check_mtimes(context: &LightContext) -> Result<()> {
             .no_deps()
             .exec()
     })?;
-    let mtime_map = mtime_map(metadata.target_directory.as_std_path())?;
+    let mtime_map = util::mtimes(metadata.target_directory.as_std_path(), |_| false)?;
     let existing = MTIME_MAP.get_or_init(|| mtime_map.clone());
     for (path, mtime) in existing {
         assert_eq!(Some(mtime), mtime_map.get(path), "failed for {path:?}");
@@ -760,15 +1157,3 @@ pub(crate) fn check_mtimes(context: &LightContext) -> Result<()> {
     }
     Ok(())
 }
-
-fn mtime_map(target_directory: &Path) -> Result<MtimeMap> {
-    let mut mtime_map = MtimeMap::new();
-    for result in walkdir::WalkDir::new(target_directory) {
-        let entry = result?;
-        let path = entry.path();
-        let metadata = path.metadata()?;
-        let mtime = metadata.modified()?;
-        mtime_map.insert(path.to_path_buf(), mtime);
-    }
-    Ok(mtime_map)
-}
@@ -0,0 +1,190 @@
+//! Determines source files' module paths by walking a crate's module tree from its root, i.e., by
+//! following `mod` items as `rustc` would. Unlike inferring a module path from a file's location,
+//! this handles `#[path = ...]` attributes and out-of-line modules declared within inline ones.
+
+use super::{parse_file, ParseCache};
+use crate::utils;
+use std::{
+    collections::BTreeMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+use syn::{Expr, ExprLit, Item, ItemMod, Lit, Meta};
+
+/// Returns the module path of each file in the module tree rooted at `root`, which should be a
+/// target's crate root. Files that cannot be read or parsed are omitted, along with their
+/// submodules. If a file is reachable by more than one module path, the first one found is used.
+pub(super) fn module_paths(
+    parse_cache: &ParseCache<syn::File>,
+    root: &Path,
+) -> BTreeMap<PathBuf, Vec<String>> {
+    let mut module_paths = BTreeMap::new();
+    let dir = root.parent().map(Path::to_path_buf).unwrap_or_default();
+    visit_file(parse_cache, &mut module_paths, root, &dir, &mut Vec::new());
+    module_paths
+}
+
+/// Visits `path`, whose out-of-line submodules are looked up in `dir`
+fn visit_file(
+    parse_cache: &ParseCache<syn::File>,
+    module_paths: &mut BTreeMap<PathBuf, Vec<String>>,
+    path: &Path,
+    dir: &Path,
+    module_path: &mut Vec<String>,
+) {
+    if module_paths.contains_key(path) {
+        return;
+    }
+    let Ok(contents) = read_to_string(path) else {
+        return;
+    };
+    let Ok(file) = parse_cache.parse(path, &contents, parse_file) else {
+        return;
+    };
+    module_paths.insert(path.to_path_buf(), module_path.clone());
+    let file_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    visit_items(
+        parse_cache,
+        module_paths,
+        &file.items,
+        &file_dir,
+        dir,
+        module_path,
+    );
+}
+
+/// Visits `items`, whose out-of-line submodules are looked up in `dir`, or in `file_dir` if they
+/// have `#[path = ...]` attributes and `items` are not within an inline module
+fn visit_items(
+    parse_cache: &ParseCache<syn::File>,
+    module_paths: &mut BTreeMap<PathBuf, Vec<String>>,
+    items: &[Item],
+    file_dir: &Path,
+    dir: &Path,
+    module_path: &mut Vec<String>,
+) {
+    for item in items {
+        let Item::Mod(item_mod) = item else {
+            continue;
+        };
+        module_path.push(item_mod.ident.to_string());
+        let path_attr = path_attr(item_mod);
+        if let Some((_, items)) = &item_mod.content {
+            let dir = dir.join(path_attr.unwrap_or_else(|| item_mod.ident.to_string()));
+            // smoelius: Within an inline module, a `#[path = ...]` attribute is relative to the
+            // module's directory rather than to the file's.
+            visit_items(parse_cache, module_paths, items, &dir, &dir, module_path);
+        } else if let Some(path_attr) = path_attr {
+            // smoelius: A file loaded with a `#[path = ...]` attribute owns its directory, like a
+            // `mod.rs` file.
+            let path = utils::normalize_path(&file_dir.join(path_attr));
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            visit_file(parse_cache, module_paths, &path, &dir, module_path);
+        } else {
+            let ident = item_mod.ident.to_string();
+            let non_mod_rs = dir.join(format!("{ident}.rs"));
+            let mod_rs = dir.join(&ident).join("mod.rs");
+            if non_mod_rs.try_exists().unwrap_or(false) {
+                visit_file(
+                    parse_cache,
+                    module_paths,
+                    &non_mod_rs,
+                    &dir.join(&ident),
+                    module_path,
+                );
+            } else {
+                visit_file(
+                    parse_cache,
+                    module_paths,
+                    &mod_rs,
+                    &dir.join(&ident),
+                    module_path,
+                );
+            }
+        }
+        module_path.pop();
+    }
+}
+
+fn path_attr(item_mod: &ItemMod) -> Option<String> {
+    item_mod.attrs.iter().find_map(|attr| {
+        let Meta::NameValue(name_value) = &attr.meta else {
+            return None;
+        };
+        if !name_value.path.is_ident("path") {
+            return None;
+        }
+        let Expr::Lit(ExprLit {
+            lit: Lit::Str(lit_str),
+            ..
+        }) = &name_value.value
+        else {
+            return None;
+        };
+        Some(lit_str.value())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{module_paths, ParseCache};
+    use necessist_core::util;
+    use std::fs::{create_dir_all, write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn module_tree() {
+        let tempdir = tempdir().unwrap();
+        let src = tempdir.path().join("src");
+        for (path, contents) in [
+            (
+                "lib.rs",
+                "mod a; #[path = \"x/y.rs\"] mod z; mod inline { mod b; #[path = \"p.rs\"] mod q; \
+                 }",
+            ),
+            ("a.rs", "mod c; mod d;"),
+            ("a/c.rs", ""),
+            ("a/d/mod.rs", "#[path = \"../../shared.rs\"] mod shared;"),
+            ("shared.rs", ""),
+            ("x/y.rs", "mod w;"),
+            ("x/w.rs", ""),
+            ("inline/b.rs", ""),
+            ("inline/p.rs", ""),
+            ("unreachable.rs", ""),
+        ] {
+            let path = src.join(path);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, contents).unwrap();
+        }
+
+        let module_paths = module_paths(&ParseCache::default(), &src.join("lib.rs"))
+            .into_iter()
+            .map(|(path, module_path)| {
+                (
+                    util::strip_prefix(&path, &src)
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned(),
+                    module_path.join("::"),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            [
+                ("a/c.rs", "a::c"),
+                ("a/d/mod.rs", "a::d"),
+                ("a.rs", "a"),
+                ("inline/b.rs", "inline::b"),
+                ("inline/p.rs", "inline::q"),
+                ("lib.rs", ""),
+                ("shared.rs", "a::d::shared"),
+                ("x/w.rs", "z::w"),
+                ("x/y.rs", "z"),
+            ]
+            .map(|(path, module_path)| (path.to_owned(), module_path.to_owned()))
+            .as_slice(),
+            module_paths
+        );
+    }
+}
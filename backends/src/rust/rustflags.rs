@@ -0,0 +1,166 @@
+//! Detection of `-D warnings`, which makes a removal that causes a warning (e.g., an unused
+//! variable) nonbuildable, and support for `rust.cap_lints`, which counteracts it.
+
+use anyhow::{Context, Result};
+use std::{
+    env::var,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use toml_edit::{DocumentMut, Item};
+
+const ENCODED_SEPARATOR: char = '\x1f';
+
+/// Caps lints at `allow` for the builds of `command`, which must be a `cargo` command.
+///
+/// Cargo uses only the first of `CARGO_ENCODED_RUSTFLAGS`, `RUSTFLAGS`, and its configuration
+/// files that sets flags. So `--cap-lints allow` is appended to whichever of the environment
+/// variables is set. If neither is set, the flags are passed with `--config`, whose arrays are
+/// joined with those in the configuration files.
+pub(super) fn cap_lints(command: &mut Command) {
+    if let Ok(encoded) = var("CARGO_ENCODED_RUSTFLAGS") {
+        let mut flags = encoded.split(ENCODED_SEPARATOR).collect::<Vec<_>>();
+        flags.retain(|flag| !flag.is_empty());
+        flags.extend(["--cap-lints", "allow"]);
+        command.env(
+            "CARGO_ENCODED_RUSTFLAGS",
+            flags.join(&ENCODED_SEPARATOR.to_string()),
+        );
+    } else if let Ok(flags) = var("RUSTFLAGS") {
+        command.env("RUSTFLAGS", format!("{flags} --cap-lints allow"));
+    } else {
+        command.args(["--config", r#"build.rustflags=["--cap-lints", "allow"]"#]);
+    }
+}
+
+/// Returns a description of where `-D warnings` is set for the builds of the project at `root`,
+/// if anywhere. Only the flags that Cargo would actually use are considered.
+pub(super) fn warnings_denied(root: &Path) -> Result<Option<String>> {
+    if let Ok(encoded) = var("CARGO_ENCODED_RUSTFLAGS") {
+        return Ok(denies_warnings(encoded.split(ENCODED_SEPARATOR))
+            .then(|| String::from("`CARGO_ENCODED_RUSTFLAGS`")));
+    }
+
+    if let Ok(flags) = var("RUSTFLAGS") {
+        return Ok(denies_warnings(flags.split_whitespace()).then(|| String::from("`RUSTFLAGS`")));
+    }
+
+    if let Ok(flags) = var("CARGO_BUILD_RUSTFLAGS") {
+        if denies_warnings(flags.split_whitespace()) {
+            return Ok(Some(String::from("`CARGO_BUILD_RUSTFLAGS`")));
+        }
+    }
+
+    for path in config_paths(root) {
+        if !path.is_file() {
+            continue;
+        }
+        let contents = read_to_string(&path)?;
+        let document = contents
+            .parse::<DocumentMut>()
+            .with_context(|| format!("Failed to parse {path:?}"))?;
+        let build_rustflags = document
+            .get("build")
+            .and_then(|build| build.get("rustflags"));
+        let target_rustflags = document
+            .get("target")
+            .and_then(Item::as_table_like)
+            .into_iter()
+            .flat_map(|targets| targets.iter())
+            .filter_map(|(_, target)| target.get("rustflags"));
+        if build_rustflags
+            .into_iter()
+            .chain(target_rustflags)
+            .any(|rustflags| denies_warnings(flags(rustflags).iter().map(String::as_str)))
+        {
+            return Ok(Some(format!("{path:?}")));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns true if `file` has a `#![deny(warnings)]` attribute.
+pub(super) fn file_denies_warnings(file: &syn::File) -> bool {
+    file.attrs.iter().any(|attr| {
+        attr.path().is_ident("deny")
+            && attr
+                .parse_args_with(
+                    syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+                )
+                .is_ok_and(|paths| paths.iter().any(|path| path.is_ident("warnings")))
+    })
+}
+
+/// Returns the Cargo configuration files that could apply to the project at `root`, in no
+/// particular order.
+fn config_paths(root: &Path) -> Vec<PathBuf> {
+    let dirs = root
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .chain(var("CARGO_HOME").ok().map(PathBuf::from));
+    dirs.flat_map(|dir| [dir.join("config.toml"), dir.join("config")])
+        .collect()
+}
+
+/// Returns the flags in a `rustflags` value, which can be either a string or an array of strings.
+fn flags(rustflags: &Item) -> Vec<String> {
+    if let Some(s) = rustflags.as_str() {
+        return s.split_whitespace().map(ToOwned::to_owned).collect();
+    }
+    rustflags
+        .as_array()
+        .into_iter()
+        .flat_map(|array| array.iter())
+        .filter_map(|value| value.as_str().map(ToOwned::to_owned))
+        .collect()
+}
+
+fn denies_warnings<'a>(flags: impl Iterator<Item = &'a str>) -> bool {
+    let mut prev = None;
+    for flag in flags {
+        if matches!(flag, "-Dwarnings" | "--deny=warnings")
+            || (flag == "warnings" && matches!(prev, Some("-D" | "--deny")))
+        {
+            return true;
+        }
+        prev = Some(flag);
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::{denies_warnings, file_denies_warnings};
+
+    #[test]
+    fn warnings_are_denied() {
+        for flags in [
+            "-Dwarnings",
+            "-D warnings",
+            "--deny warnings",
+            "--deny=warnings",
+            "-C debuginfo=0 -D warnings",
+        ] {
+            assert!(denies_warnings(flags.split_whitespace()), "{flags}");
+        }
+        for flags in [
+            "",
+            "-Wwarnings",
+            "-D unused",
+            "-A warnings",
+            "--cfg warnings",
+        ] {
+            assert!(!denies_warnings(flags.split_whitespace()), "{flags}");
+        }
+    }
+
+    #[test]
+    fn deny_warnings_attribute() {
+        let file = syn::parse_file("#![deny(unused, warnings)]").unwrap();
+        assert!(file_denies_warnings(&file));
+        let file = syn::parse_file("#![warn(warnings)]").unwrap();
+        assert!(!file_denies_warnings(&file));
+    }
+}
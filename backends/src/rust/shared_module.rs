@@ -0,0 +1,126 @@
+//! Support for modules shared by integration tests, e.g., `tests/common/mod.rs`. Such a module is
+//! not a test target of its own. Rather, it is compiled into each test target that declares it
+//! (e.g., with `mod common;`). So the statements in its helpers are attributed to the tests, in
+//! those targets, that call the helpers.
+
+use super::{
+    parse_file,
+    visitor::{is_ignored, is_should_panic, is_test},
+    ParseCache,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+use syn::{
+    visit::{visit_expr_call, visit_item_fn, visit_item_mod, Visit},
+    Expr, ExprCall, ItemFn, ItemMod,
+};
+
+/// Returns the names of the tests that call `helpers`, keyed by helper name
+///
+/// `module_paths` is the module tree of a test target that includes `source_file`, and `helpers`
+/// are functions declared in `source_file`. A call is recognized if its callee is a helper's bare
+/// name (e.g., because of `use common::*;`) or is a path whose last two segments are the shared
+/// module's name and a helper's name (e.g., `common::helper(..)`).
+pub(super) fn callers(
+    parse_cache: &ParseCache<syn::File>,
+    module_paths: &BTreeMap<PathBuf, Vec<String>>,
+    source_file: &Path,
+    helpers: &BTreeSet<String>,
+    include_should_panic_tests: bool,
+    include_ignored: bool,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let module = module_paths
+        .get(source_file)
+        .and_then(|module_path| module_path.last());
+    let mut collector = CallerCollector {
+        module: module.map(String::as_str),
+        helpers,
+        include_should_panic_tests,
+        include_ignored,
+        module_path: Vec::new(),
+        test_name: None,
+        callers: BTreeMap::new(),
+    };
+    for (path, module_path) in module_paths {
+        if path == source_file {
+            continue;
+        }
+        let Ok(contents) = read_to_string(path) else {
+            continue;
+        };
+        let Ok(file) = parse_cache.parse(path, &contents, parse_file) else {
+            continue;
+        };
+        collector.module_path.clone_from(module_path);
+        collector.visit_file(&file);
+    }
+    collector.callers
+}
+
+struct CallerCollector<'a> {
+    module: Option<&'a str>,
+    helpers: &'a BTreeSet<String>,
+    include_should_panic_tests: bool,
+    include_ignored: bool,
+    module_path: Vec<String>,
+    test_name: Option<String>,
+    callers: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<'ast> Visit<'ast> for CallerCollector<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        // smoelius: Out-of-line modules are visited separately, as part of the module tree.
+        if item.content.is_none() || self.test_name.is_some() {
+            return;
+        }
+        self.module_path.push(item.ident.to_string());
+        visit_item_mod(self, item);
+        self.module_path.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if self.test_name.is_some() {
+            visit_item_fn(self, item);
+            return;
+        }
+        let Some(ident) = is_test(item) else {
+            return;
+        };
+        if (!self.include_should_panic_tests && is_should_panic(item))
+            || (!self.include_ignored && is_ignored(item))
+        {
+            return;
+        }
+        let mut test_path = self.module_path.clone();
+        test_path.push(ident.to_string());
+        self.test_name = Some(test_path.join("::"));
+        visit_item_fn(self, item);
+        self.test_name = None;
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        if let (Some(test_name), Expr::Path(expr_path)) = (&self.test_name, &*call.func) {
+            let segments = expr_path
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>();
+            let helper = match segments.as_slice() {
+                [name] => Some(name),
+                [.., module, name] if Some(module.as_str()) == self.module => Some(name),
+                _ => None,
+            };
+            if let Some(helper) = helper.filter(|helper| self.helpers.contains(*helper)) {
+                self.callers
+                    .entry(helper.clone())
+                    .or_default()
+                    .insert(test_name.clone());
+            }
+        }
+        visit_expr_call(self, call);
+    }
+}
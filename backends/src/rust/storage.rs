@@ -1,9 +1,9 @@
-use super::TryInsert;
+use super::{module_tree, ParseCache, TryInsert};
 use anyhow::{anyhow, Error, Result};
-use cargo_metadata::{Metadata, MetadataCommand, Package};
+use cargo_metadata::{Metadata, MetadataCommand, Package, TargetKind};
 use necessist_core::{util, SourceFile};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     ffi::OsStr,
     path::{Path, PathBuf},
 };
@@ -13,6 +13,8 @@ use syn::{File, Ident};
 pub struct Storage<'ast> {
     pub module_path: Vec<&'ast Ident>,
     pub tests_needing_warnings: BTreeMap<String, Vec<Error>>,
+    pub should_panic_tests: BTreeSet<String>,
+    pub ignored_tests: BTreeSet<String>,
     pub error: Option<Error>,
 }
 
@@ -21,12 +23,15 @@ impl<'ast> Storage<'ast> {
         Self {
             module_path: Vec::new(),
             tests_needing_warnings: BTreeMap::new(),
+            should_panic_tests: BTreeSet::new(),
+            ignored_tests: BTreeSet::new(),
             error: None,
         }
     }
 
     pub fn test_path(
         &mut self,
+        parse_cache: &ParseCache<File>,
         source_file_fs_module_path_map: &mut BTreeMap<PathBuf, Vec<String>>,
         source_file_package_map: &mut BTreeMap<PathBuf, Package>,
         directory_metadata_map: &mut BTreeMap<PathBuf, Metadata>,
@@ -34,6 +39,7 @@ impl<'ast> Storage<'ast> {
         name: &str,
     ) -> Result<Vec<String>> {
         let mut test_path = cached_source_file_fs_module_path(
+            parse_cache,
             source_file_fs_module_path_map,
             source_file_package_map,
             directory_metadata_map,
@@ -48,11 +54,40 @@ impl<'ast> Storage<'ast> {
 
 #[cfg_attr(dylint_lib = "general", allow(non_local_effect_before_error_return))]
 pub(super) fn cached_source_file_fs_module_path<'a>(
+    parse_cache: &ParseCache<File>,
     source_file_fs_module_path_map: &'a mut BTreeMap<PathBuf, Vec<String>>,
     source_file_package_map: &mut BTreeMap<PathBuf, Package>,
     directory_metadata_map: &mut BTreeMap<PathBuf, Metadata>,
     source_file: &Path,
 ) -> Result<&'a Vec<String>> {
+    if !source_file_fs_module_path_map.contains_key(source_file) {
+        let package = cached_source_file_package(
+            source_file_package_map,
+            directory_metadata_map,
+            source_file,
+        )?;
+
+        // smoelius: Walking a target's module tree determines the module paths of all of the
+        // target's files. So each target is walked at most once, which is evident from whether its
+        // crate root has an entry.
+        for target in &package.targets {
+            if target.kind.contains(&TargetKind::CustomBuild)
+                || source_file_fs_module_path_map.contains_key(target.src_path.as_std_path())
+            {
+                continue;
+            }
+            for (path, module_path) in
+                module_tree::module_paths(parse_cache, target.src_path.as_std_path())
+            {
+                source_file_fs_module_path_map
+                    .entry(path)
+                    .or_insert(module_path);
+            }
+        }
+    }
+
+    // smoelius: If the source file is not in any target's module tree (e.g., because a file on its
+    // path failed to parse), its module path is inferred from its location.
     source_file_fs_module_path_map
         .entry(source_file.to_path_buf())
         .or_try_insert_with(|| {
@@ -103,11 +138,20 @@ pub(super) fn cached_source_file_package<'a>(
     source_file_package_map
         .entry(source_file.to_path_buf())
         .or_try_insert_with(|| {
-            let parent = source_file
-                .parent()
-                .ok_or_else(|| anyhow!("Failed to get parent directory"))?;
+            // smoelius: Metadata is cached by the directory of the nearest manifest, rather than by
+            // the source file's parent, so that source files in different directories of the same
+            // package share one `cargo metadata` invocation.
+            let mut manifest_dir = None;
+            for dir in source_file.ancestors().skip(1) {
+                if dir.join("Cargo.toml").try_exists()? {
+                    manifest_dir = Some(dir);
+                    break;
+                }
+            }
+            let manifest_dir = manifest_dir
+                .ok_or_else(|| anyhow!("Failed to find Cargo.toml for {source_file:?}"))?;
 
-            let metadata = cached_directory_metadata(directory_metadata_map, parent)?;
+            let metadata = cached_directory_metadata(directory_metadata_map, manifest_dir)?;
 
             // smoelius: Use the package whose manifest directory is nearest to the source file.
             let mut package_near: Option<Package> = None;
@@ -130,7 +174,7 @@ pub(super) fn cached_source_file_package<'a>(
                 }
             }
 
-            package_near.ok_or_else(|| anyhow!("Failed to determine package"))
+            package_near.ok_or_else(|| anyhow!("Failed to determine package of {source_file:?}"))
         })
         .map(|value| value as &_)
 }
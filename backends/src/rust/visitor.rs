@@ -1,17 +1,23 @@
-use super::{Call, GenericVisitor, MacroCall, Rust, Storage, Test};
+use super::{bazel, rustflags, Call, GenericVisitor, MacroCall, Named, Rust, Storage, Test};
 use anyhow::{Error, Result};
+use if_chain::if_chain;
 use necessist_core::{
-    framework::{SpanTestMaps, TestSet},
-    warn, WarnFlags, Warning,
+    framework::{SpanTestMaps, StatementKind, TestSet},
+    source_warn, warn, LineColumn, WarnFlags, Warning,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
 };
-use std::{cell::RefCell, collections::BTreeMap, fmt::Write};
 use syn::{
     visit::{
         visit_block, visit_expr_call, visit_expr_macro, visit_expr_method_call, visit_item_fn,
-        visit_item_mod, visit_stmt, visit_stmt_macro, Visit,
+        visit_item_mod, visit_local, visit_stmt, visit_stmt_macro, Visit,
     },
-    Block, ExprCall, ExprMacro, ExprMethodCall, File, Ident, ImplItemFn, ItemFn, ItemMod,
-    PathSegment, Stmt, StmtMacro,
+    Block, Expr, ExprBlock, ExprCall, ExprClosure, ExprLit, ExprMacro, ExprMethodCall, File, Ident,
+    ImplItemFn, ItemFn, ItemMod, Lit, Local, LocalInit, Pat, PatIdent, PathSegment, Stmt,
+    StmtMacro,
 };
 
 pub(super) fn collect_local_functions(file: &File) -> BTreeMap<String, Vec<&Block>> {
@@ -20,6 +26,32 @@ pub(super) fn collect_local_functions(file: &File) -> BTreeMap<String, Vec<&Bloc
     collector.blocks.split_off(&String::new())
 }
 
+/// Returns the start of each test function in `file`, i.e., the position of its first attribute.
+/// If `include_benches` is true, functions with `#[bench]` are considered test functions.
+pub(super) fn test_fn_starts(file: &File, include_benches: bool) -> Vec<LineColumn> {
+    let mut collector = TestFnCollector {
+        include_benches,
+        starts: Vec::new(),
+    };
+    collector.visit_file(file);
+    collector.starts
+}
+
+struct TestFnCollector {
+    include_benches: bool,
+    starts: Vec<LineColumn>,
+}
+
+impl<'ast> Visit<'ast> for TestFnCollector {
+    fn visit_item_fn(&mut self, item_fn: &'ast ItemFn) {
+        if is_test(item_fn).is_some() || (self.include_benches && is_bench(item_fn).is_some()) {
+            self.starts
+                .push(<_ as syn::spanned::Spanned>::span(item_fn).start());
+        }
+        visit_item_fn(self, item_fn);
+    }
+}
+
 #[derive(Default)]
 struct BlockCollector<'ast> {
     blocks: BTreeMap<String, Vec<&'ast Block>>,
@@ -45,12 +77,35 @@ impl<'ast> Visit<'ast> for BlockCollector<'ast> {
 
 #[cfg_attr(dylint_lib = "general", allow(non_local_effect_before_error_return))]
 pub(super) fn visit<'ast>(
-    generic_visitor: GenericVisitor<'_, '_, '_, 'ast, Rust>,
+    mut generic_visitor: GenericVisitor<'_, '_, '_, 'ast, Rust>,
     storage: &RefCell<Storage<'ast>>,
     file: &'ast File,
 ) -> Result<(TestSet, SpanTestMaps)> {
-    let mut visitor = Visitor::new(generic_visitor, storage);
+    let bazel = initialize_bazel(&mut generic_visitor);
+
+    let custom_harness = custom_harness(&mut generic_visitor, bazel)?;
+    if let Some(target) = &custom_harness {
+        if !custom_harness_command_configured(&generic_visitor, target)? {
+            return Ok((TestSet::default(), SpanTestMaps::default()));
+        }
+    }
+
+    if !bazel && !generic_visitor.context.config.rust.cap_lints {
+        warn_if_warnings_denied(&generic_visitor, file)?;
+    }
+
+    let criterion = !bazel && custom_harness.is_none() && is_bench_target(&mut generic_visitor)?;
+
+    let mut visitor = Visitor::new(
+        generic_visitor,
+        storage,
+        custom_harness.is_some(),
+        criterion,
+    );
     visitor.visit_file(file);
+    if !bazel && custom_harness.is_none() {
+        visit_shared_module_callers(&mut visitor.generic_visitor)?;
+    }
     while let Some(local_function) = visitor.generic_visitor.next_local_function() {
         visitor.visit_local_function(local_function);
     }
@@ -66,11 +121,225 @@ pub(super) fn visit<'ast>(
     if let Some(error) = storage.borrow_mut().error.take() {
         return Err(error);
     }
-    let _: &Vec<String> = visitor
+    if !bazel {
+        let _: &Vec<String> = visitor
+            .generic_visitor
+            .backend
+            .cached_source_file_flags(&visitor.generic_visitor.source_file)?;
+    }
+    let include_should_panic_tests = visitor
         .generic_visitor
+        .context
+        .config
+        .rust
+        .include_should_panic_tests;
+    warn_skipped_tests(&visitor.generic_visitor, storage)?;
+    let (test_set, mut span_test_maps) = visitor.generic_visitor.results()?;
+    if include_should_panic_tests {
+        tag_should_panic_spans(&mut span_test_maps, &storage.borrow().should_panic_tests);
+    }
+    Ok((test_set, span_test_maps))
+}
+
+/// Returns true if Bazel is enabled. A Bazel project need not have Cargo manifests. So the source
+/// file is assumed to be the crate root of its `rust_test` target, and the Cargo-specific steps in
+/// [`visit`] are skipped.
+fn initialize_bazel(generic_visitor: &mut GenericVisitor<'_, '_, '_, '_, Rust>) -> bool {
+    let bazel = bazel::enabled(generic_visitor.context);
+    if bazel {
+        generic_visitor
+            .backend
+            .source_file_fs_module_path_cache
+            .entry(generic_visitor.source_file.to_path_buf())
+            .or_default();
+    }
+    bazel
+}
+
+/// Returns the name of the source file's target, if the target has `harness = false`
+fn custom_harness(
+    generic_visitor: &mut GenericVisitor<'_, '_, '_, '_, Rust>,
+    bazel: bool,
+) -> Result<Option<String>> {
+    if bazel {
+        return Ok(None);
+    }
+    generic_visitor
+        .backend
+        .cached_source_file_custom_harness(&generic_visitor.source_file)
+        .map(Option::<&String>::cloned)
+}
+
+// smoelius: A target with `harness = false` ignores the arguments used to select a test. Unless a
+// command to run the target is configured, the target's source file is skipped. If a command is
+// configured, the target's `main` function is treated as its only test.
+fn custom_harness_command_configured(
+    generic_visitor: &GenericVisitor<'_, '_, '_, '_, Rust>,
+    target: &str,
+) -> Result<bool> {
+    let context = generic_visitor.context;
+    if context
+        .config
+        .rust
+        .custom_harness_commands
+        .contains_key(target)
+    {
+        return Ok(true);
+    }
+    source_warn(
+        context,
+        Warning::HarnessDisabled,
+        &generic_visitor.source_file,
+        &format!(
+            "Skipping test target `{target}`, which has `harness = false`; to run it, add an \
+             entry for `{target}` to `rust.custom_harness_commands` in necessist.toml",
+        ),
+        WarnFlags::empty(),
+    )?;
+    Ok(false)
+}
+
+/// Warns about the tests skipped because they have `#[should_panic]` or `#[ignore]`
+fn warn_skipped_tests(
+    generic_visitor: &GenericVisitor<'_, '_, '_, '_, Rust>,
+    storage: &RefCell<Storage>,
+) -> Result<()> {
+    let context = generic_visitor.context;
+    if !context.config.rust.include_should_panic_tests {
+        for test_name in &storage.borrow().should_panic_tests {
+            source_warn(
+                context,
+                Warning::ShouldPanicTestSkipped,
+                &generic_visitor.source_file,
+                &format!(
+                    "Skipping test `{test_name}`, which has `#[should_panic]`; to include such \
+                     tests, set `rust.include_should_panic_tests = true` in necessist.toml",
+                ),
+                WarnFlags::empty(),
+            )?;
+        }
+    }
+    if !context.opts.include_ignored {
+        for test_name in &storage.borrow().ignored_tests {
+            source_warn(
+                context,
+                Warning::IgnoredTestSkipped,
+                &generic_visitor.source_file,
+                &format!(
+                    "Skipping test `{test_name}`, which has `#[ignore]`; to include such tests, \
+                     pass `--include-ignored`",
+                ),
+                WarnFlags::empty(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if benchmarks are included and the source file is the crate root of a bench
+/// target, i.e., one in which to look for Criterion benchmarks
+fn is_bench_target(generic_visitor: &mut GenericVisitor<'_, '_, '_, '_, Rust>) -> Result<bool> {
+    if !generic_visitor.context.config.rust.include_benches {
+        return Ok(false);
+    }
+    let flags = generic_visitor
         .backend
-        .cached_source_file_flags(&visitor.generic_visitor.source_file)?;
-    visitor.generic_visitor.results()
+        .cached_source_file_flags(&generic_visitor.source_file)?;
+    Ok(flags.iter().any(|flag| flag == "--bench"))
+}
+
+/// If the source file is a module shared by integration tests (e.g., `tests/common/mod.rs`),
+/// queues its walkable functions to be walked on behalf of the tests, in other files, that call
+/// them
+fn visit_shared_module_callers(
+    generic_visitor: &mut GenericVisitor<'_, '_, '_, '_, Rust>,
+) -> Result<()> {
+    let helpers = generic_visitor
+        .walkable_functions
+        .keys()
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    if helpers.is_empty() {
+        return Ok(());
+    }
+    let callers = generic_visitor.backend.shared_module_callers(
+        &generic_visitor.source_file,
+        &helpers,
+        generic_visitor
+            .context
+            .config
+            .rust
+            .include_should_panic_tests,
+        generic_visitor.context.opts.include_ignored,
+    )?;
+    for (helper, test_names) in callers {
+        let local_functions = &generic_visitor.walkable_functions[&helper];
+        if local_functions.len() >= 2 {
+            generic_visitor
+                .local_functions_needing_warnings
+                .insert(helper.clone());
+        }
+        generic_visitor
+            .local_functions_pending
+            .entry(local_functions[0])
+            .or_default()
+            .extend(test_names);
+    }
+    Ok(())
+}
+
+/// Tags the spans exercised only by `#[should_panic]` tests. Removing such a span often appears
+/// to pass merely because the test panics elsewhere, so their outcomes warrant separate analysis.
+fn tag_should_panic_spans(
+    span_test_maps: &mut SpanTestMaps,
+    should_panic_tests: &BTreeSet<String>,
+) {
+    let spans = span_test_maps
+        .iter()
+        .filter(|(_, _, test_names)| {
+            test_names
+                .iter()
+                .all(|test_name| should_panic_tests.contains(test_name))
+        })
+        .map(|(span, _, _)| span.clone())
+        .collect::<Vec<_>>();
+    span_test_maps.statement_kind.extend(
+        spans
+            .into_iter()
+            .map(|span| (span, StatementKind::ShouldPanic)),
+    );
+}
+
+fn warn_if_warnings_denied(
+    generic_visitor: &GenericVisitor<'_, '_, '_, '_, Rust>,
+    file: &File,
+) -> Result<()> {
+    const ADVICE: &str = "removals that cause warnings (e.g., unused variables) will be \
+                          nonbuildable; to allow such warnings, set `rust.cap_lints = true` in \
+                          necessist.toml";
+
+    let context = generic_visitor.context;
+
+    if let Some(location) = rustflags::warnings_denied(context.root)? {
+        warn(
+            context,
+            Warning::WarningsDenied,
+            &format!("`-D warnings` is set in {location}; {ADVICE}"),
+            WarnFlags::ONCE,
+        )?;
+    }
+
+    if rustflags::file_denies_warnings(file) {
+        source_warn(
+            context,
+            Warning::WarningsDenied,
+            &generic_visitor.source_file,
+            &format!("File has `#![deny(warnings)]`; {ADVICE}"),
+            WarnFlags::empty(),
+        )?;
+    }
+
+    Ok(())
 }
 
 fn module_path_unknown_msg(test_name: &str, errors: &[Error]) -> Result<String> {
@@ -92,6 +361,10 @@ struct Visitor<'context, 'config, 'backend, 'ast, 'storage> {
     generic_visitor: GenericVisitor<'context, 'config, 'backend, 'ast, Rust>,
     storage: &'storage RefCell<Storage<'ast>>,
     test_ident: Option<&'ast Ident>,
+    main_is_test: bool,
+    criterion: bool,
+    /// Maps variables bound to Criterion benchmark groups to the groups' names
+    benchmark_groups: BTreeMap<String, String>,
 }
 
 impl<'context, 'config, 'backend, 'ast, 'storage>
@@ -100,17 +373,40 @@ impl<'context, 'config, 'backend, 'ast, 'storage>
     fn new(
         generic_visitor: GenericVisitor<'context, 'config, 'backend, 'ast, Rust>,
         storage: &'storage RefCell<Storage<'ast>>,
+        main_is_test: bool,
+        criterion: bool,
     ) -> Self {
         Self {
             generic_visitor,
             storage,
             test_ident: None,
+            main_is_test,
+            criterion,
+            benchmark_groups: BTreeMap::new(),
         }
     }
 
     fn visit_local_function(&mut self, local_function: &'ast Block) {
         visit_block(self, local_function);
     }
+
+    fn visit_criterion_benchmark(&mut self, id: String, closure: &'ast ExprClosure) {
+        let stmts = if let Expr::Block(ExprBlock { block, .. }) = &*closure.body {
+            block.stmts.as_slice()
+        } else {
+            &[]
+        };
+
+        let test = Test::criterion(id, stmts);
+
+        let walk = self.generic_visitor.visit_test(self.storage, test);
+
+        if walk {
+            self.visit_expr(&closure.body);
+        }
+
+        self.generic_visitor.visit_test_post(self.storage, test);
+    }
 }
 
 impl<'ast> Visit<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
@@ -130,12 +426,21 @@ impl<'ast> Visit<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
     }
 
     fn visit_item_fn(&mut self, item: &'ast ItemFn) {
-        if let Some(ident) = is_test(item) {
+        let test_ident = if self.main_is_test {
+            Some(&item.sig.ident)
+                .filter(|ident| *ident == "main" && self.storage.borrow().module_path.is_empty())
+        } else if self.generic_visitor.context.config.rust.include_benches {
+            is_test(item).or_else(|| is_bench(item))
+        } else {
+            is_test(item)
+        };
+        if let Some(ident) = test_ident {
             assert!(self.test_ident.is_none());
             self.test_ident = Some(ident);
 
             if let Some(test) = Test::new(
                 self.storage,
+                &self.generic_visitor.backend.parse_cache,
                 &mut self
                     .generic_visitor
                     .backend
@@ -145,6 +450,31 @@ impl<'ast> Visit<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
                 &self.generic_visitor.source_file,
                 item,
             ) {
+                if is_should_panic(item) {
+                    self.storage
+                        .borrow_mut()
+                        .should_panic_tests
+                        .insert(test.name());
+                    if !self
+                        .generic_visitor
+                        .context
+                        .config
+                        .rust
+                        .include_should_panic_tests
+                    {
+                        self.test_ident = None;
+                        return;
+                    }
+                }
+
+                if is_ignored(item) {
+                    self.storage.borrow_mut().ignored_tests.insert(test.name());
+                    if !self.generic_visitor.context.opts.include_ignored {
+                        self.test_ident = None;
+                        return;
+                    }
+                }
+
                 let walk = self.generic_visitor.visit_test(self.storage, test);
 
                 if walk {
@@ -159,6 +489,16 @@ impl<'ast> Visit<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
         }
     }
 
+    fn visit_local(&mut self, local: &'ast Local) {
+        if self.criterion {
+            if let Some((ident, name)) = benchmark_group(local) {
+                self.benchmark_groups.insert(ident, name);
+            }
+        }
+
+        visit_local(self, local);
+    }
+
     fn visit_stmt(&mut self, stmt: &'ast Stmt) {
         let walk = self.generic_visitor.visit_statement(self.storage, stmt);
 
@@ -215,6 +555,16 @@ impl<'ast> Visit<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
     }
 
     fn visit_expr_method_call(&mut self, method_call: &'ast ExprMethodCall) {
+        if_chain! {
+            if self.criterion;
+            if self.test_ident.is_none() && self.generic_visitor.test_names.is_empty();
+            if let Some((id, closure)) = criterion_benchmark(method_call, &self.benchmark_groups);
+            then {
+                self.visit_criterion_benchmark(id, closure);
+                return;
+            }
+        }
+
         let call = Call::MethodCall(method_call);
 
         let walk = self.generic_visitor.visit_call(self.storage, call);
@@ -229,7 +579,7 @@ impl<'ast> Visit<'ast> for Visitor<'_, '_, '_, 'ast, '_> {
     }
 }
 
-fn is_test(item: &ItemFn) -> Option<&Ident> {
+pub(super) fn is_test(item: &ItemFn) -> Option<&Ident> {
     if item.attrs.iter().any(|attr| {
         let path = attr
             .path()
@@ -257,13 +607,79 @@ fn is_test(item: &ItemFn) -> Option<&Ident> {
     }
 }
 
+pub(super) fn is_bench(item: &ItemFn) -> Option<&Ident> {
+    if item.attrs.iter().any(|attr| attr.path().is_ident("bench")) {
+        Some(&item.sig.ident)
+    } else {
+        None
+    }
+}
+
+/// If `local` binds a variable to a Criterion benchmark group, e.g.,
+/// `let mut group = c.benchmark_group("name");`, returns the variable and the group's name
+fn benchmark_group(local: &Local) -> Option<(String, String)> {
+    if_chain! {
+        if let Pat::Ident(PatIdent { ident, .. }) = &local.pat;
+        if let Some(LocalInit { expr, .. }) = &local.init;
+        if let Expr::MethodCall(method_call) = &**expr;
+        if method_call.method == "benchmark_group";
+        if let Some(Expr::Lit(ExprLit { lit: Lit::Str(name), .. })) = method_call.args.first();
+        then {
+            Some((ident.to_string(), name.value()))
+        } else {
+            None
+        }
+    }
+}
+
+/// If `method_call` is a call to Criterion's `bench_function` with a literal ID and a closure,
+/// returns the benchmark's full ID (i.e., prefixed with its group's name, if any) and the closure
+fn criterion_benchmark<'ast>(
+    method_call: &'ast ExprMethodCall,
+    benchmark_groups: &BTreeMap<String, String>,
+) -> Option<(String, &'ast ExprClosure)> {
+    if_chain! {
+        if method_call.method == "bench_function";
+        if let [Expr::Lit(ExprLit { lit: Lit::Str(id), .. }), Expr::Closure(closure)] =
+            method_call.args.iter().collect::<Vec<_>>().as_slice();
+        then {
+            let group = if let Expr::Path(path) = &*method_call.receiver {
+                path.path
+                    .get_ident()
+                    .and_then(|ident| benchmark_groups.get(&ident.to_string()))
+            } else {
+                None
+            };
+            let id = id.value();
+            Some((
+                group.map_or_else(|| id.clone(), |group| format!("{group}/{id}")),
+                closure,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+pub(super) fn is_should_panic(item: &ItemFn) -> bool {
+    item.attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("should_panic"))
+}
+
+pub(super) fn is_ignored(item: &ItemFn) -> bool {
+    item.attrs.iter().any(|attr| attr.path().is_ident("ignore"))
+}
+
 #[cfg(test)]
 mod test {
-    use super::Rust;
+    use super::{benchmark_group, criterion_benchmark, is_should_panic, Rust};
     use crate::ParseLow;
     use if_chain::if_chain;
-    use std::fs::read_to_string;
-    use syn::{parse_file, Expr, ExprArray, ExprLit, ExprReference, Item, ItemConst, Lit};
+    use std::{collections::BTreeMap, fs::read_to_string};
+    use syn::{
+        parse_file, parse_str, Expr, ExprArray, ExprLit, ExprReference, Item, ItemConst, Lit, Stmt,
+    };
 
     const UNNECESSARY_CONVERSION_FOR_TRAIT_URL: &str = "https://raw.githubusercontent.com/trailofbits/dylint/master/examples/supplementary/unnecessary_conversion_for_trait/src/lib.rs";
 
@@ -333,6 +749,70 @@ mod test {
         assert_eq!(sort(ADDED_METHODS), ADDED_METHODS);
     }
 
+    #[test]
+    fn should_panic_tests() {
+        let file = parse_file(
+            r#"
+#[test]
+#[should_panic(expected = "overflow")]
+fn foo() {}
+
+#[test]
+fn bar() {}
+"#,
+        )
+        .unwrap();
+        let should_panic = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Fn(item_fn) => {
+                    Some((item_fn.sig.ident.to_string(), is_should_panic(item_fn)))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            should_panic,
+            [(String::from("foo"), true), (String::from("bar"), false)]
+        );
+    }
+
+    #[test]
+    fn criterion_benchmarks() {
+        let Stmt::Local(local) =
+            parse_str::<Stmt>(r#"let mut group = c.benchmark_group("fib");"#).unwrap()
+        else {
+            panic!("Expected a `let` statement");
+        };
+        let benchmark_groups = benchmark_group(&local)
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let ids = [
+            r#"c.bench_function("fib 20", |b| b.iter(|| fib(20)))"#,
+            r#"group.bench_function("fib 20", |b| b.iter(|| fib(20)))"#,
+            r#"c.bench_function(BenchmarkId::new("fib", 20), |b| b.iter(|| fib(20)))"#,
+            r#"c.bench_with_input("fib 20", &20, |b, &n| b.iter(|| fib(n)))"#,
+        ]
+        .into_iter()
+        .map(|s| {
+            let Expr::MethodCall(method_call) = parse_str::<Expr>(s).unwrap() else {
+                panic!("Expected a method call: {s}");
+            };
+            criterion_benchmark(&method_call, &benchmark_groups).map(|(id, _)| id)
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [
+                Some(String::from("fib 20")),
+                Some(String::from("fib/fib 20")),
+                None,
+                None
+            ]
+        );
+    }
+
     #[cfg_attr(
         dylint_lib = "assert_eq_arg_misordering",
         allow(assert_eq_arg_misordering)
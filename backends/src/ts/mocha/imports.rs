@@ -0,0 +1,286 @@
+//! Support for helpers imported from other files, e.g., with `import { deploy } from "./utils";`.
+//! The statements in such a helper are attributed to the tests, in the importing files, that call
+//! the helper. Since the helper is exercised only by those tests, the importing files are run along
+//! with the helper's file.
+
+use super::{is_it_call_expr, tsconfig::Tsconfig, Mocha};
+use crate::{utils, ParseLow};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+use swc_core::ecma::{
+    ast::{
+        CallExpr, Callee, Expr, ImportDecl, ImportSpecifier, Lit, MemberProp, Module,
+        ObjectPatProp, Pat, PropName, VarDeclarator,
+    },
+    visit::{Visit, VisitWith},
+};
+
+/// The extensions tried, in order, when resolving a specifier without one
+const EXTENSIONS: &[&str] = &["ts", "js"];
+
+/// Returns the files that import each file, keyed by the imported file. The files considered are
+/// those that `mocha` walks beneath `root`. Files that cannot be parsed are omitted.
+pub(super) fn importers(mocha: &Mocha, root: &Path) -> BTreeMap<PathBuf, BTreeSet<PathBuf>> {
+    let mut importers = BTreeMap::<PathBuf, BTreeSet<PathBuf>>::new();
+    for entry in mocha.walk_dir(root).flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Ok((_, module)) = mocha.parse_source_file(path) else {
+            continue;
+        };
+        let tsconfig = Tsconfig::nearest(root, path);
+        for imported in imports(&module, path, tsconfig.as_ref()).into_keys() {
+            if imported != path {
+                importers
+                    .entry(imported)
+                    .or_default()
+                    .insert(path.to_path_buf());
+            }
+        }
+    }
+    importers
+}
+
+/// Returns the names of the tests that call `helpers`, keyed by helper name
+///
+/// `importers` are the files beneath `root` that import `source_file`, and `helpers` are functions
+/// declared in `source_file`. A call is recognized if its callee is a name bound to a helper by an
+/// import (e.g., `deploy(..)` or `d(..)` after `import { deploy as d } from "./utils";`), or is a
+/// member of a name bound to the whole file (e.g., `utils.deploy(..)` after `import * as utils from
+/// "./utils";`). Names bound by `require` calls are recognized similarly.
+pub(super) fn callers(
+    mocha: &Mocha,
+    root: &Path,
+    importers: &BTreeSet<PathBuf>,
+    source_file: &Path,
+    helpers: &BTreeSet<String>,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let mut callers = BTreeMap::<String, BTreeSet<String>>::new();
+    for importer in importers {
+        let Ok((_, module)) = mocha.parse_source_file(importer) else {
+            continue;
+        };
+        let tsconfig = Tsconfig::nearest(root, importer);
+        let Some(bindings) = imports(&module, importer, tsconfig.as_ref()).remove(source_file)
+        else {
+            continue;
+        };
+        let mut collector = CallerCollector {
+            bindings: &bindings,
+            helpers,
+            test_name: None,
+            callers: &mut callers,
+        };
+        collector.visit_module(&module);
+    }
+    callers
+}
+
+/// The names that an importing file binds to an imported file's contents
+#[derive(Default)]
+struct Bindings {
+    /// Local names bound to individual exports, mapped to the exports' names
+    functions: BTreeMap<String, String>,
+    /// Local names bound to the whole file, e.g., by `import * as utils from "./utils";`
+    namespaces: BTreeSet<String>,
+}
+
+/// Returns the bindings of the files that `module` imports, keyed by imported file
+fn imports(
+    module: &Module,
+    importer: &Path,
+    tsconfig: Option<&Tsconfig>,
+) -> BTreeMap<PathBuf, Bindings> {
+    let mut collector = ImportCollector {
+        importer,
+        tsconfig,
+        imports: BTreeMap::new(),
+    };
+    collector.visit_module(module);
+    collector.imports
+}
+
+struct ImportCollector<'a> {
+    importer: &'a Path,
+    tsconfig: Option<&'a Tsconfig>,
+    imports: BTreeMap<PathBuf, Bindings>,
+}
+
+impl ImportCollector<'_> {
+    fn bindings(&mut self, specifier: &str) -> Option<&mut Bindings> {
+        let imported = resolve(self.importer, self.tsconfig, specifier)?;
+        Some(self.imports.entry(imported).or_default())
+    }
+}
+
+impl Visit for ImportCollector<'_> {
+    fn visit_import_decl(&mut self, import_decl: &ImportDecl) {
+        if import_decl.type_only {
+            return;
+        }
+        let Some(bindings) = self.bindings(&import_decl.src.value) else {
+            return;
+        };
+        for specifier in &import_decl.specifiers {
+            match specifier {
+                ImportSpecifier::Named(named) => {
+                    let local = named.local.as_ref().to_owned();
+                    let imported = named
+                        .imported
+                        .as_ref()
+                        .map_or_else(|| local.clone(), |imported| imported.atom().to_string());
+                    bindings.functions.insert(local, imported);
+                }
+                ImportSpecifier::Default(default) => {
+                    bindings
+                        .namespaces
+                        .insert(default.local.as_ref().to_owned());
+                }
+                ImportSpecifier::Namespace(namespace) => {
+                    bindings
+                        .namespaces
+                        .insert(namespace.local.as_ref().to_owned());
+                }
+            }
+        }
+    }
+
+    fn visit_var_declarator(&mut self, var_declarator: &VarDeclarator) {
+        let Some(specifier) = var_declarator.init.as_deref().and_then(is_require_call) else {
+            var_declarator.visit_children_with(self);
+            return;
+        };
+        let Some(bindings) = self.bindings(&specifier) else {
+            return;
+        };
+        match &var_declarator.name {
+            Pat::Ident(ident) => {
+                bindings.namespaces.insert(ident.id.as_ref().to_owned());
+            }
+            Pat::Object(object) => {
+                for prop in &object.props {
+                    match prop {
+                        ObjectPatProp::Assign(assign) => {
+                            let name = assign.key.id.as_ref().to_owned();
+                            bindings.functions.insert(name.clone(), name);
+                        }
+                        ObjectPatProp::KeyValue(key_value) => {
+                            if let (PropName::Ident(key), Pat::Ident(value)) =
+                                (&key_value.key, &*key_value.value)
+                            {
+                                bindings
+                                    .functions
+                                    .insert(value.id.as_ref().to_owned(), key.sym.to_string());
+                            }
+                        }
+                        ObjectPatProp::Rest(_) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the specifier of a `require` call, e.g., `./utils` in `require("./utils")`
+fn is_require_call(expr: &Expr) -> Option<String> {
+    let Expr::Call(CallExpr {
+        callee: Callee::Expr(callee),
+        args,
+        ..
+    }) = expr.unwrap_parens()
+    else {
+        return None;
+    };
+    let Expr::Ident(ident) = &**callee else {
+        return None;
+    };
+    let [arg] = args.as_slice() else {
+        return None;
+    };
+    let Expr::Lit(Lit::Str(str)) = &*arg.expr else {
+        return None;
+    };
+    (ident.as_ref() == "require").then(|| str.value.to_string())
+}
+
+/// Resolves `specifier` to a file. A relative specifier (e.g., `./utils`) is resolved against the
+/// directory of `importer`. A non-relative one (e.g., `@test/utils`) is resolved using `tsconfig`'s
+/// `paths` and `baseUrl`, if any; otherwise, it is assumed to name a package, and is not resolved.
+fn resolve(importer: &Path, tsconfig: Option<&Tsconfig>, specifier: &str) -> Option<PathBuf> {
+    let paths = if specifier.starts_with("./") || specifier.starts_with("../") {
+        vec![utils::normalize_path(&importer.parent()?.join(specifier))]
+    } else {
+        tsconfig?.candidates(specifier)
+    };
+    paths.iter().find_map(|path| resolve_file(path))
+}
+
+/// Returns the file that `path` names the way Node and TypeScript do: the file itself, the file
+/// with one of [`EXTENSIONS`] appended, or an `index` file in the named directory. A `.js`
+/// extension may name a `.ts` file, as is conventional in TypeScript ES modules.
+fn resolve_file(path: &Path) -> Option<PathBuf> {
+    let mut candidates = vec![path.to_path_buf()];
+    if path.extension().is_some_and(|extension| extension == "js") {
+        candidates.push(path.with_extension("ts"));
+    }
+    for extension in EXTENSIONS {
+        let mut candidate = path.as_os_str().to_owned();
+        candidate.push(".");
+        candidate.push(extension);
+        candidates.push(candidate.into());
+    }
+    for extension in EXTENSIONS {
+        candidates.push(path.join("index").with_extension(extension));
+    }
+    candidates.into_iter().find(|candidate| candidate.is_file())
+}
+
+struct CallerCollector<'a> {
+    bindings: &'a Bindings,
+    helpers: &'a BTreeSet<String>,
+    test_name: Option<String>,
+    callers: &'a mut BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Visit for CallerCollector<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if self.test_name.is_none() {
+            if let Some(test) = is_it_call_expr(expr) {
+                self.test_name = Some(test.it_message.to_string());
+                test.stmts.visit_children_with(self);
+                self.test_name = None;
+                return;
+            }
+        }
+        expr.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let (Some(test_name), Callee::Expr(callee)) = (&self.test_name, &call.callee) {
+            let helper = match &**callee {
+                Expr::Ident(ident) => self.bindings.functions.get(ident.as_ref()).cloned(),
+                Expr::Member(member) => match (&*member.obj, &member.prop) {
+                    (Expr::Ident(ident), MemberProp::Ident(prop))
+                        if self.bindings.namespaces.contains(ident.as_ref()) =>
+                    {
+                        Some(prop.sym.to_string())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(helper) = helper.filter(|helper| self.helpers.contains(helper)) {
+                self.callers
+                    .entry(helper)
+                    .or_default()
+                    .insert(test_name.clone());
+            }
+        }
+        call.visit_children_with(self);
+    }
+}
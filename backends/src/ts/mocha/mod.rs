@@ -1,44 +1,53 @@
 use crate::{
-    AbstractTypes, GenericVisitor, MaybeNamed, Named, OutputAccessors, OutputStrippedOfAnsiScapes,
-    ParseLow, Spanned, WalkDirResult,
+    utils, AbstractTypes, GenericVisitor, MaybeNamed, Named, OutputAccessors,
+    OutputStrippedOfAnsiScapes, ParseCache, ParseLow, Spanned, WalkDirResult,
 };
 use anyhow::{anyhow, Result};
 use if_chain::if_chain;
-use log::debug;
 use necessist_core::{
+    config::{self, MochaReporter},
     framework::{Postprocess, SpanTestMaps, TestSet},
-    source_warn, util, LightContext, LineColumn, SourceFile, Span, WarnFlags, Warning,
+    source_warn, util, Exec, LightContext, LineColumn, SourceFile, Span, WarnFlags, Warning,
 };
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::{
-    cell::RefCell,
-    collections::BTreeMap,
+    cell::{OnceCell, RefCell},
+    collections::{BTreeMap, BTreeSet},
     convert::Infallible,
     ffi::OsStr,
+    fs::read_to_string,
     path::{Path, PathBuf},
     process::Command,
     rc::Rc,
 };
-use subprocess::{Exec, NullFile};
+use subprocess::NullFile;
 use swc_core::{
     common::{BytePos, Loc, SourceMap, Span as SwcSpan, Spanned as SwcSpanned},
     ecma::{
         ast::{
             ArrowExpr, AwaitExpr, BlockStmtOrExpr, CallExpr, Callee, EsVersion, Expr, ExprStmt,
-            FnDecl, Invalid, Lit, MemberExpr, MemberProp, Module, Stmt, Str,
+            FnExpr, Function, Invalid, Lit, MemberExpr, MemberProp, Module, Stmt, Str,
         },
         atoms::JsWord,
         parser::{lexer::Lexer, Parser, StringInput, Syntax, TsSyntax},
     },
 };
+use tracing::debug;
+
+mod imports;
 
 mod storage;
 use storage::Storage;
 
+mod tsconfig;
+
 mod visitor;
 use visitor::{collect_local_functions, visit};
 
+/// The functions that define tests
+const IT_ALIASES: &[&str] = &["it", "specify", "test"];
+
 static INVALID: Expr = Expr::Invalid(Invalid {
     span: SwcSpan {
         lo: BytePos(0),
@@ -50,6 +59,13 @@ static INVALID: Expr = Expr::Invalid(Invalid {
 enum ItMessageState {
     NotFound,
     Found,
+    /// Found only as the message from which an `it.each` test's reported messages are formatted
+    FoundFormatted,
+    /// Found, but the message is contained in another test's message, so that `--grep` would
+    /// select both tests
+    Ambiguous,
+    /// Found, but the test was skipped at runtime, e.g., by `this.skip()`
+    Pending,
     WarningEmitted,
 }
 
@@ -65,38 +81,102 @@ static LINE_WITH_TIME_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^\s*. (.*) \([0-9]+ms\)$").unwrap()
 });
 
+static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| {
+    #[allow(clippy::unwrap_used)]
+    Regex::new(r"%[sdifjoOp#%]|\$[A-Za-z_][A-Za-z0-9_.]*").unwrap()
+});
+
 static LINE_WITHOUT_TIME_RE: Lazy<Regex> = Lazy::new(|| {
     #[allow(clippy::unwrap_used)]
     Regex::new(r"^\s*. (.*)$").unwrap()
 });
 
+// smoelius: The spec reporter lists a pending test with a `-` where a passing test has a check
+// mark.
+static PENDING_LINE_RE: Lazy<Regex> = Lazy::new(|| {
+    #[allow(clippy::unwrap_used)]
+    Regex::new(r"^\s*- (.*)$").unwrap()
+});
+
 pub struct Mocha {
     subdir: PathBuf,
+    grep: bool,
     source_map: Rc<SourceMap>,
+    parse_cache: ParseCache<Module>,
     source_file_it_message_state_map: RefCell<BTreeMap<PathBuf, BTreeMap<String, ItMessageState>>>,
+    /// The files that import each file (see [`imports::importers`])
+    importers: OnceCell<BTreeMap<PathBuf, BTreeSet<PathBuf>>>,
 }
 
 impl Mocha {
     pub fn new(subdir: impl AsRef<Path>) -> Self {
         Self {
             subdir: subdir.as_ref().to_path_buf(),
+            grep: false,
             source_map: Rc::default(),
+            parse_cache: ParseCache::default(),
             source_file_it_message_state_map: RefCell::new(BTreeMap::new()),
+            importers: OnceCell::new(),
         }
     }
 
+    /// Narrows each test command to the test being run by passing its `it` message to `--grep`.
+    /// Since Mocha matches `--grep` against substrings of tests' titles, a test whose message is
+    /// contained in another's is not run; its candidates are skipped with a warning.
+    #[must_use]
+    pub fn with_grep(mut self) -> Self {
+        self.grep = true;
+        self
+    }
+
+    /// Returns the files to pass to the test command to run `source_file`'s tests: `source_file`
+    /// itself, followed by the files that import it. The latter hold the tests that exercise the
+    /// helpers in `source_file`.
+    pub fn test_files(&self, context: &LightContext, source_file: &Path) -> Vec<PathBuf> {
+        std::iter::once(source_file.to_path_buf())
+            .chain(self.importers(context, source_file))
+            .collect()
+    }
+
+    fn importers(&self, context: &LightContext, source_file: &Path) -> BTreeSet<PathBuf> {
+        self.importers
+            .get_or_init(|| imports::importers(self, context.root))
+            .get(source_file)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the names of the tests that call `helpers`, keyed by helper name, across the files
+    /// that import `source_file`
+    fn importer_callers(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+        helpers: &BTreeSet<String>,
+    ) -> BTreeMap<String, BTreeSet<String>> {
+        let importers = self.importers(context, source_file);
+        imports::callers(self, context.root, &importers, source_file, helpers)
+    }
+
     pub fn dry_run(
         &self,
-        _context: &LightContext,
+        context: &LightContext,
         source_file: &Path,
         mut command: Command,
     ) -> Result<()> {
         debug!("{:?}", command);
 
-        let output = command.output_stripped_of_ansi_escapes()?;
-        if !output.status().success() {
-            return Err(output.into());
-        }
+        let output = command.output_stripped_of_ansi_escapes(context)?;
+        let success = output.status().success();
+
+        // smoelius: The passing tests are recorded even if the dry run failed, so that only the
+        // failing tests need to be skipped.
+        let passes_and_pending = passes_and_pending(context, std::str::from_utf8(output.stdout())?);
+        let (passes, pending) = if success {
+            passes_and_pending?
+        } else {
+            passes_and_pending.unwrap_or_default()
+        };
 
         let mut source_file_it_message_state_map =
             self.source_file_it_message_state_map.borrow_mut();
@@ -104,20 +184,44 @@ impl Mocha {
             .entry(source_file.to_path_buf())
             .or_default();
 
-        let stdout = std::str::from_utf8(output.stdout())?;
-        for line in stdout.lines() {
-            if let Some(captures) = LINE_WITH_TIME_RE
-                .captures(line)
-                .or_else(|| LINE_WITHOUT_TIME_RE.captures(line))
-            {
-                assert_eq!(2, captures.len());
-                it_message_state_map.insert(captures[1].to_string(), ItMessageState::Found);
-            }
+        for it_message in &passes {
+            it_message_state_map.insert(
+                it_message.clone(),
+                if self.grep && is_ambiguous(&passes, it_message) {
+                    ItMessageState::Ambiguous
+                } else {
+                    ItMessageState::Found
+                },
+            );
+        }
+
+        for it_message in pending {
+            it_message_state_map.insert(it_message, ItMessageState::Pending);
+        }
+
+        if !success {
+            return Err(output.into());
         }
 
         Ok(())
     }
 
+    /// Returns the `it` messages of the tests that were skipped at runtime during `source_file`'s
+    /// dry run
+    pub fn skipped_tests(&self, source_file: &Path) -> BTreeSet<String> {
+        self.source_file_it_message_state_map
+            .borrow()
+            .get(source_file)
+            .map(|it_message_state_map| {
+                it_message_state_map
+                    .iter()
+                    .filter(|&(_, state)| *state == ItMessageState::Pending)
+                    .map(|(it_message, _)| it_message.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
     pub fn statement_prefix_and_suffix(&self, span: &Span) -> Result<(String, String)> {
         Ok((
@@ -143,19 +247,40 @@ impl Mocha {
             .get_mut(span.source_file.as_ref())
             .expect("Source file is not in map");
 
-        // smoelius: For Mocha-based frameworks, `test_name` is the `it` message.
+        // smoelius: For Mocha-based frameworks, `test_name` is the `it` message. A test defined
+        // with `it.each` is reported under messages formatted from its own.
+        if !it_message_state_map.contains_key(test_name)
+            && it_message_state_map.iter().any(|(it_message, state)| {
+                matches!(state, ItMessageState::Found | ItMessageState::Ambiguous)
+                    && is_formatted_from(test_name, it_message)
+            })
+        {
+            it_message_state_map.insert(test_name.to_owned(), ItMessageState::FoundFormatted);
+        }
+
         let state = it_message_state_map
             .entry(test_name.to_owned())
             .or_default();
-        if *state != ItMessageState::Found {
-            if *state == ItMessageState::NotFound {
-                source_warn(
-                    context,
+        if !matches!(
+            state,
+            ItMessageState::Found | ItMessageState::FoundFormatted
+        ) {
+            let warning = match state {
+                ItMessageState::NotFound => Some((
                     Warning::ItMessageNotFound,
-                    span,
-                    &format!("`it` message {test_name:?} was not found during dry run"),
-                    WarnFlags::empty(),
-                )?;
+                    format!("`it` message {test_name:?} was not found during dry run"),
+                )),
+                ItMessageState::Ambiguous => Some((
+                    Warning::ItMessageAmbiguous,
+                    format!(
+                        "`it` message {test_name:?} is contained in another test's message, so \
+                         `--grep` would select both tests; skipping"
+                    ),
+                )),
+                _ => None,
+            };
+            if let Some((warning, msg)) = warning {
+                source_warn(context, warning, span, &msg, WarnFlags::empty())?;
                 *state = ItMessageState::WarningEmitted;
             }
             // smoelius: Returning `None` here causes Necessist to associate `Outcome::Nonbuildable`
@@ -165,8 +290,15 @@ impl Mocha {
         }
 
         let mut exec = util::exec_from_command(command);
-        exec = exec.stdout(NullFile);
-        exec = exec.stderr(NullFile);
+        // smoelius: The messages of an `it.each` test's instances are formatted from its own, so
+        // no single `--grep` argument selects them all. Such a test is run with its whole file.
+        if self.grep && *state == ItMessageState::Found {
+            exec = exec.arg("--grep").arg(utils::escape_js_regex(test_name));
+        }
+        if !context.opts.explaining() {
+            exec = exec.stdout(NullFile);
+            exec = exec.stderr(NullFile);
+        }
 
         debug!("{:?}", exec);
 
@@ -174,6 +306,79 @@ impl Mocha {
     }
 }
 
+/// Returns true if `it_message` is contained in the message of a test other than its own, i.e., if
+/// `--grep` would select more than one test
+fn is_ambiguous(passes: &[String], it_message: &str) -> bool {
+    passes
+        .iter()
+        .filter(|other| other.contains(it_message))
+        .count()
+        >= 2
+}
+
+/// Returns the titles of the passing and pending tests (respectively) in a dry run's stdout
+fn passes_and_pending(context: &LightContext, stdout: &str) -> Result<(Vec<String>, Vec<String>)> {
+    if context.config.mocha.reporter == MochaReporter::Json {
+        return json_report_passes_and_pending(stdout);
+    }
+
+    Ok(spec_passes_and_pending(stdout))
+}
+
+/// Returns the titles of the passing and pending tests (respectively) in the output of Mocha's
+/// `spec` reporter
+fn spec_passes_and_pending(stdout: &str) -> (Vec<String>, Vec<String>) {
+    let mut passes = Vec::new();
+    let mut pending = Vec::new();
+    for line in stdout.lines() {
+        if let Some(captures) = PENDING_LINE_RE.captures(line) {
+            pending.push(captures[1].to_string());
+        } else if let Some(captures) = LINE_WITH_TIME_RE
+            .captures(line)
+            .or_else(|| LINE_WITHOUT_TIME_RE.captures(line))
+        {
+            assert_eq!(2, captures.len());
+            passes.push(captures[1].to_string());
+        }
+    }
+    (passes, pending)
+}
+
+/// Returns the titles of the passing and pending tests (respectively) in the output of Mocha's
+/// `json` reporter
+fn json_report_passes_and_pending(stdout: &str) -> Result<(Vec<String>, Vec<String>)> {
+    // smoelius: The report is not necessarily the only thing written to stdout. For example,
+    // Hardhat may print "Compiling..." messages, and tests may print to the console, possibly
+    // objects of their own. So take the first JSON object with a `stats` key as the report, and
+    // ignore anything that follows it.
+    let report = stdout
+        .match_indices('{')
+        .find_map(|(start, _)| {
+            serde_json::Deserializer::from_str(&stdout[start..])
+                .into_iter::<serde_json::Value>()
+                .next()
+                .and_then(Result::ok)
+                .filter(|value| value.get("stats").is_some())
+        })
+        .ok_or_else(|| anyhow!("Failed to find Mocha JSON report"))?;
+    let titles = |key: &str| -> Result<Vec<String>> {
+        let tests = report
+            .get(key)
+            .and_then(serde_json::Value::as_array)
+            .ok_or_else(|| anyhow!("Mocha JSON report has no `{key}` array"))?;
+        tests
+            .iter()
+            .map(|test| {
+                test.get("title")
+                    .and_then(serde_json::Value::as_str)
+                    .map(ToOwned::to_owned)
+                    .ok_or_else(|| anyhow!("Mocha JSON report test has no `title`"))
+            })
+            .collect()
+    };
+    Ok((titles("passes")?, titles("pending")?))
+}
+
 #[derive(Clone, Copy)]
 pub struct Test<'ast> {
     it_message: &'ast JsWord,
@@ -210,13 +415,24 @@ impl<T: SwcSpanned> Spanned for SourceMapped<'_, T> {
     }
 }
 
+/// A function declaration, or a function or arrow expression bound to a variable, e.g.:
+///
+/// ```ts
+/// const helper = async () => { ... };
+/// ```
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub enum LocalFunction<'ast> {
+    Arrow(&'ast ArrowExpr),
+    Function(&'ast Function),
+}
+
 pub struct Types;
 
 impl AbstractTypes for Types {
     type Storage<'ast> = Storage<'ast>;
-    type File = (Rc<SourceMap>, Module);
+    type File = (Rc<SourceMap>, Rc<Module>);
     type Test<'ast> = Test<'ast>;
-    type LocalFunction<'ast> = &'ast FnDecl;
+    type LocalFunction<'ast> = LocalFunction<'ast>;
     type Statement<'ast> = SourceMapped<'ast, Stmt>;
     type Expression<'ast> = SourceMapped<'ast, Expr>;
     type Await<'ast> = &'ast AwaitExpr;
@@ -275,6 +491,10 @@ impl ParseLow for Mocha {
 
     const IGNORED_METHODS: Option<&'static [&'static str]> = Some(&["toNumber", "toString"]);
 
+    fn ignored_overrides(config: &config::Toml) -> config::IgnoredOverrides<'_> {
+        config.mocha.ignored_overrides()
+    }
+
     fn walk_dir(&self, root: &Path) -> Box<dyn Iterator<Item = WalkDirResult>> {
         Box::new(
             walkdir::WalkDir::new(root.join(&self.subdir))
@@ -288,22 +508,34 @@ impl ParseLow for Mocha {
         )
     }
 
+    // smoelius: The helpers in a file are attributed to the tests, in the files that import it,
+    // that call them. So those files affect the file's candidates.
+    fn dependencies(&mut self, context: &LightContext, source_file: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self.importers(context, source_file).into_iter().collect())
+    }
+
     fn parse_source_file(
         &self,
         source_file: &Path,
     ) -> Result<<Self::Types as AbstractTypes>::File> {
-        let source_file = self.source_map.load_file(source_file)?;
-        let lexer = Lexer::new(
-            Syntax::Typescript(TsSyntax::default()),
-            EsVersion::default(),
-            StringInput::from(&*source_file),
-            None,
-        );
-        let mut parser = Parser::new_from(lexer);
-        parser
-            .parse_typescript_module()
-            .map(|module| (self.source_map.clone(), module))
-            .map_err(|error| anyhow!(format!("{error:?}")))
+        let contents = read_to_string(source_file)?;
+        let module = self.parse_cache.parse(source_file, &contents, |contents| {
+            let source_file = self.source_map.new_source_file(
+                Rc::new(source_file.to_path_buf().into()),
+                contents.to_owned(),
+            );
+            let lexer = Lexer::new(
+                Syntax::Typescript(TsSyntax::default()),
+                EsVersion::default(),
+                StringInput::from(&*source_file),
+                None,
+            );
+            let mut parser = Parser::new_from(lexer);
+            parser
+                .parse_typescript_module()
+                .map_err(|error| anyhow!(format!("{error:?}")))
+        })?;
+        Ok((self.source_map.clone(), module))
     }
 
     fn storage_from_file<'ast>(
@@ -498,16 +730,14 @@ fn is_it_call_expr(expr: &Expr) -> Option<Test<'_>> {
             args,
             ..
         }) = expr;
-        if let Expr::Ident(ident) = &**callee;
-        if ident.as_ref() == "it";
+        if is_it(callee);
         if let [arg0, arg1] = args.as_slice();
         if let Expr::Lit(Lit::Str(Str { value, .. })) = &*arg0.expr;
-        if let Expr::Arrow(ArrowExpr { body, .. }) = &*arg1.expr;
-        if let BlockStmtOrExpr::BlockStmt(block) = &**body;
+        if let Some(stmts) = function_body(&arg1.expr);
         then {
             Some(Test {
                 it_message: value,
-                stmts: &block.stmts,
+                stmts,
             })
         } else {
             None
@@ -515,6 +745,71 @@ fn is_it_call_expr(expr: &Expr) -> Option<Test<'_>> {
     }
 }
 
+/// Returns true if `callee` is `it`, `it.only`, or `it.each(...)`, or the equivalent with `it`
+/// replaced by one of its aliases, `specify` or `test`
+fn is_it(callee: &Expr) -> bool {
+    match callee {
+        Expr::Ident(ident) => IT_ALIASES.contains(&ident.as_ref()),
+        Expr::Member(member) => is_it_method(member, "only"),
+        Expr::Call(CallExpr {
+            callee: Callee::Expr(callee),
+            ..
+        }) => matches!(&**callee, Expr::Member(member) if is_it_method(member, "each")),
+        _ => false,
+    }
+}
+
+fn is_it_method(member: &MemberExpr, method: &str) -> bool {
+    if_chain! {
+        if let Expr::Ident(ident) = &*member.obj;
+        if IT_ALIASES.contains(&ident.as_ref());
+        if let MemberProp::Ident(prop) = &member.prop;
+        then {
+            prop.as_ref() == method
+        } else {
+            false
+        }
+    }
+}
+
+/// Returns the statements of an arrow or function expression with a block body
+fn function_body(expr: &Expr) -> Option<&Vec<Stmt>> {
+    match expr.unwrap_parens() {
+        Expr::Arrow(ArrowExpr { body, .. }) => {
+            if let BlockStmtOrExpr::BlockStmt(block) = &**body {
+                Some(&block.stmts)
+            } else {
+                None
+            }
+        }
+        Expr::Fn(FnExpr { function, .. }) => function.body.as_ref().map(|block| &block.stmts),
+        _ => None,
+    }
+}
+
+/// Returns true if `it_message` could have been formatted from `template`, the message of a test
+/// defined with `it.each`. Both printf-style placeholders (e.g., `%s`) and `$variable`
+/// placeholders are recognized.
+fn is_formatted_from(template: &str, it_message: &str) -> bool {
+    if !PLACEHOLDER_RE.is_match(template) {
+        return false;
+    }
+    let mut pattern = String::from("^");
+    let mut last = 0;
+    for placeholder in PLACEHOLDER_RE.find_iter(template) {
+        pattern.push_str(&regex::escape(&template[last..placeholder.start()]));
+        if placeholder.as_str() == "%%" {
+            pattern.push('%');
+        } else {
+            pattern.push_str(".*");
+        }
+        last = placeholder.end();
+    }
+    pattern.push_str(&regex::escape(&template[last..]));
+    pattern.push('$');
+    Regex::new(&pattern).is_ok_and(|re| re.is_match(it_message))
+}
+
 trait ToInternalSpan {
     fn to_internal_span(&self, source_map: &SourceMap, source_file: &SourceFile) -> Span;
 }
@@ -544,3 +839,372 @@ impl ToLineColumn for BytePos {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Mocha;
+    use crate::{utils::light_context, ParseAdapter, ParseHigh};
+    use necessist_core::{candidates::Candidates, config};
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        fs::{create_dir, write},
+        rc::Rc,
+    };
+    use swc_core::{
+        common::BytePos,
+        ecma::{
+            ast::{EsVersion, Module},
+            parser::{lexer::Lexer, Parser, StringInput, Syntax, TsSyntax},
+        },
+    };
+    use tempfile::tempdir;
+
+    fn parse(source: &str) -> Module {
+        let lexer = Lexer::new(
+            Syntax::Typescript(TsSyntax::default()),
+            EsVersion::default(),
+            StringInput::new(
+                source,
+                BytePos(1),
+                BytePos(1 + u32::try_from(source.len()).unwrap()),
+            ),
+            None,
+        );
+        Parser::new_from(lexer).parse_typescript_module().unwrap()
+    }
+
+    #[test]
+    fn collect_local_functions() {
+        const SOURCE: &str = "
+function declared() {}
+const arrow = async () => {};
+const expression = function () {};
+const parenthesized = (() => {});
+const value = 0;
+const outer = () => {
+    function nestedDeclared() {}
+    const nestedArrow = () => {};
+};
+";
+
+        let module = parse(SOURCE);
+        let local_functions = super::collect_local_functions(&module);
+        assert_eq!(
+            [
+                "arrow",
+                "declared",
+                "expression",
+                "nestedArrow",
+                "nestedDeclared",
+                "outer",
+                "parenthesized"
+            ]
+            .as_slice(),
+            local_functions
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn helpers_called_by_helpers_are_walked() {
+        const SOURCE: &str = r#"
+const state = { inner: 0, nested: 0, outer: 0 };
+function inner(n) {
+    state.inner += n;
+    return state.inner;
+}
+const outer = () => {
+    const nested = function () {
+        state.nested += 1;
+        return state.nested;
+    };
+    state.outer += 1;
+    return inner(nested());
+};
+describe("Counter", () => {
+    it("increments", () => {
+        outer();
+        expect(1).to.equal(1);
+    });
+});
+"#;
+
+        let tempdir = tempdir().unwrap();
+        let root = Rc::new(tempdir.path().to_path_buf());
+        create_dir(root.join("test")).unwrap();
+        let source_file = root.join("test").join("counter.ts");
+        write(&source_file, SOURCE).unwrap();
+
+        let mut config = config::Toml::default();
+        config.walkable_functions = vec![String::from("*")];
+        let context = light_context(&root, &config);
+
+        let (_, source_file_span_test_map) = ParseAdapter(Mocha::new("test"))
+            .parse(
+                &context,
+                &config,
+                &[&source_file],
+                &mut Candidates::default(),
+            )
+            .unwrap();
+
+        let texts = source_file_span_test_map
+            .values()
+            .flat_map(|span_test_maps| {
+                span_test_maps
+                    .statement
+                    .keys()
+                    .chain(span_test_maps.method_call.keys())
+            })
+            .map(|span| span.source_text().unwrap())
+            .collect::<BTreeSet<_>>();
+
+        for text in [
+            "state.inner += n;",
+            "state.nested += 1;",
+            "state.outer += 1;",
+        ] {
+            assert!(texts.contains(text), "{texts:#?}");
+        }
+    }
+
+    #[test]
+    fn imported_helpers_are_walked() {
+        const UTILS: &str = "
+export const state = { deployed: 0, funded: 0, unused: 0 };
+export async function deploy() {
+    state.deployed += 1;
+}
+export const fund = () => {
+    state.funded += 1;
+};
+export function unused() {
+    state.unused += 1;
+}
+";
+        const TOKEN: &str = r#"
+import { deploy as d } from "./utils";
+import * as utils from "./utils.js";
+describe("Token", () => {
+    it("deploys", async () => {
+        await d();
+    });
+    it("funds", () => {
+        utils.fund();
+    });
+});
+"#;
+        const VAULT: &str = r#"
+const { fund } = require("../test/utils");
+it("is funded", () => {
+    fund();
+});
+"#;
+        const POOL: &str = r#"
+import { deploy } from "@test/utils";
+it("pools", async () => {
+    await deploy();
+});
+"#;
+
+        let tempdir = tempdir().unwrap();
+        let root = Rc::new(tempdir.path().to_path_buf());
+        create_dir(root.join("test")).unwrap();
+        let utils = root.join("test").join("utils.ts");
+        write(&utils, UTILS).unwrap();
+        write(root.join("test").join("token.ts"), TOKEN).unwrap();
+        write(root.join("test").join("vault.js"), VAULT).unwrap();
+        create_dir(root.join("test").join("nested")).unwrap();
+        write(root.join("test").join("nested").join("pool.ts"), POOL).unwrap();
+        write(
+            root.join("tsconfig.json"),
+            r#"{
+  // Comments and trailing commas are allowed.
+  "compilerOptions": { "paths": { "@test/*": ["./test/*"], } },
+}"#,
+        )
+        .unwrap();
+
+        let mut config = config::Toml::default();
+        config.walkable_functions = vec![String::from("*")];
+        let context = light_context(&root, &config);
+
+        let mocha = Mocha::new("test");
+        assert_eq!(
+            [
+                utils.clone(),
+                root.join("test").join("nested").join("pool.ts"),
+                root.join("test").join("token.ts"),
+                root.join("test").join("vault.js")
+            ]
+            .as_slice(),
+            mocha.test_files(&context, &utils)
+        );
+
+        let (_, source_file_span_test_map) = ParseAdapter(mocha)
+            .parse(&context, &config, &[&utils], &mut Candidates::default())
+            .unwrap();
+
+        let text_test_names = source_file_span_test_map
+            .values()
+            .flat_map(|span_test_maps| span_test_maps.statement.iter())
+            .map(|(span, test_names)| {
+                (
+                    span.source_text().unwrap(),
+                    test_names.iter().cloned().collect::<BTreeSet<_>>(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    String::from("state.deployed += 1;"),
+                    BTreeSet::from([String::from("deploys"), String::from("pools")])
+                ),
+                (
+                    String::from("state.funded += 1;"),
+                    BTreeSet::from([String::from("funds"), String::from("is funded")])
+                ),
+            ]),
+            text_test_names
+        );
+    }
+
+    #[test]
+    fn is_it_call_expr() {
+        const SOURCE: &str = r#"
+it("arrow", () => { f(); });
+it("function", function () { f(); });
+it("async function", async function () { f(); });
+specify("specify", () => { f(); });
+test("test", () => { f(); });
+it.only("only", () => { f(); });
+it.each([1, 2])("each %i", (n) => { f(); });
+test.each([1, 2])("test each %i", (n) => { f(); });
+it.skip("skip", () => { f(); });
+describe("describe", () => { f(); });
+it("expression body", () => f());
+"#;
+
+        let module = parse(SOURCE);
+        let it_messages = module
+            .body
+            .iter()
+            .filter_map(|item| item.as_stmt().and_then(super::is_it_call_stmt))
+            .map(|test| test.it_message.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            [
+                "arrow",
+                "function",
+                "async function",
+                "specify",
+                "test",
+                "only",
+                "each %i",
+                "test each %i"
+            ]
+            .as_slice(),
+            it_messages
+        );
+    }
+
+    #[test]
+    fn is_formatted_from() {
+        assert!(super::is_formatted_from("adds %i + %i", "adds 1 + 2"));
+        assert!(super::is_formatted_from("returns $expected", "returns 3"));
+        assert!(super::is_formatted_from("100%% of %s", "100% of tests"));
+        assert!(!super::is_formatted_from("adds %i + %i", "subtracts 1 - 2"));
+        assert!(!super::is_formatted_from("adds", "adds"));
+    }
+
+    #[test]
+    fn is_ambiguous() {
+        let passes = ["should unlock", "should unlock twice", "should deploy"]
+            .map(ToOwned::to_owned)
+            .to_vec();
+        assert!(super::is_ambiguous(&passes, "should unlock"));
+        assert!(!super::is_ambiguous(&passes, "should unlock twice"));
+        assert!(!super::is_ambiguous(&passes, "should deploy"));
+    }
+
+    #[test]
+    fn spec_passes_and_pending() {
+        const STDOUT: &str = "
+  Lock
+    ✔ should deploy (with arguments)
+    ✔ should unlock (123ms)
+    - should unlock on mainnet
+
+
+  2 passing (150ms)
+  1 pending
+";
+        // smoelius: Lines other than tests' (e.g., suite titles) may be mistaken for passes, which
+        // is harmless. But a pending test must not be.
+        let (passes, pending) = super::spec_passes_and_pending(STDOUT);
+        assert!(passes.contains(&"should deploy (with arguments)".to_owned()));
+        assert!(passes.contains(&"should unlock".to_owned()));
+        assert!(!passes.contains(&"should unlock on mainnet".to_owned()));
+        assert_eq!(vec!["should unlock on mainnet".to_owned()], pending);
+    }
+
+    #[test]
+    fn json_report_passes_and_pending() {
+        const STDOUT: &str = r#"Compiling 1 file with 0.8.9
+{
+  "stats": {
+    "suites": 1,
+    "tests": 3,
+    "passes": 2,
+    "pending": 1
+  },
+  "tests": [],
+  "pending": [
+    {
+      "title": "should unlock on mainnet",
+      "fullTitle": "Lock should unlock on mainnet"
+    }
+  ],
+  "failures": [],
+  "passes": [
+    {
+      "title": "should deploy (with arguments)",
+      "fullTitle": "Lock should deploy (with arguments)"
+    },
+    {
+      "title": "should unlock (123ms)",
+      "fullTitle": "Lock should unlock (123ms)"
+    }
+  ]
+}
+console output after the report
+"#;
+        assert_eq!(
+            (
+                vec![
+                    "should deploy (with arguments)".to_owned(),
+                    "should unlock (123ms)".to_owned()
+                ],
+                vec!["should unlock on mainnet".to_owned()]
+            ),
+            super::json_report_passes_and_pending(STDOUT).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_report_after_console_output() {
+        const STDOUT: &str = r#"{ balance: 1n }
+{"event":"Transfer","args":{"value":1}}
+{"stats":{"suites":1,"tests":1,"passes":1,"pending":0},"tests":[],"pending":[],"failures":[],"passes":[{"title":"should transfer","fullTitle":"Token should transfer"}]}
+"#;
+        assert_eq!(
+            (vec!["should transfer".to_owned()], Vec::<String>::new()),
+            super::json_report_passes_and_pending(STDOUT).unwrap()
+        );
+        assert!(super::json_report_passes_and_pending("{ balance: 1n }\n").is_err());
+    }
+}
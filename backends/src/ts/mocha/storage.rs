@@ -6,7 +6,7 @@ pub struct Storage<'ast> {
 }
 
 impl<'ast> Storage<'ast> {
-    pub fn new(file: &'ast (Rc<SourceMap>, Module)) -> Self {
+    pub fn new(file: &'ast (Rc<SourceMap>, Rc<Module>)) -> Self {
         Self {
             source_map: &file.0,
         }
@@ -0,0 +1,225 @@
+//! Support for the `baseUrl` and `paths` compiler options of `tsconfig.json` files, which let
+//! TypeScript files import one another with non-relative specifiers, e.g., `@test/utils`.
+
+use crate::utils;
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// The module resolution options of a `tsconfig.json` file
+pub(super) struct Tsconfig {
+    base_url: Option<PathBuf>,
+    /// The directory against which `paths`' substitutions are resolved: `baseUrl` if it is set,
+    /// and the `tsconfig.json` file's directory otherwise
+    paths_base: PathBuf,
+    /// `paths`' patterns (e.g., `@test/*`) and their substitutions (e.g., `["test/*"]`)
+    paths: Vec<(String, Vec<String>)>,
+}
+
+impl Tsconfig {
+    /// Returns the `tsconfig.json` file nearest to `importer`, searching its ancestors up to and
+    /// including `root`. A file that cannot be read or parsed is treated as absent. An `extends`
+    /// field is not followed.
+    pub(super) fn nearest(root: &Path, importer: &Path) -> Option<Self> {
+        importer
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root))
+            .map(|dir| dir.join("tsconfig.json"))
+            .find(|path| path.is_file())
+            .and_then(|path| {
+                let contents = read_to_string(&path).ok()?;
+                Self::parse(path.parent()?, &contents)
+            })
+    }
+
+    fn parse(dir: &Path, contents: &str) -> Option<Self> {
+        let value = serde_json::from_str::<serde_json::Value>(&strip_jsonc(contents)).ok()?;
+        let compiler_options = value.get("compilerOptions");
+        let base_url = compiler_options
+            .and_then(|compiler_options| compiler_options.get("baseUrl"))
+            .and_then(serde_json::Value::as_str)
+            .map(|base_url| utils::normalize_path(&dir.join(base_url)));
+        let paths = compiler_options
+            .and_then(|compiler_options| compiler_options.get("paths"))
+            .and_then(serde_json::Value::as_object)
+            .map(|paths| {
+                paths
+                    .iter()
+                    .map(|(pattern, substitutions)| {
+                        let substitutions = substitutions
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|substitution| substitution.as_str().map(ToOwned::to_owned))
+                            .collect();
+                        (pattern.clone(), substitutions)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            paths_base: base_url.clone().unwrap_or_else(|| dir.to_path_buf()),
+            base_url,
+            paths,
+        })
+    }
+
+    /// Returns the paths to which TypeScript would map the non-relative `specifier`, in the order
+    /// it would try them. As in TypeScript, the `paths` pattern with the longest prefix matching
+    /// `specifier` is used, and if no pattern matches, `specifier` is resolved against `baseUrl`.
+    pub(super) fn candidates(&self, specifier: &str) -> Vec<PathBuf> {
+        let best = self
+            .paths
+            .iter()
+            .filter_map(|(pattern, substitutions)| {
+                let star = match_pattern(pattern, specifier)?;
+                Some((
+                    pattern.find('*').unwrap_or(pattern.len()),
+                    star,
+                    substitutions,
+                ))
+            })
+            .max_by_key(|&(prefix_len, _, _)| prefix_len);
+        if let Some((_, star, substitutions)) = best {
+            return substitutions
+                .iter()
+                .map(|substitution| {
+                    let path = self.paths_base.join(substitution.replacen('*', star, 1));
+                    utils::normalize_path(&path)
+                })
+                .collect();
+        }
+        self.base_url
+            .iter()
+            .map(|base_url| utils::normalize_path(&base_url.join(specifier)))
+            .collect()
+    }
+}
+
+/// If `specifier` matches `pattern`, returns the text matched by `pattern`'s `*`, or the empty
+/// string if `pattern` has none
+fn match_pattern<'a>(pattern: &str, specifier: &'a str) -> Option<&'a str> {
+    let Some((prefix, suffix)) = pattern.split_once('*') else {
+        return (pattern == specifier).then_some("");
+    };
+    if specifier.len() < prefix.len() + suffix.len() {
+        return None;
+    }
+    specifier.strip_prefix(prefix)?.strip_suffix(suffix)
+}
+
+/// Removes the comments and trailing commas that `tsconfig.json` files may contain, but that JSON
+/// does not allow
+fn strip_jsonc(contents: &str) -> String {
+    let mut json = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                json.push(c);
+                while let Some(c) = chars.next() {
+                    json.push(c);
+                    if c == '\\' {
+                        json.extend(chars.next());
+                    } else if c == '"' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = None;
+                for c in chars.by_ref() {
+                    if prev == Some('*') && c == '/' {
+                        break;
+                    }
+                    prev = Some(c);
+                }
+            }
+            '}' | ']' => {
+                // smoelius: Comments have already been removed from what precedes `c`, so a
+                // trailing comma is the last non-whitespace character.
+                let len = json.trim_end().len();
+                if json[..len].ends_with(',') {
+                    json.remove(len - 1);
+                }
+                json.push(c);
+            }
+            _ => json.push(c),
+        }
+    }
+    json
+}
+
+#[cfg(test)]
+mod test {
+    use super::Tsconfig;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn strip_jsonc() {
+        const CONTENTS: &str = r#"{
+  // The compiler options
+  "compilerOptions": {
+    /* Paths */ "paths": { "@test/*": ["test/*",], },
+    "outDir": "dist//*not a comment*/",
+  },
+}"#;
+        let value =
+            serde_json::from_str::<serde_json::Value>(&super::strip_jsonc(CONTENTS)).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "compilerOptions": {
+                    "paths": { "@test/*": ["test/*"] },
+                    "outDir": "dist//*not a comment*/"
+                }
+            }),
+            value
+        );
+    }
+
+    #[test]
+    fn candidates() {
+        const CONTENTS: &str = r#"{
+  "compilerOptions": {
+    "paths": {
+      "@test/*": ["test/*", "fallback/*"],
+      "@test/helpers/*": ["test/shared/helpers/*"],
+      "config": ["./hardhat.config.ts"]
+    }
+  }
+}"#;
+        let dir = Path::new("/project");
+        let tsconfig = Tsconfig::parse(dir, CONTENTS).unwrap();
+        assert_eq!(
+            [dir.join("test/utils"), dir.join("fallback/utils")].as_slice(),
+            tsconfig.candidates("@test/utils")
+        );
+        assert_eq!(
+            [dir.join("test/shared/helpers/deploy")].as_slice(),
+            tsconfig.candidates("@test/helpers/deploy")
+        );
+        assert_eq!(
+            [dir.join("hardhat.config.ts")].as_slice(),
+            tsconfig.candidates("config")
+        );
+        assert!(tsconfig.candidates("chai").is_empty());
+
+        let tsconfig = Tsconfig::parse(
+            dir,
+            r#"{ "compilerOptions": { "baseUrl": "./src", "paths": { "~/*": ["../test/*"] } } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            [dir.join("test/utils")].as_slice(),
+            tsconfig.candidates("~/utils")
+        );
+        assert_eq!(
+            [PathBuf::from("/project/src/lib/math")].as_slice(),
+            tsconfig.candidates("lib/math")
+        );
+    }
+}
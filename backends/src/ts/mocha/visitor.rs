@@ -1,32 +1,67 @@
-use super::{is_it_call_expr, is_it_call_stmt, GenericVisitor, Mocha, SourceMapped, Storage};
+use super::{
+    is_it_call_expr, is_it_call_stmt, GenericVisitor, LocalFunction, Mocha, SourceMapped, Storage,
+};
 use anyhow::Result;
 use necessist_core::framework::{SpanTestMaps, TestSet};
-use std::{cell::RefCell, collections::BTreeMap};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+};
 use swc_core::ecma::{
-    ast::{Expr, FnDecl, Module, Stmt},
+    ast::{Expr, FnDecl, Module, Pat, Stmt, VarDeclarator},
     visit::{Visit, VisitWith},
 };
 
-pub(super) fn collect_local_functions(module: &Module) -> BTreeMap<String, Vec<&FnDecl>> {
-    let mut collector = FnDeclCollector::default();
+pub(super) fn collect_local_functions(module: &Module) -> BTreeMap<String, Vec<LocalFunction<'_>>> {
+    let mut collector = LocalFunctionCollector::default();
     collector.visit_module(module);
-    collector.fn_decls.split_off(&String::new())
+    collector.local_functions.split_off(&String::new())
 }
 
 #[derive(Default)]
-struct FnDeclCollector<'ast> {
-    fn_decls: BTreeMap<String, Vec<&'ast FnDecl>>,
+struct LocalFunctionCollector<'ast> {
+    local_functions: BTreeMap<String, Vec<LocalFunction<'ast>>>,
 }
 
-impl<'ast> Visit for FnDeclCollector<'ast> {
+impl<'ast> Visit for LocalFunctionCollector<'ast> {
     fn visit_fn_decl(&mut self, fn_decl: &FnDecl) {
         // smoelius: Unsafe hack to work around: https://github.com/swc-project/swc/issues/6032
         let fn_decl = unsafe { std::mem::transmute::<&FnDecl, &'ast FnDecl>(fn_decl) };
 
-        self.fn_decls
-            .entry(fn_decl.ident.to_string())
+        self.local_functions
+            .entry(fn_decl.ident.as_ref().to_owned())
+            .or_default()
+            .push(LocalFunction::Function(&fn_decl.function));
+
+        // smoelius: A helper can declare helpers of its own, which it may call.
+        fn_decl.visit_children_with(self);
+    }
+
+    fn visit_var_declarator(&mut self, var_declarator: &VarDeclarator) {
+        // smoelius: Unsafe hack to work around: https://github.com/swc-project/swc/issues/6032
+        let var_declarator =
+            unsafe { std::mem::transmute::<&VarDeclarator, &'ast VarDeclarator>(var_declarator) };
+
+        let Pat::Ident(ident) = &var_declarator.name else {
+            var_declarator.visit_children_with(self);
+            return;
+        };
+
+        let local_function = match var_declarator.init.as_deref().map(Expr::unwrap_parens) {
+            Some(Expr::Arrow(arrow_expr)) => LocalFunction::Arrow(arrow_expr),
+            Some(Expr::Fn(fn_expr)) => LocalFunction::Function(&fn_expr.function),
+            _ => {
+                var_declarator.visit_children_with(self);
+                return;
+            }
+        };
+
+        self.local_functions
+            .entry(ident.id.as_ref().to_owned())
             .or_default()
-            .push(fn_decl);
+            .push(local_function);
+
+        var_declarator.visit_children_with(self);
     }
 }
 
@@ -38,12 +73,43 @@ pub(super) fn visit<'ast>(
 ) -> Result<(TestSet, SpanTestMaps)> {
     let mut visitor = Visitor::new(generic_visitor, storage);
     visitor.visit_module(module);
+    visit_importer_callers(&mut visitor.generic_visitor);
     while let Some(local_function) = visitor.generic_visitor.next_local_function() {
         visitor.visit_local_function(local_function);
     }
     visitor.generic_visitor.results()
 }
 
+/// Queues the helpers in the source file that are called by tests in the files that import it
+fn visit_importer_callers(generic_visitor: &mut GenericVisitor<'_, '_, '_, '_, Mocha>) {
+    let helpers = generic_visitor
+        .walkable_functions
+        .keys()
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    if helpers.is_empty() {
+        return;
+    }
+    let callers = generic_visitor.backend.importer_callers(
+        generic_visitor.context,
+        &generic_visitor.source_file,
+        &helpers,
+    );
+    for (helper, test_names) in callers {
+        let local_functions = &generic_visitor.walkable_functions[&helper];
+        if local_functions.len() >= 2 {
+            generic_visitor
+                .local_functions_needing_warnings
+                .insert(helper.clone());
+        }
+        generic_visitor
+            .local_functions_pending
+            .entry(local_functions[0])
+            .or_default()
+            .extend(test_names);
+    }
+}
+
 struct Visitor<'context, 'config, 'backend, 'ast, 'storage> {
     generic_visitor: GenericVisitor<'context, 'config, 'backend, 'ast, Mocha>,
     storage: &'storage RefCell<Storage<'ast>>,
@@ -62,8 +128,11 @@ impl<'context, 'config, 'backend, 'ast, 'storage>
         }
     }
 
-    fn visit_local_function(&mut self, local_function: &'ast FnDecl) {
-        local_function.visit_children_with(self);
+    fn visit_local_function(&mut self, local_function: LocalFunction<'ast>) {
+        match local_function {
+            LocalFunction::Arrow(arrow_expr) => arrow_expr.visit_children_with(self),
+            LocalFunction::Function(function) => function.visit_children_with(self),
+        }
     }
 }
 
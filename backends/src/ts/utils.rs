@@ -1,45 +1,504 @@
-use crate::utils::{OutputAccessors, OutputStrippedOfAnsiScapes};
-use anyhow::{ensure, Result};
-use log::debug;
-use necessist_core::LightContext;
-use std::process::Command;
+use crate::utils::{to_slash_string, OutputAccessors, OutputStrippedOfAnsiScapes};
+use anyhow::{ensure, Context, Result};
+use necessist_core::{config::PackageManager, install, util, LightContext};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeSet,
+    fs::{read, read_to_string, write},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::Instant,
+};
+use tracing::debug;
 
+/// The lock files from which node modules can be installed, in order of precedence
+const LOCK_FILES: &[&str] = &["pnpm-lock.yaml", "yarn.lock", "package-lock.json"];
+
+/// A file that Necessist writes to `node_modules` after installing it, holding a hash of the lock
+/// file it was installed from
+const MARKER: &str = ".necessist-lock-hash";
+
+/// The roots whose node modules are known to be up to date
+static INSTALLED: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Installs the node modules of the project at `context.root`, unless they are up to date. Node
+/// modules are up to date if they were installed from the current lock file, or if they were
+/// installed by something other than Necessist.
+///
+/// If the project belongs to a workspace (e.g., a Hardhat project in a monorepo), the node modules
+/// are installed from the workspace's root.
 pub fn install_node_modules(context: &LightContext) -> Result<()> {
-    if context.root.join("node_modules").try_exists()? {
+    #[allow(clippy::unwrap_used)]
+    let mut installed = INSTALLED.lock().unwrap();
+
+    if installed.contains(context.root.as_path()) {
         return Ok(());
     }
 
-    // smoelius: If a `pnpm-lock.yaml` file exists, use `pnpm install`. If a `yarn.lock` file
-    // exists, use `yarn`. If neither exist, default to `npm install`.
-    let mut command = if context.root.join("pnpm-lock.yaml").try_exists()? {
-        let mut command = script("pnpm");
-        command.arg("install");
-        command
-    } else if context.root.join("yarn.lock").try_exists()? {
-        script("yarn")
-    } else {
-        let mut command = Command::new("npm");
-        command.arg("install");
-        command
+    let dir = install_dir(context.root)?;
+
+    let lock_hash = lock_hash(&dir)?;
+
+    if !up_to_date(&dir, lock_hash.as_deref())? {
+        let package_manager = context
+            .config
+            .js
+            .package_manager
+            .map_or_else(|| package_manager(&dir), Ok)?;
+
+        let start = Instant::now();
+        run_package_manager(context, package_manager, &dir)?;
+        install::record(start.elapsed());
+
+        let node_modules = dir.join("node_modules");
+        // smoelius: With `--remote`, the node modules are installed on the remote machine.
+        if let Some(lock_hash) = lock_hash {
+            if node_modules.try_exists()? {
+                write(node_modules.join(MARKER), lock_hash)?;
+            }
+        }
+    }
+
+    installed.insert(context.root.to_path_buf());
+
+    Ok(())
+}
+
+/// Returns the directory from which the node modules of the project at `root` should be installed:
+/// the root of the workspace containing `root`, if any, and `root` otherwise. The search stops at
+/// the root of the git repository containing `root`, or at `root` if it has a lock file.
+fn install_dir(root: &Path) -> Result<PathBuf> {
+    if lock_hash(root)?.is_some() {
+        return Ok(root.to_path_buf());
+    }
+
+    for dir in root.ancestors() {
+        if is_workspace_root(dir)? {
+            return Ok(dir.to_path_buf());
+        }
+        if dir.join(".git").try_exists()? {
+            break;
+        }
+    }
+
+    Ok(root.to_path_buf())
+}
+
+/// Returns true if `dir` has a `pnpm-workspace.yaml` file, or a `package.json` file with a
+/// `workspaces` field
+fn is_workspace_root(dir: &Path) -> Result<bool> {
+    if dir.join("pnpm-workspace.yaml").try_exists()? {
+        return Ok(true);
+    }
+    Ok(package_json(dir)?.is_some_and(|package_json| package_json.get("workspaces").is_some()))
+}
+
+/// Returns the directories (relative to `root`) of the workspace packages that contain a file named
+/// `file`, e.g., `hardhat.config.ts`. The packages are those matched by the patterns in `root`'s
+/// `pnpm-workspace.yaml` file, or in its `package.json` file's `workspaces` field. A pattern
+/// beginning with `!` excludes the packages it matches.
+pub fn workspace_packages_containing(root: &Path, file: &str) -> Result<Vec<PathBuf>> {
+    let patterns = workspace_patterns(root)?;
+    if patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut includes = Vec::new();
+    let mut excludes = Vec::new();
+    for pattern in &patterns {
+        if let Some(pattern) = pattern.strip_prefix('!') {
+            excludes.push(glob_regex(pattern)?);
+        } else {
+            includes.push(glob_regex(pattern)?);
+        }
+    }
+
+    let mut packages = Vec::new();
+    for entry in walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let file_name = entry.file_name().to_string_lossy();
+            entry.file_type().is_dir() && file_name != "node_modules" && !file_name.starts_with('.')
+        })
+    {
+        let entry = entry?;
+        if !entry.path().join(file).try_exists()? {
+            continue;
+        }
+        let package = util::strip_prefix(entry.path(), root)?;
+        let package_string = to_slash_string(package);
+        if includes.iter().any(|re| re.is_match(&package_string))
+            && !excludes.iter().any(|re| re.is_match(&package_string))
+        {
+            packages.push(package.to_path_buf());
+        }
+    }
+
+    Ok(packages)
+}
+
+fn workspace_patterns(root: &Path) -> Result<Vec<String>> {
+    let pnpm_workspace_yaml = root.join("pnpm-workspace.yaml");
+    if pnpm_workspace_yaml.try_exists()? {
+        let contents = read_to_string(&pnpm_workspace_yaml)?;
+        return Ok(pnpm_workspace_packages(&contents));
+    }
+
+    // smoelius: Yarn Classic also allows `workspaces` to be an object with a `packages` field.
+    let Some(package_json) = package_json(root)? else {
+        return Ok(Vec::new());
+    };
+    let patterns = package_json.get("workspaces").and_then(|workspaces| {
+        workspaces
+            .as_array()
+            .or_else(|| workspaces.get("packages")?.as_array())
+    });
+    Ok(patterns
+        .into_iter()
+        .flatten()
+        .filter_map(|pattern| pattern.as_str().map(ToOwned::to_owned))
+        .collect())
+}
+
+/// Returns the `packages` list of a `pnpm-workspace.yaml` file. Only the block sequence form (i.e.,
+/// one `- pattern` per line) is recognized, which is the form pnpm's documentation uses.
+fn pnpm_workspace_packages(contents: &str) -> Vec<String> {
+    let mut packages = Vec::new();
+    let mut in_packages = false;
+    for line in contents.lines() {
+        let line = line.split_once(" #").map_or(line, |(line, _)| line);
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = line.trim_end() == "packages:";
+            continue;
+        }
+        if let Some(item) = line.trim_start().strip_prefix('-').filter(|_| in_packages) {
+            packages.push(item.trim().trim_matches(['\'', '"']).to_owned());
+        }
+    }
+    packages
+}
+
+/// Converts a workspace glob (e.g., `packages/*`) into a regex that matches slash-separated paths
+fn glob_regex(pattern: &str) -> Result<Regex> {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    let mut re = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                re.push_str(".*");
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).map_err(Into::into)
+}
+
+/// Returns the package manager that should install the node modules in `dir`. If `dir` has a
+/// `pnpm-lock.yaml` file or a `pnpm-workspace.yaml` file, use pnpm. If it has a `yarn.lock` file,
+/// use Yarn. Otherwise, use the package manager named by `package.json`'s `packageManager` field,
+/// or npm if there is none.
+fn package_manager(dir: &Path) -> Result<PackageManager> {
+    if dir.join("pnpm-lock.yaml").try_exists()? || dir.join("pnpm-workspace.yaml").try_exists()? {
+        return Ok(PackageManager::Pnpm);
+    }
+    if dir.join("yarn.lock").try_exists()? {
+        return Ok(PackageManager::Yarn);
+    }
+    let package_manager = package_json(dir)?.and_then(|package_json| {
+        let name = package_json.get("packageManager")?.as_str()?;
+        match name.split_once('@').map_or(name, |(name, _)| name) {
+            "pnpm" => Some(PackageManager::Pnpm),
+            "yarn" => Some(PackageManager::Yarn),
+            _ => None,
+        }
+    });
+    Ok(package_manager.unwrap_or(PackageManager::Npm))
+}
+
+fn package_json(dir: &Path) -> Result<Option<serde_json::Value>> {
+    let path = dir.join("package.json");
+    if !path.try_exists()? {
+        return Ok(None);
+    }
+    let contents = read_to_string(&path)?;
+    let value =
+        serde_json::from_str(&contents).with_context(|| format!("Failed to parse {path:?}"))?;
+    Ok(Some(value))
+}
+
+fn run_package_manager(
+    context: &LightContext,
+    package_manager: PackageManager,
+    dir: &Path,
+) -> Result<()> {
+    let mut command = match package_manager {
+        PackageManager::Npm => {
+            let mut command = Command::new("npm");
+            command.arg("install");
+            command
+        }
+        PackageManager::Pnpm => {
+            let mut command = script("pnpm");
+            command.arg("install");
+            command
+        }
+        PackageManager::Yarn => script("yarn"),
     };
 
-    command.current_dir(context.root.as_path());
+    command.current_dir(dir);
 
     debug!("{:?}", command);
 
-    let output = command.output_stripped_of_ansi_escapes()?;
+    let output = command.output_stripped_of_ansi_escapes(context)?;
     ensure!(output.status().success(), "{:#?}", output);
     Ok(())
 }
 
-#[cfg(not(windows))]
-pub fn script(program: &str) -> Command {
-    Command::new(program)
+/// Returns a hash of the lock file of the project at `root`, if it has one
+fn lock_hash(root: &Path) -> Result<Option<String>> {
+    for lock_file in LOCK_FILES {
+        let path = root.join(lock_file);
+        if path.try_exists()? {
+            let contents = read(path)?;
+            return Ok(Some(hex::encode(Sha256::digest(contents))));
+        }
+    }
+    Ok(None)
+}
+
+fn up_to_date(root: &Path, lock_hash: Option<&str>) -> Result<bool> {
+    let node_modules = root.join("node_modules");
+    if !node_modules.try_exists()? {
+        return Ok(false);
+    }
+    match read_to_string(node_modules.join(MARKER)) {
+        Ok(marker) => Ok(Some(marker.trim()) == lock_hash),
+        // smoelius: The node modules were not installed by Necessist. Assume they are up to date.
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(true),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// The loader used for an ES module package whose `package.json` configures none
+const DEFAULT_ESM_LOADER: &str = "ts-node/esm";
+
+/// Adds `--loader <LOADER>` to the `NODE_OPTIONS` with which `command` is run, if the package at
+/// `dir` needs a loader for its TypeScript files to be run as ES modules. See [`loader`].
+pub fn set_node_options(context: &LightContext, dir: &Path, command: &mut Command) -> Result<()> {
+    let package_json = package_json(dir)?;
+    let Some(loader) = loader(
+        context.config.mocha.loader.as_deref(),
+        package_json.as_ref(),
+    ) else {
+        return Ok(());
+    };
+    let node_options = std::env::var("NODE_OPTIONS").unwrap_or_default();
+    // smoelius: A loader set by the user's environment takes precedence.
+    if node_options.contains("--loader") || node_options.contains("--experimental-loader") {
+        return Ok(());
+    }
+    command.env(
+        "NODE_OPTIONS",
+        format!("{node_options} --loader {loader}").trim_start(),
+    );
+    Ok(())
+}
+
+/// Returns the loader with which to run a package's TypeScript files. If `mocha.loader` is set, it
+/// is used (an empty string meaning no loader). Otherwise, if the package's `package.json` has
+/// `"type": "module"` and its `mocha` section does not configure a loader, [`DEFAULT_ESM_LOADER`]
+/// is used.
+fn loader(config_loader: Option<&str>, package_json: Option<&serde_json::Value>) -> Option<String> {
+    if let Some(loader) = config_loader {
+        return Some(loader.to_owned()).filter(|loader| !loader.is_empty());
+    }
+    let package_json = package_json?;
+    let esm = package_json.get("type").and_then(serde_json::Value::as_str) == Some("module");
+    let mocha_loader = package_json
+        .get("mocha")
+        .is_some_and(|mocha| mocha.get("loader").is_some() || mocha.get("node-option").is_some());
+    (esm && !mocha_loader).then(|| DEFAULT_ESM_LOADER.to_owned())
 }
 
-#[cfg(windows)]
+/// Returns a command to run `program`, which on Windows is a `.cmd` script that must be run by
+/// `cmd`.
 pub fn script(program: &str) -> Command {
-    let mut command = Command::new("cmd");
-    command.args(["/c", &format!("{program}.cmd")]);
-    command
+    script_for(program, cfg!(windows))
+}
+
+fn script_for(program: &str, windows: bool) -> Command {
+    if windows {
+        let mut command = Command::new("cmd");
+        command.args(["/c", &format!("{program}.cmd")]);
+        command
+    } else {
+        Command::new(program)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        install_dir, lock_hash, package_manager, up_to_date, workspace_packages_containing, MARKER,
+    };
+    use necessist_core::config::PackageManager;
+    use serde_json::json;
+    use std::{
+        fs::{create_dir, create_dir_all, write},
+        path::Path,
+    };
+    use tempfile::tempdir;
+
+    #[test]
+    fn workspace_root_is_install_dir() {
+        let tempdir = tempdir().unwrap();
+        let workspace = tempdir.path();
+        let root = workspace.join("packages/contracts");
+        create_dir_all(&root).unwrap();
+        create_dir(workspace.join(".git")).unwrap();
+        write(root.join("package.json"), "{}").unwrap();
+
+        assert_eq!(root, install_dir(&root).unwrap());
+        assert_eq!(PackageManager::Npm, package_manager(&root).unwrap());
+
+        write(
+            workspace.join("package.json"),
+            r#"{ "workspaces": ["packages/*"], "packageManager": "yarn@4.5.0" }"#,
+        )
+        .unwrap();
+        assert_eq!(workspace, install_dir(&root).unwrap());
+        assert_eq!(PackageManager::Yarn, package_manager(workspace).unwrap());
+
+        write(workspace.join("pnpm-workspace.yaml"), "").unwrap();
+        assert_eq!(PackageManager::Pnpm, package_manager(workspace).unwrap());
+
+        // smoelius: A project with its own lock file is not installed from the workspace's root.
+        write(root.join("package-lock.json"), "{}").unwrap();
+        assert_eq!(root, install_dir(&root).unwrap());
+    }
+
+    #[test]
+    fn workspace_packages() {
+        let tempdir = tempdir().unwrap();
+        let workspace = tempdir.path();
+        for package in [
+            "packages/contracts",
+            "packages/legacy",
+            "packages/app",
+            "other/contracts",
+        ] {
+            create_dir_all(workspace.join(package)).unwrap();
+        }
+        for package in ["packages/contracts", "packages/legacy", "other/contracts"] {
+            write(workspace.join(package).join("hardhat.config.ts"), "").unwrap();
+        }
+
+        let packages = || workspace_packages_containing(workspace, "hardhat.config.ts").unwrap();
+
+        assert!(packages().is_empty());
+
+        write(
+            workspace.join("package.json"),
+            r#"{ "workspaces": { "packages": ["packages/*"] } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            [
+                Path::new("packages/contracts"),
+                Path::new("packages/legacy")
+            ]
+            .as_slice(),
+            packages()
+        );
+
+        write(
+            workspace.join("pnpm-workspace.yaml"),
+            "\
+# The Hardhat package
+packages:
+  - 'packages/**'
+  - \"!packages/legacy\" # Excluded
+onlyBuiltDependencies:
+  - other/contracts
+",
+        )
+        .unwrap();
+        assert_eq!([Path::new("packages/contracts")].as_slice(), packages());
+    }
+
+    #[test]
+    fn node_modules_up_to_date() {
+        let tempdir = tempdir().unwrap();
+        let root = tempdir.path();
+
+        assert_eq!(None, lock_hash(root).unwrap());
+        assert!(!up_to_date(root, None).unwrap());
+
+        write(root.join("package-lock.json"), "{}").unwrap();
+        let hash = lock_hash(root).unwrap();
+        assert!(hash.is_some());
+
+        // smoelius: Node modules not installed by Necessist are assumed to be up to date.
+        create_dir(root.join("node_modules")).unwrap();
+        assert!(up_to_date(root, hash.as_deref()).unwrap());
+
+        write(
+            root.join("node_modules").join(MARKER),
+            hash.as_ref().unwrap(),
+        )
+        .unwrap();
+        assert!(up_to_date(root, hash.as_deref()).unwrap());
+
+        write(root.join("package-lock.json"), "{ }").unwrap();
+        let other_hash = lock_hash(root).unwrap();
+        assert_ne!(hash, other_hash);
+        assert!(!up_to_date(root, other_hash.as_deref()).unwrap());
+    }
+
+    #[test]
+    fn loader() {
+        let cjs = json!({});
+        let esm = json!({ "type": "module" });
+        let esm_with_loader = json!({ "type": "module", "mocha": { "loader": "tsx" } });
+
+        assert_eq!(None, super::loader(None, None));
+        assert_eq!(None, super::loader(None, Some(&cjs)));
+        assert_eq!(
+            Some("ts-node/esm".to_owned()),
+            super::loader(None, Some(&esm))
+        );
+        assert_eq!(None, super::loader(None, Some(&esm_with_loader)));
+        assert_eq!(
+            Some("tsx".to_owned()),
+            super::loader(Some("tsx"), Some(&cjs))
+        );
+        assert_eq!(None, super::loader(Some(""), Some(&esm)));
+    }
+
+    #[test]
+    fn script_for() {
+        let command = super::script_for("yarn", false);
+        assert_eq!("yarn", command.get_program());
+        assert_eq!(0, command.get_args().len());
+
+        let command = super::script_for("yarn", true);
+        assert_eq!("cmd", command.get_program());
+        assert_eq!(
+            ["/c", "yarn.cmd"].as_slice(),
+            command.get_args().collect::<Vec<_>>()
+        );
+    }
 }
@@ -1,29 +1,89 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use assert_cmd::output::OutputError;
-use std::process::{Command, ExitStatus, Output};
+use necessist_core::{dispatch, truncation, LightContext};
+use std::{
+    path::{Component, Path, PathBuf, MAIN_SEPARATOR_STR},
+    process::{Command, ExitStatus},
+};
 
 pub trait OutputStrippedOfAnsiScapes {
-    fn output_stripped_of_ansi_escapes(&mut self) -> Result<OutputError>;
+    fn output_stripped_of_ansi_escapes(&mut self, context: &LightContext) -> Result<OutputError>;
 }
 
 impl OutputStrippedOfAnsiScapes for Command {
-    fn output_stripped_of_ansi_escapes(&mut self) -> Result<OutputError> {
-        #[allow(clippy::disallowed_methods)]
-        let Output {
-            status,
-            stdout,
-            stderr,
-        } = self
-            .output()
-            .with_context(|| format!("Failed to run command: {self:?}"))?;
-        Ok(OutputError::new(Output {
-            status,
-            stdout: strip_ansi_escapes::strip(stdout),
-            stderr: strip_ansi_escapes::strip(stderr),
-        }))
+    /// Like [`Command::output`], but strips ANSI escapes from the command's standard output and
+    /// error as they are read, and keeps at most `max_output_bytes` of each
+    fn output_stripped_of_ansi_escapes(&mut self, context: &LightContext) -> Result<OutputError> {
+        let mut dispatched = dispatch::command(context, self)?;
+        let command = dispatched.as_mut().unwrap_or(self);
+        truncation::output(command, context.config.max_output_bytes()).map(OutputError::new)
     }
 }
 
+/// Returns `path` as a string with `/` as the separator, as `go` package patterns and `forge` globs
+/// require even on Windows. (In a glob, `\` is an escape character.)
+pub fn to_slash_string(path: &Path) -> String {
+    path.to_string_lossy().replace(MAIN_SEPARATOR_STR, "/")
+}
+
+/// Removes `.` and `..` components from `path` without accessing the filesystem
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// smoelius: The following functions turn test names into patterns for test commands' filters
+// (e.g., `go test -run`), which are regular expressions. Test names can contain regular expression
+// metacharacters, e.g., a Mocha test's message or a Foundry test's signature (`testFoo(uint256)`).
+// Rust's `--exact` takes a test name verbatim, and so needs no pattern.
+
+/// Returns a regular expression that matches exactly `name`. The result is valid in both Rust's and
+/// Go's regular expression syntaxes, since both treat an escaped punctuation character as itself.
+pub fn exact_regex(name: &str) -> String {
+    format!("^{}$", regex::escape(name))
+}
+
+/// Returns a `go test -run` pattern that matches exactly the test `test_name`. `-run` splits its
+/// pattern at unbracketed slashes, with each element matching one level of subtest, so each element
+/// of `test_name` is matched separately.
+pub fn go_run_regex(test_name: &str) -> String {
+    test_name
+        .split('/')
+        .map(exact_regex)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns a `forge test --match-test` pattern that matches exactly the test function `test_name`,
+/// whether Foundry matches the pattern against the function's name or its signature (e.g.,
+/// `testFoo(uint256)`)
+pub fn forge_match_test_regex(test_name: &str) -> String {
+    format!(r"^{}(\(.*)?$", regex::escape(test_name))
+}
+
+/// Escapes the JavaScript regular expression metacharacters in `s`, e.g., so that `s` can be passed
+/// to Mocha's `--grep`. Unlike [`regex::escape`], only characters that JavaScript allows to be
+/// escaped are.
+pub fn escape_js_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if r"\^$.*+?()[]{}|/-".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 // smoelius: The `stderr` method is currently unused.
 #[allow(dead_code)]
 pub trait OutputAccessors {
@@ -43,3 +103,58 @@ impl OutputAccessors for OutputError {
         &self.as_output().unwrap().stderr
     }
 }
+
+/// Returns a context for unit tests, with default options and with `root` as both the root and the
+/// original root
+#[cfg(test)]
+pub fn light_context<'a>(
+    root: &'a std::rc::Rc<std::path::PathBuf>,
+    config: &'a necessist_core::config::Toml,
+) -> LightContext<'a> {
+    static OPTS: once_cell::sync::Lazy<necessist_core::Necessist> =
+        once_cell::sync::Lazy::new(necessist_core::Necessist::default);
+    LightContext {
+        opts: &OPTS,
+        root,
+        original_root: root,
+        config,
+        println: &|_| {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    #[test]
+    fn test_name_patterns() {
+        assert_eq!(r"^Test_A\.b$", super::exact_regex("Test_A.b"));
+        assert_eq!(
+            r"^TestFoo$/^case_\(1\+1\)$",
+            super::go_run_regex("TestFoo/case_(1+1)")
+        );
+        assert_eq!(
+            r"^testFoo\$(\(.*)?$",
+            super::forge_match_test_regex("testFoo$")
+        );
+        assert_eq!(
+            r"costs \$1\.00 \(or \[more\]\) \/ \\n",
+            super::escape_js_regex(r"costs $1.00 (or [more]) / \n")
+        );
+
+        let re = regex::Regex::new(&super::forge_match_test_regex("testFoo")).unwrap();
+        assert!(re.is_match("testFoo"));
+        assert!(re.is_match("testFoo(uint256)"));
+        assert!(!re.is_match("testFooBar(uint256)"));
+
+        let re = regex::Regex::new(&super::exact_regex("a|b*[c]#&~-")).unwrap();
+        assert!(re.is_match("a|b*[c]#&~-"));
+        assert!(!re.is_match("a"));
+    }
+
+    #[test]
+    fn to_slash_string() {
+        let path = Path::new(".").join("src").join("encoding").join("base64");
+        assert_eq!("./src/encoding/base64", super::to_slash_string(&path));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    core::{past_removals_init_lazy, sqlite_init_lazy},
+    sqlite, util, LightContext, Removal,
+};
+use anyhow::{anyhow, Context, Result};
+use std::{collections::BTreeMap, path::Path};
+
+/// Writes a copy of each source file with past removals to `out`. Each line on which a removal ends
+/// has the removal's outcome, and the date of the run that produced it, appended as a comment.
+pub(crate) fn annotate(context: &LightContext, out: &Path) -> Result<()> {
+    let past_removals = past_removals_init_lazy(context)?;
+
+    let sqlite = sqlite_init_lazy(context)?;
+    let mut sqlite = sqlite.borrow_mut();
+    let sqlite = sqlite
+        .as_mut()
+        .ok_or_else(|| anyhow!("`annotate` requires an sqlite database"))?;
+
+    let started_ats = sqlite::removal_started_ats(sqlite)?;
+
+    let mut source_file_removals = BTreeMap::<_, Vec<_>>::new();
+    for removal in &past_removals {
+        source_file_removals
+            .entry(&removal.span.source_file)
+            .or_default()
+            .push(removal);
+    }
+
+    let mut n = 0;
+    for (source_file, mut removals) in source_file_removals {
+        removals.sort_by(|left, right| left.span.cmp(&right.span));
+
+        // smoelius: Comments are appended to the ends of lines rather than inserted as lines of
+        // their own, so that the copies' line numbers agree with the spans in Necessist's output.
+        // If several removals end on the same line, their outcomes share one comment.
+        let mut annotations = BTreeMap::<_, Vec<_>>::new();
+        for Removal { span, outcome, .. } in removals {
+            let annotation = match started_ats.get(&span.to_string()) {
+                Some(started_at) => {
+                    let date = started_at.split(' ').next().unwrap_or(started_at);
+                    format!("{outcome} {date}")
+                }
+                None => outcome.to_string(),
+            };
+            annotations
+                .entry(span.end().line)
+                .or_default()
+                .push(annotation);
+        }
+
+        let annotated = source_file
+            .contents()
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let Some(annotations) = annotations.get(&(i + 1)) else {
+                    return line.to_owned();
+                };
+                let stripped = line.trim_end_matches(['\r', '\n']);
+                format!(
+                    "{stripped} // necessist: {}{}",
+                    annotations.join(", "),
+                    &line[stripped.len()..]
+                )
+            })
+            .collect::<String>();
+
+        let path = out.join(source_file.to_string());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        std::fs::write(&path, annotated).with_context(|| format!("Failed to write {path:?}"))?;
+        n += 1;
+    }
+
+    (context.println)(&format!(
+        "Wrote {n} annotated file{} to {:?}",
+        if n == 1 { "" } else { "s" },
+        util::strip_current_dir(out)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Support for `--audit-log`, which records each mutation applied to and reverted from the
+//! project's files, and for `necessist replay`, which reapplies a recorded mutation
+
+use crate::{LightContext, Outcome, Span};
+use anyhow::{anyhow, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{read_to_string, File, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A destination for `--audit-log`
+pub(crate) struct AuditLog(File);
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Kind {
+    /// The candidate was removed, either explicitly or by enabling its instrumentation
+    Applied,
+    /// The candidate was restored
+    Reverted,
+}
+
+/// How a candidate was removed
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Method {
+    /// The candidate's text was removed from its source file.
+    Explicit,
+    /// The source file was instrumented beforehand, and the candidate was removed by setting
+    /// `NECESSIST_REMOVAL`.
+    Instrumentation,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Record {
+    event: Kind,
+    file: String,
+    span: String,
+    text: String,
+    method: Method,
+    /// Seconds since the Unix epoch
+    timestamp: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<String>,
+}
+
+impl AuditLog {
+    pub fn new(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open `{}`", path.display()))?;
+
+        Ok(Self(file))
+    }
+
+    /// Records that `span`, whose text is `text`, was removed using `method`
+    pub fn applied(&mut self, span: &Span, text: &str, method: Method) -> Result<()> {
+        self.write(Kind::Applied, span, text, method, None)
+    }
+
+    /// Records that `span` was restored after its removal produced `outcome`
+    pub fn reverted(
+        &mut self,
+        span: &Span,
+        text: &str,
+        method: Method,
+        outcome: Option<Outcome>,
+    ) -> Result<()> {
+        self.write(Kind::Reverted, span, text, method, outcome)
+    }
+
+    fn write(
+        &mut self,
+        event: Kind,
+        span: &Span,
+        text: &str,
+        method: Method,
+        outcome: Option<Outcome>,
+    ) -> Result<()> {
+        let json = serde_json::to_string(&Record {
+            event,
+            file: span.source_file.to_string(),
+            span: span.to_string(),
+            text: text.to_owned(),
+            method,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64(),
+            outcome: outcome.as_ref().map(ToString::to_string),
+        })?;
+
+        // smoelius: Each record is flushed immediately, so that the log is complete even if
+        // Necessist is killed.
+        writeln!(self.0, "{json}")?;
+        self.0.flush().map_err(Into::into)
+    }
+}
+
+/// Reapplies the most recent mutation of `span` recorded in the audit log `path`, i.e., removes
+/// `span` from its source file and leaves it removed
+pub(crate) fn replay(context: &LightContext, path: &Path, span: &str) -> Result<()> {
+    let contents =
+        read_to_string(path).with_context(|| format!("Failed to read `{}`", path.display()))?;
+
+    let mut record = None;
+    for (i, line) in contents.lines().enumerate() {
+        let line_record = serde_json::from_str::<Record>(line)
+            .with_context(|| format!("Failed to parse line {} of `{}`", i + 1, path.display()))?;
+        if line_record.event == Kind::Applied && line_record.span == span {
+            record = Some(line_record);
+        }
+    }
+    let record =
+        record.ok_or_else(|| anyhow!("Found no mutation of `{span}` in `{}`", path.display()))?;
+
+    let span = Span::parse(context.root, &record.span)?;
+
+    // smoelius: If the source file changed, the span could lie outside of it, or could refer to
+    // different text, and removing it would not recreate the mutation.
+    let text = span.source_text().with_context(|| {
+        format!(
+            "`{}` has changed since the mutation was recorded",
+            record.file
+        )
+    })?;
+    ensure!(
+        text == record.text,
+        "`{}` has changed since the mutation was recorded; expected `{}` at {}, but found `{}`",
+        record.file,
+        record.text,
+        record.span,
+        text
+    );
+
+    std::fs::write(&*span.source_file, span.removed_contents())
+        .with_context(|| format!("Failed to write `{}`", span.source_file))?;
+
+    (context.println)(&format!(
+        "Removed `{}` from {}; undo with, e.g., `git checkout -- {}`",
+        record.text.replace('\r', ""),
+        record.span,
+        record.file
+    ));
+
+    Ok(())
+}
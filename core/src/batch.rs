@@ -0,0 +1,299 @@
+use crate::{util, LightContext};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env::current_exe,
+    fs::{create_dir_all, read_to_string, remove_file, write, File},
+    path::{Component, Path, PathBuf},
+    process::Command,
+    sync::{mpsc::channel, Mutex},
+    thread,
+};
+
+const DEFAULT_OUTPUT_DIR: &str = "necessist-batch";
+
+/// A list of repositories to run Necessist on, e.g.:
+///
+/// ```toml
+/// [[repo]]
+/// url = "https://github.com/golang/go"
+/// rev = "go1.23.0"
+/// framework = "go"
+/// subdir = "src/encoding/base64"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// Directory in which repositories are checked out and results are written; a relative path is
+    /// relative to the manifest's directory
+    #[serde(default)]
+    output_dir: Option<PathBuf>,
+
+    #[serde(default, rename = "repo")]
+    repos: Vec<Repo>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Repo {
+    /// Repo url (or local path); anything `git clone` accepts
+    url: String,
+
+    /// Name of the repo's checkout, database, and log; `None` (the default) means the last
+    /// component of `url`
+    #[serde(default)]
+    name: Option<String>,
+
+    /// Repo revision; `None` (the default) means the head of the default branch
+    #[serde(default)]
+    rev: Option<String>,
+
+    /// Subdirectory of the repo in which Necessist should run; `None` (the default) means the root
+    /// of the repository
+    #[serde(default)]
+    subdir: Option<String>,
+
+    /// Testing framework to use; `None` (the default) means `auto`
+    #[serde(default)]
+    framework: Option<String>,
+
+    /// Test files to mutilate
+    #[serde(default)]
+    source_files: Vec<String>,
+
+    /// Additional arguments to pass to Necessist, e.g., `["--resume"]`
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    name: &'a str,
+    url: &'a str,
+    rev: Option<&'a str>,
+    success: bool,
+    outcomes: BTreeMap<String, usize>,
+}
+
+pub(crate) fn batch(context: &LightContext, manifest_path: &Path, jobs: usize) -> Result<()> {
+    let contents = read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read {manifest_path:?}"))?;
+    let manifest = toml::from_str::<Manifest>(&contents)
+        .with_context(|| format!("Failed to parse {manifest_path:?}"))?;
+
+    let output_dir = manifest_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(
+            manifest
+                .output_dir
+                .as_deref()
+                .unwrap_or_else(|| Path::new(DEFAULT_OUTPUT_DIR)),
+        );
+    create_dir_all(&output_dir)?;
+
+    let names = manifest
+        .repos
+        .iter()
+        .map(Repo::name)
+        .collect::<Result<Vec<_>>>()?;
+    let mut seen = BTreeSet::new();
+    for name in &names {
+        ensure!(seen.insert(name), "Repository name `{name}` is not unique");
+    }
+
+    let exe = current_exe()?;
+
+    let queue = Mutex::new(manifest.repos.iter().zip(&names).enumerate());
+    let results = Mutex::new(BTreeMap::new());
+
+    // smoelius: `context.println` cannot be shared with the worker threads. So they send their
+    // messages to this thread, which prints them.
+    let (sender, receiver) = channel::<String>();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            let sender = sender.clone();
+            let (exe, output_dir, queue, results) = (&exe, &output_dir, &queue, &results);
+            scope.spawn(move || loop {
+                #[allow(clippy::unwrap_used)]
+                let Some((i, (repo, name))) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                let _: Result<(), _> = sender.send(format!("{name}: running"));
+                let result = run_repo(exe, output_dir, repo, name);
+                #[allow(clippy::unwrap_used)]
+                results.lock().unwrap().insert(i, result);
+            });
+        }
+        drop(sender);
+        for msg in receiver {
+            (context.println)(&msg);
+        }
+    });
+
+    #[allow(clippy::unwrap_used)]
+    let results = results.into_inner().unwrap();
+
+    let mut summaries = Vec::new();
+    let mut n_failures = 0;
+    for (i, result) in results {
+        let repo = &manifest.repos[i];
+        let name = &names[i];
+        let (success, outcomes) = match result {
+            Ok(outcomes) => (true, outcomes),
+            Err(error) => {
+                (context.println)(&format!("{name}: {error:?}"));
+                (
+                    false,
+                    outcome_counts(&output_dir.join(format!("{name}.jsonl")))?,
+                )
+            }
+        };
+        if !success {
+            n_failures += 1;
+        }
+        (context.println)(&format!(
+            "{name}: {}",
+            if outcomes.is_empty() {
+                String::from("no results")
+            } else {
+                outcomes
+                    .iter()
+                    .map(|(outcome, n)| format!("{n} {outcome}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        ));
+        summaries.push(Summary {
+            name,
+            url: &repo.url,
+            rev: repo.rev.as_deref(),
+            success,
+            outcomes,
+        });
+    }
+
+    write(
+        output_dir.join("summary.json"),
+        serde_json::to_string_pretty(&summaries)?,
+    )?;
+
+    if n_failures != 0 {
+        bail!(
+            "Necessist failed on {n_failures} of {} repositories; see the logs in {:?}",
+            summaries.len(),
+            util::strip_current_dir(&output_dir)
+        );
+    }
+
+    Ok(())
+}
+
+impl Repo {
+    fn name(&self) -> Result<String> {
+        let name = if let Some(name) = &self.name {
+            name.clone()
+        } else {
+            let url = self.url.trim_end_matches('/');
+            let last = url
+                .rsplit(['/', '\\', ':'])
+                .next()
+                .filter(|last| !last.is_empty())
+                .ok_or_else(|| anyhow!("Failed to determine name for {:?}", self.url))?;
+            last.strip_suffix(".git").unwrap_or(last).to_owned()
+        };
+        check_name(&name)?;
+        Ok(name)
+    }
+}
+
+/// Checks that `name` is a single, normal path component, so that joining it onto the output
+/// directory cannot produce a path outside of that directory
+fn check_name(name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    ensure!(
+        !name.contains(['/', '\\'])
+            && matches!(components.next(), Some(Component::Normal(_)))
+            && components.next().is_none(),
+        "Repository name `{name}` is invalid; a name cannot be `.`, `..`, or an absolute path, \
+         and cannot contain path separators"
+    );
+    Ok(())
+}
+
+/// Checks out `repo` (if necessary), runs Necessist on it, and returns the number of results with
+/// each outcome.
+fn run_repo(
+    exe: &Path,
+    output_dir: &Path,
+    repo: &Repo,
+    name: &str,
+) -> Result<BTreeMap<String, usize>> {
+    let checkout = output_dir.join(name);
+
+    if !checkout.try_exists()? {
+        let mut command = Command::new("git");
+        command.args(["clone", "--quiet", &repo.url]);
+        command.arg(&checkout);
+        run(&mut command)?;
+    }
+
+    if let Some(rev) = &repo.rev {
+        let mut command = Command::new("git");
+        command.args(["checkout", "--quiet", rev]);
+        command.current_dir(&checkout);
+        run(&mut command)?;
+    }
+
+    let root = repo
+        .subdir
+        .as_ref()
+        .map_or_else(|| checkout.clone(), |subdir| checkout.join(subdir));
+
+    let results = output_dir.join(format!("{name}.jsonl"));
+    if results.try_exists()? {
+        remove_file(&results)?;
+    }
+
+    let log = File::create(output_dir.join(format!("{name}.log")))?;
+
+    let mut command = Command::new(exe);
+    command.arg("--root");
+    command.arg(&root);
+    command.arg("--stream-results");
+    command.arg(&results);
+    if let Some(framework) = &repo.framework {
+        command.args(["--framework", framework]);
+    }
+    command.args(&repo.args);
+    command.args(&repo.source_files);
+    command.current_dir(&root);
+    command.stdout(log.try_clone()?);
+    command.stderr(log);
+    run(&mut command)?;
+
+    outcome_counts(&results)
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command.status()?;
+    ensure!(status.success(), "{command:?} exited with {status}");
+    Ok(())
+}
+
+fn outcome_counts(results: &Path) -> Result<BTreeMap<String, usize>> {
+    let mut outcomes = BTreeMap::new();
+    if !results.try_exists()? {
+        return Ok(outcomes);
+    }
+    for line in read_to_string(results)?.lines() {
+        let value = serde_json::from_str::<serde_json::Value>(line)?;
+        let outcome = value["outcome"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Result is missing an outcome: {line}"))?;
+        *outcomes.entry(outcome.to_owned()).or_default() += 1;
+    }
+    Ok(outcomes)
+}
@@ -0,0 +1,181 @@
+//! Support for reusing, when resuming, the candidates found in source files that have not changed
+//! since they were last parsed.
+//!
+//! After a source file is parsed, its candidates are recorded along with a hash of the file's
+//! contents and configuration, and of the contents of any other files that the parse depended on
+//! (e.g., the test files that call helpers in a module shared by Rust integration tests). The
+//! records are stored in the database and loaded when resuming. A source file whose hash matches
+//! its record is not parsed again.
+
+use crate::{
+    config,
+    framework::{SpanTestMap, SpanTestMaps, StatementKind},
+    LightContext, Span,
+};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs::read,
+    path::{Path, PathBuf},
+};
+
+/// The candidates found in a source file, in the form in which they are stored in the database
+#[derive(Clone, Debug)]
+pub(crate) struct Record {
+    pub hash: String,
+    pub n_tests: usize,
+    pub span_test_maps: String,
+}
+
+/// The candidate records loaded from the database, and those of the source files parsed during
+/// this run
+#[derive(Default)]
+pub struct Candidates {
+    loaded: BTreeMap<PathBuf, Record>,
+    parsed: BTreeMap<PathBuf, Record>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct SerializedSpanTestMaps {
+    statement: Vec<(String, Vec<String>)>,
+    method_call: Vec<(String, Vec<String>)>,
+    diagnostic: Vec<String>,
+    post_assertion: Vec<String>,
+    statement_kind: Vec<(String, StatementKind)>,
+}
+
+/// Returns a hash of `source_file`'s contents, of the configuration used to parse it, and of the
+/// contents of `dependencies`, the other files that its parse reads. A dependency that cannot be
+/// read is hashed as though it were empty, since the parse ignores such files.
+pub fn hash(
+    context: &LightContext,
+    config: &config::Toml,
+    source_file: &Path,
+    dependencies: &[PathBuf],
+) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update(read(source_file)?);
+    hasher.update(serde_json::to_string(config)?);
+    // smoelius: These are the options that the backends consult while parsing. Options that
+    // select kinds of candidates (e.g., `--skip-statements`) are folded into `config`, and so are
+    // hashed with it. `--shard` selects whole source files, and so does not affect a source file's
+    // candidates.
+    hasher.update([
+        u8::from(context.opts.include_go_benchmarks),
+        u8::from(context.opts.include_go_fuzz),
+        u8::from(context.opts.include_ignored),
+        u8::from(context.opts.skip_go_statements),
+        u8::from(context.opts.tolerate_unused),
+    ]);
+    for dependency in dependencies {
+        hasher.update(dependency.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        let contents = read(dependency).unwrap_or_default();
+        hasher.update(contents.len().to_le_bytes());
+        hasher.update(contents);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+impl Candidates {
+    pub(crate) fn new(loaded: BTreeMap<PathBuf, Record>) -> Self {
+        Self {
+            loaded,
+            parsed: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of tests and the candidates recorded for `source_file`, if its record
+    /// was loaded from the database and has hash `hash`
+    pub fn reuse(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+        hash: &str,
+    ) -> Result<Option<(usize, SpanTestMaps)>> {
+        let Some(record) = self
+            .loaded
+            .get(source_file)
+            .filter(|record| record.hash == hash)
+        else {
+            return Ok(None);
+        };
+        let serialized = serde_json::from_str::<SerializedSpanTestMaps>(&record.span_test_maps)?;
+        let parse = |s: &String| Span::parse(context.root, s);
+        let span_test_map = |pairs: Vec<(String, Vec<String>)>| {
+            pairs
+                .into_iter()
+                .map(|(span, test_names)| Ok((parse(&span)?, test_names.into_iter().collect())))
+                .collect::<Result<_>>()
+        };
+        let span_test_maps = SpanTestMaps {
+            statement: span_test_map(serialized.statement)?,
+            method_call: span_test_map(serialized.method_call)?,
+            diagnostic: serialized
+                .diagnostic
+                .iter()
+                .map(parse)
+                .collect::<Result<_>>()?,
+            post_assertion: serialized
+                .post_assertion
+                .iter()
+                .map(parse)
+                .collect::<Result<_>>()?,
+            statement_kind: serialized
+                .statement_kind
+                .iter()
+                .map(|(span, kind)| Ok((parse(span)?, *kind)))
+                .collect::<Result<_>>()?,
+        };
+        Ok(Some((record.n_tests, span_test_maps)))
+    }
+
+    /// Records the number of tests and the candidates found in `source_file`, so that they can
+    /// be stored in the database
+    pub fn record(
+        &mut self,
+        source_file: &Path,
+        hash: String,
+        n_tests: usize,
+        span_test_maps: &SpanTestMaps,
+    ) -> Result<()> {
+        let span_test_map = |map: &SpanTestMap| {
+            map.iter()
+                .map(|(span, test_names)| (span.to_string(), test_names.iter().cloned().collect()))
+                .collect()
+        };
+        let serialized = SerializedSpanTestMaps {
+            statement: span_test_map(&span_test_maps.statement),
+            method_call: span_test_map(&span_test_maps.method_call),
+            diagnostic: span_test_maps
+                .diagnostic
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            post_assertion: span_test_maps
+                .post_assertion
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            statement_kind: span_test_maps
+                .statement_kind
+                .iter()
+                .map(|(span, kind)| (span.to_string(), *kind))
+                .collect(),
+        };
+        let record = Record {
+            hash,
+            n_tests,
+            span_test_maps: serde_json::to_string(&serialized)?,
+        };
+        self.parsed.insert(source_file.to_path_buf(), record);
+        Ok(())
+    }
+
+    /// Returns the records of the source files parsed during this run
+    pub(crate) fn parsed(&self) -> &BTreeMap<PathBuf, Record> {
+        &self.parsed
+    }
+}
@@ -0,0 +1,113 @@
+//! Heuristics for a failed removal's probable cause, found by scanning the tests' output for common
+//! signatures, e.g., panic messages, failed assertions, and revert reasons. A cause is stored with
+//! the removal in the database and shown by `necessist show`.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::sync::Mutex;
+
+/// Causes longer than this many characters are cut short
+const MAX_CHARS: usize = 120;
+
+static ANSI_ESCAPE: Lazy<Regex> = Lazy::new(|| {
+    #[allow(clippy::unwrap_used)]
+    Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap()
+});
+
+// smoelius: The patterns are tried in order, so more specific ones come first. Each has a `cause`
+// group.
+static PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    [
+        // smoelius: Foundry, e.g., `[FAIL: revert: reason]` or `[FAIL. Reason: reason]`.
+        r"\[FAIL(?::|\. Reason:) (?P<cause>[^\]\n]+)\]",
+        // smoelius: Hardhat, e.g., `reverted with reason string 'reason'`.
+        r"(?P<cause>reverted with (?:reason string|custom error|panic code) [^\n]+)",
+        // smoelius: Rust 1.73 and later, e.g., `panicked at src/lib.rs:1:2:\nmessage`.
+        r"panicked at [^\n]*:\n(?P<cause>[^\n]+)",
+        // smoelius: Earlier Rust, e.g., `panicked at 'message', src/lib.rs:1:2`.
+        r"panicked at '(?P<cause>[^\n]*)', ",
+        // smoelius: Chai and Node, e.g., `AssertionError: expected 1 to equal 2`.
+        r"(?P<cause>AssertionError(?: \[[^\]\n]*\])?: [^\n]+)",
+        // smoelius: Go, e.g., `    foo_test.go:12: message` as written by `t.Errorf`.
+        r"(?m)^\s+(?P<cause>\w+_test\.go:\d+: [^\n]+)",
+        r"(?m)^\s*(?P<cause>(?:Error|Exception): [^\n]+)",
+    ]
+    .into_iter()
+    .map(|pattern| {
+        #[allow(clippy::unwrap_used)]
+        Regex::new(pattern).unwrap()
+    })
+    .collect()
+});
+
+static OBSERVED: Mutex<Option<String>> = Mutex::new(None);
+
+/// Scans `output`, read by a backend from a test's process (e.g., in a [`Postprocess`]), for a
+/// probable cause. The cause is recorded if the test turns out to have failed.
+///
+/// [`Postprocess`]: crate::framework::Postprocess
+pub fn observe(output: &[u8]) {
+    #[allow(clippy::unwrap_used)]
+    let mut observed = OBSERVED.lock().unwrap();
+    *observed = probable_cause(&String::from_utf8_lossy(output));
+}
+
+/// Returns the cause found by the most recent call to [`observe`], if any, and forgets it
+pub(crate) fn take_observed() -> Option<String> {
+    #[allow(clippy::unwrap_used)]
+    OBSERVED.lock().unwrap().take()
+}
+
+/// Returns a short description of why the tests that produced `output` failed, if one can be found
+pub(crate) fn probable_cause(output: &str) -> Option<String> {
+    let output = ANSI_ESCAPE.replace_all(output, "");
+    let cause = PATTERNS.iter().find_map(|pattern| {
+        pattern
+            .captures(&output)
+            .and_then(|captures| captures.name("cause"))
+    })?;
+    let cause = cause.as_str().trim();
+    if cause.chars().count() <= MAX_CHARS {
+        Some(cause.to_owned())
+    } else {
+        Some(cause.chars().take(MAX_CHARS - 3).collect::<String>() + "...")
+    }
+}
+
+#[test]
+fn probable_causes() {
+    const EXAMPLES: &[(&str, Option<&str>)] = &[
+        (
+            "thread 'test::a' panicked at src/lib.rs:9:9:\nassertion `left == right` failed\n  \
+             left: 1\n right: 2\n",
+            Some("assertion `left == right` failed"),
+        ),
+        (
+            "thread 'main' panicked at 'called `Option::unwrap()` on a `None` value', \
+             src/main.rs:2:5\n",
+            Some("called `Option::unwrap()` on a `None` value"),
+        ),
+        (
+            "[FAIL: revert: insufficient balance] test_withdraw() (gas: 1234)\n",
+            Some("revert: insufficient balance"),
+        ),
+        (
+            "Error: VM Exception while processing transaction: reverted with reason string 'Not \
+             owner'\n",
+            Some("reverted with reason string 'Not owner'"),
+        ),
+        (
+            "  1) Token\n       \x1b[31mAssertionError: expected 1 to equal 2\x1b[0m\n",
+            Some("AssertionError: expected 1 to equal 2"),
+        ),
+        (
+            "--- FAIL: TestAdd (0.00s)\n    add_test.go:12: got 3, want 4\nFAIL\n",
+            Some("add_test.go:12: got 3, want 4"),
+        ),
+        ("test result: FAILED. 0 passed; 1 failed\n", None),
+    ];
+
+    for &(output, expected) in EXAMPLES {
+        assert_eq!(expected, probable_cause(output).as_deref(), "{output:?}");
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    config,
+    framework::{self, Applicable},
+    util, LightContext,
+};
+use anyhow::{ensure, Result};
+use std::fmt::Display;
+use strum::IntoEnumIterator;
+
+/// A [`config::Diagnostic`] along with the configuration file it concerns
+#[derive(serde::Serialize)]
+struct FileDiagnostic {
+    file: String,
+    #[serde(flatten)]
+    diagnostic: config::Diagnostic,
+}
+
+/// Checks the root configuration file and those nested beneath it, and outputs each problem found
+/// as a line of JSON. Also warns about framework-specific tables that the selected framework does
+/// not use. Fails if any problem is an error.
+pub(crate) fn check_config<Identifier: Applicable + Clone + Display + IntoEnumIterator>(
+    context: &LightContext,
+    framework: &framework::Auto<Identifier>,
+) -> Result<()> {
+    let root_path = context.root.join("necessist.toml");
+
+    let mut file_diagnostics = Vec::new();
+    let mut root_table = None;
+
+    for entry in walkdir::WalkDir::new(context.root.as_path())
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let file_name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !(file_name.starts_with('.')
+                    || file_name == "node_modules"
+                    || file_name == "target")
+        })
+    {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_name() != "necessist.toml" || !entry.file_type().is_file() {
+            continue;
+        }
+        let nested = path != root_path;
+        let (diagnostics, table) = config::check_file(path, nested)?;
+        if !nested {
+            root_table = table;
+        }
+        let file = util::strip_prefix(path, context.root)?
+            .to_string_lossy()
+            .into_owned();
+        file_diagnostics.extend(diagnostics.into_iter().map(|diagnostic| FileDiagnostic {
+            file: file.clone(),
+            diagnostic,
+        }));
+    }
+
+    // smoelius: The framework is selected using the root configuration file, if it is valid, since
+    // it may contain `framework_precedence`.
+    let config = root_table
+        .clone()
+        .and_then(|table| table.try_into::<config::Toml>().ok())
+        .unwrap_or_default();
+    let context = &LightContext {
+        config: &config,
+        ..*context
+    };
+
+    if let (Some(table), Some(selected)) = (root_table, framework.resolve(context).ok().flatten()) {
+        let used = selected.config_tables();
+        for key in table.keys() {
+            let key = key.as_str();
+            if !used.contains(&key)
+                && Identifier::iter().any(|identifier| identifier.config_tables().contains(&key))
+            {
+                file_diagnostics.push(FileDiagnostic {
+                    file: "necessist.toml".to_owned(),
+                    diagnostic: config::Diagnostic {
+                        severity: config::Severity::Warning,
+                        key: Some(key.to_owned()),
+                        message: format!("not used by the selected framework ({selected})"),
+                        suggestion: None,
+                    },
+                });
+            }
+        }
+    }
+
+    for file_diagnostic in &file_diagnostics {
+        (context.println)(&serde_json::to_string(file_diagnostic)?);
+    }
+
+    let n_errors = file_diagnostics
+        .iter()
+        .filter(|file_diagnostic| file_diagnostic.diagnostic.severity == config::Severity::Error)
+        .count();
+    ensure!(
+        n_errors == 0,
+        "Found {n_errors} error{} in configuration files",
+        if n_errors == 1 { "" } else { "s" }
+    );
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use crate::{framework, Necessist, Warning};
+use crate::{
+    framework, Color, ConfigSubcommand, DbSubcommand, Docker, GroupBy, Isolate, Necessist, Order,
+    Remote, Shard, Subcommand, Warning,
+};
 use clap::{crate_version, ArgAction, Parser, ValueEnum};
 use std::path::PathBuf;
 
@@ -15,6 +18,38 @@ pub struct Opts<Identifier: Clone + Send + Sync + ValueEnum + 'static> {
         help = "Silence <WARNING>; `--allow all` silences all warnings"
     )]
     allow: Vec<Warning>,
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Append a line of JSON to <PATH> each time a candidate is removed or restored; see \
+                also `necessist replay`"
+    )]
+    audit_log: Option<String>,
+    #[clap(
+        long,
+        help = "Build each candidate's tests with the candidate removed, but do not run them; \
+                implies --no-sqlite"
+    )]
+    build_only: bool,
+    #[clap(
+        long,
+        help = "Warn when a candidate's tests modify files outside of the project's build \
+                directories"
+    )]
+    check_side_effects: bool,
+    #[clap(
+        long,
+        value_name = "WHEN",
+        help = "Color console output; `auto` (the default) colors output only if stdout is a \
+                terminal"
+    )]
+    color: Option<Color>,
+    #[clap(
+        long,
+        help = "Run each candidate's remaining tests after one fails, so that the number of tests \
+                that fail can be recorded"
+    )]
+    count_failing_tests: bool,
     #[clap(
         long,
         help = "Create a default necessist.toml file in the project's root directory"
@@ -28,33 +63,195 @@ pub struct Opts<Identifier: Clone + Send + Sync + ValueEnum + 'static> {
         help = "Treat <WARNING> as an error; `--deny all` treats all warnings as errors"
     )]
     deny: Vec<Warning>,
+    #[clap(
+        long,
+        value_name = "IMAGE",
+        require_equals = true,
+        help = "Run build and test commands in a Docker container with the project mounted at \
+                /repo; if <IMAGE> is omitted, the framework's image is used"
+    )]
+    #[allow(clippy::option_option)]
+    docker: Option<Option<String>>,
     #[clap(long, help = "Dump sqlite database contents to the console")]
     dump: bool,
     #[clap(long, help = "Dump removal candidates and exit (for debugging)")]
     dump_candidates: bool,
+    #[clap(
+        long,
+        value_name = "K",
+        help = "Stop removing statements from a test once <K> consecutive removals in it pass, \
+                and warn that the test is suspicious"
+    )]
+    fail_fast_per_test: Option<usize>,
     #[clap(long, help = "Assume testing framework is <FRAMEWORK>")]
     framework: Option<framework::Auto<Identifier>>,
+    #[clap(
+        long,
+        value_name = "GROUPING",
+        help = "Output results grouped under each test that exercises them, along with per-test \
+                outcome counts, once all candidates have been run"
+    )]
+    group_by: Option<GroupBy>,
+    #[clap(
+        long,
+        help = "Treat Go benchmarks (`func BenchmarkXxx(b *testing.B)`) as tests; each is run \
+                once with `-benchtime=1x`"
+    )]
+    include_go_benchmarks: bool,
+    #[clap(
+        long,
+        help = "Treat Go fuzz targets (`func FuzzXxx(f *testing.F)`) as tests; each is run on its \
+                seed corpus only"
+    )]
+    include_go_fuzz: bool,
+    #[clap(
+        long,
+        help = "Include Rust tests marked `#[ignore]`, and pass `--include-ignored` to the test \
+                harness"
+    )]
+    include_ignored: bool,
+    #[clap(
+        long,
+        value_name = "MODE",
+        help = "Mutilate files in an isolated copy of the project, leaving the original unmodified"
+    )]
+    isolate: Option<Isolate>,
+    #[clap(
+        long,
+        value_name = "N",
+        help = "Perform up to <N> dry runs in parallel, or, with --isolate worktree, run <N> job \
+                slots in parallel; 1 is the default"
+    )]
+    jobs: Option<usize>,
+    #[clap(long, help = "Output logs to stderr as JSON lines")]
+    log_json: bool,
     #[clap(long, help = "Do not perform dry runs")]
     no_dry_run: bool,
     #[clap(long, hide = true)]
     no_local_functions: bool,
+    #[clap(
+        long,
+        help = "Run tests with candidates removed without network access, using `unshare -n` on \
+                Linux, or proxy environment variables that lead nowhere otherwise"
+    )]
+    no_network: bool,
+    #[clap(long, help = "Do not show a progress bar")]
+    no_progress: bool,
     #[clap(long, help = "Do not output to an sqlite database")]
     no_sqlite: bool,
+    #[clap(
+        long,
+        help = "Consider only candidates that follow the last assertion in their test, or that \
+                belong to a test with no assertions"
+    )]
+    only_post_assertion: bool,
+    #[clap(
+        long,
+        value_name = "ORDER",
+        help = "Run candidates in <ORDER>; `cheapest-first` runs the candidates of the source \
+                files with the lowest estimated cost per candidate first, and `round-robin` \
+                interleaves candidates across tests and source files"
+    )]
+    order: Option<Order>,
+    #[clap(
+        long,
+        value_name = "PATH",
+        require_equals = true,
+        help = "Periodically write progress as JSON to <PATH>; if <PATH> is omitted, \
+                necessist-progress.json in the root directory is used"
+    )]
+    #[allow(clippy::option_option)]
+    progress_file: Option<Option<String>>,
     #[clap(long, help = "Do not output to the console")]
     quiet: bool,
+    #[clap(
+        long,
+        help = "Run Necessist on each project found beneath the root directory"
+    )]
+    recurse_projects: bool,
+    #[clap(
+        long,
+        value_name = "DEST",
+        help = "Run build and test commands on <DEST> over SSH, where <DEST> is of the form \
+                `user@host:path`; the project is synced to `path` with rsync"
+    )]
+    remote: Option<Remote>,
+    #[clap(
+        long,
+        help = "Skip a source file whose dry run fails, rather than only the tests that fail"
+    )]
+    require_clean_dry_run: bool,
     #[clap(long, help = "Discard sqlite database contents")]
     reset: bool,
     #[clap(long, help = "Resume from the sqlite database")]
     resume: bool,
     #[clap(long, help = "Root directory of the project under test")]
     root: Option<String>,
+    #[clap(
+        long,
+        value_name = "I/N",
+        help = "Run only the source files in shard <I> of <N>, where source files are assigned to \
+                shards by a hash of their paths"
+    )]
+    shard: Option<Shard>,
+    #[clap(long, help = "Do not remove function calls")]
+    skip_calls: bool,
+    #[clap(
+        long,
+        help = "Do not remove Go statements that start goroutines (`go f()`) or wait for them \
+                (e.g., `wg.Wait()`)"
+    )]
+    skip_go_statements: bool,
+    #[clap(long, help = "Do not remove macro calls")]
+    skip_macros: bool,
+    #[clap(long, help = "Do not remove method calls")]
+    skip_method_calls: bool,
+    #[clap(
+        long,
+        help = "Do not remove statements other than calls (e.g., `n += 1;`)"
+    )]
+    skip_statements: bool,
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Read test files to mutilate from <PATH>, one per line, in addition to any given \
+                on the command line; `-` means standard input"
+    )]
+    source_files_from: Option<String>,
+    #[clap(
+        long,
+        value_name = "DEST",
+        help = "Append each result to <DEST> as JSON as soon as it is known; <DEST> may be a file \
+                or a URL to POST to"
+    )]
+    stream_results: Option<String>,
+    #[clap(subcommand)]
+    subcommand: Option<SubcommandOpts>,
+    #[clap(
+        long,
+        help = "After the run, suggest necessist.toml entries to ignore calls whose removals \
+                passed in every test that exercised them"
+    )]
+    suggest_ignores: bool,
     #[clap(
         long,
         help = "Maximum number of seconds to run any test; 60 is the default, 0 means no timeout"
     )]
     timeout: Option<u64>,
+    #[clap(
+        long,
+        help = "Keep removals from causing build failures by leaving variables unused (supported \
+                for Go and Rust)"
+    )]
+    tolerate_unused: bool,
     #[clap(long, help = "Show test outcomes besides `passed`")]
     verbose: bool,
+    #[clap(
+        long,
+        help = "Re-run the tests of each `passed` candidate with the candidate restored; report \
+                the candidate as `inconclusive` if they then fail"
+    )]
+    verify_passed: bool,
     #[clap(value_name = "TEST_FILES", help = "Test files to mutilate (optional)")]
     zsource_files: Vec<String>,
     #[clap(
@@ -68,23 +265,58 @@ pub struct Opts<Identifier: Clone + Send + Sync + ValueEnum + 'static> {
 impl<Identifier: Clone + Send + Sync + ValueEnum> From<Opts<Identifier>>
     for (Necessist, framework::Auto<Identifier>)
 {
+    #[allow(clippy::too_many_lines)]
     fn from(opts: Opts<Identifier>) -> Self {
         let Opts {
             allow,
+            audit_log,
+            build_only,
+            check_side_effects,
+            color,
+            count_failing_tests,
             default_config,
             deny,
+            docker,
             dump,
             dump_candidates,
+            fail_fast_per_test,
             framework,
+            group_by,
+            include_go_benchmarks,
+            include_go_fuzz,
+            include_ignored,
+            isolate,
+            jobs,
+            log_json,
             no_dry_run,
             no_local_functions,
+            no_network,
+            no_progress,
             no_sqlite,
+            only_post_assertion,
+            order,
+            progress_file,
             quiet,
+            recurse_projects,
+            remote,
+            require_clean_dry_run,
             reset,
             resume,
             root,
+            shard,
+            skip_calls,
+            skip_go_statements,
+            skip_macros,
+            skip_method_calls,
+            skip_statements,
+            source_files_from,
+            stream_results,
+            subcommand,
+            suggest_ignores,
             timeout,
+            tolerate_unused,
             verbose,
+            verify_passed,
             zsource_files,
             zzargs,
         } = opts;
@@ -95,19 +327,53 @@ impl<Identifier: Clone + Send + Sync + ValueEnum> From<Opts<Identifier>>
         (
             Necessist {
                 allow,
+                audit_log: audit_log.map(PathBuf::from),
+                build_only,
+                check_side_effects,
+                color,
+                count_failing_tests,
                 default_config,
                 deny,
+                docker: docker.map(Docker::new),
                 dump,
                 dump_candidates,
+                fail_fast_per_test,
+                group_by,
+                include_go_benchmarks,
+                include_go_fuzz,
+                include_ignored,
+                isolate,
+                jobs,
+                log_json,
                 no_dry_run,
                 no_local_functions,
+                no_network,
+                no_progress,
                 no_sqlite,
+                only_post_assertion,
+                order,
+                progress_file: progress_file.map(|path| path.map(PathBuf::from)),
                 quiet,
+                recurse_projects,
+                remote,
+                require_clean_dry_run,
                 reset,
                 resume,
                 root,
+                shard,
+                skip_calls,
+                skip_go_statements,
+                skip_macros,
+                skip_method_calls,
+                skip_statements,
+                source_files_from,
+                stream_results,
+                subcommand: subcommand.map(Into::into),
+                suggest_ignores,
                 timeout,
+                tolerate_unused,
                 verbose,
+                verify_passed,
                 source_files,
                 args,
             },
@@ -115,3 +381,165 @@ impl<Identifier: Clone + Send + Sync + ValueEnum> From<Opts<Identifier>>
         )
     }
 }
+
+#[derive(Debug, clap::Subcommand)]
+enum SubcommandOpts {
+    #[clap(about = "Write a copy of each test file with its removals' outcomes added as comments")]
+    Annotate {
+        #[clap(long, help = "Directory to write the copies to")]
+        out: String,
+    },
+    #[clap(about = "Run Necessist on each repository listed in <MANIFEST>")]
+    Batch {
+        #[clap(
+            long,
+            default_value = "1",
+            help = "Number of repositories to process in parallel"
+        )]
+        jobs: usize,
+        #[clap(help = "TOML file listing the repositories")]
+        manifest: String,
+    },
+    #[clap(about = "Operate on necessist.toml files")]
+    Config {
+        #[clap(subcommand)]
+        subcommand: ConfigSubcommandOpts,
+    },
+    #[clap(about = "Operate on the sqlite database")]
+    Db {
+        #[clap(subcommand)]
+        subcommand: DbSubcommandOpts,
+    },
+    #[clap(about = "List the removals whose outcomes differ between two runs")]
+    Diff {
+        #[clap(long, help = "Id of the earlier run, or `latest`")]
+        from: String,
+        #[clap(
+            long,
+            default_value = "latest",
+            help = "Id of the later run, or `latest`"
+        )]
+        to: String,
+    },
+    #[clap(about = "Rerun the removal of <SPAN>, showing the build and test output")]
+    Explain {
+        #[clap(help = "Span as output by Necessist, e.g., `tests/a.rs:4:5-4:12`")]
+        span: String,
+    },
+    #[clap(about = "Explain which framework is selected and why")]
+    Frameworks,
+    #[clap(about = "Write a unified diff for each removal in the database with a given outcome")]
+    Patch {
+        #[clap(
+            long,
+            default_value = "passed",
+            help = "Outcome of the removals to write diffs for"
+        )]
+        outcome: String,
+        #[clap(long, help = "Directory to write the diffs to")]
+        out: String,
+    },
+    #[clap(about = "Remove <SPAN> as recorded in an audit log, and leave it removed")]
+    Replay {
+        #[clap(help = "Audit log written with --audit-log")]
+        audit_log: String,
+        #[clap(
+            long,
+            help = "Span as recorded in the audit log, e.g., `tests/a.rs:4:5-4:12`"
+        )]
+        span: String,
+    },
+    #[clap(
+        name = "self-test",
+        about = "Run Necessist on a tiny generated project to check that the toolchain for \
+                 <FRAMEWORK> (by default, Rust) works"
+    )]
+    SelfTest,
+    #[clap(about = "Show the removals produced by a run")]
+    Show {
+        #[clap(
+            long,
+            default_value = "latest",
+            help = "Id of the run to show, or `latest`"
+        )]
+        run: String,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum ConfigSubcommandOpts {
+    #[clap(
+        about = "Check necessist.toml files for unknown keys and invalid values, and output each \
+                 problem as a line of JSON"
+    )]
+    Check,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum DbSubcommandOpts {
+    #[clap(about = "Merge the contents of several sqlite databases into a new one")]
+    Merge {
+        #[clap(required = true, help = "Databases to merge")]
+        inputs: Vec<String>,
+        #[clap(
+            short,
+            long,
+            help = "Path of the merged database, which must not exist"
+        )]
+        output: String,
+    },
+    #[clap(about = "Delete all but the most recent runs, along with the removals they produced")]
+    Prune {
+        #[clap(long, help = "Number of runs to keep")]
+        keep: usize,
+    },
+}
+
+impl From<SubcommandOpts> for Subcommand {
+    fn from(subcommand: SubcommandOpts) -> Self {
+        match subcommand {
+            SubcommandOpts::Annotate { out } => Subcommand::Annotate {
+                out: PathBuf::from(out),
+            },
+            SubcommandOpts::Batch { jobs, manifest } => Subcommand::Batch {
+                jobs,
+                manifest: PathBuf::from(manifest),
+            },
+            SubcommandOpts::Config { subcommand } => Subcommand::Config(subcommand.into()),
+            SubcommandOpts::Db { subcommand } => Subcommand::Db(subcommand.into()),
+            SubcommandOpts::Diff { from, to } => Subcommand::Diff { from, to },
+            SubcommandOpts::Explain { span } => Subcommand::Explain { span },
+            SubcommandOpts::Frameworks => Subcommand::Frameworks,
+            SubcommandOpts::Patch { outcome, out } => Subcommand::Patch {
+                outcome,
+                out: PathBuf::from(out),
+            },
+            SubcommandOpts::Replay { audit_log, span } => Subcommand::Replay {
+                audit_log: PathBuf::from(audit_log),
+                span,
+            },
+            SubcommandOpts::SelfTest => Subcommand::SelfTest,
+            SubcommandOpts::Show { run } => Subcommand::Show { run },
+        }
+    }
+}
+
+impl From<ConfigSubcommandOpts> for ConfigSubcommand {
+    fn from(subcommand: ConfigSubcommandOpts) -> Self {
+        match subcommand {
+            ConfigSubcommandOpts::Check => ConfigSubcommand::Check,
+        }
+    }
+}
+
+impl From<DbSubcommandOpts> for DbSubcommand {
+    fn from(subcommand: DbSubcommandOpts) -> Self {
+        match subcommand {
+            DbSubcommandOpts::Merge { inputs, output } => DbSubcommand::Merge {
+                inputs: inputs.into_iter().map(PathBuf::from).collect(),
+                output: PathBuf::from(output),
+            },
+            DbSubcommandOpts::Prune { keep } => DbSubcommand::Prune { keep },
+        }
+    }
+}
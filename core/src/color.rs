@@ -0,0 +1,27 @@
+use crate::Necessist;
+use ansi_term::Style;
+use std::io::IsTerminal;
+
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Color {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Returns `style` if console output should be colored, and the default style otherwise. With
+/// `--color auto` (the default), output is colored only if stdout is a terminal.
+pub(crate) fn style(opts: &Necessist, style: Style) -> Style {
+    let enabled = match opts.color.unwrap_or_default() {
+        Color::Auto => std::io::stdout().is_terminal(),
+        Color::Always => true,
+        Color::Never => false,
+    };
+    if enabled {
+        style
+    } else {
+        Style::default()
+    }
+}
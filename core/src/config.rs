@@ -1,7 +1,102 @@
-use crate::LightContext;
-use anyhow::{bail, Result};
+use crate::{util, LightContext};
+use anyhow::{bail, Context, Result};
 use regex::Regex;
-use std::{collections::BTreeMap, fs::read_to_string, path::Path};
+use serde::{
+    de::{self, DeserializeOwned, Visitor},
+    forward_to_deserialize_any, Deserializer,
+};
+use std::{
+    collections::BTreeMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// Calls that check a test's expectations, e.g., `assert_eq!(..)` in Rust or `expect(..)` in
+/// TypeScript. Users can extend this list with the `assertion_calls` option.
+const ASSERTION_CALLS: &[&str] = &[
+    "Assert*",
+    "assert*",
+    "expect*",
+    "require*",
+    "t.Error*",
+    "t.Fail*",
+    "t.Fatal*",
+    "vm.expect*",
+];
+
+/// Calls that only log diagnostic context, e.g., `t.Log(..)` in Go. Users can extend this list
+/// with the `diagnostic_calls` option.
+const DIAGNOSTIC_CALLS: &[&str] = &[
+    "console.log*",
+    "console2.log*",
+    "fmt.Print*",
+    "log.Print*",
+    "t.Log*",
+];
+
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Tables and keys that configure how tests are run and, hence, may appear only in the root
+/// configuration file
+const ROOT_ONLY_TABLES: &[&str] = &[
+    "anchor",
+    "bazel",
+    "docker",
+    "fork",
+    "foundry.fuzz_seed",
+    "framework_precedence",
+    "go",
+    "hardhat",
+    "hooks",
+    "js",
+    "mocha",
+    "rust",
+];
+
+/// The keys that may appear at the top level of a configuration file, i.e., `Toml`'s fields
+const KEYS: &[&str] = &[
+    "anchor",
+    "assertion_calls",
+    "bazel",
+    "candidate_kinds",
+    "diagnostic_calls",
+    "docker",
+    "fork",
+    "foundry",
+    "framework_precedence",
+    "go",
+    "hardhat",
+    "hooks",
+    "ignored_calls",
+    "ignored_functions",
+    "ignored_macros",
+    "ignored_methods",
+    "ignored_path_disambiguation",
+    "ignored_tests",
+    "include_diagnostic_calls",
+    "js",
+    "max_output_bytes",
+    "max_statements_per_test",
+    "min_statement_lines",
+    "mocha",
+    "rust",
+    "timeout",
+    "timeout_is_failure",
+    "walkable_functions",
+];
+
+/// The kinds of candidates that can be removed. A statement that consists of a call is of the
+/// call's kind, e.g., `x.foo();` is a `method-call`.
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize, strum_macros::EnumIter,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum CandidateKind {
+    Call,
+    Macro,
+    MethodCall,
+    Statement,
+}
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum IgnoredPathDisambiguation {
@@ -12,15 +107,55 @@ pub enum IgnoredPathDisambiguation {
 }
 
 pub struct Compiled {
+    assertion_calls: Vec<Regex>,
+    candidate_kinds: Option<Vec<CandidateKind>>,
+    diagnostic_calls: Vec<Regex>,
+    ignored_calls: Vec<(Regex, Option<Regex>)>,
     ignored_functions: Vec<Regex>,
     ignored_macros: Vec<Regex>,
     ignored_methods: Vec<Regex>,
     ignored_path_disambiguation: IgnoredPathDisambiguation,
     ignored_tests: Vec<String>,
+    include_diagnostic_calls: bool,
+    max_statements_per_test: Option<usize>,
+    min_statement_lines: usize,
     walkable_functions: Vec<Regex>,
 }
 
 impl Compiled {
+    #[must_use]
+    pub fn is_assertion_call(&self, name: &str) -> bool {
+        self.assertion_calls.iter().any(|re| re.is_match(name))
+    }
+    #[must_use]
+    pub fn is_candidate_kind(&self, kind: CandidateKind) -> bool {
+        self.candidate_kinds
+            .as_ref()
+            .is_none_or(|candidate_kinds| candidate_kinds.contains(&kind))
+    }
+    #[must_use]
+    pub fn is_diagnostic_call(&self, name: &str) -> bool {
+        self.diagnostic_calls.iter().any(|re| re.is_match(name))
+    }
+    #[must_use]
+    pub fn include_diagnostic_calls(&self) -> bool {
+        self.include_diagnostic_calls
+    }
+    /// Returns true if a function or macro call is ignored by an `ignored_calls` entry. `args`
+    /// should return the source text of the call's arguments, and is called only if needed.
+    #[must_use]
+    pub fn is_ignored_call(&self, name: &str, args: impl Fn() -> Option<String>) -> bool {
+        self.ignored_calls.iter().any(|(name_re, unless_args_re)| {
+            name_re.is_match(name)
+                && match unless_args_re {
+                    None => true,
+                    Some(unless_args_re) => {
+                        args().is_some_and(|args| !unless_args_re.is_match(&args))
+                    }
+                }
+        })
+    }
+
     #[must_use]
     pub fn is_ignored_function(&self, name: &str) -> bool {
         self.ignored_functions.iter().any(|re| re.is_match(name))
@@ -41,14 +176,347 @@ impl Compiled {
     pub fn is_ignored_test(&self, name: &str) -> bool {
         self.ignored_tests.iter().any(|s| name == s)
     }
+    /// Returns the maximum number of statement candidates a test may have before it is skipped
+    #[must_use]
+    pub fn max_statements_per_test(&self) -> Option<usize> {
+        self.max_statements_per_test
+    }
+    /// Returns the minimum number of lines a statement must span to be a candidate
+    #[must_use]
+    pub fn min_statement_lines(&self) -> usize {
+        self.min_statement_lines
+    }
     #[must_use]
     pub fn is_walkable_function(&self, name: &str) -> bool {
         self.walkable_functions.iter().any(|re| re.is_match(name))
     }
 }
 
-#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoredCall {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unless_args_match: Option<String>,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Anchor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_build: Option<bool>,
+    #[serde(default)]
+    pub skip_deploy: bool,
+}
+
+impl Anchor {
+    /// Returns whether to pass `--skip-build` to `anchor test` once the programs have been built;
+    /// `true` unless set otherwise
+    #[must_use]
+    pub fn skip_build(&self) -> bool {
+        self.skip_build.unwrap_or(true)
+    }
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bazel {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub targets: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Docker {
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub images: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fork {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Foundry {
+    #[serde(default)]
+    pub extra_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub extra_ignored_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzz_seed: Option<u64>,
+    #[serde(default)]
+    pub removed_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub removed_ignored_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_before_expect_revert: Option<bool>,
+    #[serde(default)]
+    pub test_base_contracts: Vec<String>,
+}
+
+impl Foundry {
+    /// Returns the value to which `FOUNDRY_FUZZ_SEED` is set; `0` unless set otherwise
+    #[must_use]
+    pub fn fuzz_seed(&self) -> u64 {
+        self.fuzz_seed.unwrap_or_default()
+    }
+
+    /// Returns whether a statement immediately preceding `vm.expectRevert(..)` is not considered
+    /// for removal; `false` unless set otherwise
+    #[must_use]
+    pub fn skip_before_expect_revert(&self) -> bool {
+        self.skip_before_expect_revert.unwrap_or_default()
+    }
+
+    /// Extends `self` with a `foundry` table from a nested configuration file. `fuzz_seed` may
+    /// appear only in the root configuration file and so is not considered.
+    fn extend(&mut self, nested: Self) {
+        let Foundry {
+            extra_ignored_functions,
+            extra_ignored_methods,
+            fuzz_seed: _,
+            removed_ignored_functions,
+            removed_ignored_methods,
+            skip_before_expect_revert,
+            test_base_contracts,
+        } = nested;
+
+        self.extra_ignored_functions.extend(extra_ignored_functions);
+        self.extra_ignored_methods.extend(extra_ignored_methods);
+        self.removed_ignored_functions
+            .extend(removed_ignored_functions);
+        self.removed_ignored_methods.extend(removed_ignored_methods);
+        if skip_before_expect_revert.is_some() {
+            self.skip_before_expect_revert = skip_before_expect_revert;
+        }
+        self.test_base_contracts.extend(test_base_contracts);
+    }
+
+    #[must_use]
+    pub fn ignored_overrides(&self) -> IgnoredOverrides<'_> {
+        IgnoredOverrides {
+            extra_ignored_functions: &self.extra_ignored_functions,
+            extra_ignored_methods: &self.extra_ignored_methods,
+            removed_ignored_functions: &self.removed_ignored_functions,
+            removed_ignored_methods: &self.removed_ignored_methods,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Go {
+    #[serde(default)]
+    pub extra_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub extra_ignored_methods: Vec<String>,
+    #[serde(default)]
+    pub json: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel: Option<usize>,
+    #[serde(default)]
+    pub removed_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub removed_ignored_methods: Vec<String>,
+    #[serde(default)]
+    pub skip_sole_uses: bool,
+    #[serde(default)]
+    pub test_flags: Vec<String>,
+}
+
+impl Go {
+    #[must_use]
+    pub fn ignored_overrides(&self) -> IgnoredOverrides<'_> {
+        IgnoredOverrides {
+            extra_ignored_functions: &self.extra_ignored_functions,
+            extra_ignored_methods: &self.extra_ignored_methods,
+            removed_ignored_functions: &self.removed_ignored_functions,
+            removed_ignored_methods: &self.removed_ignored_methods,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hardhat {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lcov_file: Option<PathBuf>,
+    #[serde(default)]
+    pub skip_uncovered: bool,
+}
+
+impl Hardhat {
+    /// Returns the path of the lcov report consulted by `skip_uncovered`, relative to the Hardhat
+    /// package; `coverage/lcov.info` (where solidity-coverage writes it) unless set otherwise
+    #[must_use]
+    pub fn lcov_file(&self) -> &Path {
+        self.lcov_file
+            .as_deref()
+            .unwrap_or(Path::new("coverage/lcov.info"))
+    }
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(default)]
+    pub after_each_candidate: Vec<String>,
+    #[serde(default)]
+    pub before_each_candidate: Vec<String>,
+    #[serde(default)]
+    pub reset: Vec<String>,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Js {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_manager: Option<PackageManager>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Yarn,
+}
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MochaReporter {
+    #[default]
+    Spec,
+    Json,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mocha {
+    #[serde(default)]
+    pub extra_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub extra_ignored_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<String>,
+    #[serde(default)]
+    pub removed_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub removed_ignored_methods: Vec<String>,
+    #[serde(default)]
+    pub reporter: MochaReporter,
+}
+
+impl Mocha {
+    #[must_use]
+    pub fn ignored_overrides(&self) -> IgnoredOverrides<'_> {
+        IgnoredOverrides {
+            extra_ignored_functions: &self.extra_ignored_functions,
+            extra_ignored_methods: &self.extra_ignored_methods,
+            removed_ignored_functions: &self.removed_ignored_functions,
+            removed_ignored_methods: &self.removed_ignored_methods,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rust {
+    #[serde(default)]
+    pub cap_lints: bool,
+    #[serde(default)]
+    pub custom_harness_commands: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub extra_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub extra_ignored_macros: Vec<String>,
+    #[serde(default)]
+    pub extra_ignored_methods: Vec<String>,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub feature_matrix: Vec<Vec<String>>,
+    #[serde(default)]
+    pub include_benches: bool,
+    #[serde(default)]
+    pub include_should_panic_tests: bool,
+    #[serde(default)]
+    pub removed_ignored_functions: Vec<String>,
+    #[serde(default)]
+    pub removed_ignored_macros: Vec<String>,
+    #[serde(default)]
+    pub removed_ignored_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_threads: Option<usize>,
+}
+
+impl Rust {
+    #[must_use]
+    pub fn ignored_overrides(&self) -> IgnoredOverrides<'_> {
+        IgnoredOverrides {
+            extra_ignored_functions: &self.extra_ignored_functions,
+            extra_ignored_macros: &self.extra_ignored_macros,
+            extra_ignored_methods: &self.extra_ignored_methods,
+            removed_ignored_functions: &self.removed_ignored_functions,
+            removed_ignored_macros: &self.removed_ignored_macros,
+            removed_ignored_methods: &self.removed_ignored_methods,
+        }
+    }
+}
+
+/// Additions to and removals from a framework's built-in ignored functions, macros, and methods,
+/// as configured in the framework's table. A removal must name a built-in pattern exactly.
+#[derive(Clone, Copy, Default)]
+pub struct IgnoredOverrides<'a> {
+    pub extra_ignored_functions: &'a [String],
+    pub extra_ignored_macros: &'a [String],
+    pub extra_ignored_methods: &'a [String],
+    pub removed_ignored_functions: &'a [String],
+    pub removed_ignored_macros: &'a [String],
+    pub removed_ignored_methods: &'a [String],
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
 pub struct Toml {
+    #[serde(default)]
+    pub anchor: Anchor,
+    #[serde(default)]
+    pub assertion_calls: Vec<String>,
+    #[serde(default)]
+    pub bazel: Bazel,
+    #[serde(default)]
+    pub candidate_kinds: Option<Vec<CandidateKind>>,
+    #[serde(default)]
+    pub diagnostic_calls: Vec<String>,
+    #[serde(default)]
+    pub docker: Docker,
+    #[serde(default)]
+    pub fork: Fork,
+    #[serde(default)]
+    pub foundry: Foundry,
+    #[serde(default)]
+    pub framework_precedence: Vec<String>,
+    #[serde(default)]
+    pub go: Go,
+    #[serde(default)]
+    pub hardhat: Hardhat,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub ignored_calls: Vec<IgnoredCall>,
     #[serde(default)]
     pub ignored_functions: Vec<String>,
     #[serde(default)]
@@ -60,22 +528,88 @@ pub struct Toml {
     #[serde(default)]
     pub ignored_tests: Vec<String>,
     #[serde(default)]
+    pub include_diagnostic_calls: Option<bool>,
+    #[serde(default)]
+    pub js: Js,
+    #[serde(default)]
+    pub max_output_bytes: Option<usize>,
+    #[serde(default)]
+    pub max_statements_per_test: Option<usize>,
+    #[serde(default)]
+    pub min_statement_lines: Option<usize>,
+    #[serde(default)]
+    pub mocha: Mocha,
+    #[serde(default)]
+    pub rust: Rust,
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub timeout_is_failure: Option<bool>,
+    #[serde(default)]
     pub walkable_functions: Vec<String>,
     #[serde(flatten)]
     pub other: BTreeMap<String, toml::Value>,
 }
 
 impl Toml {
+    /// Returns the number of bytes of a command's standard output (or error) that are kept. Bytes
+    /// beyond this limit are dropped from the middle of the output.
+    #[must_use]
+    pub fn max_output_bytes(&self) -> usize {
+        self.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES)
+    }
+
     pub fn read(_context: &LightContext, root: &Path) -> Result<Self> {
-        let path_buf = root.join("necessist.toml");
+        Self::read_file(&root.join("necessist.toml"), false).map(Option::unwrap_or_default)
+    }
+
+    /// Returns the configuration that applies to `source_file`, i.e., `self` extended with the
+    /// necessist.toml files in the directories between the project's root and `source_file`.
+    /// Files nearer to `source_file` take precedence.
+    pub fn for_source_file(&self, context: &LightContext, source_file: &Path) -> Result<Self> {
+        let mut toml = self.clone();
 
-        if !path_buf.try_exists()? {
-            return Ok(Self::default());
+        let Some(parent) = source_file.parent() else {
+            return Ok(toml);
+        };
+
+        let Ok(suffix) = util::strip_prefix(parent, context.root) else {
+            return Ok(toml);
+        };
+
+        let mut dir = context.root.to_path_buf();
+        for component in suffix.components() {
+            dir.push(component);
+            if let Some(nested) = Self::read_file(&dir.join("necessist.toml"), true)? {
+                toml.extend(nested);
+            }
         }
 
-        let contents = read_to_string(path_buf)?;
+        Ok(toml)
+    }
 
-        let toml: Self = toml::from_str(&contents)?;
+    fn read_file(path: &Path, nested: bool) -> Result<Option<Self>> {
+        if !path.try_exists()? {
+            return Ok(None);
+        }
+
+        let contents = read_to_string(path)?;
+
+        let table = contents
+            .parse::<toml::Table>()
+            .with_context(|| format!("Failed to parse {path:?}"))?;
+
+        if nested {
+            let keys = root_only_keys(&table);
+            if !keys.is_empty() {
+                bail!(
+                    "{path:?} contains tables or keys that may appear only in the root \
+                     configuration file: {keys:#?}",
+                );
+            }
+        }
+
+        let toml: Self = table.try_into()?;
 
         if !toml.other.is_empty() {
             bail!(
@@ -84,16 +618,108 @@ impl Toml {
             );
         }
 
-        Ok(toml)
+        Ok(Some(toml))
+    }
+
+    /// Extends `self` with a configuration file nested beneath `self`'s. Lists are concatenated,
+    /// and options set in `nested` override those in `self`.
+    fn extend(&mut self, nested: Self) {
+        let Toml {
+            anchor: _,
+            assertion_calls,
+            bazel: _,
+            candidate_kinds,
+            diagnostic_calls,
+            docker: _,
+            fork: _,
+            foundry,
+            framework_precedence: _,
+            go: _,
+            hardhat: _,
+            hooks: _,
+            ignored_calls,
+            ignored_functions,
+            ignored_macros,
+            ignored_methods,
+            ignored_path_disambiguation,
+            ignored_tests,
+            include_diagnostic_calls,
+            js: _,
+            max_output_bytes,
+            max_statements_per_test,
+            min_statement_lines,
+            mocha: _,
+            rust: _,
+            timeout,
+            timeout_is_failure,
+            walkable_functions,
+            other: _,
+        } = nested;
+
+        self.assertion_calls.extend(assertion_calls);
+        if candidate_kinds.is_some() {
+            self.candidate_kinds = candidate_kinds;
+        }
+        self.diagnostic_calls.extend(diagnostic_calls);
+        self.foundry.extend(foundry);
+        self.ignored_calls.extend(ignored_calls);
+        self.ignored_functions.extend(ignored_functions);
+        self.ignored_macros.extend(ignored_macros);
+        self.ignored_methods.extend(ignored_methods);
+        if ignored_path_disambiguation.is_some() {
+            self.ignored_path_disambiguation = ignored_path_disambiguation;
+        }
+        self.ignored_tests.extend(ignored_tests);
+        if include_diagnostic_calls.is_some() {
+            self.include_diagnostic_calls = include_diagnostic_calls;
+        }
+        if max_output_bytes.is_some() {
+            self.max_output_bytes = max_output_bytes;
+        }
+        if max_statements_per_test.is_some() {
+            self.max_statements_per_test = max_statements_per_test;
+        }
+        if min_statement_lines.is_some() {
+            self.min_statement_lines = min_statement_lines;
+        }
+        if timeout.is_some() {
+            self.timeout = timeout;
+        }
+        if timeout_is_failure.is_some() {
+            self.timeout_is_failure = timeout_is_failure;
+        }
+        self.walkable_functions.extend(walkable_functions);
     }
 
     pub fn merge(&mut self, other: &Self) -> Option<&mut Self> {
         let Toml {
+            anchor,
+            assertion_calls,
+            bazel,
+            candidate_kinds,
+            diagnostic_calls,
+            docker,
+            fork,
+            foundry,
+            framework_precedence,
+            go,
+            hardhat,
+            hooks,
+            ignored_calls,
             ignored_functions,
             ignored_macros,
             ignored_methods,
             ignored_path_disambiguation,
             ignored_tests,
+            include_diagnostic_calls,
+            js,
+            max_output_bytes,
+            max_statements_per_test,
+            min_statement_lines,
+            mocha,
+            rust,
+            timeout,
+            timeout_is_failure,
             walkable_functions,
             other: _,
         } = other;
@@ -105,6 +731,20 @@ impl Toml {
             return None;
         }
 
+        self.anchor = anchor.clone();
+        self.assertion_calls.extend_from_slice(assertion_calls);
+        self.bazel = bazel.clone();
+        self.candidate_kinds.clone_from(candidate_kinds);
+        self.diagnostic_calls.extend_from_slice(diagnostic_calls);
+        self.docker = docker.clone();
+        self.fork = fork.clone();
+        self.foundry = foundry.clone();
+        self.framework_precedence.clone_from(framework_precedence);
+        self.go = go.clone();
+        self.hardhat = hardhat.clone();
+        self.hooks = hooks.clone();
+
+        self.ignored_calls.extend_from_slice(ignored_calls);
         self.ignored_functions.extend_from_slice(ignored_functions);
         self.ignored_macros.extend_from_slice(ignored_macros);
         self.ignored_methods.extend_from_slice(ignored_methods);
@@ -113,6 +753,15 @@ impl Toml {
         self.ignored_path_disambiguation = *ignored_path_disambiguation;
 
         self.ignored_tests.extend_from_slice(ignored_tests);
+        self.include_diagnostic_calls = *include_diagnostic_calls;
+        self.js = js.clone();
+        self.max_output_bytes = *max_output_bytes;
+        self.max_statements_per_test = *max_statements_per_test;
+        self.min_statement_lines = *min_statement_lines;
+        self.mocha = mocha.clone();
+        self.rust = rust.clone();
+        self.timeout = *timeout;
+        self.timeout_is_failure = *timeout_is_failure;
         self.walkable_functions
             .extend_from_slice(walkable_functions);
 
@@ -121,31 +770,292 @@ impl Toml {
 
     pub fn compile(self) -> Result<Compiled> {
         let Toml {
+            anchor: _,
+            assertion_calls,
+            bazel: _,
+            candidate_kinds,
+            diagnostic_calls,
+            docker: _,
+            fork: _,
+            foundry: _,
+            framework_precedence: _,
+            go: _,
+            hardhat: _,
+            hooks: _,
+            ignored_calls,
             ignored_functions,
             ignored_macros,
             ignored_methods,
             ignored_path_disambiguation,
             ignored_tests,
+            include_diagnostic_calls,
+            js: _,
+            max_output_bytes: _,
+            max_statements_per_test,
+            min_statement_lines,
+            mocha: _,
+            rust: _,
+            timeout: _,
+            timeout_is_failure: _,
             walkable_functions,
             other: _,
         } = self;
 
+        let assertion_calls = compile_patterns(
+            ASSERTION_CALLS
+                .iter()
+                .copied()
+                .chain(assertion_calls.iter().map(String::as_str)),
+            false,
+        )?;
+        let diagnostic_calls = compile_patterns(
+            DIAGNOSTIC_CALLS
+                .iter()
+                .copied()
+                .chain(diagnostic_calls.iter().map(String::as_str)),
+            false,
+        )?;
+        let ignored_calls = ignored_calls
+            .into_iter()
+            .map(
+                |IgnoredCall {
+                     name,
+                     unless_args_match,
+                 }| {
+                    let name = compile_pattern(&name, false)?;
+                    let unless_args_match = unless_args_match
+                        .map(|unless_args_match| Regex::new(&unless_args_match))
+                        .transpose()?;
+                    Ok((name, unless_args_match))
+                },
+            )
+            .collect::<Result<_>>()?;
         let ignored_functions = compile_patterns(ignored_functions, false)?;
         let ignored_macros = compile_patterns(ignored_macros, false)?;
         let ignored_methods = compile_patterns(ignored_methods, true)?;
         let walkable_functions = compile_patterns(walkable_functions, false)?;
 
         Ok(Compiled {
+            assertion_calls,
+            candidate_kinds,
+            diagnostic_calls,
+            ignored_calls,
             ignored_functions,
             ignored_macros,
             ignored_methods,
             ignored_path_disambiguation: ignored_path_disambiguation.unwrap_or_default(),
             ignored_tests,
+            include_diagnostic_calls: include_diagnostic_calls.unwrap_or_default(),
+            max_statements_per_test,
+            min_statement_lines: min_statement_lines.unwrap_or_default(),
             walkable_functions,
         })
     }
 }
 
+/// A problem found in a configuration file by `necessist config check`
+#[derive(Debug, serde::Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The key concerned, e.g., `foundry.fuzz_seed`, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub message: String,
+    /// A known key that the key concerned may be a misspelling of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Diagnostic {
+    fn error(key: Option<String>, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            key,
+            message,
+            suggestion: None,
+        }
+    }
+}
+
+/// Checks the configuration file at `path`, which is nested beneath the root configuration file if
+/// `nested` is true. Returns the problems found, and the file's contents if they could be parsed.
+/// Unlike [`Toml::read`], every unknown key is reported, along with a suggestion if the key
+/// resembles a known one.
+pub fn check_file(path: &Path, nested: bool) -> Result<(Vec<Diagnostic>, Option<toml::Table>)> {
+    let contents = read_to_string(path)?;
+
+    let table = match contents.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(error) => {
+            let message = error.message().to_owned();
+            return Ok((vec![Diagnostic::error(None, message)], None));
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+
+    for (key, value) in &table {
+        if !KEYS.contains(&key.as_str()) {
+            diagnostics.push(unknown_key(key, key, KEYS, None));
+            continue;
+        }
+        if let (Some(subkeys), Some(subtable)) = (table_keys(key), value.as_table()) {
+            for subkey in subtable.keys() {
+                if !subkeys.contains(&subkey.as_str()) {
+                    diagnostics.push(unknown_key(
+                        &format!("{key}.{subkey}"),
+                        subkey,
+                        subkeys,
+                        Some(key),
+                    ));
+                }
+            }
+        }
+    }
+
+    if nested {
+        for key in root_only_keys(&table) {
+            diagnostics.push(Diagnostic::error(
+                Some(key),
+                "may appear only in the root configuration file".to_owned(),
+            ));
+        }
+    }
+
+    // smoelius: Only once every key is known can errors in the values be reported meaningfully.
+    if diagnostics.is_empty() {
+        if let Err(error) = table.clone().try_into::<Toml>() {
+            diagnostics.push(Diagnostic::error(None, error.message().to_owned()));
+        }
+    }
+
+    Ok((diagnostics, Some(table)))
+}
+
+/// Returns the tables and keys in `table` that may appear only in the root configuration file,
+/// e.g., `go` or `foundry.fuzz_seed`
+fn root_only_keys(table: &toml::Table) -> Vec<String> {
+    let mut keys = Vec::new();
+    for (key, value) in table {
+        if ROOT_ONLY_TABLES.contains(&key.as_str()) {
+            keys.push(key.clone());
+            continue;
+        }
+        if let Some(subtable) = value.as_table() {
+            keys.extend(
+                subtable
+                    .keys()
+                    .map(|subkey| format!("{key}.{subkey}"))
+                    .filter(|key| ROOT_ONLY_TABLES.contains(&key.as_str())),
+            );
+        }
+    }
+    keys
+}
+
+/// Returns the keys of the table `key`, or `None` if `key` is not a table
+fn table_keys(key: &str) -> Option<&'static [&'static str]> {
+    Some(match key {
+        "anchor" => struct_fields::<Anchor>(),
+        "bazel" => struct_fields::<Bazel>(),
+        "docker" => struct_fields::<Docker>(),
+        "fork" => struct_fields::<Fork>(),
+        "foundry" => struct_fields::<Foundry>(),
+        "go" => struct_fields::<Go>(),
+        "hardhat" => struct_fields::<Hardhat>(),
+        "hooks" => struct_fields::<Hooks>(),
+        "js" => struct_fields::<Js>(),
+        "mocha" => struct_fields::<Mocha>(),
+        "rust" => struct_fields::<Rust>(),
+        _ => return None,
+    })
+}
+
+fn unknown_key(key: &str, name: &str, known: &[&str], table: Option<&str>) -> Diagnostic {
+    let suggestion = suggest(name, known).map(|suggestion| {
+        table.map_or_else(
+            || suggestion.to_owned(),
+            |table| format!("{table}.{suggestion}"),
+        )
+    });
+    Diagnostic {
+        severity: Severity::Error,
+        key: Some(key.to_owned()),
+        message: "unknown key".to_owned(),
+        suggestion,
+    }
+}
+
+/// Returns the known name nearest to `name`, if it differs from `name` in at most a third of its
+/// characters
+fn suggest<'a>(name: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|&candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance * 3 <= name.chars().count())
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, x) in a.chars().enumerate() {
+        let mut curr = vec![i + 1];
+        for (j, &y) in b.iter().enumerate() {
+            curr.push(
+                (prev[j] + usize::from(x != y))
+                    .min(prev[j + 1] + 1)
+                    .min(curr[j] + 1),
+            );
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+/// Returns the names of `T`'s fields, where `T` is a struct that derives `Deserialize`. The names
+/// are those that `T`'s implementation passes to `Deserializer::deserialize_struct`.
+fn struct_fields<T: DeserializeOwned>() -> &'static [&'static str] {
+    struct FieldsDeserializer<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldsDeserializer<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _: Result<T, _> = T::deserialize(FieldsDeserializer(&mut fields));
+    fields
+}
+
 fn compile_patterns(
     ignored: impl IntoIterator<Item = impl AsRef<str>>,
     methods: bool,
@@ -231,3 +1141,17 @@ fn patterns() {
         }
     }
 }
+
+#[test]
+fn suggestions() {
+    assert_eq!(Some("timeout"), suggest("timout", KEYS));
+    assert_eq!(
+        Some("fuzz_seed"),
+        suggest("fuzz_sed", struct_fields::<Foundry>())
+    );
+    assert_eq!(None, suggest("gp", KEYS));
+    assert_eq!(
+        ["skip_build", "skip_deploy"].as_slice(),
+        struct_fields::<Anchor>()
+    );
+}
@@ -1,36 +1,96 @@
 use crate::{
-    config,
-    framework::{self, Applicable, Postprocess, SourceFileSpanTestMap, SpanKind, ToImplementation},
-    note, source_warn, sqlite, util, warn, Backup, Outcome, Rewriter, SourceFile, Span, WarnFlags,
-    Warning, __ToConsoleString,
+    annotate,
+    audit::{self, AuditLog},
+    batch,
+    candidates::Candidates,
+    cause, check_config, color,
+    config::{self, CandidateKind},
+    diff, dispatch,
+    event::{self, Event},
+    exec::{Captured, Exec, ProcessGroup},
+    explain,
+    framework::{
+        self, Applicable, Postprocess, RemovalStrategy, SourceFileSpanTestMap, SpanKind,
+        SpanTestMaps, ToImplementation,
+    },
+    frameworks,
+    group::{GroupBy, Groups},
+    hooks, install,
+    isolate::{Isolate, Worktree},
+    network, note,
+    order::{self, Order, Rank},
+    patch,
+    progress_file::ProgressFile,
+    rewriter::Edits,
+    self_test, show,
+    side_effects::Snapshot,
+    source_warn, sqlite,
+    stream::ResultsStream,
+    suggest::Suggestions,
+    truncation, util, warn, Backup, Color, LineColumn, Outcome, Rewriter, SourceFile, Span,
+    WarnFlags, Warning, __ToConsoleString,
 };
-use ansi_term::Style;
-use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use ansi_term::{
+    Color::{Cyan, Green, Red},
+    Style,
+};
+use anyhow::{anyhow, bail, ensure, Context as _, Error, Result};
 use heck::ToKebabCase;
 use indexmap::IndexSet;
 use indicatif::ProgressBar;
 use itertools::{peek_nth, PeekNth};
-use log::debug;
 use once_cell::sync::OnceCell;
+use similar::TextDiff;
 use std::{
     cell::RefCell,
-    collections::BTreeMap,
-    env::{current_dir, var},
+    collections::{BTreeMap, BTreeSet},
+    env::{args_os, current_dir, current_exe, var, var_os},
     fmt::Display,
-    io::{IsTerminal, Write},
+    fs::File,
+    io::{IsTerminal, Read, Write},
     iter::Peekable,
     path::{Path, PathBuf},
     process::{Command, ExitStatus as StdExitStatus, Stdio},
     rc::Rc,
-    sync::atomic::{AtomicBool, Ordering},
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 use strum::IntoEnumIterator;
-use subprocess::{Exec, ExitStatus};
+use subprocess::{ExitStatus, Popen};
+use tracing::{debug, field, info_span};
+use walkdir::WalkDir;
 
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
 
-static CTRLC: AtomicBool = AtomicBool::new(false);
+const CTRLC_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Environment variable through which a subprocess is told which `rust.feature_matrix` entry to
+/// use
+const FEATURE_SET_VAR: &str = "NECESSIST_FEATURE_SET";
+
+/// Environment variable through which a subprocess is told which project (relative to the root
+/// directory) to run on when `--recurse-projects` is used
+const PROJECT_VAR: &str = "NECESSIST_PROJECT";
+
+/// Environment variable through which a subprocess is told which job slot (given as `i/n`) it
+/// occupies when `--isolate worktree` is used with `--jobs`
+const JOB_SLOT_VAR: &str = "NECESSIST_JOB_SLOT";
+
+/// Maximum depth of subdirectories searched for projects when `--recurse-projects` is used
+const MAX_PROJECT_DEPTH: usize = 3;
+
+/// Directories not searched for projects when `--recurse-projects` is used (in addition to hidden
+/// directories)
+const IGNORED_PROJECT_DIRS: &[&str] = &["node_modules", "target"];
+
+/// Value for `NECESSIST_REMOVAL` that is a well-formed span id, but that matches no span
+const NO_REMOVAL: &str = "0000000000000000";
+
+pub(crate) static CTRLC: AtomicBool = AtomicBool::new(false);
 
 #[derive(Clone)]
 pub(crate) struct Removal {
@@ -51,6 +111,38 @@ impl Display for MismatchKind {
     }
 }
 
+/// The state of a source file that is kept across its appearances in the order in which source
+/// files are processed (see `--order round-robin`)
+#[derive(Default)]
+struct SourceFileState {
+    failing_tests: BTreeSet<String>,
+    consecutive_passes: BTreeMap<String, usize>,
+    suspicious_tests: BTreeSet<String>,
+    /// Whether the source file's candidates are skipped, e.g., because its dry run failed
+    skipped: bool,
+}
+
+impl SourceFileState {
+    fn skipped() -> Self {
+        Self {
+            skipped: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the tests of `test_names` that are neither failing nor suspicious
+    fn passing_tests(&self, test_names: &IndexSet<String>) -> IndexSet<String> {
+        test_names
+            .iter()
+            .filter(|test_name| {
+                !self.failing_tests.contains(*test_name)
+                    && !self.suspicious_tests.contains(*test_name)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
 struct Mismatch {
     kind: MismatchKind,
     removal: Removal,
@@ -59,9 +151,17 @@ struct Mismatch {
 struct Context<'a> {
     opts: Necessist,
     root: Rc<PathBuf>,
+    original_root: Rc<PathBuf>,
+    config: config::Toml,
     println: &'a dyn Fn(&dyn AsRef<str>),
     backend: Box<dyn framework::Interface>,
+    candidates: Candidates,
     progress: Option<&'a ProgressBar>,
+    progress_file: Option<ProgressFile>,
+    results_stream: Option<ResultsStream>,
+    audit_log: Option<AuditLog>,
+    groups: Option<Groups>,
+    suggestions: Option<Suggestions>,
 }
 
 impl Context<'_> {
@@ -69,6 +169,8 @@ impl Context<'_> {
         LightContext {
             opts: &self.opts,
             root: &self.root,
+            original_root: &self.original_root,
+            config: &self.config,
             println: self.println,
         }
     }
@@ -77,53 +179,267 @@ impl Context<'_> {
 pub struct LightContext<'a> {
     pub opts: &'a Necessist,
     pub root: &'a Rc<PathBuf>,
+    /// The project's root directory before any isolation is applied; differs from `root` only
+    /// when `--isolate` is used, or for a project found by `--recurse-projects`, in which case it
+    /// is the directory that was searched (and that holds the database shared by all projects)
+    pub original_root: &'a Rc<PathBuf>,
+    pub config: &'a config::Toml,
     pub println: &'a dyn Fn(&dyn AsRef<str>),
 }
 
+/// A subcommand, which replaces Necessist's usual behavior
+#[derive(Clone, Debug)]
+pub enum Subcommand {
+    /// Write to `out` a copy of each source file with removals in the database, in which each
+    /// removal's last line ends with a comment giving the removal's outcome and the date it was
+    /// produced
+    Annotate { out: PathBuf },
+    /// Run Necessist on each repository listed in `manifest`
+    Batch { jobs: usize, manifest: PathBuf },
+    /// Operate on configuration files
+    Config(ConfigSubcommand),
+    /// Operate on the database
+    Db(DbSubcommand),
+    /// List the removals whose outcomes differ between the runs `from` and `to` (each a run id or
+    /// `latest`)
+    Diff { from: String, to: String },
+    /// Rerun the removal of `span` (e.g., `tests/a.rs:4:5-4:12`), showing the build and test
+    /// output
+    Explain { span: String },
+    /// Explain which frameworks are applicable, which one is selected, and why
+    Frameworks,
+    /// Write a unified diff to `out` for each removal in the database with outcome `outcome`
+    Patch { outcome: String, out: PathBuf },
+    /// Remove `span` (e.g., `tests/a.rs:4:5-4:12`) as recorded in the audit log `audit_log`, and
+    /// leave it removed
+    Replay { audit_log: PathBuf, span: String },
+    /// Run Necessist on a tiny generated project for the selected framework (Rust, if none is
+    /// selected), and check that its candidates have the expected outcomes
+    SelfTest,
+    /// Show the removals produced by the run `run` (a run id or `latest`)
+    Show { run: String },
+}
+
+/// A subcommand of [`Subcommand::Config`]
+#[derive(Clone, Debug)]
+pub enum ConfigSubcommand {
+    /// Check the configuration files for unknown keys and invalid values, and output each problem
+    /// as a line of JSON
+    Check,
+}
+
+/// A subcommand of [`Subcommand::Db`]
+#[derive(Clone, Debug)]
+pub enum DbSubcommand {
+    /// Merge the contents of the databases `inputs` into a new database `output`
+    Merge {
+        inputs: Vec<PathBuf>,
+        output: PathBuf,
+    },
+    /// Delete all but the `keep` most recent runs, along with the removals they produced
+    Prune { keep: usize },
+}
+
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Default)]
 pub struct Necessist {
     pub allow: Vec<Warning>,
+    pub audit_log: Option<PathBuf>,
+    pub build_only: bool,
+    pub check_side_effects: bool,
+    pub color: Option<Color>,
+    pub count_failing_tests: bool,
     pub default_config: bool,
     pub deny: Vec<Warning>,
+    pub docker: Option<crate::Docker>,
     pub dump: bool,
     pub dump_candidates: bool,
+    pub fail_fast_per_test: Option<usize>,
+    pub group_by: Option<GroupBy>,
+    pub include_go_benchmarks: bool,
+    pub include_go_fuzz: bool,
+    pub include_ignored: bool,
+    pub isolate: Option<Isolate>,
+    pub jobs: Option<usize>,
+    pub log_json: bool,
     pub no_dry_run: bool,
     pub no_local_functions: bool,
+    pub no_network: bool,
+    pub no_progress: bool,
     pub no_sqlite: bool,
+    pub only_post_assertion: bool,
+    pub order: Option<Order>,
+    #[allow(clippy::option_option)]
+    pub progress_file: Option<Option<PathBuf>>,
     pub quiet: bool,
+    pub recurse_projects: bool,
+    pub remote: Option<crate::Remote>,
+    pub require_clean_dry_run: bool,
     pub reset: bool,
     pub resume: bool,
     pub root: Option<PathBuf>,
+    pub shard: Option<crate::Shard>,
+    pub skip_calls: bool,
+    pub skip_go_statements: bool,
+    pub skip_macros: bool,
+    pub skip_method_calls: bool,
+    pub skip_statements: bool,
+    pub source_files_from: Option<String>,
+    pub stream_results: Option<String>,
+    pub subcommand: Option<Subcommand>,
+    pub suggest_ignores: bool,
     pub timeout: Option<u64>,
+    pub tolerate_unused: bool,
     pub verbose: bool,
+    pub verify_passed: bool,
     pub source_files: Vec<PathBuf>,
     pub args: Vec<String>,
 }
 
+impl Necessist {
+    /// Returns true if a span is being explained, in which case build and test output should be
+    /// shown rather than captured
+    #[must_use]
+    pub fn explaining(&self) -> bool {
+        matches!(self.subcommand, Some(Subcommand::Explain { .. }))
+    }
+}
+
 /// Necessist's main entrypoint.
+#[allow(clippy::too_many_lines)]
 // smoelius: The reason `framework` is not included as a field in `Necessist` is to avoid having
 // to parameterize every function that takes a `Necessist` as an argument.
-pub fn necessist<Identifier: Applicable + Display + IntoEnumIterator + ToImplementation>(
+pub fn necessist<Identifier: Applicable + Clone + Display + IntoEnumIterator + ToImplementation>(
     opts: &Necessist,
     framework: framework::Auto<Identifier>,
 ) -> Result<()> {
-    let opts = opts.clone();
+    let mut opts = opts.clone();
+
+    if let Some(source_files_from) = &opts.source_files_from {
+        let source_files = read_source_files_from(source_files_from)?;
+        opts.source_files.extend(source_files);
+    }
 
     process_options(&opts)?;
 
+    // smoelius: `--build-only` never runs tests, so there are no dry runs. And its results are not
+    // recorded in the database, so that a later run cannot mistake them for complete results.
+    if opts.build_only {
+        opts.no_dry_run = true;
+        opts.no_sqlite = true;
+    }
+
+    // smoelius: A job slot runs the source files of its own shard, and performs its dry runs one at
+    // a time.
+    let job_slot = job_slot()?;
+    if let Some(shard) = job_slot {
+        opts.shard = Some(shard);
+        opts.jobs = None;
+    }
+
+    // smoelius: `batch` and `self-test` run Necessist in subprocesses. Their contexts are used
+    // only for printing.
+    if let Some(subcommand @ (Subcommand::Batch { .. } | Subcommand::SelfTest)) = &opts.subcommand {
+        let root = Rc::new(current_dir()?);
+        let config = config::Toml::default();
+        let println = |msg: &dyn AsRef<str>| {
+            println!("{}", msg.as_ref());
+        };
+        let context = LightContext {
+            opts: &opts,
+            root: &root,
+            original_root: &root,
+            config: &config,
+            println: if opts.quiet { &|_| {} } else { &println },
+        };
+        return if let Subcommand::Batch { jobs, manifest } = subcommand {
+            batch::batch(&context, manifest, *jobs)
+        } else {
+            self_test::self_test(
+                &context,
+                &framework
+                    .named()
+                    .map_or_else(|| String::from("rust"), ToString::to_string),
+            )
+        };
+    }
+
+    let explain_span = prepare_subcommand(&mut opts)?;
+
+    // smoelius: The stream is opened before `--isolate` can change the current directory.
+    let results_stream = opts
+        .stream_results
+        .as_deref()
+        .map(ResultsStream::new)
+        .transpose()?;
+
+    let audit_log = opts.audit_log.as_deref().map(AuditLog::new).transpose()?;
+
     let root = opts
         .root
         .as_ref()
         .map_or_else(current_dir, dunce::canonicalize)
         .map(Rc::new)?;
 
+    // smoelius: Similarly, the progress file's path is resolved before `--isolate` can change the
+    // current directory or the root.
+    let progress_file = opts
+        .progress_file
+        .as_ref()
+        .map(|path| ProgressFile::new(path.as_deref(), &root))
+        .transpose()?;
+
+    let project = project();
+
+    // smoelius: A subprocess running one entry of a feature matrix, one project found by
+    // `--recurse-projects`, or one job slot of `--isolate worktree`, relies on its parent's lock.
     #[cfg(feature = "lock_root")]
-    let _file: std::fs::File = lock_root(&root)?;
+    let _file: Option<std::fs::File> =
+        if feature_set_index()?.is_none() && project.is_none() && job_slot.is_none() {
+            Some(lock_root(&root)?)
+        } else {
+            None
+        };
+
+    if opts.recurse_projects && project.is_none() {
+        return recurse_projects(&opts, &root, &framework);
+    }
+
+    if opts.isolate == Some(Isolate::Worktree)
+        && opts.jobs.unwrap_or(1) > 1
+        && opts.subcommand.is_none()
+        && !opts.default_config
+        && !opts.dump
+        && !opts.dump_candidates
+    {
+        return job_slots(&opts, &root);
+    }
+
+    let original_root = root.clone();
+
+    let root = project
+        .as_ref()
+        .filter(|project| !project.as_os_str().is_empty())
+        .map_or(root, |project| Rc::new(original_root.join(project)));
+
+    // smoelius: `worktree` must outlive `context`, as the former is removed when dropped.
+    let mut worktree = None;
+
+    let root = if opts.isolate == Some(Isolate::Worktree) && !opts.default_config && !opts.dump {
+        let worktree = worktree.insert(Worktree::new(&root)?);
+        isolate(&mut opts, worktree, &root)?
+    } else {
+        root
+    };
+
+    // smoelius: `config` is replaced with the contents of necessist.toml once the file is read.
+    let config = config::Toml::default();
 
     let mut context = LightContext {
         opts: &opts,
         root: &root,
+        original_root: &original_root,
+        config: &config,
         println: &|_| {},
     };
 
@@ -144,28 +460,63 @@ pub fn necessist<Identifier: Applicable + Display + IntoEnumIterator + ToImpleme
         )?;
     }
 
-    let Some((backend, n_spans, source_file_span_test_map)) = prepare(&context, framework)? else {
+    let Some((
+        config,
+        backend,
+        framework_name,
+        toolchain,
+        n_spans,
+        source_file_span_test_map,
+        candidates,
+    )) = prepare(&context, framework)?
+    else {
         return Ok(());
     };
 
+    if let Some(docker) = &mut opts.docker {
+        docker.resolve(&config.docker, &framework_name)?;
+    }
+
     let mut context = Context {
         opts,
         root,
+        original_root,
+        config,
         println: &|_| {},
         backend,
+        candidates,
         progress: None,
+        progress_file,
+        results_stream,
+        audit_log,
+        groups: None,
+        suggestions: None,
     };
 
     if !context.opts.quiet {
         context.println = &println;
     }
 
-    let progress =
-        if var("RUST_LOG").is_err() && !context.opts.quiet && std::io::stdout().is_terminal() {
-            Some(ProgressBar::new(n_spans as u64))
-        } else {
-            None
-        };
+    if let Some((start, end)) = explain_span {
+        return explain::explain(
+            &context.light(),
+            &*context.backend,
+            &source_file_span_test_map,
+            start,
+            end,
+        );
+    }
+
+    let progress = if var("RUST_LOG").is_err()
+        && !context.opts.log_json
+        && !context.opts.no_progress
+        && !context.opts.quiet
+        && std::io::stdout().is_terminal()
+    {
+        Some(ProgressBar::new(n_spans as u64))
+    } else {
+        None
+    };
 
     let progress_println = |msg: &dyn AsRef<str>| {
         #[allow(clippy::unwrap_used)]
@@ -177,37 +528,170 @@ pub fn necessist<Identifier: Applicable + Display + IntoEnumIterator + ToImpleme
         context.progress = progress.as_ref();
     }
 
+    if let Some(progress_file) = &context.progress_file {
+        progress_file.set_n_candidates(n_spans)?;
+    }
+
+    // smoelius: The fuzz seed is recorded so that a Foundry run's outcomes can be reproduced.
+    if framework_name == "foundry" {
+        let sqlite = sqlite_init_lazy(&context.light())?;
+        if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+            sqlite::set_run_fuzz_seed(sqlite, context.config.foundry.fuzz_seed())?;
+        };
+    }
+
+    // smoelius: Toolchain versions are recorded so that runs on different machines can be
+    // compared.
+    record_toolchain(&context.light(), toolchain)?;
+
     run(context, source_file_span_test_map)
 }
 
-#[allow(clippy::type_complexity)]
-fn prepare<Identifier: Applicable + Display + IntoEnumIterator + ToImplementation>(
+/// Removes the candidate kinds skipped on the command line (e.g., with `--skip-macros`) from
+/// `config`'s `candidate_kinds`. Since every backend consults `candidate_kinds`, the options apply
+/// uniformly across frameworks.
+fn skip_candidate_kinds(opts: &Necessist, config: &mut config::Toml) {
+    let skipped = [
+        (opts.skip_calls, CandidateKind::Call),
+        (opts.skip_macros, CandidateKind::Macro),
+        (opts.skip_method_calls, CandidateKind::MethodCall),
+        (opts.skip_statements, CandidateKind::Statement),
+    ]
+    .into_iter()
+    .filter_map(|(skip, kind)| if skip { Some(kind) } else { None })
+    .collect::<Vec<_>>();
+
+    if skipped.is_empty() {
+        return;
+    }
+
+    config
+        .candidate_kinds
+        .get_or_insert_with(|| CandidateKind::iter().collect())
+        .retain(|kind| !skipped.contains(kind));
+}
+
+/// Makes `opts` and the current directory refer to `worktree` rather than the original checkout,
+/// and returns the project's root directory within `worktree`.
+fn isolate(opts: &mut Necessist, worktree: &mut Worktree, root: &Path) -> Result<Rc<PathBuf>> {
+    let root = worktree
+        .translate(root)
+        .ok_or_else(|| anyhow!("{root:?} is not in the repository's working tree"))?;
+
+    // smoelius: Source files that cannot be canonicalized are left alone so that
+    // `canonicalize_source_files` can report them.
+    for source_file in &mut opts.source_files {
+        if let Some(path_buf) = dunce::canonicalize(&source_file)
+            .ok()
+            .and_then(|path_buf| worktree.translate(&path_buf))
+        {
+            *source_file = path_buf;
+        }
+    }
+
+    worktree.enter()?;
+
+    Ok(Rc::new(root))
+}
+
+#[allow(clippy::too_many_lines, clippy::type_complexity)]
+fn prepare<Identifier: Applicable + Clone + Display + IntoEnumIterator + ToImplementation>(
     context: &LightContext,
     framework: framework::Auto<Identifier>,
-) -> Result<Option<(Box<dyn framework::Interface>, usize, SourceFileSpanTestMap)>> {
+) -> Result<
+    Option<(
+        config::Toml,
+        Box<dyn framework::Interface>,
+        String,
+        &'static [&'static [&'static str]],
+        usize,
+        SourceFileSpanTestMap,
+        Candidates,
+    )>,
+> {
     if context.opts.default_config {
         default_config(context, context.root)?;
         return Ok(None);
     }
 
-    let config = config::Toml::read(context, context.root)?;
+    // smoelius: The configuration files are checked before they are read, since reading them fails
+    // at the first problem.
+    if let Some(Subcommand::Config(ConfigSubcommand::Check)) = &context.opts.subcommand {
+        check_config::check_config(context, &framework)?;
+        return Ok(None);
+    }
+
+    let mut config = config::Toml::read(context, context.root)?;
+
+    skip_candidate_kinds(context.opts, &mut config);
+
+    if let Some(index) = feature_set_index()? {
+        config.rust.features = config
+            .rust
+            .feature_matrix
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow!("Feature set {index} does not exist"))?;
+    } else if !config.rust.feature_matrix.is_empty() {
+        feature_matrix(context, &config.rust.feature_matrix)?;
+        return Ok(None);
+    }
+
+    let context = &LightContext {
+        config: &config,
+        ..*context
+    };
+
+    if let Some(Subcommand::Frameworks) = &context.opts.subcommand {
+        frameworks::explain_framework_selection(context, &framework)?;
+        return Ok(None);
+    }
+
+    if let Some(Subcommand::Replay { audit_log, span }) = &context.opts.subcommand {
+        audit::replay(context, audit_log, span)?;
+        return Ok(None);
+    }
+
+    if database_subcommand(context)? {
+        return Ok(None);
+    }
 
     if context.opts.dump {
         let past_removals = past_removals_init_lazy(context)?;
-        dump(context, &past_removals);
+        dump(context, &past_removals)?;
         return Ok(None);
     }
 
-    let mut backend = backend_for_framework(context, framework)?;
+    let (framework_name, toolchain, mut backend) = backend_for_framework(context, framework)?;
 
     let paths = canonicalize_source_files(context)?;
 
-    let (n_tests, source_file_span_test_map) = backend.parse(
+    let mut candidates = load_candidates(context)?;
+
+    let paths = paths.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+
+    event::publish(
         context,
-        &config,
-        &paths.iter().map(AsRef::as_ref).collect::<Vec<_>>(),
+        &Event::ParseStarted {
+            source_files: &paths,
+        },
     )?;
 
+    let (n_tests, mut source_file_span_test_map) = info_span!("parse")
+        .in_scope(|| backend.parse(context, &config, &paths, &mut candidates))?;
+
+    if context.opts.only_post_assertion {
+        for span_test_maps in source_file_span_test_map.values_mut() {
+            let post_assertion = &span_test_maps.post_assertion;
+            span_test_maps
+                .statement
+                .retain(|span, _| post_assertion.contains(span));
+            span_test_maps
+                .method_call
+                .retain(|span, _| post_assertion.contains(span));
+        }
+    }
+
     let n_spans = source_file_span_test_map
         .values()
         .map(|span_test_maps| {
@@ -241,227 +725,1436 @@ fn prepare<Identifier: Applicable + Display + IntoEnumIterator + ToImplementatio
         )
     });
 
-    Ok(Some((backend, n_spans, source_file_span_test_map)))
+    Ok(Some((
+        config,
+        backend,
+        framework_name,
+        toolchain,
+        n_spans,
+        source_file_span_test_map,
+        candidates,
+    )))
 }
 
 fn run(mut context: Context, source_file_span_test_map: SourceFileSpanTestMap) -> Result<()> {
     ctrlc::set_handler(|| CTRLC.store(true, Ordering::SeqCst))?;
 
-    let past_removals = past_removals_init_lazy(&context.light())?;
+    if context.opts.group_by == Some(GroupBy::Test) {
+        context.groups = Some(Groups::default());
+    }
 
-    let mut past_removal_iter = past_removals.into_iter().peekable();
+    if context.opts.suggest_ignores {
+        context.suggestions = Some(Suggestions::default());
+    }
 
-    for (source_file, span_test_maps) in source_file_span_test_map {
-        let mut span_test_iter = peek_nth(span_test_maps.iter());
+    let mut past_removals = past_removals_init_lazy(&context.light())?;
+
+    // smoelius: The database may hold removals from source files in other shards (e.g., those of
+    // other job slots). They are not this run's to skip.
+    if let Some(shard) = &context.opts.shard {
+        let mut retained = Vec::with_capacity(past_removals.len());
+        for removal in past_removals {
+            if shard.contains(&context.root, &removal.span.source_file)? {
+                retained.push(removal);
+            }
+        }
+        past_removals = retained;
+    }
 
-        let (mismatch, n) = skip_past_removals(&mut span_test_iter, &mut past_removal_iter);
+    record_statement_kinds(&context, &source_file_span_test_map)?;
 
-        update_progress(&context, mismatch, n)?;
+    record_candidates(&context)?;
 
-        if span_test_iter.peek().is_none() {
+    let cheapest_first = context.opts.order == Some(Order::CheapestFirst);
+
+    // smoelius: `--order cheapest-first` needs the dry runs' durations up front.
+    let dry_run_results =
+        if context.opts.no_dry_run || (context.opts.jobs.unwrap_or(1) <= 1 && !cheapest_first) {
+            BTreeMap::new()
+        } else {
+            parallel_dry_runs(&context, &source_file_span_test_map, &past_removals)?
+        };
+
+    for (source_file, (result, duration)) in &dry_run_results {
+        if result.is_ok() {
+            record_dry_run(&context, source_file, *duration)?;
+        }
+    }
+
+    let mut source_file_span_test_maps = source_file_span_test_map.into_iter().collect::<Vec<_>>();
+
+    // smoelius: Past removals must be visited in the same order as the candidates (see
+    // `order::Rank`).
+    let mut rank = Rank::default();
+    if cheapest_first {
+        let estimates = estimates(&context, &source_file_span_test_maps, &dry_run_results)?;
+        order::cheapest_first(&mut source_file_span_test_maps, &estimates);
+        rank = Rank::by_source_file(&source_file_span_test_maps);
+        past_removals.sort_by(|left, right| rank.cmp(&left.span, &right.span));
+    } else if context.opts.order == Some(Order::RoundRobin) {
+        source_file_span_test_maps = order::round_robin(source_file_span_test_maps);
+        rank = Rank::by_span(&source_file_span_test_maps);
+        past_removals.sort_by(|left, right| rank.cmp(&left.span, &right.span));
+    }
+
+    let mut state = RunState {
+        rank,
+        past_removal_iter: past_removals.into_iter().peekable(),
+        dry_run_results,
+        source_file_states: BTreeMap::new(),
+        known_node_processes: hooks::node_processes()?,
+        n_diagnostic_passed: 0,
+    };
+
+    for (source_file, span_test_maps) in source_file_span_test_maps {
+        run_source_file(&mut context, &mut state, source_file, &span_test_maps)?;
+    }
+
+    context.progress.map(ProgressBar::finish);
+
+    if let Some(progress_file) = &context.progress_file {
+        progress_file.finish()?;
+    }
+
+    // smoelius: Record any warnings that occurred after the last removal.
+    let sqlite = sqlite_init_lazy(&context.light())?;
+    if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        sqlite::insert_warnings(sqlite)?;
+    }
+
+    print_groups(&mut context);
+
+    if let Some(suggestions) = context.suggestions.take() {
+        suggestions.print(&context.light());
+    }
+
+    event::publish(
+        &context.light(),
+        &Event::Summary {
+            n_diagnostic_passed: state.n_diagnostic_passed,
+            installing: install::elapsed(),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// The state that `run` keeps from one source file to the next
+struct RunState {
+    rank: Rank,
+    past_removal_iter: Peekable<std::vec::IntoIter<Removal>>,
+    dry_run_results: BTreeMap<PathBuf, (Result<()>, Duration)>,
+    // smoelius: With `--order round-robin`, a source file can appear more than once in
+    // `source_file_span_test_maps`. The state of a source file's first appearance is kept for its
+    // later ones, so that, e.g., the source file is dry run only once.
+    source_file_states: BTreeMap<SourceFile, SourceFileState>,
+    // smoelius: Nodes that were running before Necessist started are not orphans.
+    known_node_processes: BTreeSet<u32>,
+    n_diagnostic_passed: usize,
+}
+
+impl RunState {
+    /// Skips the candidates at the front of `span_test_iter` that are among the past removals, and
+    /// updates the progress accordingly
+    fn skip_past_removals<'a, I>(
+        &mut self,
+        context: &Context,
+        span_test_iter: &mut PeekNth<I>,
+    ) -> Result<()>
+    where
+        I: Iterator<Item = (&'a Span, SpanKind, &'a IndexSet<String>)>,
+    {
+        let (mismatch, n) =
+            skip_past_removals(&self.rank, span_test_iter, &mut self.past_removal_iter);
+        update_progress(context, mismatch, n)
+    }
+}
+
+/// A source file whose candidates are being run, along with the settings that apply to it
+struct SourceFileRun<'a> {
+    source_file: &'a SourceFile,
+    span_test_maps: &'a SpanTestMaps,
+    tolerance_insertions: Vec<(LineColumn, String)>,
+    timeout: Option<Duration>,
+    timeout_is_failure: bool,
+}
+
+/// Dry runs `source_file` (unless it was already visited) and runs each of its candidates that is
+/// not among the past removals
+fn run_source_file(
+    context: &mut Context,
+    state: &mut RunState,
+    source_file: SourceFile,
+    span_test_maps: &SpanTestMaps,
+) -> Result<()> {
+    let mut span_test_iter = peek_nth(span_test_maps.iter());
+
+    state.skip_past_removals(context, &mut span_test_iter)?;
+
+    if span_test_iter.peek().is_none() {
+        return Ok(());
+    }
+
+    let _source_file_span = info_span!(
+        "source_file",
+        path = %util::strip_current_dir(&source_file).to_string_lossy()
+    )
+    .entered();
+
+    if let Some(progress_file) = &context.progress_file {
+        progress_file.set_source_file(&source_file)?;
+    }
+
+    let source_file_config = context
+        .config
+        .for_source_file(&context.light(), &source_file)?;
+    let timeout = timeout(&context.opts, source_file_config.timeout);
+    let timeout_is_failure = source_file_config.timeout_is_failure.unwrap_or_default();
+
+    let prev_state = state.source_file_states.remove(&source_file);
+
+    if prev_state.as_ref().is_some_and(|state| state.skipped) {
+        return skip_source_file(context, state, source_file, span_test_iter);
+    }
+
+    let revisited = prev_state.is_some();
+
+    // smoelius: `failing_tests` are the tests that fail without any removal. A candidate is run
+    // with only its other tests, and a candidate with no other tests is skipped.
+    // smoelius: With `--fail-fast-per-test`, `consecutive_passes` holds the number of consecutive
+    // removals that passed in each test, and `suspicious_tests` holds the tests in which that
+    // number reached the limit. A candidate is run with only its tests that are not suspicious, and
+    // a candidate with no such tests is skipped.
+    let mut source_file_state = prev_state.unwrap_or_default();
+
+    if !context.opts.no_dry_run
+        && !revisited
+        && !dry_run_source_file(
+            context,
+            state,
+            &source_file,
+            span_test_maps,
+            timeout,
+            &mut source_file_state.failing_tests,
+        )?
+    {
+        return skip_source_file(context, state, source_file, span_test_iter);
+    }
+
+    (context.println)(&format!(
+        "{}: mutilating",
+        util::strip_current_dir(&source_file).to_string_lossy()
+    ));
+
+    let tolerance_insertions = if context.opts.tolerate_unused {
+        context
+            .backend
+            .unused_tolerance_insertions(&context.light(), &source_file)?
+    } else {
+        Vec::new()
+    };
+
+    // smoelius: With `RemovalStrategy::Delete`, there is no instrumentation, and so every candidate
+    // is removed explicitly.
+    let mut instrumentation_backup = if context.backend.removal_strategy() == RemovalStrategy::Guard
+    {
+        instrument_statements(
+            context,
+            &source_file,
+            &mut span_test_iter,
+            &tolerance_insertions,
+        )?
+    } else {
+        None
+    };
+
+    let source_file_run = SourceFileRun {
+        source_file: &source_file,
+        span_test_maps,
+        tolerance_insertions,
+        timeout,
+        timeout_is_failure,
+    };
+
+    loop {
+        state.skip_past_removals(context, &mut span_test_iter)?;
+
+        let Some((span, span_kind, test_names)) = span_test_iter.next() else {
+            break;
+        };
+
+        let passing_tests = source_file_state.passing_tests(test_names);
+
+        if passing_tests.is_empty() {
+            let n = skip_present_spans(context, std::iter::once((span, span_kind, test_names)))?;
+            update_progress(context, None, n)?;
             continue;
         }
 
-        if !context.opts.no_dry_run {
-            (context.println)(&format!(
-                "{}: dry running",
-                util::strip_current_dir(&source_file).to_string_lossy()
-            ));
+        if span_kind != SpanKind::Statement {
+            drop(instrumentation_backup.take());
+        }
 
-            let result = context.backend.dry_run(&context.light(), &source_file);
+        let explicit_removal = instrumentation_backup.is_none() || span_kind != SpanKind::Statement;
 
-            if let Err(error) = &result {
-                source_warn(
-                    &context.light(),
-                    Warning::DryRunFailed,
-                    &source_file,
-                    &format!("dry run failed: {error:?}"),
-                    WarnFlags::empty(),
-                )?;
-            }
+        run_candidate(
+            context,
+            state,
+            &source_file_run,
+            &mut source_file_state,
+            span,
+            &passing_tests,
+            explicit_removal,
+        )?;
+    }
+
+    state
+        .source_file_states
+        .insert(source_file, source_file_state);
+
+    Ok(())
+}
+
+/// Skips the candidates remaining in `span_test_iter`, and all of `source_file`'s candidates in its
+/// later appearances
+fn skip_source_file<'a, I>(
+    context: &Context,
+    state: &mut RunState,
+    source_file: SourceFile,
+    span_test_iter: I,
+) -> Result<()>
+where
+    I: Iterator<Item = (&'a Span, SpanKind, &'a IndexSet<String>)>,
+{
+    let n = skip_present_spans(context, span_test_iter)?;
+    update_progress(context, None, n)?;
+    state
+        .source_file_states
+        .insert(source_file, SourceFileState::skipped());
+    Ok(())
+}
+
+/// Dry runs `source_file`, unless its dry run was already performed in parallel, and adds the tests
+/// that fail or were skipped during the dry run to `failing_tests`. Returns whether the source
+/// file's candidates should be run.
+fn dry_run_source_file(
+    context: &Context,
+    state: &mut RunState,
+    source_file: &SourceFile,
+    span_test_maps: &SpanTestMaps,
+    timeout: Option<Duration>,
+    failing_tests: &mut BTreeSet<String>,
+) -> Result<bool> {
+    (context.println)(&format!(
+        "{}: dry running",
+        util::strip_current_dir(source_file).to_string_lossy()
+    ));
+
+    let result = if let Some((result, _)) = state.dry_run_results.remove(&**source_file) {
+        result
+    } else {
+        let start = Instant::now();
+        let installing = install::elapsed();
+        let result = info_span!("dry_run")
+            .in_scope(|| context.backend.dry_run(&context.light(), source_file));
+        if result.is_ok() {
+            // smoelius: Time spent installing dependencies is not part of the cost of running the
+            // source file's tests.
+            let installed = install::elapsed().saturating_sub(installing);
+            let duration = start.elapsed().saturating_sub(installed);
+            record_dry_run(context, source_file, duration)?;
+        }
+        result
+    };
+
+    if let Err(error) = &result {
+        source_warn(
+            &context.light(),
+            Warning::DryRunFailed,
+            source_file,
+            &format!("dry run failed: {error:?}"),
+            WarnFlags::empty(),
+        )?;
+    }
+
+    if CTRLC.load(Ordering::SeqCst) {
+        bail!("Ctrl-C detected");
+    }
+
+    if result.is_err() {
+        if !context.opts.require_clean_dry_run {
+            *failing_tests = find_failing_tests(context, span_test_maps, timeout)?;
 
             if CTRLC.load(Ordering::SeqCst) {
                 bail!("Ctrl-C detected");
             }
+        }
 
-            if result.is_err() {
-                let n = skip_present_spans(&context, span_test_iter)?;
-                update_progress(&context, None, n)?;
-                continue;
-            }
+        if context.opts.require_clean_dry_run
+            || span_test_maps.iter().all(|(_, _, test_names)| {
+                test_names
+                    .iter()
+                    .all(|test_name| failing_tests.contains(test_name))
+            })
+        {
+            return Ok(false);
         }
+    }
 
-        (context.println)(&format!(
-            "{}: mutilating",
-            util::strip_current_dir(&source_file).to_string_lossy()
-        ));
+    // smoelius: Tests skipped at runtime (e.g., by Mocha's `this.skip()`) are treated like failing
+    // tests, since running them with a candidate removed reveals nothing.
+    let skipped_tests = context
+        .backend
+        .skipped_tests(&context.light(), source_file)?;
+    for test_name in skipped_tests.iter().filter(|test_name| {
+        span_test_maps
+            .iter()
+            .any(|(_, _, test_names)| test_names.contains(*test_name))
+    }) {
+        source_warn(
+            &context.light(),
+            Warning::TestSkipped,
+            source_file,
+            &format!("Skipping test `{test_name}`, which was skipped during dry run"),
+            WarnFlags::empty(),
+        )?;
+    }
+    failing_tests.extend(skipped_tests);
 
-        let mut instrumentation_backup =
-            instrument_statements(&context, &source_file, &mut span_test_iter)?;
+    Ok(true)
+}
 
-        loop {
-            let (mismatch, n) = skip_past_removals(&mut span_test_iter, &mut past_removal_iter);
+/// Runs `test_names` with `span` removed, and records the removal's outcome
+fn run_candidate(
+    context: &mut Context,
+    state: &mut RunState,
+    source_file_run: &SourceFileRun,
+    source_file_state: &mut SourceFileState,
+    span: &Span,
+    test_names: &IndexSet<String>,
+    explicit_removal: bool,
+) -> Result<()> {
+    let candidate_span = info_span!(
+        "candidate",
+        span = %span.to_console_string(),
+        outcome = field::Empty
+    )
+    .entered();
 
-            update_progress(&context, mismatch, n)?;
+    let text = span.source_text()?;
 
-            let Some((span, span_kind, test_names)) = span_test_iter.next() else {
-                break;
-            };
+    if let Some(progress_file) = &context.progress_file {
+        progress_file.set_candidate(span, &text)?;
+    }
 
-            if span_kind != SpanKind::Statement {
-                drop(instrumentation_backup.take());
-            }
+    event::publish(
+        &context.light(),
+        &Event::CandidateStarted { span, text: &text },
+    )?;
 
-            let text = span.source_text()?;
+    let start = Instant::now();
 
-            let explicit_removal =
-                instrumentation_backup.is_none() || span_kind != SpanKind::Statement;
+    hooks::run(
+        &context.light(),
+        "before_each_candidate",
+        &context.config.hooks.before_each_candidate,
+    )?;
 
-            let _explicit_backup = if explicit_removal {
-                let (_, explicit_backup) = span.remove()?;
-                Some(explicit_backup)
-            } else {
-                None
-            };
+    let (outcome, cause, n_run, n_failing) = run_with_removal(
+        context,
+        &mut state.known_node_processes,
+        source_file_run,
+        span,
+        &text,
+        test_names,
+        explicit_removal,
+    )?;
+
+    if CTRLC.load(Ordering::SeqCst) {
+        // smoelius: The caller's instrumentation backup restores the source file when dropped.
+        abort(context, span, &text)?;
+        print_groups(context);
+        bail!("Ctrl-C detected");
+    }
+
+    // smoelius: With `timeout_is_failure`, a timeout is treated as a failure everywhere except the
+    // database's `raw_outcome` column.
+    let (outcome, raw_outcome) = match outcome {
+        Some(Outcome::TimedOut) if source_file_run.timeout_is_failure => {
+            (Some(Outcome::Failed), Some(Outcome::TimedOut))
+        }
+        _ => (outcome, None),
+    };
+
+    if let Some(outcome) = outcome {
+        record_removal(context, source_file_run.source_file, start.elapsed())?;
+        candidate_span.record("outcome", field::display(outcome));
+        let diagnostic = source_file_run.span_test_maps.diagnostic.contains(span);
+        if diagnostic && outcome == Outcome::Passed {
+            state.n_diagnostic_passed += 1;
+        }
+        let details = sqlite::Details {
+            cause: cause.as_deref(),
+            covering_tests: Some(test_names.len()),
+            failing_tests: (n_run == test_names.len()).then_some(n_failing),
+            raw_outcome,
+        };
+        emit(
+            context, span, &text, outcome, &details, diagnostic, test_names,
+        )?;
+        if let Some(limit) = context.opts.fail_fast_per_test {
+            update_consecutive_passes(
+                context,
+                source_file_run.source_file,
+                test_names,
+                outcome,
+                limit,
+                &mut source_file_state.consecutive_passes,
+                &mut source_file_state.suspicious_tests,
+            )?;
+        }
+    }
+
+    drop(candidate_span);
+
+    update_progress(context, None, 1)
+}
+
+/// Removes `span`, runs `test_names`, and restores `span`'s source file. If the removal is not
+/// explicit, the source file must already be instrumented. Returns the same as [`run_tests`], but
+/// with a passing outcome verified if `--verify-passed` was given.
+fn run_with_removal(
+    context: &mut Context,
+    known_node_processes: &mut BTreeSet<u32>,
+    source_file_run: &SourceFileRun,
+    span: &Span,
+    text: &str,
+    test_names: &IndexSet<String>,
+    explicit_removal: bool,
+) -> Result<(Option<Outcome>, Option<String>, usize, usize)> {
+    let mut explicit_backup = if explicit_removal {
+        let (_, explicit_backup) =
+            span.remove_with_insertions(&source_file_run.tolerance_insertions)?;
+        Some(explicit_backup)
+    } else {
+        None
+    };
+
+    let audit_method = if explicit_removal {
+        audit::Method::Explicit
+    } else {
+        audit::Method::Instrumentation
+    };
+
+    if let Some(audit_log) = context.audit_log.as_mut() {
+        audit_log.applied(span, text, audit_method)?;
+    }
+
+    let snapshot = if context.opts.check_side_effects {
+        Some(Snapshot::new(&context.root)?)
+    } else {
+        None
+    };
+
+    let (outcome, cause, n_run, n_failing) = run_tests(
+        context,
+        span,
+        test_names,
+        explicit_removal,
+        source_file_run.timeout,
+    )?;
+
+    let outcome = if context.opts.verify_passed && outcome == Some(Outcome::Passed) {
+        // smoelius: Dropping `explicit_backup` restores the source file.
+        drop(explicit_backup.take());
+        verify_passed(context, span, test_names, source_file_run.timeout)?
+    } else {
+        outcome
+    };
+
+    if let Some(snapshot) = snapshot {
+        snapshot.warn_if_changed(&context.light(), span)?;
+    }
+
+    hooks::run(
+        &context.light(),
+        "after_each_candidate",
+        &context.config.hooks.after_each_candidate,
+    )?;
+
+    warn_about_orphaned_node_processes(context, span, known_node_processes)?;
+
+    // smoelius: Dropping `explicit_backup` restores the source file. With instrumentation, the
+    // source file is "restored" once `NECESSIST_REMOVAL` is no longer set.
+    drop(explicit_backup.take());
+
+    if let Some(audit_log) = context.audit_log.as_mut() {
+        audit_log.reverted(span, text, audit_method, outcome)?;
+    }
+
+    Ok((outcome, cause, n_run, n_failing))
+}
+
+/// Runs `test_names` with `span` removed. Returns the removal's outcome, the probable cause of the
+/// first failure, the number of tests run, and the number of those that failed or timed out.
+fn run_tests(
+    context: &Context,
+    span: &Span,
+    test_names: &IndexSet<String>,
+    explicit_removal: bool,
+    timeout: Option<Duration>,
+) -> Result<(Option<Outcome>, Option<String>, usize, usize)> {
+    let mut cause = None;
+    let mut n_run = 0;
+    let mut n_failing = 0;
+
+    let outcome = test_names
+        .into_iter()
+        .try_fold(Some(Outcome::Passed), |prev, test_name| {
+            // smoelius: With `--count-failing-tests`, a candidate's remaining tests are run after
+            // one fails or times out, so that the number that do can be recorded. The first such
+            // outcome is the candidate's.
+            if prev != Some(Outcome::Passed)
+                && !(context.opts.count_failing_tests && detected(prev))
+            {
+                return Ok::<_, Error>(prev);
+            }
+
+            let _run_span = info_span!("run", test = %test_name).entered();
+
+            if let Some(progress_file) = &context.progress_file {
+                progress_file.set_test(test_name)?;
+            }
+
+            if let Some((exec, postprocess)) =
+                context.backend.exec(&context.light(), test_name, span)?
+            {
+                // smoelius: With `--build-only`, one successful build suffices.
+                if context.opts.build_only {
+                    return Ok(Some(Outcome::Buildable));
+                }
+
+                // smoelius: Even if the removal is explicit (i.e., not with instrumentation), it
+                // doesn't hurt to set `NECESSIST_REMOVAL`.
+                let exec = exec.env("NECESSIST_REMOVAL", span.id());
+
+                let exec = if context.opts.no_network {
+                    network::exec(&context.light(), exec)?
+                } else {
+                    exec
+                };
+
+                let _: Option<String> = cause::take_observed();
+
+                // smoelius: Output that would be discarded is captured so that a failure's
+                // probable cause can be recorded. Output read by a backend is instead scanned by
+                // the backend (see `cause::observe`).
+                let (outcome, captured) =
+                    perform_exec_capturing_discarded(&context.light(), exec, postprocess, timeout)?;
+
+                let observed = cause::take_observed();
+
+                if outcome == Some(Outcome::Failed) && cause.is_none() {
+                    cause = match observed {
+                        Some(observed) => Some(observed),
+                        None => probable_cause(captured)?,
+                    };
+                }
+
+                n_run += 1;
+                if detected(outcome) {
+                    n_failing += 1;
+                }
+
+                Ok(if prev == Some(Outcome::Passed) {
+                    outcome
+                } else {
+                    prev
+                })
+            } else {
+                assert!(
+                    explicit_removal,
+                    "Instrumentation failed to build after it was verified to"
+                );
+
+                Ok(Some(Outcome::Nonbuildable))
+            }
+        })?;
+
+    Ok((outcome, cause, n_run, n_failing))
+}
+
+macro_rules! incompatible {
+    ($opts:ident, $x:ident, $y:ident) => {
+        ensure!(
+            !($opts.$x && $opts.$y),
+            "--{} and --{} are incompatible",
+            stringify!($x).to_kebab_case(),
+            stringify!($y).to_kebab_case()
+        );
+    };
+}
+
+fn process_options(opts: &Necessist) -> Result<()> {
+    // smoelius: This list of incompatibilities is not exhaustive.
+    incompatible!(opts, build_only, dump);
+    incompatible!(opts, build_only, reset);
+    incompatible!(opts, build_only, resume);
+    incompatible!(opts, build_only, verify_passed);
+    incompatible!(opts, dump, quiet);
+    incompatible!(opts, dump, reset);
+    incompatible!(opts, dump, resume);
+    incompatible!(opts, dump, no_sqlite);
+    incompatible!(opts, quiet, verbose);
+    incompatible!(opts, reset, no_sqlite);
+    incompatible!(opts, resume, no_sqlite);
+    ensure!(
+        !opts.recurse_projects || opts.source_files.is_empty(),
+        "--recurse-projects cannot be used with test files"
+    );
+    ensure!(
+        opts.remote.is_none() || opts.docker.is_none(),
+        "--remote and --docker are incompatible"
+    );
+    ensure!(
+        !opts.no_network || (opts.remote.is_none() && opts.docker.is_none()),
+        "--no-network cannot be used with --remote or --docker"
+    );
+    // smoelius: Each job slot of `--isolate worktree` is a separate process. So options that
+    // select source files, or that write to a single file, cannot be divided among them.
+    if opts.isolate == Some(Isolate::Worktree) && opts.jobs.unwrap_or(1) > 1 {
+        for (used, option) in [
+            (opts.recurse_projects, "--recurse-projects"),
+            (opts.shard.is_some(), "--shard"),
+            (opts.audit_log.is_some(), "--audit-log"),
+            (opts.progress_file.is_some(), "--progress-file"),
+            (opts.stream_results.is_some(), "--stream-results"),
+        ] {
+            ensure!(
+                !used,
+                "{option} cannot be used with --isolate worktree and --jobs greater than 1"
+            );
+        }
+    }
+    if let Some(Subcommand::Explain { .. }) = &opts.subcommand {
+        ensure!(
+            !opts.recurse_projects,
+            "--recurse-projects cannot be used with `explain`"
+        );
+        ensure!(
+            opts.source_files.is_empty(),
+            "`explain` cannot be used with test files"
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "lock_root")]
+fn lock_root(root: &Path) -> Result<std::fs::File> {
+    if enabled("TRYCMD") {
+        crate::flock::lock_path(root)
+    } else {
+        crate::flock::try_lock_path(root)
+    }
+    .with_context(|| format!("Failed to lock {root:?}"))
+}
+
+#[cfg(feature = "lock_root")]
+fn enabled(key: &str) -> bool {
+    var(key).is_ok_and(|value| value != "0")
+}
+
+/// Runs Necessist once for each `rust.feature_matrix` entry. Each run happens in a subprocess
+/// and uses its own database.
+fn feature_matrix(context: &LightContext, feature_matrix: &[Vec<String>]) -> Result<()> {
+    let exe = current_exe()?;
+
+    for (index, features) in feature_matrix.iter().enumerate() {
+        (context.println)(&format!(
+            "feature set {index}: {}",
+            if features.is_empty() {
+                String::from("(default)")
+            } else {
+                features.join(" ")
+            }
+        ));
+
+        let status = Command::new(&exe)
+            .args(args_os().skip(1))
+            .env(FEATURE_SET_VAR, index.to_string())
+            .status()?;
+
+        ensure!(status.success(), "Feature set {index} failed with {status}");
+    }
+
+    Ok(())
+}
+
+fn feature_set_index() -> Result<Option<usize>> {
+    var(FEATURE_SET_VAR)
+        .ok()
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .with_context(|| format!("Failed to parse {FEATURE_SET_VAR}"))
+        })
+        .transpose()
+}
+
+fn database_name() -> Result<String> {
+    Ok(feature_set_index()?.map_or_else(
+        || String::from("necessist.db"),
+        |index| format!("necessist.features-{index}.db"),
+    ))
+}
+
+/// Runs Necessist once for each project found in the root directory or its subdirectories. Each
+/// run happens in a subprocess, and all runs share one database.
+fn recurse_projects<Identifier: Applicable + IntoEnumIterator>(
+    opts: &Necessist,
+    root: &Rc<PathBuf>,
+    framework: &framework::Auto<Identifier>,
+) -> Result<()> {
+    let config = config::Toml::default();
+
+    let println = |msg: &dyn AsRef<str>| {
+        println!("{}", msg.as_ref());
+    };
+
+    let context = &LightContext {
+        opts,
+        root,
+        original_root: root,
+        config: &config,
+        println: if opts.quiet { &|_| {} } else { &println },
+    };
+
+    let projects = discover_projects(context, framework)?;
+
+    if projects.is_empty() {
+        bail!("Found no projects in {:?}", context.root);
+    }
+
+    // smoelius: Each subprocess opens a database that may have been created by a sibling. So
+    // whether a database already exists is checked once, here.
+    if !context.opts.no_sqlite {
+        sqlite::check_database_path(
+            context,
+            context.original_root,
+            &database_name()?,
+            context.opts.dump,
+            context.opts.reset,
+            context.opts.resume,
+        )?;
+    }
+
+    let exe = current_exe()?;
+
+    for project in projects {
+        (context.println)(&format!(
+            "project {}",
+            if project.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                &project
+            }
+            .display()
+        ));
+
+        let status = Command::new(&exe)
+            .args(args_os().skip(1))
+            .env(PROJECT_VAR, &project)
+            .status()?;
+
+        ensure!(status.success(), "Project {project:?} failed with {status}");
+    }
+
+    Ok(())
+}
+
+/// Runs Necessist once for each of `--jobs` job slots, each in its own worktree. Each run happens
+/// in a subprocess and considers the source files in one shard (see `--shard`), and all runs share
+/// one database.
+fn job_slots(opts: &Necessist, root: &Rc<PathBuf>) -> Result<()> {
+    let config = config::Toml::default();
+
+    let println = |msg: &dyn AsRef<str>| {
+        println!("{}", msg.as_ref());
+    };
+
+    let context = &LightContext {
+        opts,
+        root,
+        original_root: root,
+        config: &config,
+        println: if opts.quiet { &|_| {} } else { &println },
+    };
+
+    // smoelius: The job slots open the database at the same time. So whether it exists is checked,
+    // and it is reset, once, here.
+    if !context.opts.no_sqlite {
+        let database_name = database_name()?;
+        let exists = sqlite::check_database_path(
+            context,
+            context.original_root,
+            &database_name,
+            context.opts.dump,
+            context.opts.reset,
+            context.opts.resume,
+        )?;
+        if context.opts.reset && exists {
+            sqlite::reset(context.original_root, &database_name)?;
+        }
+    }
+
+    let exe = current_exe()?;
+    let jobs = opts.jobs.unwrap_or(1);
+
+    let children = (1..=jobs)
+        .map(|index| {
+            Command::new(&exe)
+                .args(args_os().skip(1))
+                .env(JOB_SLOT_VAR, format!("{index}/{jobs}"))
+                .spawn()
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    // smoelius: Every job slot is waited for before any failure is reported, so that none is left
+    // running.
+    let statuses = children
+        .into_iter()
+        .map(|mut child| child.wait())
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    for (index, status) in (1..).zip(statuses) {
+        ensure!(status.success(), "Job slot {index} failed with {status}");
+    }
+
+    Ok(())
+}
+
+/// Returns the job slot this process occupies, if it was spawned by `job_slots`
+fn job_slot() -> Result<Option<crate::Shard>> {
+    var(JOB_SLOT_VAR)
+        .ok()
+        .map(|value| {
+            value
+                .parse::<crate::Shard>()
+                .with_context(|| format!("Failed to parse {JOB_SLOT_VAR}"))
+        })
+        .transpose()
+}
+
+/// Returns the directories (relative to the root directory) to which some framework applies.
+/// Subdirectories of such directories are not searched.
+fn discover_projects<Identifier: Applicable + IntoEnumIterator>(
+    context: &LightContext,
+    framework: &framework::Auto<Identifier>,
+) -> Result<Vec<PathBuf>> {
+    let mut projects = Vec::new();
+
+    let mut walkdir = WalkDir::new(context.root.as_path())
+        .max_depth(MAX_PROJECT_DEPTH)
+        .sort_by_file_name()
+        .into_iter();
+
+    while let Some(entry) = walkdir.next() {
+        let entry = entry?;
+
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        if entry.depth() > 0
+            && entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with('.') || IGNORED_PROJECT_DIRS.contains(&name))
+        {
+            walkdir.skip_current_dir();
+            continue;
+        }
+
+        let root = Rc::new(entry.path().to_path_buf());
+        let project_context = LightContext {
+            root: &root,
+            ..*context
+        };
+
+        if framework.applicable(&project_context)? {
+            projects.push(util::strip_prefix(entry.path(), context.root)?.to_path_buf());
+            walkdir.skip_current_dir();
+        }
+    }
+
+    Ok(projects)
+}
+
+/// Returns the project (relative to the root directory) to run on, if this process was spawned by
+/// `recurse_projects`.
+fn project() -> Option<PathBuf> {
+    var_os(PROJECT_VAR).map(PathBuf::from)
+}
+
+fn default_config(_context: &LightContext, root: &Path) -> Result<()> {
+    let path_buf = root.join("necessist.toml");
+
+    if path_buf.try_exists()? {
+        bail!("A configuration file already exists at {:?}", path_buf);
+    }
+
+    let toml = toml::to_string(&config::Toml::default())?;
+
+    std::fs::write(path_buf, toml).map_err(Into::into)
+}
+
+fn dump(context: &LightContext, removals: &[Removal]) -> Result<()> {
+    let mut other_than_passed = false;
+    for removal in removals {
+        emit_to_console(context, removal, false);
+        other_than_passed |= removal.outcome != Outcome::Passed;
+    }
+
+    let sqlite = sqlite_init_lazy(context)?;
+    let aborted = sqlite
+        .borrow_mut()
+        .as_mut()
+        .map(sqlite::aborted)
+        .transpose()?
+        .unwrap_or_default();
+    for (span, text) in aborted {
+        if context.opts.verbose {
+            (context.println)(&format!(
+                "{}: `{}` {}",
+                span.to_console_string(),
+                text.replace('\r', ""),
+                color::style(context.opts, Style::default().dimmed().bold()).paint("aborted")
+            ));
+        }
+        other_than_passed = true;
+    }
+
+    if !context.opts.verbose && other_than_passed {
+        note(context, "More output would be produced with --verbose");
+    }
+
+    Ok(())
+}
+
+/// Performs the subcommand, if it is one that operates only on the database. Returns whether it
+/// was.
+fn database_subcommand(context: &LightContext) -> Result<bool> {
+    match &context.opts.subcommand {
+        Some(Subcommand::Annotate { out }) => annotate::annotate(context, out)?,
+        Some(Subcommand::Db(DbSubcommand::Merge { inputs, output })) => {
+            merge(context, inputs, output)?;
+        }
+        Some(Subcommand::Db(DbSubcommand::Prune { keep })) => prune(context, *keep)?,
+        Some(Subcommand::Diff { from, to }) => diff::diff(context, from, to)?,
+        Some(Subcommand::Patch { outcome, out }) => {
+            let past_removals = past_removals_init_lazy(context)?;
+            patch::patch(context, &past_removals, outcome.parse()?, out)?;
+        }
+        Some(Subcommand::Show { run }) => show::show(context, run)?,
+        Some(
+            Subcommand::Batch { .. }
+            | Subcommand::Config(_)
+            | Subcommand::Explain { .. }
+            | Subcommand::Frameworks
+            | Subcommand::Replay { .. }
+            | Subcommand::SelfTest,
+        )
+        | None => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Merges the runs and removals of the databases `inputs` into the database `output`
+fn merge(context: &LightContext, inputs: &[PathBuf], output: &Path) -> Result<()> {
+    let (n_runs, n_removals) = sqlite::merge(inputs, output)?;
+
+    (context.println)(&format!(
+        "Merged {n_runs} run{} and {n_removals} removal{} into {output:?}",
+        if n_runs == 1 { "" } else { "s" },
+        if n_removals == 1 { "" } else { "s" }
+    ));
+
+    Ok(())
+}
+
+/// Deletes all but the `keep` most recent runs from the database, along with the removals they
+/// produced.
+fn prune(context: &LightContext, keep: usize) -> Result<()> {
+    let sqlite = sqlite_init_lazy(context)?;
+    let mut sqlite = sqlite.borrow_mut();
+    let sqlite = sqlite
+        .as_mut()
+        .ok_or_else(|| anyhow!("`db prune` requires an sqlite database"))?;
+
+    let (n_runs, n_removals) = sqlite::prune(sqlite, keep)?;
+
+    (context.println)(&format!(
+        "Deleted {n_runs} run{} and {n_removals} removal{}",
+        if n_runs == 1 { "" } else { "s" },
+        if n_removals == 1 { "" } else { "s" }
+    ));
+
+    Ok(())
+}
+
+/// Returns a unified diff of `span`'s removal with two lines of context, colored as `git diff`
+/// would color it
+pub(crate) fn removal_preview(context: &LightContext, span: &Span) -> String {
+    let contents = span.source_file.contents();
+    // smoelius: If the removal leaves its line blank, the line is omitted, so that the diff shows
+    // only the removed line(s).
+    let mutilated = span
+        .removed_contents()
+        .split_inclusive('\n')
+        .enumerate()
+        .filter(|&(i, line)| i + 1 != span.start().line || !line.trim().is_empty())
+        .map(|(_, line)| line)
+        .collect::<String>();
+    let diff = TextDiff::from_lines(contents, &mutilated);
+    let mut unified_diff = diff.unified_diff();
+    unified_diff.context_radius(2);
+    unified_diff
+        .to_string()
+        .lines()
+        .map(|line| {
+            let style = if line.starts_with("@@") {
+                Cyan.normal()
+            } else if line.starts_with('-') {
+                Red.normal()
+            } else if line.starts_with('+') {
+                Green.normal()
+            } else {
+                Style::default()
+            };
+            color::style(context.opts, style)
+                .paint(line.replace('\r', ""))
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[allow(clippy::type_complexity)]
+fn backend_for_framework<
+    Identifier: Applicable + Clone + Display + IntoEnumIterator + ToImplementation,
+>(
+    context: &LightContext,
+    identifier: framework::Auto<Identifier>,
+) -> Result<(
+    String,
+    &'static [&'static [&'static str]],
+    Box<dyn framework::Interface>,
+)> {
+    let framework = identifier.resolve(context)?;
+
+    drop(identifier);
+
+    let implementation = framework
+        .as_ref()
+        .map(|framework| framework.to_implementation(context))
+        .transpose()?
+        .flatten();
+
+    framework
+        .zip(implementation)
+        .map(|(framework, implementation)| {
+            (framework.to_string(), framework.toolchain(), implementation)
+        })
+        .ok_or_else(|| anyhow!("Found no applicable frameworks"))
+}
+
+/// Records the versions of the programs in `toolchain` with the current run. A program's version
+/// is the first line of its output. Programs that cannot be run are omitted.
+fn record_toolchain(context: &LightContext, toolchain: &[&[&str]]) -> Result<()> {
+    if toolchain.is_empty() {
+        return Ok(());
+    }
 
-            let outcome =
-                test_names
-                    .into_iter()
-                    .try_fold(Some(Outcome::Passed), |outcome, test_name| {
-                        if outcome != Some(Outcome::Passed) {
-                            return Ok(outcome);
-                        }
-
-                        if let Some((exec, postprocess)) =
-                            context.backend.exec(&context.light(), test_name, span)?
-                        {
-                            // smoelius: Even if the removal is explicit (i.e., not with
-                            // instrumentation), it doesn't hurt to set `NECESSIST_REMOVAL`.
-                            let exec = exec.env("NECESSIST_REMOVAL", span.id());
-
-                            perform_exec(&context, exec, postprocess)
-                        } else {
-                            assert!(
-                                explicit_removal,
-                                "Instrumentation failed to build after it was verified to"
-                            );
-
-                            Ok(Some(Outcome::Nonbuildable))
-                        }
-                    })?;
+    let sqlite = sqlite_init_lazy(context)?;
+    let mut sqlite = sqlite.borrow_mut();
+    let Some(sqlite) = sqlite.as_mut() else {
+        return Ok(());
+    };
 
-            if CTRLC.load(Ordering::SeqCst) {
-                bail!("Ctrl-C detected");
-            }
+    let mut versions = BTreeMap::new();
+    for argv in toolchain {
+        let Some((program, args)) = argv.split_first() else {
+            continue;
+        };
+        let Ok(output) = Command::new(program).args(args).output() else {
+            continue;
+        };
+        if !output.status.success() {
+            continue;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some(line) = stdout.lines().next() {
+            versions.insert((*program).to_owned(), line.trim().to_owned());
+        }
+    }
 
-            if let Some(outcome) = outcome {
-                emit(&mut context, span, &text, outcome)?;
-            }
+    sqlite::set_run_toolchain(sqlite, &versions)
+}
 
-            update_progress(&context, None, 1)?;
-        }
+/// Reads the newline-separated list of source files in `source_files_from` (standard input if
+/// `-`). Blank lines are ignored. Like source files passed on the command line, relative paths are
+/// relative to the current directory.
+fn read_source_files_from(source_files_from: &str) -> Result<Vec<PathBuf>> {
+    let mut contents = String::new();
+    if source_files_from == "-" {
+        std::io::stdin()
+            .read_to_string(&mut contents)
+            .context("Failed to read standard input")?;
+    } else {
+        File::open(source_files_from)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .with_context(|| format!("Failed to read `{source_files_from}`"))?;
     }
 
-    context.progress.map(ProgressBar::finish);
+    let source_files = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
 
-    Ok(())
+    // smoelius: An empty list would otherwise mean "all test files", which is almost certainly not
+    // what the user intended (e.g., if `git diff --name-only` produced no output).
+    ensure!(
+        !source_files.is_empty(),
+        "`--source-files-from {source_files_from}` produced no test files"
+    );
+
+    Ok(source_files)
 }
 
-macro_rules! incompatible {
-    ($opts:ident, $x:ident, $y:ident) => {
+/// Canonicalizes the source files passed on the command line. Duplicates (e.g., the same file
+/// named by two different paths) are removed, keeping the first occurrence.
+fn canonicalize_source_files(context: &LightContext) -> Result<Vec<PathBuf>> {
+    let mut seen = BTreeSet::new();
+    let mut paths = Vec::new();
+    for path in &context.opts.source_files {
+        let path_buf = dunce::canonicalize(path)
+            .with_context(|| format!("Failed to canonicalize {path:?}"))?;
         ensure!(
-            !($opts.$x && $opts.$y),
-            "--{} and --{} are incompatible",
-            stringify!($x).to_kebab_case(),
-            stringify!($y).to_kebab_case()
+            path_buf.starts_with(context.root.as_path()),
+            "{:?} is not in the root directory {:?}; use `--root` to specify a directory \
+             containing all of the test files",
+            path,
+            context.root
         );
-    };
-}
-
-fn process_options(opts: &Necessist) -> Result<()> {
-    // smoelius: This list of incompatibilities is not exhaustive.
-    incompatible!(opts, dump, quiet);
-    incompatible!(opts, dump, reset);
-    incompatible!(opts, dump, resume);
-    incompatible!(opts, dump, no_sqlite);
-    incompatible!(opts, quiet, verbose);
-    incompatible!(opts, reset, no_sqlite);
-    incompatible!(opts, resume, no_sqlite);
-
-    Ok(())
+        ensure!(path_buf.is_file(), "{path:?} is not a file");
+        if seen.insert(path_buf.clone()) {
+            paths.push(path_buf);
+        }
+    }
+    Ok(paths)
 }
 
-#[cfg(feature = "lock_root")]
-fn lock_root(root: &Path) -> Result<std::fs::File> {
-    if enabled("TRYCMD") {
-        crate::flock::lock_path(root)
-    } else {
-        crate::flock::try_lock_path(root)
+/// Adjusts `opts` for the subcommand, if any. If a span is being explained, restricts `opts`'s
+/// source files to the span's, and returns the span's start and end.
+fn prepare_subcommand(opts: &mut Necessist) -> Result<Option<(LineColumn, LineColumn)>> {
+    match &opts.subcommand {
+        Some(Subcommand::Explain { span }) => {
+            let (source_file, start, end) = explain::parse_explain_span(span)?;
+            opts.source_files = vec![source_file];
+            Ok(Some((start, end)))
+        }
+        // smoelius: Annotating, writing patches, and showing runs, like `--dump`, only read the
+        // database. Pruning writes to the database, but should not record a run of its own.
+        // Explaining framework detection, checking configuration files, and replaying mutations do
+        // not touch the database at all.
+        Some(
+            Subcommand::Annotate { .. }
+            | Subcommand::Config(_)
+            | Subcommand::Db(_)
+            | Subcommand::Diff { .. }
+            | Subcommand::Frameworks
+            | Subcommand::Patch { .. }
+            | Subcommand::Replay { .. }
+            | Subcommand::Show { .. },
+        ) => {
+            opts.dump = true;
+            Ok(None)
+        }
+        Some(Subcommand::Batch { .. } | Subcommand::SelfTest) | None => Ok(None),
     }
-    .with_context(|| format!("Failed to lock {root:?}"))
 }
 
-#[cfg(feature = "lock_root")]
-fn enabled(key: &str) -> bool {
-    var(key).is_ok_and(|value| value != "0")
-}
+/// Performs, in parallel, the dry runs of the source files with candidates not among
+/// `past_removals`. Source files whose dry runs cannot be performed in parallel are left to the
+/// main loop.
+fn parallel_dry_runs(
+    context: &Context,
+    source_file_span_test_map: &SourceFileSpanTestMap,
+    past_removals: &[Removal],
+) -> Result<BTreeMap<PathBuf, (Result<()>, Duration)>> {
+    let past_spans = past_removals
+        .iter()
+        .map(|removal| &removal.span)
+        .collect::<BTreeSet<_>>();
 
-fn default_config(_context: &LightContext, root: &Path) -> Result<()> {
-    let path_buf = root.join("necessist.toml");
+    let mut source_files = Vec::new();
+    let mut commands = Vec::new();
+    for (source_file, span_test_maps) in source_file_span_test_map {
+        if span_test_maps
+            .iter()
+            .all(|(span, _, _)| past_spans.contains(span))
+        {
+            continue;
+        }
+        if let Some(command) = context
+            .backend
+            .dry_run_command(&context.light(), source_file)?
+        {
+            let command = dispatch::command(&context.light(), &command)?.unwrap_or(command);
+            source_files.push(source_file);
+            commands.push(command);
+        }
+    }
 
-    if path_buf.try_exists()? {
-        bail!("A configuration file already exists at {:?}", path_buf);
+    let queue = Mutex::new(commands.into_iter().enumerate());
+    let outputs = Mutex::new(BTreeMap::new());
+
+    let _dry_run_span = info_span!("parallel_dry_runs", n = source_files.len()).entered();
+
+    let max_output_bytes = context.config.max_output_bytes();
+
+    thread::scope(|scope| {
+        for _ in 0..context.opts.jobs.unwrap_or(1) {
+            scope.spawn(|| loop {
+                #[allow(clippy::unwrap_used)]
+                let Some((i, mut command)) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                debug!("{:?}", command);
+                let start = Instant::now();
+                let output = truncation::output(&mut command, max_output_bytes);
+                #[allow(clippy::unwrap_used)]
+                outputs.lock().unwrap().insert(i, (output, start.elapsed()));
+            });
+        }
+    });
+
+    if CTRLC.load(Ordering::SeqCst) {
+        bail!("Ctrl-C detected");
     }
 
-    let toml = toml::to_string(&config::Toml::default())?;
+    #[allow(clippy::unwrap_used)]
+    let outputs = outputs.into_inner().unwrap();
+
+    Ok(outputs
+        .into_iter()
+        .map(|(i, (output, duration))| {
+            let source_file = source_files[i];
+            let result = output.and_then(|output| {
+                context
+                    .backend
+                    .check_dry_run_output(&context.light(), source_file, output)
+            });
+            (source_file.to_path_buf(), (result, duration))
+        })
+        .collect())
+}
 
-    std::fs::write(path_buf, toml).map_err(Into::into)
+/// Returns, for each source file, the estimated time to run one of its candidates. Estimates
+/// recorded in the database take precedence over the durations of the current run's dry runs.
+fn estimates(
+    context: &Context,
+    source_file_span_test_maps: &[(SourceFile, framework::SpanTestMaps)],
+    dry_run_results: &BTreeMap<PathBuf, (Result<()>, Duration)>,
+) -> Result<BTreeMap<SourceFile, Duration>> {
+    let sqlite = sqlite_init_lazy(&context.light())?;
+    let recorded = if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        sqlite::estimates(sqlite)?
+    } else {
+        BTreeMap::new()
+    };
+
+    Ok(source_file_span_test_maps
+        .iter()
+        .filter_map(|(source_file, _)| {
+            let estimate = recorded
+                .get(&source_file.to_string())
+                .copied()
+                .or_else(|| {
+                    dry_run_results
+                        .get(&**source_file)
+                        .and_then(|(result, duration)| result.is_ok().then_some(*duration))
+                })?;
+            Some((source_file.clone(), estimate))
+        })
+        .collect())
 }
 
-fn dump(context: &LightContext, removals: &[Removal]) {
-    let mut other_than_passed = false;
-    for removal in removals {
-        emit_to_console(context, removal);
-        other_than_passed |= removal.outcome != Outcome::Passed;
+fn record_statement_kinds(
+    context: &Context,
+    source_file_span_test_map: &SourceFileSpanTestMap,
+) -> Result<()> {
+    let sqlite = sqlite_init_lazy(&context.light())?;
+    if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        sqlite::insert_statement_kinds(
+            sqlite,
+            source_file_span_test_map
+                .values()
+                .flat_map(|span_test_maps| &span_test_maps.statement_kind),
+        )?;
     }
+    Ok(())
+}
 
-    if !context.opts.verbose && other_than_passed {
-        note(context, "More output would be produced with --verbose");
+/// Loads the candidates recorded in the database, so that unchanged source files need not be
+/// parsed. Candidates are reused only when resuming.
+fn load_candidates(context: &LightContext) -> Result<Candidates> {
+    // smoelius: The database is not opened if it does not exist, so that the usual warnings about
+    // its nonexistence are emitted in their usual places.
+    if !context.opts.resume
+        || context.opts.no_sqlite
+        || !context.original_root.join(database_name()?).try_exists()?
+    {
+        return Ok(Candidates::default());
+    }
+    let sqlite = sqlite_init_lazy(context)?;
+    if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        return Ok(Candidates::new(sqlite::load_candidates(sqlite)?));
     }
+    Ok(Candidates::default())
 }
 
-fn backend_for_framework<Identifier: Applicable + Display + IntoEnumIterator + ToImplementation>(
-    context: &LightContext,
-    identifier: framework::Auto<Identifier>,
-) -> Result<Box<dyn framework::Interface>> {
-    let implementation = identifier.to_implementation(context)?;
-
-    drop(identifier);
+fn record_candidates(context: &Context) -> Result<()> {
+    let sqlite = sqlite_init_lazy(&context.light())?;
+    if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        sqlite::insert_candidates(sqlite, context.candidates.parsed())?;
+    }
+    Ok(())
+}
 
-    implementation.ok_or_else(|| anyhow!("Found no applicable frameworks"))
+fn record_dry_run(context: &Context, source_file: &Path, duration: Duration) -> Result<()> {
+    let sqlite = sqlite_init_lazy(&context.light())?;
+    if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        let source_file = SourceFile::new(context.root.clone(), source_file.to_path_buf())?;
+        sqlite::record_dry_run(sqlite, &source_file, duration)?;
+    }
+    Ok(())
 }
 
-fn canonicalize_source_files(context: &LightContext) -> Result<Vec<PathBuf>> {
-    context
-        .opts
-        .source_files
-        .iter()
-        .map(|path| {
-            let path_buf = dunce::canonicalize(path)
-                .with_context(|| format!("Failed to canonicalize {path:?}"))?;
-            ensure!(
-                path_buf.starts_with(context.root.as_path()),
-                "{:?} is not in {:?}",
-                path_buf,
-                context.root
-            );
-            Ok(path_buf)
-        })
-        .collect::<Result<Vec<_>>>()
+fn record_removal(context: &Context, source_file: &SourceFile, duration: Duration) -> Result<()> {
+    let sqlite = sqlite_init_lazy(&context.light())?;
+    if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        sqlite::record_removal(sqlite, source_file, duration)?;
+    }
+    Ok(())
 }
 
 #[must_use]
 fn skip_past_removals<'a, I, J>(
+    rank: &Rank,
     span_test_iter: &mut PeekNth<I>,
     removal_iter: &mut Peekable<J>,
 ) -> (Option<Mismatch>, usize)
@@ -475,7 +2168,7 @@ where
         let Some(removal) = removal_iter.peek() else {
             break;
         };
-        match span.cmp(&removal.span) {
+        match rank.cmp(span, &removal.span) {
             std::cmp::Ordering::Less => {
                 mismatch = Some(Mismatch {
                     kind: MismatchKind::Unexpected,
@@ -519,7 +2212,7 @@ fn skip_present_spans<'a>(
                 text,
                 outcome: Outcome::Skipped,
             };
-            sqlite::insert(sqlite, &removal)?;
+            sqlite::insert(sqlite, &removal, &sqlite::Details::default())?;
         }
         n += 1;
     }
@@ -552,6 +2245,10 @@ Configuration or source files have changed since necessist.db was created; the f
         bar.inc(n as u64);
     }
 
+    if let Some(progress_file) = &context.progress_file {
+        progress_file.advance(n)?;
+    }
+
     Ok(())
 }
 
@@ -584,6 +2281,7 @@ fn instrument_statements<'a, I>(
     context: &Context,
     source_file: &SourceFile,
     span_test_iter: &mut PeekNth<I>,
+    tolerance_insertions: &[(LineColumn, String)],
 ) -> Result<Option<Backup>>
 where
     I: Iterator<Item = (&'a Span, SpanKind, &'a IndexSet<String>)>,
@@ -603,26 +2301,23 @@ where
     )?;
 
     let mut i_span = 0;
-    let mut insertion_map = BTreeMap::<_, Vec<_>>::new();
+    let mut edits = Edits::default();
     // smoelius: Do not advance the underlying iterator while instrumenting. This way, if a
     // statement cannot be removed with instrumentation, it will be removed explicitly.
     while let Some((span, SpanKind::Statement, _)) = span_test_iter.peek_nth(i_span) {
         let (prefix, suffix) = context.backend.statement_prefix_and_suffix(span)?;
-        let insertions = insertion_map.entry(span.start()).or_default();
-        insertions.push(prefix);
-        let insertions = insertion_map.entry(span.end()).or_default();
-        insertions.push(suffix);
+        edits.guard(span, prefix, suffix)?;
         i_span += 1;
     }
 
     assert_eq!(n_instrumentable_statements, i_span);
 
-    for (line_column, insertions) in insertion_map {
-        for insertion in insertions {
-            source_file.insert(&mut rewriter, line_column, &insertion);
-        }
+    for (line_column, insertion) in tolerance_insertions {
+        edits.insert(*line_column, insertion.clone());
     }
 
+    edits.apply(source_file, &mut rewriter);
+
     let mut file = std::fs::OpenOptions::new()
         .truncate(true)
         .write(true)
@@ -630,9 +2325,11 @@ where
     file.write_all(rewriter.contents().as_bytes())?;
     drop(file);
 
-    let result = context
-        .backend
-        .build_source_file(&context.light(), source_file);
+    let result = info_span!("build").in_scope(|| {
+        context
+            .backend
+            .build_source_file(&context.light(), source_file)
+    });
     if let Err(error) = result {
         warn(
             &context.light(),
@@ -663,11 +2360,39 @@ where
     n_instrumentable_statements
 }
 
-fn perform_exec(
-    context: &Context,
+pub(crate) fn perform_exec(
+    context: &LightContext,
     exec: Exec,
     postprocess: Option<Box<Postprocess>>,
+    timeout: Option<Duration>,
 ) -> Result<Option<Outcome>> {
+    perform_exec_inner(context, exec, postprocess, timeout, false).map(|(outcome, _)| outcome)
+}
+
+/// Like [`perform_exec`], but also returns whichever of the process's stdout and stderr would
+/// otherwise be discarded
+fn perform_exec_capturing_discarded(
+    context: &LightContext,
+    exec: Exec,
+    postprocess: Option<Box<Postprocess>>,
+    timeout: Option<Duration>,
+) -> Result<(Option<Outcome>, Option<Captured>)> {
+    perform_exec_inner(context, exec, postprocess, timeout, true)
+}
+
+fn perform_exec_inner(
+    context: &LightContext,
+    exec: Exec,
+    postprocess: Option<Box<Postprocess>>,
+    timeout: Option<Duration>,
+    capture_discarded: bool,
+) -> Result<(Option<Outcome>, Option<Captured>)> {
+    // smoelius: `hooks.reset` is run after the test is built (i.e., after `Run::exec` returns), so
+    // that the reset state is as fresh as possible when the test starts.
+    hooks::run(context, "reset", &context.config.hooks.reset)?;
+
+    let exec = dispatch::exec(context, exec)?;
+
     debug!("{:?}", exec);
 
     #[cfg(all(feature = "limit_threads", unix))]
@@ -676,41 +2401,248 @@ fn perform_exec(
         *rlimit::NPROC_INIT + rlimit::NPROC_ALLOWANCE,
     )?;
 
-    let mut popen = exec.popen()?;
-    let status = if let Some(dur) = timeout(&context.opts) {
-        popen.wait_timeout(dur)?
+    let (mut popen, captured) = if capture_discarded {
+        let (popen, captured) =
+            exec.popen_capturing_discarded(context.config.max_output_bytes())?;
+        (popen, Some(captured))
     } else {
-        popen.wait().map(Option::Some)?
+        (exec.popen()?, None)
     };
+    let process_group = ProcessGroup::new(&popen)?;
+    let status = wait_unless_ctrlc(&mut popen, timeout)?;
 
     #[cfg(all(feature = "limit_threads", unix))]
     rlimit::set_soft_rlimit(rlimit::Resource::NPROC, nprocs_prev)?;
 
     if status.is_some() {
         if let Some(postprocess) = postprocess {
-            if !postprocess(&context.light(), popen)? {
-                return Ok(None);
+            if !postprocess(context, popen)? {
+                return Ok((None, captured));
             }
         }
     } else {
         let pid = popen.pid().ok_or_else(|| anyhow!("Failed to get pid"))?;
         transitive_kill(pid)?;
+        // smoelius: Descendants whose parents have already exited are not found by
+        // `transitive_kill`, but they remain in the process group.
+        if let Some(process_group) = process_group {
+            process_group.kill()?;
+        }
         let _: ExitStatus = popen.wait()?;
+        if CTRLC.load(Ordering::SeqCst) {
+            return Ok((None, captured));
+        }
     }
 
     let Some(status) = status else {
-        return Ok(Some(Outcome::TimedOut));
+        return Ok((Some(Outcome::TimedOut), captured));
     };
 
-    Ok(Some(if status.success() {
+    let outcome = if status.success() {
         Outcome::Passed
     } else {
         Outcome::Failed
-    }))
+    };
+
+    Ok((Some(outcome), captured))
+}
+
+/// Updates the number of consecutive removals that passed in each of `test_names`, given that a
+/// removal in them produced `outcome`. A test whose number reaches `limit` is added to
+/// `suspicious_tests` and warned about.
+fn update_consecutive_passes(
+    context: &Context,
+    source_file: &SourceFile,
+    test_names: &IndexSet<String>,
+    outcome: Outcome,
+    limit: usize,
+    consecutive_passes: &mut BTreeMap<String, usize>,
+    suspicious_tests: &mut BTreeSet<String>,
+) -> Result<()> {
+    for test_name in test_names {
+        let n = consecutive_passes.entry(test_name.clone()).or_default();
+        // smoelius: Outcomes that say nothing about the test's strength (e.g., `nonbuildable`)
+        // neither count toward the limit nor reset it.
+        if outcome == Outcome::Passed {
+            *n += 1;
+        } else if detected(Some(outcome)) {
+            *n = 0;
+        }
+        if *n >= limit && suspicious_tests.insert(test_name.clone()) {
+            source_warn(
+                &context.light(),
+                Warning::TestSuspicious,
+                source_file,
+                &format!(
+                    "{n} consecutive removals passed in test `{test_name}`; skipping its \
+                     remaining candidates"
+                ),
+                WarnFlags::empty(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if `outcome` indicates that a test detected a removal, i.e., failed or timed out
+fn detected(outcome: Option<Outcome>) -> bool {
+    matches!(outcome, Some(Outcome::Failed | Outcome::TimedOut))
+}
+
+/// Returns the probable cause of the failure whose output was captured in `captured`
+fn probable_cause(captured: Option<Captured>) -> Result<Option<String>> {
+    let Some(captured) = captured else {
+        return Ok(None);
+    };
+    let output = captured.finish()?;
+    Ok(cause::probable_cause(&String::from_utf8_lossy(&output)))
+}
+
+/// Re-runs `test_names` with `span`'s candidate restored. Returns `Passed` if the tests all pass,
+/// and `Inconclusive` otherwise, i.e., if the tests' passing likely had nothing to do with the
+/// removal.
+/// Runs each test exercising a candidate in `span_test_maps` without any removal, and returns
+/// the names of those that do not pass
+fn find_failing_tests(
+    context: &Context,
+    span_test_maps: &SpanTestMaps,
+    timeout: Option<Duration>,
+) -> Result<BTreeSet<String>> {
+    let mut test_spans = BTreeMap::<&String, &Span>::new();
+    for (span, _, test_names) in span_test_maps.iter() {
+        for test_name in test_names {
+            test_spans.entry(test_name).or_insert(span);
+        }
+    }
+
+    let mut failing_tests = BTreeSet::new();
+
+    for (test_name, span) in test_spans {
+        let _run_span = info_span!("dry_run_test", test = %test_name).entered();
+
+        let outcome = if let Some((exec, postprocess)) =
+            context.backend.exec(&context.light(), test_name, span)?
+        {
+            let exec = exec.env("NECESSIST_REMOVAL", NO_REMOVAL);
+            perform_exec(&context.light(), exec, postprocess, timeout)?
+        } else {
+            Some(Outcome::Nonbuildable)
+        };
+
+        if CTRLC.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if outcome != Some(Outcome::Passed) {
+            failing_tests.insert(test_name.clone());
+        }
+    }
+
+    Ok(failing_tests)
+}
+
+fn verify_passed(
+    context: &Context,
+    span: &Span,
+    test_names: &IndexSet<String>,
+    timeout: Option<Duration>,
+) -> Result<Option<Outcome>> {
+    for test_name in test_names {
+        let _run_span = info_span!("verify", test = %test_name).entered();
+
+        let outcome = if let Some((exec, postprocess)) =
+            context.backend.exec(&context.light(), test_name, span)?
+        {
+            // smoelius: `NO_REMOVAL` matches no span's id, so no instrumented statement is skipped.
+            let exec = exec.env("NECESSIST_REMOVAL", NO_REMOVAL);
+
+            perform_exec(&context.light(), exec, postprocess, timeout)?
+        } else {
+            Some(Outcome::Nonbuildable)
+        };
+
+        if CTRLC.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        if outcome != Some(Outcome::Passed) {
+            return Ok(Some(Outcome::Inconclusive));
+        }
+    }
+
+    Ok(Some(Outcome::Passed))
+}
+
+/// Waits for `popen` to exit. Returns `None` if `timeout` elapses or Ctrl-C is detected first.
+fn wait_unless_ctrlc(popen: &mut Popen, timeout: Option<Duration>) -> Result<Option<ExitStatus>> {
+    let deadline = timeout.map(|dur| Instant::now() + dur);
+    loop {
+        let mut dur = CTRLC_POLL_INTERVAL;
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            dur = dur.min(remaining);
+        }
+        if let Some(status) = popen.wait_timeout(dur)? {
+            return Ok(Some(status));
+        }
+        if CTRLC.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+    }
+}
+
+/// Records that the run of `span`'s candidate was interrupted.
+/// Warns about local chain nodes that were started since `known_node_processes` was last updated,
+/// and adds them to `known_node_processes`.
+fn warn_about_orphaned_node_processes(
+    context: &Context,
+    span: &Span,
+    known_node_processes: &mut BTreeSet<u32>,
+) -> Result<()> {
+    let orphaned = hooks::node_processes()?
+        .difference(known_node_processes)
+        .copied()
+        .collect::<Vec<_>>();
+    if orphaned.is_empty() {
+        return Ok(());
+    }
+    source_warn(
+        &context.light(),
+        Warning::NodeProcessOrphaned,
+        span,
+        &format!(
+            "Local node processes outlived the candidate's tests: {orphaned:?}; consider using \
+             `hooks.after_each_candidate` to stop them"
+        ),
+        WarnFlags::empty(),
+    )?;
+    known_node_processes.extend(orphaned);
+    Ok(())
+}
+
+fn abort(context: &Context, span: &Span, text: &str) -> Result<()> {
+    let sqlite = sqlite_init_lazy(&context.light())?;
+
+    if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
+        sqlite::insert_aborted(sqlite, span, text)?;
+    }
+
+    Ok(())
 }
 
 #[cfg_attr(dylint_lib = "general", allow(non_local_effect_before_error_return))]
-fn emit(context: &mut Context, span: &Span, text: &str, outcome: Outcome) -> Result<()> {
+fn emit(
+    context: &mut Context,
+    span: &Span,
+    text: &str,
+    outcome: Outcome,
+    details: &sqlite::Details,
+    diagnostic: bool,
+    test_names: &IndexSet<String>,
+) -> Result<()> {
     let removal = Removal {
         span: span.clone(),
         text: text.to_owned(),
@@ -720,43 +2652,106 @@ fn emit(context: &mut Context, span: &Span, text: &str, outcome: Outcome) -> Res
     let sqlite = sqlite_init_lazy(&context.light())?;
 
     if let Some(sqlite) = sqlite.borrow_mut().as_mut() {
-        sqlite::insert(sqlite, &removal)?;
+        sqlite::insert(sqlite, &removal, details)?;
+    }
+
+    if let Some(results_stream) = context.results_stream.as_mut() {
+        if let Err(error) = results_stream.send(&removal) {
+            warn(
+                &context.light(),
+                Warning::StreamResultsFailed,
+                &format!("Failed to stream result: {error:?}"),
+                WarnFlags::empty(),
+            )?;
+        }
     }
 
-    emit_to_console(&context.light(), &removal);
+    if let Some(progress_file) = &context.progress_file {
+        progress_file.record_outcome(outcome)?;
+    }
 
-    Ok(())
+    if let Some(suggestions) = context.suggestions.as_mut() {
+        suggestions.push(test_names, &removal, diagnostic);
+    }
+
+    if let Some(groups) = context.groups.as_mut() {
+        groups.push(test_names, &removal, diagnostic);
+    }
+
+    event::publish(
+        &context.light(),
+        &Event::OutcomeReady {
+            span,
+            text,
+            outcome,
+            cause: details.cause,
+            diagnostic,
+            test_names,
+        },
+    )
+}
+
+/// Outputs the results buffered by `--group-by test`, if any.
+fn print_groups(context: &mut Context) {
+    if let Some(groups) = context.groups.take() {
+        groups.print(&context.light());
+    }
+}
+
+fn emit_to_console(context: &LightContext, removal: &Removal, diagnostic: bool) {
+    if let Some(msg) = removal_message(context, removal, diagnostic) {
+        (context.println)(&msg);
+    }
 }
 
-fn emit_to_console(context: &LightContext, removal: &Removal) {
+/// Returns the console message for `removal`, or `None` if the removal should not be output.
+pub(crate) fn removal_message(
+    context: &LightContext,
+    removal: &Removal,
+    diagnostic: bool,
+) -> Option<String> {
     let Removal {
         span,
         text,
         outcome,
     } = removal;
 
-    if !context.opts.quiet && (context.opts.verbose || *outcome == Outcome::Passed) {
-        let msg = format!(
-            "{}: `{}` {}",
-            span.to_console_string(),
-            text.replace('\r', ""),
-            if std::io::stdout().is_terminal() {
-                outcome.style().bold()
-            } else {
-                Style::default()
-            }
-            .paint(outcome.to_string())
-        );
-        (context.println)(&msg);
+    outcome_message(context, span, text, *outcome, diagnostic)
+}
+
+/// Returns the console message for removing `span` with outcome `outcome`, or `None` if the
+/// removal should not be output.
+pub(crate) fn outcome_message(
+    context: &LightContext,
+    span: &Span,
+    text: &str,
+    outcome: Outcome,
+    diagnostic: bool,
+) -> Option<String> {
+    // smoelius: With `--build-only`, no candidate can pass, so all results are shown.
+    if context.opts.quiet
+        || !(context.opts.verbose || context.opts.build_only || outcome == Outcome::Passed)
+    {
+        return None;
     }
+
+    Some(format!(
+        "{}: `{}` {}{}",
+        span.to_console_string(),
+        text.replace('\r', ""),
+        color::style(context.opts, outcome.style().bold()).paint(outcome.to_string()),
+        if diagnostic { " (diagnostic)" } else { "" }
+    ))
 }
 
-fn sqlite_init_lazy(context: &LightContext) -> Result<Rc<RefCell<Option<sqlite::Sqlite>>>> {
+pub(crate) fn sqlite_init_lazy(
+    context: &LightContext,
+) -> Result<Rc<RefCell<Option<sqlite::Sqlite>>>> {
     let (sqlite, _) = sqlite_and_past_removals_init_lazy(context)?;
     Ok(sqlite)
 }
 
-fn past_removals_init_lazy(context: &LightContext) -> Result<Vec<Removal>> {
+pub(crate) fn past_removals_init_lazy(context: &LightContext) -> Result<Vec<Removal>> {
     let (_, past_removals) = sqlite_and_past_removals_init_lazy(context)?;
     Ok(past_removals.take())
 }
@@ -788,6 +2783,10 @@ fn sqlite_and_past_removals_init_lazy(
                     let (sqlite, mut past_removals) = sqlite::init(
                         context,
                         context.root,
+                        context.original_root,
+                        &database_name()?,
+                        project().as_deref(),
+                        job_slot()?.is_some(),
                         context.opts.dump,
                         context.opts.reset,
                         context.opts.resume,
@@ -836,8 +2835,10 @@ mod rlimit {
     }
 }
 
-fn timeout(opts: &Necessist) -> Option<Duration> {
-    match opts.timeout {
+/// Returns the timeout for a source file. `--timeout` takes precedence over a timeout set in a
+/// configuration file.
+pub(crate) fn timeout(opts: &Necessist, config_timeout: Option<u64>) -> Option<Duration> {
+    match opts.timeout.or(config_timeout) {
         None => Some(DEFAULT_TIMEOUT),
         Some(0) => None,
         Some(secs) => Some(Duration::from_secs(secs)),
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS aborted (
+    project TEXT NOT NULL DEFAULT '',
+    span TEXT NOT NULL,
+    text TEXT NOT NULL,
+    PRIMARY KEY (project, span)
+)
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS archived_removal (
+    project TEXT NOT NULL,
+    span    TEXT NOT NULL,
+    text    TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    url     TEXT NOT NULL,
+    run_id  INTEGER REFERENCES run (id),
+    statement_normalized TEXT,
+    cause   TEXT,
+    covering_tests INTEGER,
+    failing_tests  INTEGER,
+    raw_outcome TEXT,
+    PRIMARY KEY (project, span, run_id)
+)
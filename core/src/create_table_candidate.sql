@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS candidate (
+    project        TEXT    NOT NULL DEFAULT '',
+    source_file    TEXT    NOT NULL,
+    hash           TEXT    NOT NULL,
+    n_tests        INTEGER NOT NULL,
+    span_test_maps TEXT    NOT NULL,
+    PRIMARY KEY (project, source_file)
+)
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS cost (
+    project     TEXT NOT NULL DEFAULT '',
+    source_file TEXT NOT NULL,
+    dry_run     REAL,
+    removals    REAL NOT NULL DEFAULT 0,
+    n_removals  INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (project, source_file)
+)
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS feature_set (
+    features TEXT NOT NULL,
+    PRIMARY KEY (features)
+)
@@ -1,7 +1,14 @@
-CREATE TABLE removal (
+CREATE TABLE IF NOT EXISTS removal (
+    project TEXT NOT NULL DEFAULT '',
     span    TEXT NOT NULL,
     text    TEXT NOT NULL,
-    outcome TEXT NOT NULL CHECK (outcome IN ('skipped', 'nonbuildable', 'failed', 'timed-out', 'passed')),
+    outcome TEXT NOT NULL CHECK (outcome IN ('skipped', 'nonbuildable', 'failed', 'timed-out', 'inconclusive', 'passed')),
     url     TEXT NOT NULL,
-    PRIMARY KEY (span)
+    run_id  INTEGER REFERENCES run (id),
+    statement_normalized TEXT,
+    cause   TEXT,
+    covering_tests INTEGER,
+    failing_tests  INTEGER,
+    raw_outcome TEXT,
+    PRIMARY KEY (project, span)
 )
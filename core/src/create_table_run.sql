@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS run (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    commit_id  TEXT NOT NULL,
+    branch     TEXT NOT NULL,
+    dirty      BOOLEAN NOT NULL,
+    fuzz_seed  TEXT,
+    truncated_outputs INTEGER NOT NULL DEFAULT 0,
+    truncated_bytes   INTEGER NOT NULL DEFAULT 0,
+    toolchain  TEXT
+)
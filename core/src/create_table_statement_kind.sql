@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS statement_kind (
+    project TEXT NOT NULL DEFAULT '',
+    span    TEXT NOT NULL,
+    kind    TEXT NOT NULL,
+    PRIMARY KEY (project, span)
+)
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS warning (
+    id      INTEGER PRIMARY KEY AUTOINCREMENT,
+    project TEXT NOT NULL DEFAULT '',
+    run_id  INTEGER REFERENCES run (id),
+    kind    TEXT NOT NULL,
+    source  TEXT,
+    message TEXT NOT NULL
+)
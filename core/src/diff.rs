@@ -0,0 +1,55 @@
+use crate::{__ToConsoleString, color, core::sqlite_init_lazy, sqlite, LightContext, Removal};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+/// Outputs the removals whose outcomes differ between the runs selected by `from` and `to`.
+/// Removals are matched by span; a removal produced by only one of the runs is not output.
+pub(crate) fn diff(context: &LightContext, from: &str, to: &str) -> Result<()> {
+    let sqlite = sqlite_init_lazy(context)?;
+    let mut sqlite = sqlite.borrow_mut();
+    let sqlite = sqlite
+        .as_mut()
+        .ok_or_else(|| anyhow!("`diff` requires an sqlite database"))?;
+
+    let from = sqlite::run(sqlite, from)?;
+    let to = sqlite::run(sqlite, to)?;
+
+    let from_outcomes = sqlite::run_removals(sqlite, from.id)?
+        .into_iter()
+        .map(|removal| (removal.span, removal.outcome))
+        .collect::<BTreeMap<_, _>>();
+    let mut to_removals = sqlite::run_removals(sqlite, to.id)?;
+    to_removals.sort_by(|left, right| left.span.cmp(&right.span));
+
+    let mut n = 0;
+    for Removal {
+        span,
+        text,
+        outcome,
+    } in &to_removals
+    {
+        let Some(from_outcome) = from_outcomes.get(span) else {
+            continue;
+        };
+        if from_outcome == outcome {
+            continue;
+        }
+        (context.println)(&format!(
+            "{}: `{}` {} -> {}",
+            span.to_console_string(),
+            text.replace('\r', ""),
+            color::style(context.opts, from_outcome.style().bold()).paint(from_outcome.to_string()),
+            color::style(context.opts, outcome.style().bold()).paint(outcome.to_string())
+        ));
+        n += 1;
+    }
+
+    (context.println)(&format!(
+        "{n} outcome{} changed from run {} to run {}",
+        if n == 1 { "" } else { "s" },
+        from.id,
+        to.id
+    ));
+
+    Ok(())
+}
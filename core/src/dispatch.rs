@@ -0,0 +1,170 @@
+//! Support for running build and test commands somewhere other than directly on the local machine,
+//! i.e., on a remote worker (`--remote`) or in a Docker container (`--docker`).
+
+use crate::{Exec, LightContext};
+use anyhow::Result;
+use std::{
+    env::current_dir,
+    ffi::{OsStr, OsString},
+    path::{Component, Path},
+    process::Command,
+};
+
+/// A way of running commands beneath a project's root somewhere else, where the root is `base`
+pub(crate) trait Dispatcher {
+    /// Returns a command that runs `argv` with the environment modifications `env` (a `None` value
+    /// means the variable is removed) in `cwd`, which is a path beneath `base`. The words in `argv`
+    /// and `env` have already been translated. `root` is the project's local root.
+    fn command(
+        &self,
+        root: &Path,
+        argv: &[String],
+        env: &[(String, Option<String>)],
+        cwd: &str,
+    ) -> Result<Command>;
+
+    /// The path to which the project's root corresponds
+    fn base(&self) -> &str;
+
+    /// Called before each command is returned, e.g., to sync files
+    fn prepare(&self, _root: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns a command that runs `command` as `--remote` or `--docker` requires, or `None` if
+/// neither was passed.
+///
+/// # Errors
+///
+/// Returns an error if preparing to run the command failed, e.g., if files could not be synced.
+pub fn command(context: &LightContext, command: &Command) -> Result<Option<Command>> {
+    let Some(dispatcher) = dispatcher(context) else {
+        return Ok(None);
+    };
+    let argv = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .collect::<Vec<_>>();
+    let env = command.get_envs().collect::<Vec<_>>();
+    wrap(
+        dispatcher,
+        context.root,
+        &argv,
+        &env,
+        command.get_current_dir(),
+    )
+    .map(Some)
+}
+
+/// Like [`command`], but for an [`Exec`]. `exec`'s redirections are preserved.
+pub(crate) fn exec(context: &LightContext, mut exec: Exec) -> Result<Exec> {
+    let Some(dispatcher) = dispatcher(context) else {
+        return Ok(exec);
+    };
+    let argv = exec
+        .argv
+        .iter()
+        .map(OsString::as_os_str)
+        .collect::<Vec<_>>();
+    let env = exec
+        .env
+        .iter()
+        .map(|(key, val)| (key.as_os_str(), val.as_deref()))
+        .collect::<Vec<_>>();
+    let command = wrap(
+        dispatcher,
+        context.root,
+        &argv,
+        &env,
+        exec.cwd.as_deref().map(Path::new),
+    )?;
+    exec.argv = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(ToOwned::to_owned)
+        .collect();
+    exec.env.clear();
+    exec.cwd = None;
+    Ok(exec)
+}
+
+fn dispatcher<'a>(context: &'a LightContext) -> Option<&'a dyn Dispatcher> {
+    context
+        .opts
+        .remote
+        .as_ref()
+        .map(|remote| remote as &dyn Dispatcher)
+        .or_else(|| {
+            context
+                .opts
+                .docker
+                .as_ref()
+                .map(|docker| docker as &dyn Dispatcher)
+        })
+}
+
+fn wrap(
+    dispatcher: &dyn Dispatcher,
+    root: &Path,
+    argv: &[&OsStr],
+    env: &[(&OsStr, Option<&OsStr>)],
+    cwd: Option<&Path>,
+) -> Result<Command> {
+    dispatcher.prepare(root)?;
+
+    let base = dispatcher.base();
+
+    let cwd = match cwd {
+        Some(cwd) => Some(cwd.to_path_buf()),
+        None => current_dir().ok(),
+    };
+    let cwd = cwd
+        .and_then(|cwd| translate(root, &cwd, base))
+        .unwrap_or_else(|| base.to_owned());
+
+    let argv = argv
+        .iter()
+        .map(|arg| translate_word(root, arg, base))
+        .collect::<Vec<_>>();
+    let env = env
+        .iter()
+        .map(|(key, val)| {
+            (
+                key.to_string_lossy().to_string(),
+                val.map(|val| translate_word(root, val, base)),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    dispatcher.command(root, &argv, &env, &cwd)
+}
+
+/// Translates `path`, which should be beneath `root`, to the corresponding path beneath `base`
+fn translate(root: &Path, path: &Path, base: &str) -> Option<String> {
+    let suffix = crate::util::strip_prefix(path, root).ok()?;
+    if suffix.as_os_str().is_empty() {
+        Some(base.to_owned())
+    } else {
+        Some(format!("{base}/{}", to_slash_string(suffix)))
+    }
+}
+
+/// Translates `word` if it is a path beneath `root`; otherwise, returns `word` unchanged
+fn translate_word(root: &Path, word: &OsStr, base: &str) -> String {
+    let path = Path::new(word);
+    if path.is_absolute() {
+        if let Some(translated) = translate(root, path, base) {
+            return translated;
+        }
+    }
+    word.to_string_lossy().to_string()
+}
+
+pub(crate) fn to_slash_string(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(s) => Some(s.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
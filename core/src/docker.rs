@@ -0,0 +1,98 @@
+//! Support for `--docker`, which runs build and test commands in a Docker container with the
+//! project's root mounted at [`MOUNT_POINT`].
+
+use crate::{config, dispatch::Dispatcher};
+use anyhow::{anyhow, Result};
+use std::{path::Path, process::Command};
+
+const MOUNT_POINT: &str = "/repo";
+
+/// Images used when `--docker` is passed without an image, and necessist.toml does not name one for
+/// the framework
+const DEFAULT_IMAGES: &[(&str, &str)] = &[
+    ("foundry", "ghcr.io/foundry-rs/foundry"),
+    ("go", "golang"),
+    ("hardhat", "node"),
+    ("rust", "rust"),
+];
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Docker {
+    image: Option<String>,
+    args: Vec<String>,
+}
+
+impl Docker {
+    /// Returns a `Docker` that uses `image`, or, if `image` is `None`, the framework's image once
+    /// [`Docker::resolve`] is called
+    #[must_use]
+    pub fn new(image: Option<String>) -> Self {
+        Self {
+            image,
+            args: Vec::new(),
+        }
+    }
+
+    /// Determines the image to use for `framework` (unless one was given explicitly), and the
+    /// additional arguments to pass to `docker run`, from the `[docker]` table of necessist.toml.
+    pub(crate) fn resolve(&mut self, config: &config::Docker, framework: &str) -> Result<()> {
+        if self.image.is_none() {
+            let image = config
+                .images
+                .get(framework)
+                .map(String::as_str)
+                .or_else(|| {
+                    DEFAULT_IMAGES
+                        .iter()
+                        .find_map(|&(key, image)| (key == framework).then_some(image))
+                })
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Found no Docker image for {framework}; pass one with `--docker=<IMAGE>` \
+                         or set `docker.images.{framework}` in necessist.toml"
+                    )
+                })?;
+            self.image = Some(image.to_owned());
+        }
+        self.args.clone_from(&config.args);
+        Ok(())
+    }
+}
+
+impl Dispatcher for Docker {
+    fn command(
+        &self,
+        root: &Path,
+        argv: &[String],
+        env: &[(String, Option<String>)],
+        cwd: &str,
+    ) -> Result<Command> {
+        let image = self
+            .image
+            .as_ref()
+            .ok_or_else(|| anyhow!("Docker image was not resolved"))?;
+
+        let mut command = Command::new("docker");
+        command.args(["run", "--rm", "--volume"]);
+        command.arg(format!("{}:{MOUNT_POINT}", root.display()));
+        command.args(["--workdir", cwd]);
+        // smoelius: Some images (e.g., Foundry's) have an entrypoint that would misinterpret the
+        // command.
+        command.args(["--entrypoint", ""]);
+        // smoelius: A container's environment does not derive from Necessist's. So there is
+        // nothing to remove.
+        for (key, val) in env {
+            if let Some(val) = val {
+                command.args(["--env", &format!("{key}={val}")]);
+            }
+        }
+        command.args(&self.args);
+        command.arg(image);
+        command.args(argv);
+        Ok(command)
+    }
+
+    fn base(&self) -> &str {
+        MOUNT_POINT
+    }
+}
@@ -0,0 +1,173 @@
+//! An event bus through which a run's progress and results are published. The console is the
+//! default subscriber. Embedders (e.g., a TUI) can register additional subscribers with
+//! [`subscribe`].
+
+use crate::{
+    color, core::removal_preview, LightContext, Outcome, Span, Warning,
+    __ToConsoleString as ToConsoleString,
+};
+use ansi_term::Color::Yellow;
+use anyhow::Result;
+use indexmap::IndexSet;
+use std::{io::IsTerminal, path::Path, sync::Mutex, time::Duration};
+
+/// An event published during a run
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// Test files are about to be parsed. `source_files` are the files named on the command line,
+    /// and are empty if all of the project's test files are to be parsed.
+    ParseStarted { source_files: &'a [&'a Path] },
+    /// `span` is about to be removed.
+    CandidateStarted { span: &'a Span, text: &'a str },
+    /// Removing `span` produced `outcome` in `test_names`. For a failed removal, `cause` is the
+    /// failure's probable cause, if one was found in the tests' output.
+    OutcomeReady {
+        span: &'a Span,
+        text: &'a str,
+        outcome: Outcome,
+        cause: Option<&'a str>,
+        diagnostic: bool,
+        test_names: &'a IndexSet<String>,
+    },
+    /// A warning was emitted. `first_of_kind` is true if no warning of the same kind was emitted
+    /// before it.
+    Warning {
+        warning: Warning,
+        source: Option<String>,
+        msg: &'a str,
+        first_of_kind: bool,
+    },
+    /// The run finished. `n_diagnostic_passed` is the number of passed candidates that are
+    /// diagnostic calls, and `installing` is the time spent installing dependencies.
+    Summary {
+        n_diagnostic_passed: usize,
+        installing: Duration,
+    },
+}
+
+/// A receiver of [`Event`]s
+pub trait Subscriber: Send {
+    /// Handles `event`. A subscriber must not emit warnings, as warnings are themselves published.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event could not be handled, in which case the run is aborted.
+    fn notify(&mut self, context: &LightContext, event: &Event) -> Result<()>;
+}
+
+static SUBSCRIBERS: Mutex<Vec<Box<dyn Subscriber>>> = Mutex::new(Vec::new());
+
+/// Registers `subscriber` to be notified of subsequent events, after the console.
+pub fn subscribe(subscriber: Box<dyn Subscriber>) {
+    #[allow(clippy::unwrap_used)]
+    SUBSCRIBERS.lock().unwrap().push(subscriber);
+}
+
+/// Notifies the console and then each registered subscriber of `event`.
+pub(crate) fn publish(context: &LightContext, event: &Event) -> Result<()> {
+    Console.notify(context, event)?;
+
+    #[allow(clippy::unwrap_used)]
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+
+    for subscriber in subscribers.iter_mut() {
+        subscriber.notify(context, event)?;
+    }
+
+    Ok(())
+}
+
+/// The default subscriber, which prints events to the console
+struct Console;
+
+impl Subscriber for Console {
+    fn notify(&mut self, context: &LightContext, event: &Event) -> Result<()> {
+        match *event {
+            Event::ParseStarted { .. } => {}
+            Event::CandidateStarted { span, text } => {
+                // smoelius: The preview is for users following along in a terminal, so it is not
+                // shown when stdout is redirected. Nor is it shown with `--group-by`, which defers
+                // output.
+                if context.opts.verbose
+                    && !context.opts.quiet
+                    && context.opts.group_by.is_none()
+                    && std::io::stdout().is_terminal()
+                {
+                    (context.println)(&format!(
+                        "{}: removing `{}`\n{}",
+                        span.to_console_string(),
+                        text.replace('\r', ""),
+                        removal_preview(context, span)
+                    ));
+                }
+            }
+            Event::OutcomeReady {
+                span,
+                text,
+                outcome,
+                diagnostic,
+                ..
+            } => {
+                // smoelius: With `--group-by`, outcomes are output once the run finishes.
+                if context.opts.group_by.is_none() {
+                    if let Some(msg) =
+                        crate::core::outcome_message(context, span, text, outcome, diagnostic)
+                    {
+                        (context.println)(&msg);
+                    }
+                }
+            }
+            Event::Warning {
+                warning,
+                ref source,
+                msg,
+                first_of_kind,
+            } => {
+                (context.println)(&format!(
+                    "{}{}: {}{}",
+                    source
+                        .as_ref()
+                        .map_or(String::new(), |source| format!("{source}: ")),
+                    color::style(context.opts, Yellow.bold()).paint("Warning"),
+                    msg,
+                    if first_of_kind {
+                        format!(
+                            "
+Silence this warning with: --allow {warning}"
+                        )
+                    } else {
+                        String::new()
+                    }
+                ));
+            }
+            Event::Summary {
+                n_diagnostic_passed,
+                installing,
+            } => {
+                // smoelius: Removing a diagnostic call is expected to leave a test passing. So such
+                // candidates are tallied separately from the others.
+                if n_diagnostic_passed != 0 {
+                    (context.println)(&format!(
+                        "{} of the passed candidates {} diagnostic call{}",
+                        n_diagnostic_passed,
+                        if n_diagnostic_passed == 1 {
+                            "is a"
+                        } else {
+                            "are"
+                        },
+                        if n_diagnostic_passed == 1 { "" } else { "s" }
+                    ));
+                }
+
+                if !installing.is_zero() {
+                    (context.println)(&format!(
+                        "Installing dependencies took {:.1}s",
+                        installing.as_secs_f64()
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
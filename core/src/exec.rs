@@ -0,0 +1,349 @@
+//! A replacement for [`subprocess::Exec`] whose processes can be killed along with all of their
+//! descendants.
+//!
+//! Test commands like `cargo test`, `go test`, and `forge test` spawn processes of their own (e.g.,
+//! test binaries, node processes, or Anvil instances). If a test times out and only the immediate
+//! child is killed, those processes can linger and skew subsequent runs. So on Unix, each process
+//! is started in a new process group, and on Windows, each process is assigned to a job object.
+
+use crate::truncation;
+use anyhow::Result;
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+    fs::OpenOptions,
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+use subprocess::{NullFile, Popen, PopenConfig, Redirection};
+
+/// How long [`Captured::finish`] waits for a pipe to be read to its end once the process has exited
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[cfg(not(windows))]
+const NULL_DEVICE: &str = "/dev/null";
+
+#[cfg(windows)]
+const NULL_DEVICE: &str = "nul";
+
+/// A builder for [`Popen`]s, with the subset of [`subprocess::Exec`]'s interface that Necessist
+/// uses
+pub struct Exec {
+    pub(crate) argv: Vec<OsString>,
+    pub(crate) env: Vec<(OsString, Option<OsString>)>,
+    pub(crate) cwd: Option<OsString>,
+    stdout: Output,
+    stderr: Output,
+}
+
+/// Where a process's stdout or stderr goes: either a [`Redirection`] or a [`NullFile`]
+pub struct Output(Option<Redirection>);
+
+impl From<Redirection> for Output {
+    fn from(redirection: Redirection) -> Self {
+        Self(Some(redirection))
+    }
+}
+
+impl From<NullFile> for Output {
+    fn from(_: NullFile) -> Self {
+        Self(None)
+    }
+}
+
+impl Output {
+    fn into_redirection(self) -> Result<Redirection> {
+        if let Some(redirection) = self.0 {
+            Ok(redirection)
+        } else {
+            let file = OpenOptions::new().write(true).open(NULL_DEVICE)?;
+            Ok(Redirection::File(file))
+        }
+    }
+}
+
+impl Exec {
+    #[must_use]
+    pub fn cmd(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            argv: vec![program.as_ref().to_owned()],
+            env: Vec::new(),
+            cwd: None,
+            stdout: Redirection::None.into(),
+            stderr: Redirection::None.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.argv.push(arg.as_ref().to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn args(mut self, args: &[impl AsRef<OsStr>]) -> Self {
+        self.argv
+            .extend(args.iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    #[must_use]
+    pub fn env(mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> Self {
+        self.env
+            .push((key.as_ref().to_owned(), Some(val.as_ref().to_owned())));
+        self
+    }
+
+    #[must_use]
+    pub fn env_remove(mut self, key: impl AsRef<OsStr>) -> Self {
+        self.env.push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    #[must_use]
+    pub fn cwd(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cwd = Some(dir.as_ref().as_os_str().to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn stdout(mut self, stdout: impl Into<Output>) -> Self {
+        self.stdout = stdout.into();
+        self
+    }
+
+    #[must_use]
+    pub fn stderr(mut self, stderr: impl Into<Output>) -> Self {
+        self.stderr = stderr.into();
+        self
+    }
+
+    /// Like [`Exec::popen`], but pipes whichever of stdout and stderr would be discarded, and reads
+    /// them through [`truncation::strip_and_cap`] as the process runs. The output can be examined
+    /// with [`Captured::finish`] once the process exits.
+    pub(crate) fn popen_capturing_discarded(
+        mut self,
+        max_output_bytes: usize,
+    ) -> Result<(Popen, Captured)> {
+        let capture_stdout = self.stdout.0.is_none();
+        let capture_stderr = self.stderr.0.is_none();
+        if capture_stdout {
+            self.stdout = Redirection::Pipe.into();
+        }
+        if capture_stderr {
+            self.stderr = Redirection::Pipe.into();
+        }
+
+        let mut popen = self.popen()?;
+
+        let files = [
+            capture_stdout.then(|| popen.stdout.take()).flatten(),
+            capture_stderr.then(|| popen.stderr.take()).flatten(),
+        ];
+        let receivers = files
+            .into_iter()
+            .flatten()
+            .map(|file| {
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let _: Result<(), _> =
+                        sender.send(truncation::strip_and_cap(file, max_output_bytes));
+                });
+                receiver
+            })
+            .collect();
+
+        Ok((popen, Captured(receivers)))
+    }
+
+    /// Starts the process. On Unix, the process is made the leader of a new process group.
+    pub fn popen(self) -> Result<Popen> {
+        let Self {
+            argv,
+            env,
+            cwd,
+            stdout,
+            stderr,
+        } = self;
+
+        let env = if env.is_empty() {
+            None
+        } else {
+            let mut vars = PopenConfig::current_env();
+            for (key, val) in env {
+                vars.retain(|(other, _)| *other != key);
+                if let Some(val) = val {
+                    vars.push((key, val));
+                }
+            }
+            Some(vars)
+        };
+
+        let config = PopenConfig {
+            stdout: stdout.into_redirection()?,
+            stderr: stderr.into_redirection()?,
+            env,
+            cwd,
+            #[cfg(unix)]
+            setpgid: true,
+            ..Default::default()
+        };
+
+        Popen::create(&argv, config).map_err(Into::into)
+    }
+}
+
+impl fmt::Debug for Exec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cmdline = self
+            .argv
+            .iter()
+            .map(|arg| format!("{:?}", arg.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "Exec {{ {cmdline} }}")
+    }
+}
+
+/// The output captured by [`Exec::popen_capturing_discarded`]
+pub(crate) struct Captured(Vec<Receiver<Result<Vec<u8>>>>);
+
+impl Captured {
+    /// Returns the captured output, stdout's followed by stderr's. Should be called once the
+    /// process has exited.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        for receiver in self.0 {
+            // smoelius: A descendant that outlives the process can hold a pipe open. Rather than
+            // wait for the descendant to exit, the pipe's output is forgone.
+            if let Ok(result) = receiver.recv_timeout(CAPTURE_TIMEOUT) {
+                output.extend(result?);
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// The processes started by a [`Popen`] returned by [`Exec::popen`], i.e., the process and its
+/// descendants
+pub(crate) struct ProcessGroup(sys::ProcessGroup);
+
+impl ProcessGroup {
+    pub fn new(popen: &Popen) -> Result<Option<Self>> {
+        let Some(pid) = popen.pid() else {
+            return Ok(None);
+        };
+        sys::ProcessGroup::new(pid).map(|group| Some(Self(group)))
+    }
+
+    /// Kills every process in the group. Processes that have already exited are ignored.
+    pub fn kill(&self) -> Result<()> {
+        self.0.kill()
+    }
+}
+
+#[cfg(not(windows))]
+mod sys {
+    use anyhow::Result;
+    use std::process::{Command, ExitStatus, Stdio};
+
+    pub(super) struct ProcessGroup {
+        pgid: u32,
+    }
+
+    impl ProcessGroup {
+        // smoelius: `setpgid(0, 0)` makes the process's id its process group id.
+        #[allow(clippy::unnecessary_wraps)]
+        pub(super) fn new(pid: u32) -> Result<Self> {
+            Ok(Self { pgid: pid })
+        }
+
+        pub(super) fn kill(&self) -> Result<()> {
+            let _status: ExitStatus = Command::new("kill")
+                .args(["--", &format!("-{}", self.pgid)])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()?;
+            // smoelius: The process group may no longer exist.
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use anyhow::{bail, Result};
+    use std::{io::Error, ptr::null};
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        System::{
+            JobObjects::{AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject},
+            Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE},
+        },
+    };
+
+    pub(super) struct ProcessGroup {
+        job: HANDLE,
+    }
+
+    impl ProcessGroup {
+        // smoelius: Processes that the process starts before it is assigned to the job are not
+        // in the job. The window for this is small.
+        pub(super) fn new(pid: u32) -> Result<Self> {
+            unsafe {
+                let job = CreateJobObjectW(null(), null());
+                if job.is_null() {
+                    bail!("Failed to create job object: {}", Error::last_os_error());
+                }
+                let group = Self { job };
+                let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+                if process.is_null() {
+                    bail!("Failed to open process {pid}: {}", Error::last_os_error());
+                }
+                let ret = AssignProcessToJobObject(group.job, process);
+                CloseHandle(process);
+                if ret == 0 {
+                    bail!(
+                        "Failed to assign process {pid} to job object: {}",
+                        Error::last_os_error()
+                    );
+                }
+                Ok(group)
+            }
+        }
+
+        pub(super) fn kill(&self) -> Result<()> {
+            // smoelius: The processes may have already exited.
+            unsafe {
+                TerminateJobObject(self.job, 1);
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for ProcessGroup {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.job);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn popen_capturing_discarded_caps_output() {
+    let (mut popen, captured) = Exec::cmd("sh")
+        .args(&["-c", "printf abcdefgh; printf ij >&2"])
+        .stdout(NullFile)
+        .stderr(NullFile)
+        .popen_capturing_discarded(4)
+        .unwrap();
+    popen.wait().unwrap();
+    assert_eq!(
+        b"ab\n[4 bytes truncated]\nghij".as_slice(),
+        captured.finish().unwrap()
+    );
+}
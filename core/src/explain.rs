@@ -0,0 +1,118 @@
+use crate::{
+    __ToConsoleString, color,
+    core::{perform_exec, timeout, CTRLC},
+    framework::{self, SourceFileSpanTestMap},
+    network, util, LightContext, LineColumn, Outcome,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use std::{path::PathBuf, sync::atomic::Ordering};
+
+/// Parses a span of the form `file:line:col-line:col`, as output by Necessist. The file is
+/// interpreted relative to the current directory.
+pub(crate) fn parse_explain_span(s: &str) -> Result<(PathBuf, LineColumn, LineColumn)> {
+    // smoelius: The span is split from the right so that the file may contain colons (e.g., a
+    // Windows drive letter).
+    let parse = || -> Option<(PathBuf, LineColumn, LineColumn)> {
+        let (rest, end_column) = s.rsplit_once(':')?;
+        let (rest, start_column_end_line) = rest.rsplit_once(':')?;
+        let (start_column, end_line) = start_column_end_line.split_once('-')?;
+        let (source_file, start_line) = rest.rsplit_once(':')?;
+        let line_column = |line: &str, column: &str| -> Option<LineColumn> {
+            Some(LineColumn {
+                line: line.parse().ok()?,
+                column: column.parse::<usize>().ok()?.checked_sub(1)?,
+            })
+        };
+        Some((
+            PathBuf::from(source_file),
+            line_column(start_line, start_column)?,
+            line_column(end_line, end_column)?,
+        ))
+    };
+    parse().ok_or_else(|| anyhow!("Span {s:?} does not have the form `file:line:col-line:col`"))
+}
+
+/// Dry runs the source file containing the span from `start` to `end`, removes the span, and runs
+/// each of the span's tests with their output shown. Nothing is recorded in the database.
+pub(crate) fn explain(
+    context: &LightContext,
+    backend: &dyn framework::Interface,
+    source_file_span_test_map: &SourceFileSpanTestMap,
+    start: LineColumn,
+    end: LineColumn,
+) -> Result<()> {
+    let Some((span, _, test_names)) = source_file_span_test_map
+        .values()
+        .flat_map(framework::SpanTestMaps::iter)
+        .find(|(span, _, _)| span.start == start && span.end == end)
+    else {
+        bail!(
+            "No candidate starts at {}:{} and ends at {}:{}",
+            start.line,
+            start.column + 1,
+            end.line,
+            end.column + 1
+        );
+    };
+
+    ctrlc::set_handler(|| CTRLC.store(true, Ordering::SeqCst))?;
+
+    let text = span.source_text()?;
+
+    if !context.opts.no_dry_run {
+        (context.println)(&format!(
+            "{}: dry running",
+            util::strip_current_dir(&span.source_file).to_string_lossy()
+        ));
+        backend
+            .dry_run(context, &span.source_file)
+            .context("Dry run failed")?;
+    }
+
+    let timeout = timeout(
+        context.opts,
+        context
+            .config
+            .for_source_file(context, &span.source_file)?
+            .timeout,
+    );
+
+    let (_, _backup) = span.remove()?;
+
+    for test_name in test_names {
+        (context.println)(&format!(
+            "{}: running `{test_name}` with `{}` removed",
+            span.to_console_string(),
+            text.replace('\r', ""),
+        ));
+
+        let outcome = if let Some((exec, postprocess)) = backend.exec(context, test_name, span)? {
+            // smoelius: When explaining, backends do not capture the output, and so return no
+            // postprocessing beyond what is needed to keep their state alive.
+            let exec = exec.env("NECESSIST_REMOVAL", span.id());
+            let exec = if context.opts.no_network {
+                network::exec(context, exec)?
+            } else {
+                exec
+            };
+            perform_exec(context, exec, postprocess, timeout)?
+        } else {
+            Some(Outcome::Nonbuildable)
+        };
+
+        if CTRLC.load(Ordering::SeqCst) {
+            bail!("Ctrl-C detected");
+        }
+
+        if let Some(outcome) = outcome {
+            (context.println)(&format!(
+                "{}: `{}` {}",
+                span.to_console_string(),
+                text.replace('\r', ""),
+                color::style(context.opts, outcome.style().bold()).paint(outcome.to_string())
+            ));
+        }
+    }
+
+    Ok(())
+}
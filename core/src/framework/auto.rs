@@ -1,6 +1,6 @@
 use super::{Applicable, Interface, ToImplementation, Union};
 use crate::LightContext;
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 use std::fmt::Display;
 use strum::IntoEnumIterator;
 
@@ -22,46 +22,152 @@ impl<T> Default for Auto<T> {
     }
 }
 
-impl<T> ToImplementation for Auto<T>
+impl<T> Applicable for Auto<T>
 where
-    T: Applicable + Display + IntoEnumIterator + ToImplementation,
+    T: Applicable + IntoEnumIterator,
 {
-    fn to_implementation(&self, context: &LightContext) -> Result<Option<Box<dyn Interface>>> {
+    fn applicable(&self, context: &LightContext) -> Result<bool> {
         match &self.0 {
             Union::Left(_) => {
-                let unflattened_frameworks = T::iter()
-                    .map(|framework| {
-                        if framework.applicable(context)? {
-                            Ok(Some(framework))
-                        } else {
-                            Ok(None)
-                        }
-                    })
-                    .collect::<Result<Vec<_>>>()?;
-
-                let applicable_frameworks = unflattened_frameworks
-                    .into_iter()
-                    .flatten()
-                    .collect::<Vec<_>>();
-
-                ensure!(
-                    applicable_frameworks.len() <= 1,
-                    "Found multiple applicable frameworks: {}; please select one with --framework \
-                     <FRAMEWORK>",
-                    applicable_frameworks
-                        .iter()
-                        .map(ToString::to_string)
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                );
-
-                if let Some(framework) = applicable_frameworks.into_iter().next() {
-                    framework.to_implementation(context)
-                } else {
-                    Ok(None)
+                for framework in T::iter() {
+                    if framework.applicable(context)? {
+                        return Ok(true);
+                    }
                 }
+                Ok(false)
+            }
+            Union::Right(framework) => framework.applicable(context),
+        }
+    }
+
+    fn marker(&self) -> Option<&'static str> {
+        match &self.0 {
+            Union::Left(_) => None,
+            Union::Right(framework) => framework.marker(),
+        }
+    }
+
+    fn config_tables(&self) -> &'static [&'static str] {
+        match &self.0 {
+            Union::Left(_) => &[],
+            Union::Right(framework) => framework.config_tables(),
+        }
+    }
+
+    fn toolchain(&self) -> &'static [&'static [&'static str]] {
+        match &self.0 {
+            Union::Left(_) => &[],
+            Union::Right(framework) => framework.toolchain(),
+        }
+    }
+}
+
+impl<T> Auto<T>
+where
+    T: Applicable + Clone + Display + IntoEnumIterator,
+{
+    /// Returns the framework that `self` names, or `None` if `self` is `auto`
+    pub(crate) fn named(&self) -> Option<&T> {
+        match &self.0 {
+            Union::Left(_) => None,
+            Union::Right(framework) => Some(framework),
+        }
+    }
+
+    /// Returns the framework that `self` refers to. If `self` is `auto`, that is the only
+    /// applicable framework, or, if there are several, the first of them in
+    /// `framework_precedence`.
+    pub(crate) fn resolve(&self, context: &LightContext) -> Result<Option<T>> {
+        match self.select(context)? {
+            Selection::Named(framework)
+            | Selection::OnlyApplicable(framework)
+            | Selection::Preferred(framework) => Ok(Some(framework)),
+            Selection::NoneApplicable => Ok(None),
+            Selection::Ambiguous(frameworks) => bail!(
+                "Found multiple applicable frameworks: {}; please select one with --framework \
+                 <FRAMEWORK>, or list them in order of precedence with `framework_precedence`",
+                frameworks
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// Like [`Auto::resolve`], but also says how the framework was selected
+    pub(crate) fn select(&self, context: &LightContext) -> Result<Selection<T>> {
+        let framework = match &self.0 {
+            Union::Left(_) => None,
+            Union::Right(framework) => Some(framework),
+        };
+
+        if let Some(framework) = framework {
+            return Ok(Selection::Named(framework.clone()));
+        }
+
+        let names = T::iter()
+            .map(|framework| framework.to_string())
+            .collect::<Vec<_>>();
+
+        for name in &context.config.framework_precedence {
+            ensure!(
+                names.contains(name),
+                "`framework_precedence` contains unknown framework `{name}`; expected one of: {}",
+                names.join(", ")
+            );
+        }
+
+        let mut applicable_frameworks = Vec::new();
+
+        for framework in T::iter() {
+            if framework.applicable(context)? {
+                applicable_frameworks.push(framework);
             }
-            Union::Right(framework) => framework.to_implementation(context),
+        }
+
+        if applicable_frameworks.len() <= 1 {
+            return Ok(applicable_frameworks
+                .pop()
+                .map_or(Selection::NoneApplicable, Selection::OnlyApplicable));
+        }
+
+        let preferred = context.config.framework_precedence.iter().find_map(|name| {
+            applicable_frameworks
+                .iter()
+                .find(|framework| framework.to_string() == *name)
+        });
+
+        Ok(preferred.cloned().map_or(
+            Selection::Ambiguous(applicable_frameworks),
+            Selection::Preferred,
+        ))
+    }
+}
+
+/// How [`Auto::select`] selected a framework, if it did
+pub(crate) enum Selection<T> {
+    /// The framework was named with `--framework`.
+    Named(T),
+    /// The framework was the only applicable one.
+    OnlyApplicable(T),
+    /// The framework was the first applicable one in `framework_precedence`.
+    Preferred(T),
+    /// No framework was applicable.
+    NoneApplicable,
+    /// Several frameworks were applicable, and none of them were in `framework_precedence`.
+    Ambiguous(Vec<T>),
+}
+
+impl<T> ToImplementation for Auto<T>
+where
+    T: Applicable + Clone + Display + IntoEnumIterator + ToImplementation,
+{
+    fn to_implementation(&self, context: &LightContext) -> Result<Option<Box<dyn Interface>>> {
+        if let Some(framework) = self.resolve(context)? {
+            framework.to_implementation(context)
+        } else {
+            Ok(None)
         }
     }
 }
@@ -1,14 +1,19 @@
-use crate::{config, rewriter::Rewriter, LightContext, SourceFile, Span};
+use crate::{
+    candidates::Candidates, config, rewriter::Rewriter, Exec, LightContext, LineColumn, SourceFile,
+    Span,
+};
 use anyhow::Result;
 use indexmap::IndexSet;
 use std::{
     collections::{BTreeMap, BTreeSet},
     path::Path,
+    process::{Command, Output},
 };
-use subprocess::{Exec, Popen};
+use subprocess::Popen;
 
 mod auto;
 pub use auto::Auto;
+pub(crate) use auto::Selection;
 
 mod empty;
 pub use empty::Empty;
@@ -21,6 +26,22 @@ type AutoUnion<T, U> = Auto<Union<T, U>>;
 
 pub trait Applicable {
     fn applicable(&self, context: &LightContext) -> Result<bool>;
+    /// Returns the file whose presence in the project's root makes the framework applicable, if
+    /// any, e.g., `Cargo.toml`. Used by `necessist frameworks` to explain framework detection.
+    fn marker(&self) -> Option<&'static str> {
+        None
+    }
+    /// Returns the configuration tables that only this framework and possibly others use, e.g.,
+    /// `rust`. Used by `necessist config check` to flag tables that the selected framework ignores.
+    fn config_tables(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Returns the commands that output the versions of the framework's toolchain, e.g., `cargo
+    /// --version`. The versions are recorded with each run, so that runs on different machines can
+    /// be compared.
+    fn toolchain(&self) -> &'static [&'static [&'static str]] {
+        &[]
+    }
 }
 
 pub trait ToImplementation {
@@ -37,6 +58,13 @@ pub type SourceFileSpanTestMap = BTreeMap<SourceFile, SpanTestMaps>;
 pub struct SpanTestMaps {
     pub statement: SpanTestMap,
     pub method_call: SpanTestMap,
+    /// Statement spans that are calls matching a `diagnostic_calls` pattern
+    pub diagnostic: BTreeSet<Span>,
+    /// Spans that follow the last call matching an `assertion_calls` pattern in their test, or
+    /// that belong to a test with no such calls
+    pub post_assertion: BTreeSet<Span>,
+    /// Spans of statements of special kinds, e.g., Go statements that start goroutines
+    pub statement_kind: BTreeMap<Span, StatementKind>,
 }
 
 impl SpanTestMaps {
@@ -57,6 +85,44 @@ impl SpanTestMaps {
 /// The test names are needed because they are passed to [`Run::exec`].
 pub type SpanTestMap = BTreeMap<Span, IndexSet<String>>;
 
+/// A kind of statement whose removals are recorded in the database's `statement_kind` table, so
+/// that their outcomes can be analyzed separately
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatementKind {
+    /// A Go statement that starts a goroutine, e.g., `go f()`
+    Go,
+    /// A call that waits for goroutines, e.g., `wg.Wait()`
+    Wait,
+    /// A statement or method call exercised only by `#[should_panic]` tests
+    ShouldPanic,
+}
+
+impl std::fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Go => write!(f, "go"),
+            Self::Wait => write!(f, "wait"),
+            Self::ShouldPanic => write!(f, "should-panic"),
+        }
+    }
+}
+
+/// How a backend removes statements
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RemovalStrategy {
+    /// Guard each of a source file's statements with a conditional (see
+    /// [`Run::statement_prefix_and_suffix`]), so that one build serves all of the file's
+    /// statements
+    #[default]
+    Guard,
+    /// Delete each statement from its source file, build, run its tests, and restore the file from
+    /// a pristine copy. For languages in which a statement cannot be guarded, e.g., declarative
+    /// DSLs. [`Run::instrument_source_file`] and [`Run::statement_prefix_and_suffix`] are not
+    /// called.
+    Delete,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum SpanKind {
     Statement,
@@ -69,13 +135,45 @@ pub trait Parse {
         context: &LightContext,
         config: &config::Toml,
         source_files: &[&Path],
+        candidates: &mut Candidates,
     ) -> Result<(usize, SourceFileSpanTestMap)>;
 }
 
 pub type Postprocess = dyn Fn(&LightContext, Popen) -> Result<bool>;
 
 pub trait Run {
+    fn removal_strategy(&self) -> RemovalStrategy {
+        RemovalStrategy::Guard
+    }
     fn dry_run(&self, context: &LightContext, source_file: &Path) -> Result<()>;
+    /// Returns a command that performs `source_file`'s dry run, if the dry run can be performed in
+    /// parallel with other source files' dry runs. The command's output is then passed to
+    /// [`Run::check_dry_run_output`]. Returns `Ok(None)` if [`Run::dry_run`] must be used instead.
+    fn dry_run_command(
+        &self,
+        _context: &LightContext,
+        _source_file: &Path,
+    ) -> Result<Option<Command>> {
+        Ok(None)
+    }
+    /// Checks the output of a command returned by [`Run::dry_run_command`].
+    fn check_dry_run_output(
+        &self,
+        _context: &LightContext,
+        _source_file: &Path,
+        _output: Output,
+    ) -> Result<()> {
+        Ok(())
+    }
+    /// Returns the tests that `source_file`'s dry run found to be skipped at runtime (e.g., by
+    /// Mocha's `this.skip()`).
+    fn skipped_tests(
+        &self,
+        _context: &LightContext,
+        _source_file: &Path,
+    ) -> Result<BTreeSet<String>> {
+        Ok(BTreeSet::new())
+    }
     fn instrument_source_file(
         &self,
         context: &LightContext,
@@ -84,6 +182,16 @@ pub trait Run {
         n_instrumentable_statements: usize,
     ) -> Result<()>;
     fn statement_prefix_and_suffix(&self, span: &Span) -> Result<(String, String)>;
+    /// Returns text to insert into `source_file` so that removals do not make it nonbuildable by
+    /// leaving variables unused (used with `--tolerate-unused`). Each insertion is applied along
+    /// with instrumentation or an explicit removal, so its position is in the unmodified file.
+    fn unused_tolerance_insertions(
+        &self,
+        _context: &LightContext,
+        _source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        Ok(Vec::new())
+    }
     fn build_source_file(&self, context: &LightContext, source_file: &Path) -> Result<()>;
     /// Execute test `test_name` with `span` removed. Returns `Ok(None)` if the test could not be
     /// built.
@@ -114,15 +222,43 @@ impl<T: AsParse> Parse for T {
         context: &LightContext,
         config: &config::Toml,
         source_files: &[&Path],
+        candidates: &mut Candidates,
     ) -> Result<(usize, SourceFileSpanTestMap)> {
-        self.as_parse_mut().parse(context, config, source_files)
+        self.as_parse_mut()
+            .parse(context, config, source_files, candidates)
     }
 }
 
 impl<T: AsRun> Run for T {
+    fn removal_strategy(&self) -> RemovalStrategy {
+        self.as_run().removal_strategy()
+    }
     fn dry_run(&self, context: &LightContext, source_file: &Path) -> Result<()> {
         self.as_run().dry_run(context, source_file)
     }
+    fn dry_run_command(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Result<Option<Command>> {
+        self.as_run().dry_run_command(context, source_file)
+    }
+    fn check_dry_run_output(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+        output: Output,
+    ) -> Result<()> {
+        self.as_run()
+            .check_dry_run_output(context, source_file, output)
+    }
+    fn skipped_tests(
+        &self,
+        context: &LightContext,
+        source_file: &Path,
+    ) -> Result<BTreeSet<String>> {
+        self.as_run().skipped_tests(context, source_file)
+    }
     fn instrument_source_file(
         &self,
         context: &LightContext,
@@ -140,6 +276,14 @@ impl<T: AsRun> Run for T {
     fn statement_prefix_and_suffix(&self, span: &Span) -> Result<(String, String)> {
         self.as_run().statement_prefix_and_suffix(span)
     }
+    fn unused_tolerance_insertions(
+        &self,
+        context: &LightContext,
+        source_file: &SourceFile,
+    ) -> Result<Vec<(LineColumn, String)>> {
+        self.as_run()
+            .unused_tolerance_insertions(context, source_file)
+    }
     fn build_source_file(&self, context: &LightContext, source_file: &Path) -> Result<()> {
         self.as_run().build_source_file(context, source_file)
     }
@@ -0,0 +1,59 @@
+use crate::{
+    framework::{self, Applicable},
+    LightContext,
+};
+use anyhow::Result;
+use std::fmt::Display;
+use strum::IntoEnumIterator;
+
+/// Outputs whether each framework is applicable, followed by the framework that `framework` selects
+/// and why.
+pub(crate) fn explain_framework_selection<
+    Identifier: Applicable + Clone + Display + IntoEnumIterator,
+>(
+    context: &LightContext,
+    framework: &framework::Auto<Identifier>,
+) -> Result<()> {
+    for identifier in Identifier::iter() {
+        let applicable = identifier.applicable(context)?;
+        (context.println)(&format!(
+            "{identifier}: {}{}",
+            if applicable {
+                "applicable"
+            } else {
+                "not applicable"
+            },
+            identifier.marker().map_or(String::new(), |marker| format!(
+                " ({marker} {})",
+                if applicable { "found" } else { "not found" }
+            ))
+        ));
+    }
+
+    let msg = match framework.select(context)? {
+        framework::Selection::Named(identifier) => {
+            format!("Selected: {identifier} (named with --framework)")
+        }
+        framework::Selection::OnlyApplicable(identifier) => {
+            format!("Selected: {identifier} (the only applicable framework)")
+        }
+        framework::Selection::Preferred(identifier) => format!(
+            "Selected: {identifier} (the first applicable framework in `framework_precedence`)"
+        ),
+        framework::Selection::NoneApplicable => {
+            "Selected: none (no framework is applicable)".to_owned()
+        }
+        framework::Selection::Ambiguous(identifiers) => format!(
+            "Selected: none (multiple frameworks are applicable: {}; select one with --framework \
+             <FRAMEWORK>, or list them in order of precedence with `framework_precedence`)",
+            identifiers
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    (context.println)(&msg);
+
+    Ok(())
+}
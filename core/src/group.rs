@@ -0,0 +1,53 @@
+use crate::{LightContext, Outcome, Removal};
+use indexmap::{IndexMap, IndexSet};
+use strum::IntoEnumIterator;
+
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GroupBy {
+    Test,
+}
+
+/// Results buffered by `--group-by test`. A removal appears under each test that exercises it.
+/// Tests are listed in the order in which their first results became known.
+#[derive(Default)]
+pub(crate) struct Groups {
+    tests: IndexMap<String, Vec<(Removal, bool)>>,
+}
+
+impl Groups {
+    pub fn push(&mut self, test_names: &IndexSet<String>, removal: &Removal, diagnostic: bool) {
+        for test_name in test_names {
+            self.tests
+                .entry(test_name.clone())
+                .or_default()
+                .push((removal.clone(), diagnostic));
+        }
+    }
+
+    /// Outputs each test's name and outcome counts, followed by its removals. Like ungrouped
+    /// output, the removals are limited to `passed` ones unless `--verbose` is used.
+    pub fn print(self, context: &LightContext) {
+        for (test_name, removals) in self.tests {
+            let counts = Outcome::iter()
+                .filter_map(|outcome| {
+                    let n = removals
+                        .iter()
+                        .filter(|(removal, _)| removal.outcome == outcome)
+                        .count();
+                    if n == 0 {
+                        None
+                    } else {
+                        Some(format!("{n} {outcome}"))
+                    }
+                })
+                .collect::<Vec<_>>();
+            (context.println)(&format!("{test_name}: {}", counts.join(", ")));
+            for (removal, diagnostic) in &removals {
+                if let Some(msg) = crate::core::removal_message(context, removal, *diagnostic) {
+                    (context.println)(&format!("    {msg}"));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Support for `hooks.before_each_candidate`, `hooks.after_each_candidate`, and `hooks.reset`, and
+//! detection of local chain nodes (e.g., Anvil instances) that outlive the candidates that started
+//! them.
+
+use crate::LightContext;
+use anyhow::{bail, Context, Result};
+use std::{collections::BTreeSet, process::Command};
+
+/// Patterns matching the command lines of local chain nodes that tests commonly spawn
+#[cfg(not(windows))]
+const NODE_PATTERNS: &[&str] = &[
+    "(^|/)anvil( |$)",
+    "(^|/)solana-test-validator( |$)",
+    "hardhat(\\.js)? node( |$)",
+];
+
+/// Like [`NODE_PATTERNS`], but as WQL `like` patterns
+#[cfg(windows)]
+const NODE_PATTERNS: &[&str] = &[
+    "%anvil.exe%",
+    "%solana-test-validator.exe%",
+    "%hardhat% node%",
+];
+
+/// Runs a hook command from the project's root. `name` is the hook's key in necessist.toml, and is
+/// used in error messages. An empty command does nothing.
+pub(crate) fn run(context: &LightContext, name: &str, command: &[String]) -> Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        return Ok(());
+    };
+
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(context.root.as_path())
+        .output()
+        .with_context(|| format!("Failed to run `hooks.{name}`"))?;
+
+    if !output.status.success() {
+        bail!(
+            "`hooks.{name}` failed ({})\nstdout: {:?}\nstderr: {:?}",
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the ids of the running processes that appear to be local chain nodes
+#[cfg(not(windows))]
+pub(crate) fn node_processes() -> Result<BTreeSet<u32>> {
+    let mut pids = BTreeSet::new();
+    for pattern in NODE_PATTERNS {
+        // smoelius: `pgrep` exits with 1 when no processes match.
+        let output = Command::new("pgrep").args(["-f", pattern]).output()?;
+        pids.extend(parse_pids(&String::from_utf8(output.stdout)?)?);
+    }
+    Ok(pids)
+}
+
+#[cfg(windows)]
+pub(crate) fn node_processes() -> Result<BTreeSet<u32>> {
+    let condition = NODE_PATTERNS
+        .iter()
+        .map(|pattern| format!("CommandLine like '{pattern}'"))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    let output = Command::new("wmic")
+        .args(["process", "where", &condition, "get", "ProcessId"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    // smoelius: Skip the `ProcessId` header.
+    parse_pids(&stdout.lines().skip(1).collect::<Vec<_>>().join("\n"))
+}
+
+fn parse_pids(s: &str) -> Result<BTreeSet<u32>> {
+    s.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse::<u32>()
+                .with_context(|| format!("failed to parse `{line}`"))
+        })
+        .collect()
+}
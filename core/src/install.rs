@@ -0,0 +1,19 @@
+//! Time spent installing a project's dependencies (e.g., its node modules), which is reported
+//! separately from the time spent running tests.
+
+use std::{sync::Mutex, time::Duration};
+
+static ELAPSED: Mutex<Duration> = Mutex::new(Duration::ZERO);
+
+/// Adds `duration` to the time spent installing dependencies
+pub fn record(duration: Duration) {
+    #[allow(clippy::unwrap_used)]
+    let mut elapsed = ELAPSED.lock().unwrap();
+    *elapsed += duration;
+}
+
+/// Returns the time spent installing dependencies so far
+pub(crate) fn elapsed() -> Duration {
+    #[allow(clippy::unwrap_used)]
+    *ELAPSED.lock().unwrap()
+}
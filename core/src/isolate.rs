@@ -0,0 +1,219 @@
+use crate::util;
+use anyhow::{ensure, Context, Result};
+use std::{
+    env::{current_dir, set_current_dir, temp_dir},
+    ffi::OsStr,
+    fs::{copy, create_dir_all, remove_dir},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+use tempfile::TempDir;
+use tracing::debug;
+
+/// Ignored directories that are shared with (rather than recreated in) a worktree
+const SHARED_DIRS: &[&str] = &["node_modules"];
+
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Isolate {
+    Worktree,
+}
+
+/// A temporary git worktree mirroring the working tree of the repository containing a project.
+///
+/// Uncommitted changes and untracked files are copied into the worktree. Submodules and
+/// [`SHARED_DIRS`] are symlinked rather than copied. The worktree is removed when dropped.
+pub(crate) struct Worktree {
+    toplevel: PathBuf,
+    tempdir: TempDir,
+    prev_dir: Option<PathBuf>,
+}
+
+impl Worktree {
+    pub fn new(root: &Path) -> Result<Self> {
+        let toplevel = git(root, ["rev-parse", "--show-toplevel"])?;
+        let toplevel = dunce::canonicalize(String::from_utf8(toplevel.stdout)?.trim_end())?;
+
+        // smoelius: The temporary directory's path is canonicalized so that paths within the
+        // worktree can be compared to ones produced by `dunce::canonicalize`.
+        let tempdir = tempfile::tempdir_in(dunce::canonicalize(temp_dir())?)?;
+
+        let worktree = Self {
+            toplevel,
+            tempdir,
+            prev_dir: None,
+        };
+
+        git(
+            &worktree.toplevel,
+            [
+                OsStr::new("worktree"),
+                OsStr::new("add"),
+                OsStr::new("--detach"),
+                worktree.path().as_os_str(),
+                OsStr::new("HEAD"),
+            ],
+        )?;
+
+        worktree.copy_uncommitted_changes()?;
+        worktree.copy_untracked_files()?;
+        worktree.link_submodules()?;
+        worktree.link_shared_dirs()?;
+
+        Ok(worktree)
+    }
+
+    pub fn path(&self) -> &Path {
+        self.tempdir.path()
+    }
+
+    /// Changes the current directory to the corresponding directory in the worktree, if there is
+    /// one. The previous current directory is restored when the worktree is dropped.
+    pub fn enter(&mut self) -> Result<()> {
+        let current_dir = current_dir()?;
+        if let Some(dir) = self.translate(&current_dir) {
+            set_current_dir(dir)?;
+            self.prev_dir = Some(current_dir);
+        }
+        Ok(())
+    }
+
+    /// Maps a path in the original checkout to the corresponding path in the worktree. Returns
+    /// `None` if `path` is not in the original checkout.
+    pub fn translate(&self, path: &Path) -> Option<PathBuf> {
+        util::strip_prefix(path, &self.toplevel)
+            .ok()
+            .map(|suffix| self.path().join(suffix))
+    }
+
+    fn copy_uncommitted_changes(&self) -> Result<()> {
+        let diff = git(&self.toplevel, ["diff", "HEAD", "--binary"])?;
+        if diff.stdout.is_empty() {
+            return Ok(());
+        }
+
+        let mut child = Command::new("git")
+            .args(["apply", "--binary", "-"])
+            .current_dir(self.path())
+            .stdin(Stdio::piped())
+            .spawn()?;
+        {
+            #[allow(clippy::unwrap_used)]
+            let mut stdin = child.stdin.take().unwrap();
+            stdin.write_all(&diff.stdout)?;
+        }
+        let status = child.wait()?;
+        ensure!(
+            status.success(),
+            "Failed to apply uncommitted changes to worktree"
+        );
+
+        Ok(())
+    }
+
+    fn copy_untracked_files(&self) -> Result<()> {
+        let output = git(
+            &self.toplevel,
+            ["ls-files", "--others", "--exclude-standard", "-z"],
+        )?;
+        for path in nul_separated(&output.stdout)? {
+            let to = self.path().join(path);
+            if let Some(parent) = to.parent() {
+                create_dir_all(parent)?;
+            }
+            copy(self.toplevel.join(path), &to)
+                .with_context(|| format!("Failed to copy {path:?} to worktree"))?;
+        }
+        Ok(())
+    }
+
+    fn link_submodules(&self) -> Result<()> {
+        let output = git(&self.toplevel, ["ls-files", "--stage", "-z"])?;
+        for entry in nul_separated(&output.stdout)? {
+            // smoelius: Each entry has the form `<mode> <object> <stage>\t<path>`. Submodules have
+            // mode 160000.
+            let Some((info, path)) = entry.split_once('\t') else {
+                continue;
+            };
+            if !info.starts_with("160000 ") {
+                continue;
+            }
+            let to = self.path().join(path);
+            // smoelius: `git worktree add` leaves an empty directory for each submodule.
+            remove_dir(&to).or_else(|error| if to.try_exists()? { Err(error) } else { Ok(()) })?;
+            symlink_dir(&self.toplevel.join(path), &to)?;
+        }
+        Ok(())
+    }
+
+    fn link_shared_dirs(&self) -> Result<()> {
+        for dir in SHARED_DIRS {
+            let from = self.toplevel.join(dir);
+            let to = self.path().join(dir);
+            if from.is_dir() && !to.try_exists()? {
+                symlink_dir(&from, &to)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        if let Some(dir) = self.prev_dir.take() {
+            if let Err(error) = set_current_dir(dir) {
+                debug!("Failed to restore current directory: {error:?}");
+            }
+        }
+        let result = git(
+            &self.toplevel,
+            [
+                OsStr::new("worktree"),
+                OsStr::new("remove"),
+                OsStr::new("--force"),
+                self.path().as_os_str(),
+            ],
+        );
+        if let Err(error) = result {
+            debug!("Failed to remove worktree: {error:?}");
+        }
+    }
+}
+
+fn git<I, S>(dir: &Path, args: I) -> Result<Output>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = Command::new("git");
+    command.args(args);
+    command.current_dir(dir);
+    debug!("{:?}", command);
+    let output = command.output()?;
+    ensure!(
+        output.status.success(),
+        "{:?} failed: {}",
+        command,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(output)
+}
+
+fn nul_separated(bytes: &[u8]) -> Result<Vec<&str>> {
+    bytes
+        .split(|&byte| byte == b'\0')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| std::str::from_utf8(entry).map_err(Into::into))
+        .collect()
+}
+
+#[cfg(unix)]
+fn symlink_dir(original: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(original, link).map_err(Into::into)
+}
+
+#[cfg(windows)]
+fn symlink_dir(original: &Path, link: &Path) -> Result<()> {
+    std::os::windows::fs::symlink_dir(original, link).map_err(Into::into)
+}
@@ -12,34 +12,97 @@ use backup::Backup;
 #[doc(hidden)]
 pub use backup::Backup as __Backup;
 
+mod annotate;
+
+mod audit;
+
+mod batch;
+
 #[cfg(feature = "clap")]
 pub mod cli;
 
+pub mod candidates;
+
+pub mod cause;
+
+mod check_config;
+
+mod color;
+pub use color::Color;
+
 pub mod config;
 
 mod core;
 use crate::core::Removal;
-pub use crate::core::{necessist, LightContext, Necessist};
+pub use crate::core::{
+    necessist, ConfigSubcommand, DbSubcommand, LightContext, Necessist, Subcommand,
+};
+
+mod diff;
+
+pub mod dispatch;
+
+pub mod event;
+
+mod docker;
+pub use docker::Docker;
+
+mod exec;
+pub use exec::Exec;
+
+mod explain;
 
 #[cfg(feature = "lock_root")]
 mod flock;
 
 pub mod framework;
+
+mod frameworks;
+
+mod group;
+pub use group::GroupBy;
+
+mod hooks;
+
+pub mod install;
+
+mod isolate;
+pub use isolate::Isolate;
+
+mod network;
+
 mod offset_calculator {
     pub type OffsetCalculator<'original> = rewriter::OffsetCalculator<'original, crate::Span>;
 }
 
+mod order;
+pub use order::Order;
+
 mod outcome;
-use outcome::Outcome;
+pub use outcome::Outcome;
 
-mod rewriter {
-    pub type Rewriter<'original> = rewriter::Rewriter<'original, crate::Span>;
-}
+mod patch;
+
+mod rewriter;
 use rewriter::Rewriter;
 // smoelius: `Rewriter` is used by the Go framework to convert byte offsets to char offsets.
 #[doc(hidden)]
 pub use rewriter::Rewriter as __Rewriter;
 
+mod progress_file;
+
+mod remote;
+pub use remote::Remote;
+
+mod self_test;
+
+mod shard;
+pub use shard::Shard;
+
+mod show;
+
+mod side_effects;
+
 mod source_file;
 pub use source_file::SourceFile;
 
@@ -48,6 +111,12 @@ pub use span::{Span, ToInternalSpan};
 
 mod sqlite;
 
+mod suggest;
+
+mod stream;
+
+pub mod truncation;
+
 mod to_console_string;
 #[doc(hidden)]
 pub use to_console_string::ToConsoleString as __ToConsoleString;
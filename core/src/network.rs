@@ -0,0 +1,118 @@
+//! Support for `--no-network`, which runs tests with candidates removed without network access, so
+//! that a removal cannot turn a hermetic test into one that reaches real endpoints.
+
+use crate::{warn, Exec, LightContext, WarnFlags, Warning};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use std::process::{Command, Stdio};
+
+/// An address to which nothing listens (port 9 is the "discard" port)
+const BLACKHOLE: &str = "http://127.0.0.1:9";
+
+const PROXY_VARS: &[&str] = &[
+    "ALL_PROXY",
+    "HTTPS_PROXY",
+    "HTTP_PROXY",
+    "all_proxy",
+    "https_proxy",
+    "http_proxy",
+];
+
+const NO_PROXY_VARS: &[&str] = &["NO_PROXY", "no_proxy"];
+
+/// How commands are cut off from the network
+#[derive(Debug, Eq, PartialEq)]
+enum Isolation {
+    /// Run commands with `unshare` and these arguments, which create a new network namespace
+    /// containing only a loopback device that is down
+    Unshare(&'static [&'static str]),
+    /// Point commands' proxy environment variables to [`BLACKHOLE`]. Programs that ignore the
+    /// variables are not affected.
+    Proxy,
+}
+
+static ISOLATION: OnceCell<Isolation> = OnceCell::new();
+
+/// Returns `exec` modified to run without network access
+pub(crate) fn exec(context: &LightContext, exec: Exec) -> Result<Exec> {
+    let isolation = ISOLATION.get_or_try_init(|| isolation(context))?;
+    Ok(isolate(isolation, exec))
+}
+
+fn isolation(context: &LightContext) -> Result<Isolation> {
+    if !cfg!(target_os = "linux") {
+        return Ok(Isolation::Proxy);
+    }
+
+    // smoelius: Creating a network namespace requires `CAP_SYS_ADMIN`. Without it, `-r` (i.e.,
+    // `--map-root-user`) creates a user namespace in which the current user has it.
+    for args in [&["-n"][..], &["-r", "-n"][..]] {
+        let status = Command::new("unshare")
+            .args(args)
+            .arg("true")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if status.is_ok_and(|status| status.success()) {
+            return Ok(Isolation::Unshare(args));
+        }
+    }
+
+    warn(
+        context,
+        Warning::UnshareFailed,
+        "Failed to run `unshare -n`; tests will instead be run with proxy environment variables \
+         that lead nowhere, which does not stop programs that ignore them",
+        WarnFlags::ONCE,
+    )?;
+
+    Ok(Isolation::Proxy)
+}
+
+fn isolate(isolation: &Isolation, mut exec: Exec) -> Exec {
+    match isolation {
+        Isolation::Unshare(args) => {
+            exec.argv = std::iter::once("unshare")
+                .chain(args.iter().copied())
+                .chain(std::iter::once("--"))
+                .map(Into::into)
+                .chain(exec.argv)
+                .collect();
+            exec
+        }
+        Isolation::Proxy => {
+            for var in PROXY_VARS {
+                exec = exec.env(var, BLACKHOLE);
+            }
+            for var in NO_PROXY_VARS {
+                exec = exec.env_remove(var);
+            }
+            exec
+        }
+    }
+}
+
+#[test]
+fn isolate_with_unshare() {
+    let exec = isolate(
+        &Isolation::Unshare(&["-r", "-n"]),
+        Exec::cmd("cargo").arg("test"),
+    );
+    assert_eq!(
+        ["unshare", "-r", "-n", "--", "cargo", "test"].as_slice(),
+        exec.argv
+    );
+}
+
+#[test]
+fn isolate_with_proxy() {
+    let exec = isolate(&Isolation::Proxy, Exec::cmd("cargo").env("NO_PROXY", "*"));
+    assert!(exec
+        .env
+        .iter()
+        .any(|(key, val)| key == "HTTPS_PROXY" && val.as_deref() == Some(BLACKHOLE.as_ref())));
+    assert_eq!(
+        Some(&("NO_PROXY".into(), None)),
+        exec.env.iter().rfind(|(key, _)| key == "NO_PROXY")
+    );
+}
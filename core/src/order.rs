@@ -0,0 +1,155 @@
+use crate::{
+    framework::{SpanKind, SpanTestMaps},
+    SourceFile, Span,
+};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Order {
+    CheapestFirst,
+    RoundRobin,
+}
+
+/// The positions of source files (with `--order cheapest-first`) or of candidates (with `--order
+/// round-robin`) in the order in which they are processed, if that differs from their sorted
+/// order. Past removals must be visited in the same order.
+#[derive(Default)]
+pub(crate) struct Rank {
+    source_files: BTreeMap<SourceFile, usize>,
+    spans: BTreeMap<Span, usize>,
+}
+
+impl Rank {
+    /// Ranks the source files by their positions in `source_file_span_test_maps`
+    pub fn by_source_file(source_file_span_test_maps: &[(SourceFile, SpanTestMaps)]) -> Self {
+        Self {
+            source_files: source_file_span_test_maps
+                .iter()
+                .enumerate()
+                .map(|(i, (source_file, _))| (source_file.clone(), i))
+                .collect(),
+            spans: BTreeMap::new(),
+        }
+    }
+
+    /// Ranks each candidate by the position of the entry it belongs to in
+    /// `source_file_span_test_maps`
+    pub fn by_span(source_file_span_test_maps: &[(SourceFile, SpanTestMaps)]) -> Self {
+        Self {
+            source_files: BTreeMap::new(),
+            spans: source_file_span_test_maps
+                .iter()
+                .enumerate()
+                .flat_map(|(i, (_, span_test_maps))| {
+                    span_test_maps
+                        .iter()
+                        .map(move |(span, _, _)| (span.clone(), i))
+                })
+                .collect(),
+        }
+    }
+
+    /// Compares spans first by their ranks, and then as spans. Spans without ranks come before
+    /// those with them.
+    pub fn cmp(&self, left: &Span, right: &Span) -> Ordering {
+        self.spans
+            .get(left)
+            .cmp(&self.spans.get(right))
+            .then_with(|| {
+                self.source_files
+                    .get(&left.source_file)
+                    .cmp(&self.source_files.get(&right.source_file))
+            })
+            .then_with(|| left.cmp(right))
+    }
+}
+
+/// Sorts source files by the estimated time to run one of their candidates, cheapest first.
+/// Source files without an estimate come last. Otherwise, the original order is preserved.
+pub(crate) fn cheapest_first(
+    source_file_span_test_maps: &mut [(SourceFile, SpanTestMaps)],
+    estimates: &BTreeMap<SourceFile, Duration>,
+) {
+    source_file_span_test_maps.sort_by(|(left, _), (right, _)| {
+        match (estimates.get(left), estimates.get(right)) {
+            (Some(left), Some(right)) => left.cmp(right),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    });
+}
+
+/// Splits each source file's candidates into rounds, where a round holds at most one candidate per
+/// test, and then interleaves the rounds: the first round of every source file, then the second
+/// round of every source file, etc. Thus, a source file can appear more than once in the result.
+///
+/// Within a round, statements still come before method calls, as required by instrumentation.
+pub(crate) fn round_robin(
+    source_file_span_test_maps: Vec<(SourceFile, SpanTestMaps)>,
+) -> Vec<(SourceFile, SpanTestMaps)> {
+    let mut source_file_rounds = source_file_span_test_maps
+        .into_iter()
+        .map(|(source_file, span_test_maps)| {
+            (source_file, rounds(&span_test_maps).into_iter().peekable())
+        })
+        .collect::<Vec<_>>();
+
+    let mut result = Vec::new();
+    while source_file_rounds
+        .iter_mut()
+        .any(|(_, rounds)| rounds.peek().is_some())
+    {
+        for (source_file, rounds) in &mut source_file_rounds {
+            if let Some(round) = rounds.next() {
+                result.push((source_file.clone(), round));
+            }
+        }
+    }
+
+    result
+}
+
+fn rounds(span_test_maps: &SpanTestMaps) -> Vec<SpanTestMaps> {
+    let mut remaining = span_test_maps.iter().collect::<Vec<_>>();
+
+    let mut rounds = Vec::new();
+    while !remaining.is_empty() {
+        let mut round = SpanTestMaps::default();
+        let mut tests_in_round = BTreeSet::new();
+        remaining.retain(|&(span, span_kind, test_names)| {
+            // smoelius: A candidate is added to the round only if none of its tests already have a
+            // candidate in it. The first remaining candidate is always added.
+            if test_names
+                .iter()
+                .any(|test_name| tests_in_round.contains(test_name))
+            {
+                return true;
+            }
+            tests_in_round.extend(test_names.iter());
+            let span_test_map = match span_kind {
+                SpanKind::Statement => &mut round.statement,
+                SpanKind::MethodCall => &mut round.method_call,
+            };
+            span_test_map.insert(span.clone(), test_names.clone());
+            if span_test_maps.diagnostic.contains(span) {
+                round.diagnostic.insert(span.clone());
+            }
+            if span_test_maps.post_assertion.contains(span) {
+                round.post_assertion.insert(span.clone());
+            }
+            if let Some(&statement_kind) = span_test_maps.statement_kind.get(span) {
+                round.statement_kind.insert(span.clone(), statement_kind);
+            }
+            false
+        });
+        rounds.push(round);
+    }
+
+    rounds
+}
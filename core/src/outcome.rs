@@ -1,5 +1,5 @@
 use ansi_term::{
-    Color::{Blue, Green, Red, Yellow},
+    Color::{Blue, Cyan, Green, Purple, Red, Yellow},
     Style,
 };
 use anyhow::{anyhow, Error};
@@ -10,15 +10,20 @@ use strum_macros::EnumIter;
 
 /// The outcome of running a test with a statement or method call removed.
 #[derive(Clone, Copy, Debug, EnumIter, PartialEq)]
-pub(crate) enum Outcome {
+pub enum Outcome {
     // The test(s) were not run (e.g., because a dry run failed).
     Skipped,
     /// The test(s) did not build.
     Nonbuildable,
+    /// The test(s) built, but were not run (see `--build-only`).
+    Buildable,
     /// The test(s) built but failed.
     Failed,
     /// The test(s) built but timed-out.
     TimedOut,
+    /// The test(s) passed with the removal, but failed when re-run without it (see
+    /// `--verify-passed`).
+    Inconclusive,
     // The test(s) built and passed.
     Passed,
 }
@@ -39,12 +44,14 @@ impl FromStr for Outcome {
 }
 
 impl Outcome {
-    pub fn style(self) -> Style {
+    pub(crate) fn style(self) -> Style {
         match self {
             Outcome::Skipped => Style::default().dimmed(),
             Outcome::Nonbuildable => Blue.normal(),
+            Outcome::Buildable => Cyan.normal(),
             Outcome::Failed => Green.normal(),
             Outcome::TimedOut => Yellow.normal(),
+            Outcome::Inconclusive => Purple.normal(),
             Outcome::Passed => Red.normal(),
         }
     }
@@ -0,0 +1,47 @@
+use crate::{util, LightContext, Outcome, Removal};
+use anyhow::{Context, Result};
+use similar::TextDiff;
+use std::path::Path;
+
+/// Writes a unified diff to `out` for each of `removals` with outcome `outcome`. Paths in the diffs
+/// are relative to the project's root, with `a/` and `b/` prefixes, as `git diff` would produce.
+pub(crate) fn patch(
+    context: &LightContext,
+    removals: &[Removal],
+    outcome: Outcome,
+    out: &Path,
+) -> Result<()> {
+    std::fs::create_dir_all(out).with_context(|| format!("Failed to create {out:?}"))?;
+
+    let mut n = 0;
+    for Removal {
+        span,
+        text,
+        outcome: _,
+    } in removals.iter().filter(|removal| removal.outcome == outcome)
+    {
+        let relative_path = span.source_file.to_string();
+        let contents = span.source_file.contents();
+        let mutilated = span.removed_contents();
+        let diff = TextDiff::from_lines(contents, &mutilated);
+        let patch = format!(
+            "{span}: `{}` {outcome}\n{}",
+            text.replace('\r', ""),
+            diff.unified_diff()
+                .header(&format!("a/{relative_path}"), &format!("b/{relative_path}"))
+        );
+        // smoelius: A patch's file name is its span with path separators and colons replaced,
+        // e.g., `src-lib.rs-4-5-4-12.patch`.
+        let file_name = format!("{}.patch", span.to_string().replace(['/', '\\', ':'], "-"));
+        std::fs::write(out.join(file_name), patch)?;
+        n += 1;
+    }
+
+    (context.println)(&format!(
+        "Wrote {n} patch{} to {:?}",
+        if n == 1 { "" } else { "es" },
+        util::strip_current_dir(out)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,166 @@
+//! Support for `--progress-file`, which periodically writes Necessist's progress to a JSON file, so
+//! that external tools can track a long run without parsing its output.
+
+use crate::{Outcome, SourceFile, Span};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs::{rename, write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_FILE_NAME: &str = "necessist-progress.json";
+
+/// The minimum time between writes, other than the last
+const INTERVAL: Duration = Duration::from_secs(1);
+
+pub(crate) struct ProgressFile {
+    path: PathBuf,
+    start: Instant,
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    last_written: Option<Instant>,
+    n_candidates: usize,
+    n_done: usize,
+    outcomes: BTreeMap<String, usize>,
+    source_file: Option<String>,
+    candidate: Option<Candidate>,
+    test: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct Candidate {
+    span: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    n_candidates: usize,
+    n_done: usize,
+    outcomes: &'a BTreeMap<String, usize>,
+    source_file: Option<&'a str>,
+    candidate: Option<&'a Candidate>,
+    test: Option<&'a str>,
+    elapsed_secs: f64,
+    eta_secs: Option<f64>,
+    finished: bool,
+}
+
+impl ProgressFile {
+    /// Creates a progress file at `path`, or, if `path` is `None`, at `necessist-progress.json` in
+    /// `root`. A relative `path` is relative to the current directory.
+    pub fn new(path: Option<&Path>, root: &Path) -> Result<Self> {
+        let path = match path {
+            Some(path) => std::env::current_dir()?.join(path),
+            None => root.join(DEFAULT_FILE_NAME),
+        };
+        Ok(Self {
+            path,
+            start: Instant::now(),
+            state: RefCell::default(),
+        })
+    }
+
+    pub fn set_n_candidates(&self, n_candidates: usize) -> Result<()> {
+        self.state.borrow_mut().n_candidates = n_candidates;
+        self.write(true)
+    }
+
+    pub fn set_source_file(&self, source_file: &SourceFile) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.source_file = Some(source_file.to_string());
+        state.candidate = None;
+        state.test = None;
+        drop(state);
+        self.write(false)
+    }
+
+    pub fn set_candidate(&self, span: &Span, text: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.candidate = Some(Candidate {
+            span: span.to_string(),
+            text: text.to_owned(),
+        });
+        state.test = None;
+        drop(state);
+        self.write(false)
+    }
+
+    pub fn set_test(&self, test_name: &str) -> Result<()> {
+        self.state.borrow_mut().test = Some(test_name.to_owned());
+        self.write(false)
+    }
+
+    pub fn record_outcome(&self, outcome: Outcome) -> Result<()> {
+        *self
+            .state
+            .borrow_mut()
+            .outcomes
+            .entry(outcome.to_string())
+            .or_default() += 1;
+        self.write(false)
+    }
+
+    pub fn advance(&self, n: usize) -> Result<()> {
+        self.state.borrow_mut().n_done += n;
+        self.write(false)
+    }
+
+    pub fn finish(&self) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.candidate = None;
+        state.test = None;
+        drop(state);
+        self.write_record(true)
+    }
+
+    /// Writes the file if `force` is true or if enough time has passed since it was last written
+    fn write(&self, force: bool) -> Result<()> {
+        let last_written = self.state.borrow().last_written;
+        if !force && last_written.is_some_and(|instant| instant.elapsed() < INTERVAL) {
+            return Ok(());
+        }
+        self.write_record(false)
+    }
+
+    fn write_record(&self, finished: bool) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+
+        let elapsed = self.start.elapsed().as_secs_f64();
+        #[allow(clippy::cast_precision_loss)]
+        let eta_secs = if state.n_done == 0 {
+            None
+        } else {
+            let n_remaining = state.n_candidates.saturating_sub(state.n_done);
+            Some(elapsed / state.n_done as f64 * n_remaining as f64)
+        };
+
+        let json = serde_json::to_string_pretty(&Record {
+            n_candidates: state.n_candidates,
+            n_done: state.n_done,
+            outcomes: &state.outcomes,
+            source_file: state.source_file.as_deref(),
+            candidate: state.candidate.as_ref(),
+            test: state.test.as_deref(),
+            elapsed_secs: elapsed,
+            eta_secs,
+            finished,
+        })?;
+
+        // smoelius: The file is written atomically, so that a reader never sees a partial write.
+        let tmp = self.path.with_extension("json.tmp");
+        write(&tmp, json).with_context(|| format!("Failed to write {tmp:?}"))?;
+        rename(&tmp, &self.path).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        state.last_written = Some(Instant::now());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+//! Support for `--remote`, which dispatches build and test commands to another machine over SSH.
+//!
+//! The project is mirrored beneath the remote path with `rsync`. Before a command is dispatched,
+//! each file whose modification time changed since it was last synced (e.g., the source file from
+//! which a candidate was just removed) is synced again. Paths beneath the project's root in a
+//! command's arguments and environment are translated to paths beneath the remote path.
+
+use crate::{
+    dispatch::{to_slash_string, Dispatcher},
+    util,
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::Mutex,
+    time::SystemTime,
+};
+use tracing::debug;
+
+/// Directories that are not synced, since they hold build artifacts or version control data
+const UNSYNCED_DIRS: &[&str] = &[".git", "target"];
+
+/// The modification times of the files as of when they were last synced
+static SYNCED: Mutex<BTreeMap<PathBuf, SystemTime>> = Mutex::new(BTreeMap::new());
+
+/// A remote worker, given as `user@host:path`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Remote {
+    destination: String,
+    path: String,
+}
+
+impl FromStr for Remote {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (destination, path) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Remote `{s}` is not of the form `user@host:path`"))?;
+        ensure!(
+            !destination.is_empty() && !path.is_empty(),
+            "Remote `{s}` is not of the form `user@host:path`"
+        );
+        Ok(Self {
+            destination: destination.to_owned(),
+            path: path.trim_end_matches('/').to_owned(),
+        })
+    }
+}
+
+impl std::fmt::Display for Remote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.destination, self.path)
+    }
+}
+
+impl Dispatcher for Remote {
+    fn command(
+        &self,
+        _root: &Path,
+        argv: &[String],
+        env: &[(String, Option<String>)],
+        cwd: &str,
+    ) -> Result<Command> {
+        let mut words = vec![String::from("env")];
+        for (key, val) in env {
+            if let Some(val) = val {
+                words.push(quote(&format!("{key}={val}")));
+            } else {
+                words.extend([String::from("-u"), quote(key)]);
+            }
+        }
+        words.extend(argv.iter().map(|arg| quote(arg)));
+
+        let script = format!("cd {} && {}", quote(cwd), words.join(" "));
+
+        let mut command = Command::new("ssh");
+        command.args(["-o", "BatchMode=yes", &self.destination, &script]);
+        Ok(command)
+    }
+
+    fn base(&self) -> &str {
+        &self.path
+    }
+
+    /// Pushes the files beneath `root` whose modification times changed since they were last
+    /// synced. The first call pushes every file.
+    fn prepare(&self, root: &Path) -> Result<()> {
+        let mtimes = util::mtimes(root, |path| {
+            path.file_name()
+                .is_some_and(|name| UNSYNCED_DIRS.iter().any(|dir| name == *dir))
+        })?;
+
+        #[allow(clippy::unwrap_used)]
+        let mut synced = SYNCED.lock().unwrap();
+
+        let changed = mtimes
+            .iter()
+            .filter(|&(path, mtime)| !path.is_dir() && synced.get(path) != Some(mtime))
+            .map(|(path, _)| util::strip_prefix(path, root).map(to_slash_string))
+            .collect::<Result<Vec<_>>>()?;
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        // smoelius: `--ignore-times` ensures that a file is transferred even if its size and
+        // modification time (to the second) are unchanged, e.g., when two removals of equal length
+        // are made in quick succession.
+        let mut command = Command::new("rsync");
+        command
+            .args(["--archive", "--ignore-times", "--files-from=-"])
+            .arg(root)
+            .arg(format!("{}:{}/", self.destination, self.path))
+            .stdin(Stdio::piped());
+
+        debug!("{:?}", command);
+
+        let mut child = command.spawn().context("Failed to run `rsync`")?;
+        #[allow(clippy::unwrap_used)]
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(changed.join("\n").as_bytes())?;
+        let output = child.wait_with_output()?;
+        ensure!(
+            output.status.success(),
+            "Failed to sync files to `{self}`: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        synced.extend(mtimes);
+
+        Ok(())
+    }
+}
+
+/// Quotes `s` for a POSIX shell
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
@@ -0,0 +1,147 @@
+use crate::{LineColumn, SourceFile, Span};
+use anyhow::{ensure, Result};
+use std::cmp::Reverse;
+
+pub type Rewriter<'original> = ::rewriter::Rewriter<'original, crate::Span>;
+
+/// Insertions into a source file that, unlike a [`Rewriter`]'s rewrites, can be made in any order
+///
+/// A guarded region is a span wrapped in a prefix and a suffix (e.g., `if cond {` and `}`). Guarded
+/// regions can be nested, but not overlap partially, as that would interleave their prefixes and
+/// suffixes. When insertions share a position, suffixes come first (innermost first), then
+/// prefixes (outermost first), then other insertions (in the order they were made).
+#[derive(Default)]
+pub(crate) struct Edits {
+    guards: Vec<(LineColumn, LineColumn)>,
+    insertions: Vec<(LineColumn, Kind, String)>,
+}
+
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+enum Kind {
+    Suffix(Reverse<LineColumn>, Reverse<usize>),
+    Prefix(Reverse<LineColumn>, usize),
+    Insertion(usize),
+}
+
+impl Edits {
+    /// Wraps `span` in `prefix` and `suffix`. Fails if `span` partially overlaps a previously
+    /// guarded region.
+    pub fn guard(&mut self, span: &Span, prefix: String, suffix: String) -> Result<()> {
+        self.guard_line_columns(span.start, span.end, prefix, suffix)
+    }
+
+    fn guard_line_columns(
+        &mut self,
+        start: LineColumn,
+        end: LineColumn,
+        prefix: String,
+        suffix: String,
+    ) -> Result<()> {
+        ensure!(
+            self.guards.iter().all(|&(other_start, other_end)| {
+                !(other_start < start && start < other_end && other_end < end
+                    || start < other_start && other_start < end && end < other_end)
+            }),
+            "Guarded region {}:{}-{}:{} partially overlaps another",
+            start.line,
+            start.column + 1,
+            end.line,
+            end.column + 1
+        );
+        let index = self.guards.len();
+        self.guards.push((start, end));
+        self.insertions
+            .push((start, Kind::Prefix(Reverse(end), index), prefix));
+        self.insertions
+            .push((end, Kind::Suffix(Reverse(start), Reverse(index)), suffix));
+        Ok(())
+    }
+
+    pub fn insert(&mut self, line_column: LineColumn, insertion: String) {
+        let index = self.insertions.len();
+        self.insertions
+            .push((line_column, Kind::Insertion(index), insertion));
+    }
+
+    /// Makes the insertions into `source_file` using `rewriter`.
+    pub fn apply(self, source_file: &SourceFile, rewriter: &mut Rewriter) {
+        for (line_column, insertion) in self.sorted() {
+            source_file.insert(rewriter, line_column, &insertion);
+        }
+    }
+
+    /// Returns the insertions ordered as a [`Rewriter`] requires
+    fn sorted(mut self) -> Vec<(LineColumn, String)> {
+        self.insertions
+            .sort_by_key(|&(line_column, kind, _)| (line_column, kind));
+        self.insertions
+            .into_iter()
+            .map(|(line_column, _, insertion)| (line_column, insertion))
+            .collect()
+    }
+}
+
+#[test]
+fn nested_guards() {
+    let line_column = |column| LineColumn { line: 1, column };
+
+    let mut edits = Edits::default();
+    edits
+        .guard_line_columns(
+            line_column(0),
+            line_column(9),
+            "a{".to_owned(),
+            "}a".to_owned(),
+        )
+        .unwrap();
+    edits
+        .guard_line_columns(
+            line_column(0),
+            line_column(3),
+            "b{".to_owned(),
+            "}b".to_owned(),
+        )
+        .unwrap();
+    edits
+        .guard_line_columns(
+            line_column(3),
+            line_column(9),
+            "c{".to_owned(),
+            "}c".to_owned(),
+        )
+        .unwrap();
+    edits
+        .guard_line_columns(
+            line_column(3),
+            line_column(9),
+            "d{".to_owned(),
+            "}d".to_owned(),
+        )
+        .unwrap();
+    edits.insert(line_column(3), "e".to_owned());
+    assert!(edits
+        .guard_line_columns(
+            line_column(1),
+            line_column(5),
+            "f{".to_owned(),
+            "}f".to_owned()
+        )
+        .is_err());
+
+    assert_eq!(
+        [
+            (0, "a{"),
+            (0, "b{"),
+            (3, "}b"),
+            (3, "c{"),
+            (3, "d{"),
+            (3, "e"),
+            (9, "}d"),
+            (9, "}c"),
+            (9, "}a"),
+        ]
+        .map(|(column, insertion)| (line_column(column), insertion.to_owned()))
+        .as_slice(),
+        edits.sorted()
+    );
+}
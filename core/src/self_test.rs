@@ -0,0 +1,170 @@
+//! Support for `necessist self-test`, which runs Necessist on a tiny generated project, so that a
+//! user can check that their toolchain and environment work before starting a long run.
+//!
+//! Each project has one test with two candidates. Removing the first causes the test to fail, and
+//! removing the second does not.
+
+use crate::{LightContext, Outcome};
+use anyhow::{anyhow, bail, ensure, Result};
+use std::{
+    env::{current_exe, temp_dir},
+    fs::{create_dir_all, read_to_string, write},
+    process::Command,
+};
+
+/// A framework's project: its files' paths and contents, and the texts of its candidates along
+/// with their expected outcomes
+struct Template {
+    files: &'static [(&'static str, &'static str)],
+    expected: &'static [(&'static str, Outcome)],
+}
+
+const RUST_CARGO_TOML: &str = r#"[package]
+name = "necessist-self-test"
+version = "0.1.0"
+edition = "2021"
+
+[workspace]
+"#;
+
+const RUST: Template = Template {
+    files: &[
+        ("Cargo.toml", RUST_CARGO_TOML),
+        (
+            "tests/self_test.rs",
+            include_str!("self_test/rust/tests/self_test.rs"),
+        ),
+    ],
+    expected: &[("n += 1", Outcome::Failed), ("m += 1", Outcome::Passed)],
+};
+
+const GO: Template = Template {
+    files: &[
+        ("go.mod", include_str!("self_test/go/go.mod")),
+        (
+            "self_test_test.go",
+            include_str!("self_test/go/self_test_test.go"),
+        ),
+    ],
+    expected: &[("n++", Outcome::Failed), ("m++", Outcome::Passed)],
+};
+
+const FOUNDRY: Template = Template {
+    files: &[
+        (
+            "foundry.toml",
+            include_str!("self_test/foundry/foundry.toml"),
+        ),
+        (
+            "test/SelfTest.t.sol",
+            include_str!("self_test/foundry/test/SelfTest.t.sol"),
+        ),
+    ],
+    expected: &[("n += 1", Outcome::Failed), ("m += 1", Outcome::Passed)],
+};
+
+const HARDHAT: Template = Template {
+    files: &[
+        (
+            "hardhat.config.js",
+            include_str!("self_test/hardhat/hardhat.config.js"),
+        ),
+        (
+            "package.json",
+            include_str!("self_test/hardhat/package.json"),
+        ),
+        (
+            "test/selfTest.js",
+            include_str!("self_test/hardhat/test/selfTest.js"),
+        ),
+    ],
+    expected: &[("n += 1", Outcome::Failed), ("m += 1", Outcome::Passed)],
+};
+
+/// Generates `framework`'s project in a temporary directory, runs Necessist on it, and checks that
+/// its candidates have the expected outcomes. If the check fails, the directory is kept.
+pub(crate) fn self_test(context: &LightContext, framework: &str) -> Result<()> {
+    let template = match framework {
+        "foundry" => &FOUNDRY,
+        "go" => &GO,
+        "hardhat" => &HARDHAT,
+        "rust" => &RUST,
+        _ => bail!("There is no self-test for framework `{framework}`"),
+    };
+
+    let tempdir = tempfile::tempdir_in(dunce::canonicalize(temp_dir())?)?;
+    let root = tempdir.path().join("necessist-self-test");
+    for (path, contents) in template.files {
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(path, contents)?;
+    }
+
+    let results = tempdir.path().join("results.jsonl");
+
+    (context.println)(&format!("Running self-test for `{framework}` in {root:?}"));
+
+    let mut command = Command::new(current_exe()?);
+    command.arg("--root");
+    command.arg(&root);
+    command.args(["--framework", framework, "--no-sqlite", "--verbose"]);
+    command.arg("--stream-results");
+    command.arg(&results);
+    let status = command.status()?;
+
+    let result = (|| {
+        ensure!(status.success(), "{command:?} exited with {status}");
+        check(template, &read_to_string(&results).unwrap_or_default())
+    })();
+
+    if let Err(error) = result {
+        let path = tempdir.into_path();
+        return Err(error.context(format!("Self-test failed; see {path:?}")));
+    }
+
+    (context.println)(&"Self-test passed");
+
+    Ok(())
+}
+
+/// Checks that the results in `jsonl` (as written by `--stream-results`) include the expected
+/// outcomes
+fn check(template: &Template, jsonl: &str) -> Result<()> {
+    let records = jsonl
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    let mut failures = Vec::new();
+    for (expected_text, expected_outcome) in template.expected {
+        // smoelius: Depending on the framework, a statement's text may or may not include its
+        // trailing semicolon.
+        let outcome = records
+            .iter()
+            .find(|record| {
+                record["text"]
+                    .as_str()
+                    .is_some_and(|text| text.trim_end_matches(';') == *expected_text)
+            })
+            .map(|record| {
+                record["outcome"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Result is missing an outcome: {record}"))
+            })
+            .transpose()?;
+        match outcome {
+            Some(outcome) if outcome == expected_outcome.to_string() => {}
+            Some(outcome) => failures.push(format!(
+                "removing `{expected_text}` should have produced `{expected_outcome}`, but \
+                 produced `{outcome}`"
+            )),
+            None => failures.push(format!("removing `{expected_text}` produced no result")),
+        }
+    }
+
+    ensure!(failures.is_empty(), "{}", failures.join("; "));
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.0;
+
+contract SelfTest {
+    function testSelfTest() public pure {
+        uint256 n = 0;
+        n += 1;
+        uint256 m = 0;
+        m += 1;
+        assert(n == 1);
+        assert(m <= 1);
+    }
+}
@@ -0,0 +1,3 @@
+module necessist_self_test
+
+go 1.21
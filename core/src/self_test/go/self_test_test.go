@@ -0,0 +1,16 @@
+package selftest
+
+import "testing"
+
+func TestSelfTest(t *testing.T) {
+	n := 0
+	n++
+	m := 0
+	m++
+	if n != 1 {
+		t.Fatal("n != 1")
+	}
+	if m > 1 {
+		t.Fatal("m > 1")
+	}
+}
@@ -0,0 +1,3 @@
+module.exports = {
+  solidity: "0.8.24",
+};
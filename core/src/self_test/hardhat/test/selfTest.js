@@ -0,0 +1,12 @@
+const assert = require("assert");
+
+describe("SelfTest", function () {
+  it("self test", function () {
+    let n = 0;
+    n += 1;
+    let m = 0;
+    m += 1;
+    assert.equal(n, 1);
+    assert.ok(m <= 1);
+  });
+});
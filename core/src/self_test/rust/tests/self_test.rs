@@ -0,0 +1,9 @@
+#[test]
+fn self_test() {
+    let mut n = 0;
+    n += 1;
+    let mut m = 0;
+    m += 1;
+    assert_eq!(n, 1);
+    assert!(m <= 1);
+}
@@ -0,0 +1,52 @@
+//! Support for `--shard`, which deterministically partitions a project's source files, so that
+//! several CI jobs can each run Necessist on a disjoint subset of them.
+//!
+//! A source file's shard is determined by a hash of its path relative to the project's root. So
+//! the partition does not depend on the machine on which Necessist runs, or on which other source
+//! files exist.
+
+use crate::{dispatch::to_slash_string, util};
+use anyhow::{anyhow, ensure, Result};
+use sha2::{Digest, Sha256};
+use std::{path::Path, str::FromStr};
+
+/// A shard, given as `i/n`, where `1 <= i <= n`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Shard {
+    index: u64,
+    count: u64,
+}
+
+impl FromStr for Shard {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("Shard `{s}` is not of the form `i/n`"))?;
+        let index = index.parse::<u64>()?;
+        let count = count.parse::<u64>()?;
+        ensure!(
+            1 <= index && index <= count,
+            "Shard `{s}` is not of the form `i/n`, where `1 <= i <= n`"
+        );
+        Ok(Self { index, count })
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// Returns true if `source_file`, which must be beneath `root`, belongs to this shard
+    pub fn contains(&self, root: &Path, source_file: &Path) -> Result<bool> {
+        let relative_path = to_slash_string(util::strip_prefix(source_file, root)?);
+        let digest = Sha256::digest(relative_path.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        Ok(u64::from_be_bytes(bytes) % self.count == self.index - 1)
+    }
+}
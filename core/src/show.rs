@@ -0,0 +1,117 @@
+use crate::{
+    color,
+    core::{removal_message, sqlite_init_lazy},
+    note, sqlite, LightContext, Outcome,
+};
+use ansi_term::Color::Yellow;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+
+/// Outputs the run selected by `run` (a run id or `latest`), followed by the removals it produced.
+pub(crate) fn show(context: &LightContext, run: &str) -> Result<()> {
+    let sqlite = sqlite_init_lazy(context)?;
+    let mut sqlite = sqlite.borrow_mut();
+    let sqlite = sqlite
+        .as_mut()
+        .ok_or_else(|| anyhow!("`show` requires an sqlite database"))?;
+
+    let run = sqlite::run(sqlite, run)?;
+    let mut removals = sqlite::run_removals(sqlite, run.id)?;
+    removals.sort_by(|left, right| left.span.cmp(&right.span));
+
+    (context.println)(&format!(
+        "Run {} started at {}{}{}{}",
+        run.id,
+        run.started_at,
+        if run.commit_id.is_empty() {
+            String::new()
+        } else {
+            format!(" on commit {}", run.commit_id)
+        },
+        if run.branch.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", run.branch)
+        },
+        if run.dirty {
+            " with uncommitted changes"
+        } else {
+            ""
+        }
+    ));
+
+    if let Some(fuzz_seed) = &run.fuzz_seed {
+        (context.println)(&format!("Fuzz seed: {fuzz_seed}"));
+    }
+
+    if let Some(toolchain) = &run.toolchain {
+        let versions = serde_json::from_str::<BTreeMap<String, String>>(toolchain)
+            .with_context(|| format!("Failed to parse toolchain {toolchain:?}"))?;
+        // smoelius: Some programs' versions do not name the program (e.g., `node --version`
+        // prints just `v20.0.0`).
+        for (program, version) in versions {
+            if version.starts_with(&program) {
+                (context.println)(&format!("Toolchain: {version}"));
+            } else {
+                (context.println)(&format!("Toolchain: {program} {version}"));
+            }
+        }
+    }
+
+    if run.truncated_outputs != 0 {
+        (context.println)(&format!(
+            "{} output{} exceeded `max_output_bytes` ({} bytes truncated)",
+            run.truncated_outputs,
+            if run.truncated_outputs == 1 { "" } else { "s" },
+            run.truncated_bytes
+        ));
+    }
+
+    let causes = sqlite::run_causes(sqlite, run.id)?;
+    let failing_tests = sqlite::run_failing_tests(sqlite, run.id)?;
+
+    let mut other_than_passed = false;
+    for removal in &removals {
+        if let Some(msg) = removal_message(context, removal, false) {
+            let span = removal.span.to_string();
+            // smoelius: The number of failing tests is shown only for removals exercised by more
+            // than one test.
+            let annotations = causes
+                .get(&span)
+                .cloned()
+                .into_iter()
+                .chain(
+                    failing_tests
+                        .get(&span)
+                        .filter(|&&(covering, _)| covering > 1)
+                        .map(|(covering, failing)| format!("{failing} of {covering} tests failed")),
+                )
+                .collect::<Vec<_>>();
+            if annotations.is_empty() {
+                (context.println)(&msg);
+            } else {
+                (context.println)(&format!("{msg} ({})", annotations.join("; ")));
+            }
+        }
+        other_than_passed |= removal.outcome != Outcome::Passed;
+    }
+
+    for sqlite::RecordedWarning {
+        kind,
+        source,
+        message,
+    } in sqlite::run_warnings(sqlite, run.id)?
+    {
+        (context.println)(&format!(
+            "{}{} ({kind}): {message}",
+            source.map_or(String::new(), |source| format!("{source}: ")),
+            color::style(context.opts, Yellow.bold()).paint("Warning"),
+        ));
+    }
+
+    if !context.opts.verbose && other_than_passed {
+        note(context, "More output would be produced with --verbose");
+    }
+
+    Ok(())
+}
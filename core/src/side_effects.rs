@@ -0,0 +1,82 @@
+//! Support for `--check-side-effects`, which warns when a candidate's tests modify files beneath
+//! the project's root, other than in directories where build artifacts and caches are written.
+
+use crate::{util, LightContext, Span, WarnFlags, Warning};
+use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Directories in which frameworks write build artifacts and caches, e.g., Cargo's `target`,
+/// Foundry's `out` and `cache`, and Hardhat's `artifacts`
+const BUILD_DIRS: &[&str] = &[
+    ".anchor",
+    ".git",
+    "artifacts",
+    "build",
+    "cache",
+    "node_modules",
+    "out",
+    "target",
+    "test-ledger",
+    "typechain-types",
+];
+
+/// The modification times of the files beneath a project's root, other than those in build
+/// directories
+pub(crate) struct Snapshot(BTreeMap<PathBuf, SystemTime>);
+
+impl Snapshot {
+    pub fn new(root: &Path) -> Result<Self> {
+        let mut mtimes = util::mtimes(root, |path| {
+            path.file_name()
+                .is_some_and(|name| BUILD_DIRS.iter().any(|dir| name == *dir))
+        })?;
+        // smoelius: A directory's modification time changes when an entry is added or removed,
+        // which is reported for the entry itself.
+        mtimes.retain(|path, _| !path.is_dir());
+        Ok(Self(mtimes))
+    }
+
+    /// Warns if files were created, modified, or deleted since `self` was taken. `span`'s source
+    /// file is not considered, since Necessist itself modifies it (e.g., with `--verify-passed`).
+    pub fn warn_if_changed(&self, context: &LightContext, span: &Span) -> Result<()> {
+        let Self(before) = self;
+        let Self(after) = Self::new(context.root)?;
+
+        let changed = before
+            .iter()
+            .filter(|&(path, mtime)| after.get(path) != Some(mtime))
+            .map(|(path, _)| path)
+            .chain(after.keys().filter(|path| !before.contains_key(*path)))
+            .filter(|path| {
+                path.as_path() != span.source_file.as_ref() && !is_database(context.root, path)
+            })
+            .map(|path| util::strip_prefix(path, context.root).unwrap_or(path))
+            .collect::<Vec<_>>();
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        crate::source_warn(
+            context,
+            Warning::SideEffectsDetected,
+            span,
+            &format!("Tests modified files outside of build directories: {changed:?}"),
+            WarnFlags::empty(),
+        )
+    }
+}
+
+/// Returns true if `path` is one of Necessist's databases, which Necessist may write to while tests
+/// run (e.g., to record warnings)
+fn is_database(root: &Path, path: &Path) -> bool {
+    path.parent() == Some(root)
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("necessist.") && name.contains(".db"))
+}
@@ -4,7 +4,7 @@ use crate::{
 };
 use anyhow::Result;
 use std::{
-    cell::RefCell,
+    cell::{OnceCell, RefCell},
     collections::HashMap,
     fs::read_to_string,
     ops::Deref,
@@ -17,6 +17,16 @@ thread_local! {
     static SOURCE_FILES: RefCell<HashMap<PathBuf, SourceFile>> = RefCell::new(HashMap::new());
 }
 
+/// A file beneath a project's root, e.g., a test file
+///
+/// A source file is read when it is first created, and the contents read then are shared by every
+/// `SourceFile` (and [`Span`]) for the same path. It dereferences to its absolute path, and
+/// displays as its path relative to the root, as in the `span` columns of necessist.db.
+///
+/// [`SourceFile::new`], [`SourceFile::contents`], and the `Display` output are covered by
+/// Necessist's [semantic versioning policy].
+///
+/// [semantic versioning policy]: https://github.com/trailofbits/necessist#semantic-versioning-policy
 #[derive(Clone, Eq, PartialEq)]
 pub struct SourceFile {
     inner: Rc<Inner>,
@@ -27,6 +37,8 @@ struct Inner {
     path: PathBuf,
     contents: &'static str,
     offset_calculator: Rc<RefCell<OffsetCalculator<'static>>>,
+    /// The number of characters in each line, computed when first needed
+    line_lengths: OnceCell<Vec<usize>>,
 }
 
 impl Eq for Inner {}
@@ -38,6 +50,17 @@ impl PartialEq for Inner {
 }
 
 impl SourceFile {
+    /// Returns the source file at `path`, which must be beneath `root`, reading it if it has not
+    /// been read already
+    ///
+    /// # Errors
+    ///
+    /// If the file has not been read already and cannot be read, an error is returned.
+    ///
+    /// # Panics
+    ///
+    /// `root` must be absolute, `path` must start with `root`, and every `root` passed within a
+    /// process must be the same.
     pub fn new(root: Rc<PathBuf>, path: PathBuf) -> Result<Self> {
         ROOT.with(|root_prev| {
             let mut root_prev = root_prev.borrow_mut();
@@ -66,6 +89,7 @@ impl SourceFile {
                         path: path.clone(),
                         contents: leaked,
                         offset_calculator: Rc::new(RefCell::new(OffsetCalculator::new(leaked))),
+                        line_lengths: OnceCell::new(),
                     }),
                 };
                 source_files.insert(path, source_file.clone());
@@ -79,12 +103,30 @@ impl SourceFile {
         util::strip_prefix(&self.inner.path, &self.inner.root).unwrap()
     }
 
+    /// Returns the file's contents when it was first read
     // smoelius: Leaking the file contents is a hack.
     #[must_use]
     pub fn contents(&self) -> &'static str {
         self.inner.contents
     }
 
+    /// Returns true if `line_column` (whose line is 1-based and whose column is 0-based) is a
+    /// position in the file, including the position just past the end of a line
+    pub(crate) fn contains(&self, line_column: LineColumn) -> bool {
+        let line_lengths = self.inner.line_lengths.get_or_init(|| {
+            self.inner
+                .contents
+                .split('\n')
+                .map(|line| line.chars().count())
+                .collect()
+        });
+        line_column
+            .line
+            .checked_sub(1)
+            .and_then(|i| line_lengths.get(i))
+            .is_some_and(|&n| line_column.column <= n)
+    }
+
     #[must_use]
     pub fn offset_calculator(&self) -> Rc<RefCell<OffsetCalculator<'static>>> {
         self.inner.offset_calculator.clone()
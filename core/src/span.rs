@@ -1,14 +1,42 @@
-use crate::{Backup, Rewriter, SourceFile, __ToConsoleString as ToConsoleString};
-use anyhow::{anyhow, Result};
+use crate::{Backup, LineColumn, Rewriter, SourceFile, __ToConsoleString as ToConsoleString};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use sha2::{Digest, Sha256};
 use std::{fs::OpenOptions, io::Write, path::PathBuf, rc::Rc};
 
+/// A region of a source file, e.g., a statement or method call that is a candidate for removal
+///
+/// A span's string form (i.e., its [`Display`](std::fmt::Display) output) is
+/// `<path>:<start line>:<start column>-<end line>:<end column>`, where `<path>` is relative to the
+/// project's root, and lines and columns are 1-based. This is the form stored in the `span` columns
+/// of necessist.db, and [`Span::parse`] converts it back to a `Span`. So a tool can map a
+/// necessist.db row back to the source text it concerns, e.g.:
+///
+/// ```
+/// use necessist_core::Span;
+/// use std::{fs::write, rc::Rc};
+///
+/// let tempdir = tempfile::tempdir()?;
+/// let root = Rc::new(tempdir.path().canonicalize()?);
+/// write(root.join("lib.rs"), "fn f() {\n    let x = 1;\n}\n")?;
+///
+/// let span = Span::parse(&root, "lib.rs:2:5-2:15")?;
+/// assert_eq!("let x = 1;", span.source_text()?);
+/// assert_eq!("lib.rs:2:5-2:15", span.to_string());
+/// # Ok::<_, anyhow::Error>(())
+/// ```
+///
+/// The string form, the public fields, [`Span::parse`], [`Span::id`], and [`Span::source_text`]
+/// are covered by Necessist's [semantic versioning policy].
+///
+/// [semantic versioning policy]: https://github.com/trailofbits/necessist#semantic-versioning-policy
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Span {
     pub source_file: SourceFile,
+    /// The span's start; its line is 1-based, but its column is 0-based
     pub start: proc_macro2::LineColumn,
+    /// The span's end (exclusive); its line is 1-based, but its column is 0-based
     pub end: proc_macro2::LineColumn,
 }
 
@@ -48,6 +76,8 @@ static SPAN_RE: Lazy<Regex> = Lazy::new(|| {
 });
 
 impl Span {
+    /// Returns a 16-digit hexadecimal identifier derived from the span's string form. When a
+    /// candidate is removed by instrumentation, `NECESSIST_REMOVAL` is set to its span's id.
     #[must_use]
     pub fn id(&self) -> String {
         const ID_LEN: usize = 16;
@@ -57,6 +87,21 @@ impl Span {
         hex::encode(digest)[..ID_LEN].to_owned()
     }
 
+    /// Parses a span's string form (see [`Span`]), whose path is relative to `root`
+    ///
+    /// The span is not checked against the source file's contents, since a span recorded in
+    /// necessist.db may concern an earlier version of the file. [`Span::source_text`] performs that
+    /// check.
+    ///
+    /// # Errors
+    ///
+    /// If `s` is not a span's string form, or if the source file cannot be read, an error is
+    /// returned.
+    ///
+    /// # Panics
+    ///
+    /// `root` must be absolute, and every `root` passed within a process must be the same. (Source
+    /// files are read once and shared.)
     pub fn parse(root: &Rc<PathBuf>, s: &str) -> Result<Self> {
         let (source_file, start_line, start_column, end_line, end_column) = SPAN_RE
             .captures(s)
@@ -70,21 +115,28 @@ impl Span {
                     captures[5].to_owned(),
                 )
             })
-            .ok_or_else(|| anyhow!("Span has unexpected format"))?;
+            .ok_or_else(|| anyhow!("Span has unexpected format: `{s}`"))?;
         let start_line = start_line.parse::<usize>()?;
         let start_column = start_column.parse::<usize>()?;
         let end_line = end_line.parse::<usize>()?;
         let end_column = end_column.parse::<usize>()?;
-        let source_file = SourceFile::new(root.clone(), root.join(source_file))?;
+        // smoelius: Columns in a span's string form are 1-based. So a zero column would underflow.
+        let (Some(start_column), Some(end_column)) =
+            (start_column.checked_sub(1), end_column.checked_sub(1))
+        else {
+            bail!("Span has a zero column: `{s}`");
+        };
+        let source_file = SourceFile::new(root.clone(), root.join(&source_file))
+            .with_context(|| format!("Failed to read `{source_file}`"))?;
         Ok(Self {
             source_file,
             start: proc_macro2::LineColumn {
                 line: start_line,
-                column: start_column - 1,
+                column: start_column,
             },
             end: proc_macro2::LineColumn {
                 line: end_line,
-                column: end_column - 1,
+                column: end_column,
             },
         })
     }
@@ -143,8 +195,22 @@ impl Span {
         }
     }
 
-    /// Returns the spanned text.
+    /// Returns the spanned text, as it appears in the source file when the file was first read
+    ///
+    /// # Errors
+    ///
+    /// If the span does not lie within the source file (e.g., because the span was recorded for an
+    /// earlier version of the file), an error is returned.
     pub fn source_text(&self) -> Result<String> {
+        // smoelius: The offset calculator assumes that spans are within their files.
+        ensure!(
+            self.start <= self.end
+                && self.source_file.contains(self.start)
+                && self.source_file.contains(self.end),
+            "Span is not within `{}`: `{self}`",
+            self.source_file
+        );
+
         let contents = self.source_file.contents();
 
         // smoelius: Creating a new `Rewriter` here is just as silly as it is in `attempt_removal`
@@ -164,15 +230,40 @@ impl Span {
     }
 
     pub fn remove(&self) -> Result<(String, Backup)> {
+        self.remove_with_insertions(&[])
+    }
+
+    /// Like [`Span::remove`], but also makes `insertions`, which are at positions in the unmodified
+    /// file. Insertions strictly within the span are ignored.
+    pub(crate) fn remove_with_insertions(
+        &self,
+        insertions: &[(LineColumn, String)],
+    ) -> Result<(String, Backup)> {
         let backup = Backup::new(&*self.source_file)?;
 
-        let mut rewriter = Rewriter::with_offset_calculator(
-            self.source_file.contents(),
-            self.source_file.offset_calculator(),
-        );
+        let mut rewriter = self.rewriter();
+
+        // smoelius: The rewriter requires its rewrites to be ordered by position.
+        let mut insertions = insertions
+            .iter()
+            .filter(|(line_column, _)| *line_column <= self.start || self.end <= *line_column)
+            .collect::<Vec<_>>();
+        insertions.sort_by_key(|(line_column, _)| *line_column);
+        let (before, after) = insertions
+            .split_at(insertions.partition_point(|(line_column, _)| *line_column <= self.start));
+
+        for (line_column, insertion) in before {
+            self.source_file
+                .insert(&mut rewriter, *line_column, insertion);
+        }
 
         let text = rewriter.rewrite(self, "");
 
+        for (line_column, insertion) in after {
+            self.source_file
+                .insert(&mut rewriter, *line_column, insertion);
+        }
+
         let mut file = OpenOptions::new()
             .truncate(true)
             .write(true)
@@ -181,6 +272,23 @@ impl Span {
 
         Ok((text, backup))
     }
+
+    /// Returns the contents of the source file with the span removed, without modifying the file
+    #[must_use]
+    pub fn removed_contents(&self) -> String {
+        let mut rewriter = self.rewriter();
+
+        let _: String = rewriter.rewrite(self, "");
+
+        rewriter.contents()
+    }
+
+    fn rewriter(&self) -> Rewriter<'static> {
+        Rewriter::with_offset_calculator(
+            self.source_file.contents(),
+            self.source_file.offset_calculator(),
+        )
+    }
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -197,3 +305,27 @@ impl ToInternalSpan for proc_macro2::Span {
         }
     }
 }
+
+#[test]
+fn source_text_rejects_spans_outside_source_file() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let root = Rc::new(tempdir.path().canonicalize().unwrap());
+    std::fs::write(root.join("lib.rs"), "fn f() {\n    let x = 1;\n}\n").unwrap();
+
+    for s in ["lib.rs:2:0-2:15", "lib.rs:2:5", "missing.rs:1:1-1:1"] {
+        assert!(Span::parse(&root, s).is_err(), "{s}");
+    }
+
+    for s in [
+        "lib.rs:2:5-2:16",
+        "lib.rs:0:1-1:1",
+        "lib.rs:9:1-9:2",
+        "lib.rs:2:15-2:5",
+    ] {
+        let span = Span::parse(&root, s).unwrap();
+        assert!(span.source_text().is_err(), "{s}");
+    }
+
+    let span = Span::parse(&root, "lib.rs:2:15-3:2").unwrap();
+    assert_eq!("\n}", span.source_text().unwrap());
+}
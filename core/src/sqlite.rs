@@ -4,57 +4,281 @@
     allow(inconsistent_qualification)
 )]
 
-use crate::{util, warn, LightContext, Outcome, Span, WarnFlags, Warning};
-use anyhow::{bail, Context, Result};
-use diesel::{insert_into, prelude::*, sql_query};
-use git2::{Oid, Repository, RepositoryOpenFlags};
+use crate::{
+    candidates::Record, framework::StatementKind, truncation, util, warn, LightContext, Outcome,
+    SourceFile, Span, WarnFlags, Warning,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use diesel::{
+    connection::SimpleConnection,
+    define_sql_function, delete, insert_into,
+    prelude::*,
+    replace_into, select, sql_query,
+    sql_types::{Bool, Text},
+    upsert::excluded,
+};
+use git2::{Oid, Repository, RepositoryOpenFlags, StatusOptions};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::{
+    collections::BTreeMap,
     ffi::OsStr,
     fmt::Debug,
     include_str,
     iter::empty,
     path::{Path, PathBuf},
     rc::Rc,
+    time::Duration,
 };
+use strum::IntoEnumIterator;
 
 pub(crate) struct Sqlite {
+    root: Rc<PathBuf>,
+    project: String,
     connection: SqliteConnection,
     remote: Option<Remote>,
+    run_id: Option<i64>,
 }
 
 struct Remote {
-    pub repository: Repository,
+    pub workdir: PathBuf,
     pub url: String,
     pub oid: Oid,
 }
 
+// smoelius: `project` is the path of the project's root directory relative to the directory
+// containing the database. It is nonempty only for projects found by `--recurse-projects`.
+// `text` is the removed source text, exactly as it appears in the source file, for every framework.
+// `statement_normalized` is the text's tokens separated by single spaces, without comments, so that
+// removals that differ only in formatting can be matched. The text is tokenized according to the
+// source file's language (see `normalize`). It is null for removals produced by earlier versions of
+// Necessist.
+// `cause` is a failed removal's probable cause, as found in the tests' output (e.g., a panic
+// message or revert reason). It is null if no cause was found, or if the removal did not fail.
+// `covering_tests` is the number of tests that exercised the removal, and `failing_tests` is the
+// number of those that failed or timed out. `covering_tests` is null if no tests were run (e.g.,
+// for a skipped removal), and `failing_tests` is null if not every covering test was run (see
+// `--count-failing-tests`).
+// `raw_outcome` is the outcome before `timeout_is_failure` was applied. It is `timed-out` for a
+// removal whose tests timed out but whose `outcome` is `failed`, and null otherwise.
+diesel::table! {
+    removal (project, span) {
+        project -> Text,
+        span -> Text,
+        text -> Text,
+        outcome -> Text,
+        url -> Text,
+        run_id -> Nullable<BigInt>,
+        statement_normalized -> Nullable<Text>,
+        cause -> Nullable<Text>,
+        covering_tests -> Nullable<BigInt>,
+        failing_tests -> Nullable<BigInt>,
+        raw_outcome -> Nullable<Text>,
+    }
+}
+
+// smoelius: `run` holds one row for each time Necessist was run (but not, e.g., for `--dump`). A
+// removal's `run_id` refers to the run that produced it; it is null for removals produced by
+// earlier versions of Necessist. `commit_id` and `branch` are empty if the project is not in a git
+// repository, or if `HEAD` is not a commit or branch (respectively). `fuzz_seed` is the seed with
+// which a Foundry project's fuzz tests were run; it is null for other frameworks.
+// `truncated_outputs` is the number of command outputs that exceeded `max_output_bytes`, and
+// `truncated_bytes` is the number of bytes dropped from them. `toolchain` maps the programs of the
+// framework's toolchain to their versions, serialized as JSON; it is null if they were not
+// recorded.
 diesel::table! {
-    removal (span) {
+    run (id) {
+        id -> BigInt,
+        started_at -> Text,
+        commit_id -> Text,
+        branch -> Text,
+        dirty -> Bool,
+        fuzz_seed -> Nullable<Text>,
+        truncated_outputs -> BigInt,
+        truncated_bytes -> BigInt,
+        toolchain -> Nullable<Text>,
+    }
+}
+
+// smoelius: `archived_removal` holds removals discarded by `--reset`, so that the runs that
+// produced them can still be shown and compared. Only removals associated with a run are archived.
+diesel::table! {
+    archived_removal (project, span, run_id) {
+        project -> Text,
         span -> Text,
         text -> Text,
         outcome -> Text,
         url -> Text,
+        run_id -> Nullable<BigInt>,
+        statement_normalized -> Nullable<Text>,
+        cause -> Nullable<Text>,
+        covering_tests -> Nullable<BigInt>,
+        failing_tests -> Nullable<BigInt>,
+        raw_outcome -> Nullable<Text>,
+    }
+}
+
+// smoelius: `warning` holds the warnings that occurred during each run, so that, e.g., a missing
+// result can be correlated with its cause. `kind` is the warning's name as passed to `--allow`, and
+// `source` is the span or source file the warning concerns, if any.
+diesel::table! {
+    warning (id) {
+        id -> BigInt,
+        project -> Text,
+        run_id -> Nullable<BigInt>,
+        kind -> Text,
+        source -> Nullable<Text>,
+        message -> Text,
     }
 }
 
+// smoelius: `cost` holds, for each source file, how long its most recent dry run took and how long
+// its removals have taken in total, in seconds. They are used by `--order cheapest-first`.
+diesel::table! {
+    cost (project, source_file) {
+        project -> Text,
+        source_file -> Text,
+        dry_run -> Nullable<Double>,
+        removals -> Double,
+        n_removals -> BigInt,
+    }
+}
+
+// smoelius: `statement_kind` holds the kinds of candidates that are statements of special kinds,
+// e.g., Go statements that start goroutines. `kind` is the kind's name, e.g., `go`. A row can be
+// joined with `removal` on `project` and `span`.
+diesel::table! {
+    statement_kind (project, span) {
+        project -> Text,
+        span -> Text,
+        kind -> Text,
+    }
+}
+
+// smoelius: `candidate` holds, for each source file, the candidates found when the file was last
+// parsed, serialized as JSON, along with the number of tests in the file. `hash` is a hash of the
+// file's contents and configuration. The rows are used to avoid parsing unchanged files when
+// resuming.
+diesel::table! {
+    candidate (project, source_file) {
+        project -> Text,
+        source_file -> Text,
+        hash -> Text,
+        n_tests -> BigInt,
+        span_test_maps -> Text,
+    }
+}
+
+diesel::joinable!(removal -> run (run_id));
+diesel::joinable!(archived_removal -> run (run_id));
+
+diesel::allow_tables_to_appear_in_same_query!(archived_removal, removal, run);
+
+define_sql_function! {
+    fn last_insert_rowid() -> BigInt;
+}
+
+// smoelius: `aborted` holds candidates whose runs were interrupted (e.g., by Ctrl-C). A candidate
+// is removed from the table once it has an outcome.
+diesel::table! {
+    aborted (project, span) {
+        project -> Text,
+        span -> Text,
+        text -> Text,
+    }
+}
+
+// smoelius: `feature_set` holds the cargo feature arguments (as a JSON array) with which the
+// database's removals were produced. It has at most one row.
+diesel::table! {
+    feature_set (features) {
+        features -> Text,
+    }
+}
+
+#[derive(Debug, Queryable)]
+#[diesel(table_name = run)]
+pub(crate) struct Run {
+    pub id: i64,
+    pub started_at: String,
+    pub commit_id: String,
+    pub branch: String,
+    pub dirty: bool,
+    pub fuzz_seed: Option<String>,
+    pub truncated_outputs: i64,
+    pub truncated_bytes: i64,
+    pub toolchain: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = run)]
+struct NewRun {
+    pub commit_id: String,
+    pub branch: String,
+    pub dirty: bool,
+}
+
+#[derive(Debug, Insertable, Queryable, QueryableByName)]
+#[diesel(table_name = feature_set)]
+struct FeatureSet {
+    pub features: String,
+}
+
+#[derive(Debug, Queryable)]
+pub(crate) struct RecordedWarning {
+    pub kind: String,
+    pub source: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = warning)]
+struct NewWarning {
+    pub project: String,
+    pub run_id: Option<i64>,
+    pub kind: String,
+    pub source: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Insertable, Queryable)]
+#[diesel(table_name = aborted)]
+struct Aborted {
+    pub project: String,
+    pub span: String,
+    pub text: String,
+}
+
 #[derive(Debug, Insertable, Queryable)]
 #[diesel(table_name = removal)]
 struct Removal {
+    pub project: String,
     pub span: String,
     pub text: String,
     pub outcome: String,
     pub url: String,
+    pub run_id: Option<i64>,
+    pub statement_normalized: Option<String>,
+    pub cause: Option<String>,
+    pub covering_tests: Option<i64>,
+    pub failing_tests: Option<i64>,
+    pub raw_outcome: Option<String>,
 }
 
 impl Removal {
     fn into_internal_removal(self, root: &Rc<PathBuf>) -> Result<crate::Removal> {
         let Removal {
+            project: _,
             span,
             text,
             outcome,
             url: _,
+            run_id: _,
+            statement_normalized: _,
+            cause: _,
+            covering_tests: _,
+            failing_tests: _,
+            raw_outcome: _,
         } = self;
         let span = Span::parse(root, &span)?;
         let outcome = outcome.parse::<Outcome>()?;
@@ -66,15 +290,435 @@ impl Removal {
     }
 }
 
+#[allow(clippy::fn_params_excessive_bools, clippy::too_many_arguments)]
 pub(crate) fn init(
     context: &LightContext,
     root: &Path,
+    database_dir: &Path,
+    database_name: &str,
+    project: Option<&Path>,
+    job_slot: bool,
     dump: bool,
     reset: bool,
     resume: bool,
 ) -> Result<(Sqlite, Vec<crate::Removal>)> {
     let root = Rc::new(root.to_path_buf());
-    let path_buf = root.join("necessist.db");
+    let path_buf = database_dir.join(database_name);
+
+    // smoelius: For a project found by `--recurse-projects`, or for a job slot of `--isolate
+    // worktree`, the parent process has already checked whether the database exists (and, for a
+    // job slot, reset it). Moreover, the database may have been created by a sibling.
+    let exists = if project.is_some() || job_slot {
+        path_buf.try_exists()?
+    } else {
+        check_database_path(context, database_dir, database_name, dump, reset, resume)?
+    };
+
+    let project = project
+        .map(|project| project.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut connection = establish(&path_buf)?;
+
+    // smoelius: Dropping the tables would discard the results of the project's siblings, so only
+    // the project's own rows are deleted (below). In either case, removals are archived first, and
+    // `run` is kept, so that earlier runs can still be shown and compared.
+    if reset && exists && project.is_empty() && !job_slot {
+        drop_tables(&mut connection)?;
+    }
+
+    create_tables(&mut connection)?;
+
+    if reset && !project.is_empty() {
+        archive_removals(&mut connection, Some(&project))?;
+        delete(removal::table.filter(removal::project.eq(&project)))
+            .execute(&mut connection)
+            .with_context(|| "Failed to delete removals")?;
+        delete(aborted::table.filter(aborted::project.eq(&project)))
+            .execute(&mut connection)
+            .with_context(|| "Failed to delete aborted removals")?;
+    }
+
+    check_feature_set(context, &mut connection, &path_buf, dump)?;
+
+    let removals = if reset || !exists {
+        Vec::new()
+    } else {
+        let removals = removal::table
+            .filter(removal::project.eq(&project))
+            .load::<Removal>(&mut connection)?;
+        removals
+            .into_iter()
+            .map(|removal| removal.into_internal_removal(&root))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    // smoelius: URLs' paths are relative to the repository's top-level directory, which need not
+    // be `root` (e.g., if `--root` is a subdirectory of the repository). The top-level directory is
+    // canonicalized because source files' paths are.
+    let remote = Repository::open_ext(&*root, RepositoryOpenFlags::empty(), empty::<&OsStr>())
+        .ok()
+        .and_then(|repository| {
+            let workdir = repository
+                .workdir()
+                .and_then(|path| dunce::canonicalize(path).ok())?;
+            let url = repository
+                .find_remote("origin")
+                .ok()
+                .and_then(|origin| origin.url().map(str::to_owned))?;
+            let oid = repository.refname_to_id("HEAD").ok()?;
+            Some(Remote { workdir, url, oid })
+        });
+
+    let run_id = if dump {
+        None
+    } else {
+        Some(insert_run(&mut connection, &root)?)
+    };
+
+    Ok((
+        Sqlite {
+            root,
+            project,
+            connection,
+            remote,
+            run_id,
+        },
+        removals,
+    ))
+}
+
+/// Archives the database's removals and drops its tables, as `--reset` does. The parent of
+/// `--isolate worktree`'s job slots does this once, before the job slots open the database.
+pub(crate) fn reset(database_dir: &Path, database_name: &str) -> Result<()> {
+    let mut connection = establish(&database_dir.join(database_name))?;
+    drop_tables(&mut connection)
+}
+
+fn establish(path: &Path) -> Result<SqliteConnection> {
+    let database_url = format!("sqlite://{}", path.to_string_lossy());
+    let mut connection = SqliteConnection::establish(&database_url)?;
+
+    // smoelius: The job slots of `--isolate worktree` write to the database at the same time. A
+    // process that finds the database locked waits (up to a minute) for the lock rather than
+    // failing.
+    connection
+        .batch_execute("PRAGMA busy_timeout = 60000")
+        .with_context(|| "Failed to set busy timeout")?;
+
+    Ok(connection)
+}
+
+fn drop_tables(connection: &mut SqliteConnection) -> Result<()> {
+    // smoelius: The tables are brought up to date so that the removals can be archived.
+    create_tables(connection)?;
+    archive_removals(connection, None)?;
+    let sql = include_str!("drop_table_removal.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to drop sqlite database")?;
+    let sql = include_str!("drop_table_aborted.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to drop sqlite database")?;
+    let sql = include_str!("drop_table_feature_set.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to drop sqlite database")?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_lines)]
+fn create_tables(connection: &mut SqliteConnection) -> Result<()> {
+    let sql = include_str!("create_table_removal.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+
+    // smoelius: `aborted` may not exist in databases created by earlier versions of Necessist.
+    let sql = include_str!("create_table_aborted.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+
+    // smoelius: Similarly, `feature_set` may not exist in databases created by earlier versions.
+    let sql = include_str!("create_table_feature_set.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+
+    // smoelius: Similarly, `run`, `archived_removal`, `warning`, `cost`, `statement_kind`, and
+    // `candidate` may not exist in databases created by earlier versions.
+    let sql = include_str!("create_table_run.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+    let sql = include_str!("create_table_archived_removal.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+    let sql = include_str!("create_table_warning.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+    let sql = include_str!("create_table_cost.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+    let sql = include_str!("create_table_statement_kind.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+    let sql = include_str!("create_table_candidate.sql");
+    sql_query(sql)
+        .execute(connection)
+        .with_context(|| "Failed to create sqlite database")?;
+
+    // smoelius: And `removal` and `aborted` may lack a `project` column.
+    add_project_column(
+        connection,
+        "removal",
+        include_str!("create_table_removal.sql"),
+        "span, text, outcome, url",
+    )?;
+    add_project_column(
+        connection,
+        "aborted",
+        include_str!("create_table_aborted.sql"),
+        "span, text",
+    )?;
+
+    // smoelius: And `removal` may lack a `run_id` column.
+    if sql_query("SELECT run_id FROM removal LIMIT 0")
+        .execute(connection)
+        .is_err()
+    {
+        connection
+            .batch_execute("ALTER TABLE removal ADD COLUMN run_id INTEGER REFERENCES run (id)")
+            .with_context(|| "Failed to add `run_id` column to `removal`")?;
+    }
+
+    // smoelius: And `removal`'s `outcome` column may not allow `inconclusive`.
+    if !select(diesel::dsl::sql::<Bool>(
+        "EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'removal' AND sql \
+         LIKE '%inconclusive%')",
+    ))
+    .get_result::<bool>(connection)?
+    {
+        connection
+            .batch_execute(&format!(
+                "ALTER TABLE removal RENAME TO removal_old;
+                 {};
+                 INSERT INTO removal (project, span, text, outcome, url, run_id) SELECT project, \
+                 span, text, outcome, url, run_id FROM removal_old;
+                 DROP TABLE removal_old",
+                include_str!("create_table_removal.sql")
+            ))
+            .with_context(|| "Failed to allow `inconclusive` outcomes in `removal`")?;
+    }
+
+    // smoelius: And `run` may lack a `fuzz_seed` column.
+    if sql_query("SELECT fuzz_seed FROM run LIMIT 0")
+        .execute(connection)
+        .is_err()
+    {
+        connection
+            .batch_execute("ALTER TABLE run ADD COLUMN fuzz_seed TEXT")
+            .with_context(|| "Failed to add `fuzz_seed` column to `run`")?;
+    }
+
+    // smoelius: And `run` may lack `truncated_outputs` and `truncated_bytes` columns.
+    for column in ["truncated_outputs", "truncated_bytes"] {
+        if sql_query(format!("SELECT {column} FROM run LIMIT 0"))
+            .execute(connection)
+            .is_err()
+        {
+            connection
+                .batch_execute(&format!(
+                    "ALTER TABLE run ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0"
+                ))
+                .with_context(|| format!("Failed to add `{column}` column to `run`"))?;
+        }
+    }
+
+    // smoelius: And `run` may lack a `toolchain` column.
+    if sql_query("SELECT toolchain FROM run LIMIT 0")
+        .execute(connection)
+        .is_err()
+    {
+        connection
+            .batch_execute("ALTER TABLE run ADD COLUMN toolchain TEXT")
+            .with_context(|| "Failed to add `toolchain` column to `run`")?;
+    }
+
+    // smoelius: And `removal` and `archived_removal` may lack a `statement_normalized` column.
+    for table in ["removal", "archived_removal"] {
+        if sql_query(format!("SELECT statement_normalized FROM {table} LIMIT 0"))
+            .execute(connection)
+            .is_err()
+        {
+            connection
+                .batch_execute(&format!(
+                    "ALTER TABLE {table} ADD COLUMN statement_normalized TEXT"
+                ))
+                .with_context(|| {
+                    format!("Failed to add `statement_normalized` column to `{table}`")
+                })?;
+        }
+    }
+
+    // smoelius: And they may lack a `cause` column.
+    for table in ["removal", "archived_removal"] {
+        if sql_query(format!("SELECT cause FROM {table} LIMIT 0"))
+            .execute(connection)
+            .is_err()
+        {
+            connection
+                .batch_execute(&format!("ALTER TABLE {table} ADD COLUMN cause TEXT"))
+                .with_context(|| format!("Failed to add `cause` column to `{table}`"))?;
+        }
+    }
+
+    // smoelius: And they may lack `covering_tests` and `failing_tests` columns.
+    for table in ["removal", "archived_removal"] {
+        for column in ["covering_tests", "failing_tests"] {
+            if sql_query(format!("SELECT {column} FROM {table} LIMIT 0"))
+                .execute(connection)
+                .is_err()
+            {
+                connection
+                    .batch_execute(&format!("ALTER TABLE {table} ADD COLUMN {column} INTEGER"))
+                    .with_context(|| format!("Failed to add `{column}` column to `{table}`"))?;
+            }
+        }
+    }
+
+    // smoelius: And they may lack a `raw_outcome` column.
+    for table in ["removal", "archived_removal"] {
+        if sql_query(format!("SELECT raw_outcome FROM {table} LIMIT 0"))
+            .execute(connection)
+            .is_err()
+        {
+            connection
+                .batch_execute(&format!("ALTER TABLE {table} ADD COLUMN raw_outcome TEXT"))
+                .with_context(|| format!("Failed to add `raw_outcome` column to `{table}`"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the removals of `project` (or of all projects, if `project` is `None`) that are
+/// associated with a run to `archived_removal`.
+fn archive_removals(connection: &mut SqliteConnection, project: Option<&str>) -> Result<()> {
+    let sql = "INSERT OR REPLACE INTO archived_removal
+                   (project, span, text, outcome, url, run_id, statement_normalized, cause,
+                    covering_tests, failing_tests, raw_outcome)
+               SELECT project, span, text, outcome, url, run_id, statement_normalized, cause,
+                   covering_tests, failing_tests, raw_outcome
+               FROM removal
+               WHERE run_id IS NOT NULL";
+    if let Some(project) = project {
+        sql_query(format!("{sql} AND project = ?"))
+            .bind::<Text, _>(project)
+            .execute(connection)
+    } else {
+        sql_query(sql).execute(connection)
+    }
+    .with_context(|| "Failed to archive removals")?;
+    Ok(())
+}
+
+/// Records the start of a run, along with the state of the git repository containing `root` (if
+/// any), and returns the run's id.
+fn insert_run(connection: &mut SqliteConnection, root: &Path) -> Result<i64> {
+    let repository =
+        Repository::open_ext(root, RepositoryOpenFlags::empty(), empty::<&OsStr>()).ok();
+
+    let head = repository
+        .as_ref()
+        .and_then(|repository| repository.head().ok());
+
+    let commit_id = head
+        .as_ref()
+        .and_then(|head| head.peel_to_commit().ok())
+        .map(|commit| commit.id().to_string())
+        .unwrap_or_default();
+
+    let branch = head
+        .as_ref()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand())
+        .unwrap_or_default()
+        .to_owned();
+
+    // smoelius: Like `git describe --dirty`, untracked files do not make the repository dirty.
+    // Otherwise, the database itself could.
+    drop(head);
+
+    let dirty = repository
+        .map(|repository| {
+            let mut status_options = StatusOptions::new();
+            status_options.include_untracked(false);
+            repository
+                .statuses(Some(&mut status_options))
+                .map(|statuses| !statuses.is_empty())
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    insert_into(run::table)
+        .values(&NewRun {
+            commit_id,
+            branch,
+            dirty,
+        })
+        .execute(connection)
+        .with_context(|| "Failed to insert run")?;
+
+    select(last_insert_rowid())
+        .get_result(connection)
+        .with_context(|| "Failed to get run id")
+}
+
+/// Records the seed with which the current run's fuzz tests are run
+pub(crate) fn set_run_fuzz_seed(sqlite: &mut Sqlite, fuzz_seed: u64) -> Result<()> {
+    let Some(run_id) = sqlite.run_id else {
+        return Ok(());
+    };
+    diesel::update(run::table.filter(run::id.eq(run_id)))
+        .set(run::fuzz_seed.eq(fuzz_seed.to_string()))
+        .execute(&mut sqlite.connection)
+        .with_context(|| "Failed to record fuzz seed")?;
+    Ok(())
+}
+
+/// Records the versions of the current run's toolchain, keyed by program
+pub(crate) fn set_run_toolchain(
+    sqlite: &mut Sqlite,
+    toolchain: &BTreeMap<String, String>,
+) -> Result<()> {
+    let Some(run_id) = sqlite.run_id else {
+        return Ok(());
+    };
+    diesel::update(run::table.filter(run::id.eq(run_id)))
+        .set(run::toolchain.eq(serde_json::to_string(toolchain)?))
+        .execute(&mut sqlite.connection)
+        .with_context(|| "Failed to record toolchain")?;
+    Ok(())
+}
+
+/// Checks whether a database exists and whether that is consistent with `dump`, `reset`, and
+/// `resume`. Returns whether the database exists.
+pub(crate) fn check_database_path(
+    context: &LightContext,
+    database_dir: &Path,
+    database_name: &str,
+    dump: bool,
+    reset: bool,
+    resume: bool,
+) -> Result<bool> {
+    let path_buf = database_dir.join(database_name);
 
     let exists = path_buf.try_exists()?;
 
@@ -109,49 +753,84 @@ pub(crate) fn init(
         _ => (),
     }
 
-    let database_url = format!("sqlite://{}", path_buf.to_string_lossy());
-    let mut connection = SqliteConnection::establish(&database_url)?;
+    Ok(exists)
+}
 
-    if reset && exists {
-        let sql = include_str!("drop_table_removal.sql");
-        sql_query(sql)
-            .execute(&mut connection)
-            .with_context(|| "Failed to drop sqlite database")?;
+/// Recreates `table` with a `project` column if it does not already have one. `columns` are the
+/// table's other columns, which are copied.
+fn add_project_column(
+    connection: &mut SqliteConnection,
+    table: &str,
+    create_table_sql: &str,
+    columns: &str,
+) -> Result<()> {
+    if sql_query(format!("SELECT project FROM {table} LIMIT 0"))
+        .execute(connection)
+        .is_ok()
+    {
+        return Ok(());
     }
 
-    let removals = if reset || !exists {
-        let sql = include_str!("create_table_removal.sql");
-        sql_query(sql)
-            .execute(&mut connection)
-            .with_context(|| "Failed to create sqlite database")?;
-        Vec::new()
-    } else {
-        let removals = removal::table.load::<Removal>(&mut connection)?;
-        removals
-            .into_iter()
-            .map(|removal| removal.into_internal_removal(&root))
-            .collect::<Result<Vec<_>>>()?
-    };
+    connection
+        .batch_execute(&format!(
+            "ALTER TABLE {table} RENAME TO {table}_old;
+             {create_table_sql};
+             INSERT INTO {table} ({columns}) SELECT {columns} FROM {table}_old;
+             DROP TABLE {table}_old"
+        ))
+        .with_context(|| format!("Failed to add `project` column to `{table}`"))
+}
 
-    let remote = Repository::open_ext(&*root, RepositoryOpenFlags::empty(), empty::<&OsStr>())
-        .ok()
-        .and_then(|repository| {
-            let url_oid = repository
-                .find_remote("origin")
-                .ok()
-                .and_then(|origin| origin.url().map(str::to_owned))
-                .and_then(|url| repository.refname_to_id("HEAD").ok().map(|oid| (url, oid)));
-            url_oid.map(|(url, oid)| Remote {
-                repository,
-                url,
-                oid,
-            })
-        });
+/// Records the configured cargo features in the database, or verifies that they match the ones
+/// already recorded.
+fn check_feature_set(
+    context: &LightContext,
+    connection: &mut SqliteConnection,
+    path: &Path,
+    dump: bool,
+) -> Result<()> {
+    let features = serde_json::to_string(&context.config.rust.features)?;
 
-    Ok((Sqlite { connection, remote }, removals))
+    let recorded = feature_set::table.load::<FeatureSet>(connection)?;
+
+    if let Some(FeatureSet {
+        features: recorded_features,
+    }) = recorded.first()
+    {
+        if !dump && *recorded_features != features {
+            bail!(
+                r#"Features {features} differ from those with which "{}" was created ({recorded_features}); please pass --reset"#,
+                path.display()
+            );
+        }
+    } else if !dump {
+        insert_into(feature_set::table)
+            .values(&FeatureSet { features })
+            .execute(connection)
+            .with_context(|| "Failed to insert feature set")?;
+    }
+
+    Ok(())
 }
 
-pub(crate) fn insert(sqlite: &mut Sqlite, removal: &crate::Removal) -> Result<()> {
+/// Information recorded along with a removal
+#[derive(Default)]
+pub(crate) struct Details<'a> {
+    /// The probable cause of a failed removal, if one was found
+    pub cause: Option<&'a str>,
+    /// The number of tests that exercised the removal, if any were run
+    pub covering_tests: Option<usize>,
+    /// The number of those tests that failed or timed out, if every one was run
+    pub failing_tests: Option<usize>,
+    /// The outcome before `timeout_is_failure` was applied, if it was changed
+    pub raw_outcome: Option<Outcome>,
+}
+
+pub(crate) fn insert(
+    sqlite: &mut Sqlite,
+    removal: &crate::Removal,
+    details: &Details,
+) -> Result<()> {
     let crate::Removal {
         span,
         text,
@@ -159,14 +838,21 @@ pub(crate) fn insert(sqlite: &mut Sqlite, removal: &crate::Removal) -> Result<()
     } = removal;
 
     let removal = Removal {
+        project: sqlite.project.clone(),
         span: span.to_string(),
         text: text.clone(),
         outcome: outcome.to_string(),
         url: sqlite
             .remote
             .as_ref()
-            .map(|remote| url_from_span(remote, span))
+            .and_then(|remote| url_from_span(remote, span))
             .unwrap_or_default(),
+        run_id: sqlite.run_id,
+        statement_normalized: Some(normalize(span.source_file.extension(), text)),
+        cause: details.cause.map(ToOwned::to_owned),
+        covering_tests: details.covering_tests.map(i64::try_from).transpose()?,
+        failing_tests: details.failing_tests.map(i64::try_from).transpose()?,
+        raw_outcome: details.raw_outcome.as_ref().map(ToString::to_string),
     };
 
     insert_into(removal::table)
@@ -174,15 +860,617 @@ pub(crate) fn insert(sqlite: &mut Sqlite, removal: &crate::Removal) -> Result<()
         .execute(&mut sqlite.connection)
         .with_context(|| format!("Failed to insert {removal:?}"))?;
 
+    delete(
+        aborted::table
+            .filter(aborted::project.eq(&removal.project))
+            .filter(aborted::span.eq(&removal.span)),
+    )
+    .execute(&mut sqlite.connection)
+    .with_context(|| format!("Failed to delete aborted {:?}", removal.span))?;
+
+    insert_warnings(sqlite)
+}
+
+/// Records the kinds of the statements in `statement_kinds`, replacing any earlier records.
+pub(crate) fn insert_statement_kinds<'a>(
+    sqlite: &mut Sqlite,
+    statement_kinds: impl Iterator<Item = (&'a Span, &'a StatementKind)>,
+) -> Result<()> {
+    let rows = statement_kinds
+        .map(|(span, kind)| {
+            (
+                statement_kind::project.eq(&sqlite.project),
+                statement_kind::span.eq(span.to_string()),
+                statement_kind::kind.eq(kind.to_string()),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    replace_into(statement_kind::table)
+        .values(&rows)
+        .execute(&mut sqlite.connection)
+        .with_context(|| "Failed to insert statement kinds")?;
+
+    Ok(())
+}
+
+pub(crate) fn insert_aborted(sqlite: &mut Sqlite, span: &Span, text: &str) -> Result<()> {
+    let aborted = Aborted {
+        project: sqlite.project.clone(),
+        span: span.to_string(),
+        text: text.to_owned(),
+    };
+
+    replace_into(aborted::table)
+        .values(&aborted)
+        .execute(&mut sqlite.connection)
+        .with_context(|| format!("Failed to insert {aborted:?}"))?;
+
+    insert_warnings(sqlite)
+}
+
+/// Records the warnings that have occurred since the last call, along with the number of outputs
+/// truncated so far, unless the database was opened with `--dump`.
+pub(crate) fn insert_warnings(sqlite: &mut Sqlite) -> Result<()> {
+    let records = crate::warn::take_records();
+
+    let Some(run_id) = sqlite.run_id else {
+        return Ok(());
+    };
+
+    let warnings = records
+        .into_iter()
+        .map(
+            |crate::warn::Record {
+                 warning,
+                 source,
+                 msg,
+             }| NewWarning {
+                project: sqlite.project.clone(),
+                run_id: Some(run_id),
+                kind: warning.to_string(),
+                source,
+                message: msg,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    insert_into(warning::table)
+        .values(&warnings)
+        .execute(&mut sqlite.connection)
+        .with_context(|| "Failed to insert warnings")?;
+
+    let truncation::Totals { n_outputs, n_bytes } = truncation::totals();
+
+    if n_outputs != 0 {
+        diesel::update(run::table.filter(run::id.eq(run_id)))
+            .set((
+                run::truncated_outputs.eq(i64::try_from(n_outputs)?),
+                run::truncated_bytes.eq(i64::try_from(n_bytes)?),
+            ))
+            .execute(&mut sqlite.connection)
+            .with_context(|| "Failed to record truncated outputs")?;
+    }
+
     Ok(())
 }
 
+pub(crate) fn aborted(sqlite: &mut Sqlite) -> Result<Vec<(Span, String)>> {
+    let aborted = aborted::table
+        .filter(aborted::project.eq(&sqlite.project))
+        .load::<Aborted>(&mut sqlite.connection)?;
+    aborted
+        .into_iter()
+        .map(|Aborted { span, text, .. }| Ok((Span::parse(&sqlite.root, &span)?, text)))
+        .collect()
+}
+
 static SSH_RE: Lazy<Regex> = Lazy::new(|| {
     #[allow(clippy::unwrap_used)]
     Regex::new(r"^[^@]*@([^:]*):(.*)$").unwrap()
 });
 
-fn url_from_span(remote: &Remote, span: &Span) -> String {
+/// Returns the run selected by `run`, which is either a run id or `latest`.
+pub(crate) fn run(sqlite: &mut Sqlite, run: &str) -> Result<Run> {
+    let runs = if run == "latest" {
+        run::table
+            .order(run::id.desc())
+            .limit(1)
+            .load::<Run>(&mut sqlite.connection)?
+    } else {
+        let id = run
+            .parse::<i64>()
+            .with_context(|| format!("Failed to parse run id `{run}`"))?;
+        run::table
+            .filter(run::id.eq(id))
+            .load::<Run>(&mut sqlite.connection)?
+    };
+    runs.into_iter().next().ok_or_else(|| {
+        if run == "latest" {
+            anyhow!("Found no runs in the database")
+        } else {
+            anyhow!("Found no run with id {run}")
+        }
+    })
+}
+
+/// Returns the project's removals produced by the run with id `run_id`, including archived ones.
+pub(crate) fn run_removals(sqlite: &mut Sqlite, run_id: i64) -> Result<Vec<crate::Removal>> {
+    let removals = removal::table
+        .filter(removal::project.eq(&sqlite.project))
+        .filter(removal::run_id.eq(run_id))
+        .load::<Removal>(&mut sqlite.connection)?;
+    let archived_removals = archived_removal::table
+        .filter(archived_removal::project.eq(&sqlite.project))
+        .filter(archived_removal::run_id.eq(run_id))
+        .load::<Removal>(&mut sqlite.connection)?;
+    removals
+        .into_iter()
+        .chain(archived_removals)
+        .map(|removal| removal.into_internal_removal(&sqlite.root))
+        .collect()
+}
+
+/// Returns the probable causes of the project's failed removals produced by the run with id
+/// `run_id`, including archived ones, keyed by span
+pub(crate) fn run_causes(sqlite: &mut Sqlite, run_id: i64) -> Result<BTreeMap<String, String>> {
+    let causes = removal::table
+        .filter(removal::project.eq(&sqlite.project))
+        .filter(removal::run_id.eq(run_id))
+        .filter(removal::cause.is_not_null())
+        .select((removal::span, removal::cause.assume_not_null()))
+        .load::<(String, String)>(&mut sqlite.connection)?;
+    let archived_causes = archived_removal::table
+        .filter(archived_removal::project.eq(&sqlite.project))
+        .filter(archived_removal::run_id.eq(run_id))
+        .filter(archived_removal::cause.is_not_null())
+        .select((
+            archived_removal::span,
+            archived_removal::cause.assume_not_null(),
+        ))
+        .load::<(String, String)>(&mut sqlite.connection)?;
+    Ok(causes.into_iter().chain(archived_causes).collect())
+}
+
+/// Returns the numbers of covering and failing tests of the project's removals produced by the run
+/// with id `run_id`, including archived ones, keyed by span. Only removals for which both numbers
+/// were recorded are included.
+pub(crate) fn run_failing_tests(
+    sqlite: &mut Sqlite,
+    run_id: i64,
+) -> Result<BTreeMap<String, (i64, i64)>> {
+    let failing_tests = removal::table
+        .filter(removal::project.eq(&sqlite.project))
+        .filter(removal::run_id.eq(run_id))
+        .filter(removal::covering_tests.is_not_null())
+        .filter(removal::failing_tests.is_not_null())
+        .select((
+            removal::span,
+            (
+                removal::covering_tests.assume_not_null(),
+                removal::failing_tests.assume_not_null(),
+            ),
+        ))
+        .load::<(String, (i64, i64))>(&mut sqlite.connection)?;
+    let archived_failing_tests = archived_removal::table
+        .filter(archived_removal::project.eq(&sqlite.project))
+        .filter(archived_removal::run_id.eq(run_id))
+        .filter(archived_removal::covering_tests.is_not_null())
+        .filter(archived_removal::failing_tests.is_not_null())
+        .select((
+            archived_removal::span,
+            (
+                archived_removal::covering_tests.assume_not_null(),
+                archived_removal::failing_tests.assume_not_null(),
+            ),
+        ))
+        .load::<(String, (i64, i64))>(&mut sqlite.connection)?;
+    Ok(failing_tests
+        .into_iter()
+        .chain(archived_failing_tests)
+        .collect())
+}
+
+/// Returns the warnings that occurred during the run with id `run_id`, in the order they occurred.
+pub(crate) fn run_warnings(sqlite: &mut Sqlite, run_id: i64) -> Result<Vec<RecordedWarning>> {
+    warning::table
+        .filter(warning::project.eq(&sqlite.project))
+        .filter(warning::run_id.eq(run_id))
+        .order(warning::id)
+        .select((warning::kind, warning::source, warning::message))
+        .load::<RecordedWarning>(&mut sqlite.connection)
+        .map_err(Into::into)
+}
+
+/// Returns the start times of the runs that produced the project's removals, keyed by span.
+/// Removals produced by earlier versions of Necessist, which have no run, are omitted.
+pub(crate) fn removal_started_ats(sqlite: &mut Sqlite) -> Result<BTreeMap<String, String>> {
+    let started_ats = removal::table
+        .inner_join(run::table)
+        .filter(removal::project.eq(&sqlite.project))
+        .select((removal::span, run::started_at))
+        .load::<(String, String)>(&mut sqlite.connection)?;
+    Ok(started_ats.into_iter().collect())
+}
+
+/// Records that the dry run of `source_file` took `duration`, replacing any earlier dry run's.
+pub(crate) fn record_dry_run(
+    sqlite: &mut Sqlite,
+    source_file: &SourceFile,
+    duration: Duration,
+) -> Result<()> {
+    insert_into(cost::table)
+        .values((
+            cost::project.eq(&sqlite.project),
+            cost::source_file.eq(source_file.to_string()),
+            cost::dry_run.eq(duration.as_secs_f64()),
+        ))
+        .on_conflict((cost::project, cost::source_file))
+        .do_update()
+        .set(cost::dry_run.eq(excluded(cost::dry_run)))
+        .execute(&mut sqlite.connection)
+        .with_context(|| format!("Failed to record dry run of {source_file:?}"))?;
+    Ok(())
+}
+
+/// Records that a removal in `source_file` took `duration`.
+pub(crate) fn record_removal(
+    sqlite: &mut Sqlite,
+    source_file: &SourceFile,
+    duration: Duration,
+) -> Result<()> {
+    insert_into(cost::table)
+        .values((
+            cost::project.eq(&sqlite.project),
+            cost::source_file.eq(source_file.to_string()),
+            cost::removals.eq(duration.as_secs_f64()),
+            cost::n_removals.eq(1),
+        ))
+        .on_conflict((cost::project, cost::source_file))
+        .do_update()
+        .set((
+            cost::removals.eq(cost::removals + excluded(cost::removals)),
+            cost::n_removals.eq(cost::n_removals + 1),
+        ))
+        .execute(&mut sqlite.connection)
+        .with_context(|| format!("Failed to record removal in {source_file:?}"))?;
+    Ok(())
+}
+
+/// Returns, for each source file with recorded costs, the estimated time to run one of its
+/// candidates: the average time taken by its past removals, or, if there were none, the time
+/// taken by its most recent dry run. Source files are identified by their paths relative to the
+/// project root.
+pub(crate) fn estimates(sqlite: &mut Sqlite) -> Result<BTreeMap<String, Duration>> {
+    let costs = cost::table
+        .filter(cost::project.eq(&sqlite.project))
+        .select((
+            cost::source_file,
+            cost::dry_run,
+            cost::removals,
+            cost::n_removals,
+        ))
+        .load::<(String, Option<f64>, f64, i64)>(&mut sqlite.connection)?;
+    Ok(costs
+        .into_iter()
+        .filter_map(|(source_file, dry_run, removals, n_removals)| {
+            #[allow(clippy::cast_precision_loss)]
+            let secs = if n_removals > 0 {
+                removals / n_removals as f64
+            } else {
+                dry_run?
+            };
+            Some((source_file, Duration::from_secs_f64(secs)))
+        })
+        .collect())
+}
+
+/// Returns the project's candidate records, keyed by source file.
+pub(crate) fn load_candidates(sqlite: &mut Sqlite) -> Result<BTreeMap<PathBuf, Record>> {
+    let rows = candidate::table
+        .filter(candidate::project.eq(&sqlite.project))
+        .select((
+            candidate::source_file,
+            candidate::hash,
+            candidate::n_tests,
+            candidate::span_test_maps,
+        ))
+        .load::<(String, String, i64, String)>(&mut sqlite.connection)?;
+    rows.into_iter()
+        .map(|(source_file, hash, n_tests, span_test_maps)| {
+            Ok((
+                sqlite.root.join(source_file),
+                Record {
+                    hash,
+                    n_tests: usize::try_from(n_tests)?,
+                    span_test_maps,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Stores candidate records, replacing any existing records for the same source files.
+pub(crate) fn insert_candidates(
+    sqlite: &mut Sqlite,
+    records: &BTreeMap<PathBuf, Record>,
+) -> Result<()> {
+    let rows = records
+        .iter()
+        .map(|(source_file, record)| {
+            let source_file = util::strip_prefix(source_file, &sqlite.root)?;
+            Ok((
+                candidate::project.eq(&sqlite.project),
+                candidate::source_file.eq(source_file.to_string_lossy().into_owned()),
+                candidate::hash.eq(&record.hash),
+                candidate::n_tests.eq(i64::try_from(record.n_tests)?),
+                candidate::span_test_maps.eq(&record.span_test_maps),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    replace_into(candidate::table)
+        .values(&rows)
+        .execute(&mut sqlite.connection)
+        .with_context(|| "Failed to insert candidates")?;
+
+    Ok(())
+}
+
+/// Deletes all but the `keep` most recent runs, along with the removals they produced. Removals
+/// not associated with any run are left alone. Returns the numbers of runs and removals deleted.
+pub(crate) fn prune(sqlite: &mut Sqlite, keep: usize) -> Result<(usize, usize)> {
+    let kept = run::table
+        .select(run::id)
+        .order(run::id.desc())
+        .limit(i64::try_from(keep)?)
+        .load::<i64>(&mut sqlite.connection)?;
+
+    sqlite.connection.transaction(|connection| {
+        // smoelius: A null `run_id` is neither equal nor unequal to anything, so removals not
+        // associated with any run are not deleted.
+        let n_removals = delete(removal::table.filter(removal::run_id.ne_all(&kept)))
+            .execute(connection)
+            .with_context(|| "Failed to delete removals")?
+            + delete(archived_removal::table.filter(archived_removal::run_id.ne_all(&kept)))
+                .execute(connection)
+                .with_context(|| "Failed to delete archived removals")?;
+        delete(warning::table.filter(warning::run_id.ne_all(&kept)))
+            .execute(connection)
+            .with_context(|| "Failed to delete warnings")?;
+        let n_runs = delete(run::table.filter(run::id.ne_all(&kept)))
+            .execute(connection)
+            .with_context(|| "Failed to delete runs")?;
+        Ok((n_runs, n_removals))
+    })
+}
+
+/// Merges the contents of the databases `inputs` into a new database `output`. The inputs' runs
+/// are renumbered so that their ids do not collide. If several inputs have a removal for the same
+/// span, the one with the most severe outcome is kept, and among those, the one produced by the
+/// most recent run. Returns the numbers of runs merged and of removals in the output.
+pub(crate) fn merge(inputs: &[PathBuf], output: &Path) -> Result<(usize, usize)> {
+    if output.try_exists()? {
+        bail!("{output:?} already exists");
+    }
+
+    for input in inputs {
+        if !input.try_exists()? {
+            bail!("{input:?} does not exist");
+        }
+        // smoelius: Bring the input's tables up to date, so that they have the same columns as
+        // the output's.
+        let mut connection =
+            SqliteConnection::establish(&format!("sqlite://{}", input.to_string_lossy()))?;
+        create_tables(&mut connection)?;
+    }
+
+    let mut connection =
+        SqliteConnection::establish(&format!("sqlite://{}", output.to_string_lossy()))?;
+    create_tables(&mut connection)?;
+
+    let mut n_runs = 0;
+
+    for input in inputs {
+        sql_query("ATTACH DATABASE ? AS input")
+            .bind::<Text, _>(input.to_string_lossy())
+            .execute(&mut connection)
+            .with_context(|| format!("Failed to attach {input:?}"))?;
+
+        connection.transaction(|connection| {
+            merge_feature_set(connection, input)?;
+
+            let offset = run::table
+                .select(diesel::dsl::max(run::id))
+                .get_result::<Option<i64>>(connection)?
+                .unwrap_or_default();
+
+            n_runs += sql_query(format!(
+                "INSERT INTO run
+                     (id, started_at, commit_id, branch, dirty, fuzz_seed, truncated_outputs,
+                      truncated_bytes, toolchain)
+                 SELECT id + {offset}, started_at, commit_id, branch, dirty, fuzz_seed,
+                     truncated_outputs, truncated_bytes, toolchain
+                 FROM input.run"
+            ))
+            .execute(connection)
+            .with_context(|| format!("Failed to merge runs of {input:?}"))?;
+
+            // smoelius: The `WHERE true` resolves a parsing ambiguity. See:
+            // https://www.sqlite.org/lang_upsert.html#parsing_ambiguity
+            sql_query(format!(
+                "INSERT INTO removal
+                     (project, span, text, outcome, url, run_id, statement_normalized, cause,
+                      covering_tests, failing_tests, raw_outcome)
+                 SELECT project, span, text, outcome, url, run_id + {offset}, statement_normalized,
+                     cause, covering_tests, failing_tests, raw_outcome
+                 FROM input.removal
+                 WHERE true
+                 ON CONFLICT (project, span) DO UPDATE
+                 SET text = excluded.text,
+                     outcome = excluded.outcome,
+                     url = excluded.url,
+                     run_id = excluded.run_id,
+                     statement_normalized = excluded.statement_normalized,
+                     cause = excluded.cause,
+                     covering_tests = excluded.covering_tests,
+                     failing_tests = excluded.failing_tests,
+                     raw_outcome = excluded.raw_outcome
+                 WHERE ({new_severity}, {new_started_at}) >= ({old_severity}, {old_started_at})",
+                new_severity = severity("excluded.outcome"),
+                old_severity = severity("removal.outcome"),
+                new_started_at = started_at("excluded.run_id"),
+                old_started_at = started_at("removal.run_id"),
+            ))
+            .execute(connection)
+            .with_context(|| format!("Failed to merge removals of {input:?}"))?;
+
+            connection
+                .batch_execute(&format!(
+                    "INSERT OR REPLACE INTO archived_removal
+                         (project, span, text, outcome, url, run_id, statement_normalized, cause,
+                          covering_tests, failing_tests, raw_outcome)
+                     SELECT project, span, text, outcome, url, run_id + {offset},
+                         statement_normalized, cause, covering_tests, failing_tests, raw_outcome
+                     FROM input.archived_removal;
+                     INSERT INTO warning (project, run_id, kind, source, message)
+                     SELECT project, run_id + {offset}, kind, source, message FROM input.warning
+                     ORDER BY id;
+                     INSERT OR REPLACE INTO cost SELECT * FROM input.cost;
+                     INSERT OR REPLACE INTO statement_kind SELECT * FROM input.statement_kind;
+                     INSERT OR REPLACE INTO candidate SELECT * FROM input.candidate;
+                     INSERT OR REPLACE INTO aborted (project, span, text)
+                     SELECT project, span, text FROM input.aborted;
+                     DELETE FROM aborted WHERE EXISTS
+                         (SELECT 1 FROM removal
+                          WHERE removal.project = aborted.project AND removal.span = aborted.span)"
+                ))
+                .with_context(|| format!("Failed to merge {input:?}"))?;
+
+            Ok::<_, anyhow::Error>(())
+        })?;
+
+        sql_query("DETACH DATABASE input")
+            .execute(&mut connection)
+            .with_context(|| format!("Failed to detach {input:?}"))?;
+    }
+
+    let n_removals = removal::table.count().get_result::<i64>(&mut connection)?;
+
+    Ok((n_runs, usize::try_from(n_removals)?))
+}
+
+/// Returns an SQL expression for the severity of the outcome in `column`, i.e., the outcome's
+/// position in the declaration of [`Outcome`]
+fn severity(column: &str) -> String {
+    let cases = Outcome::iter()
+        .enumerate()
+        .map(|(i, outcome)| format!(" WHEN '{outcome}' THEN {i}"))
+        .collect::<String>();
+    format!("CASE {column}{cases} END")
+}
+
+/// Returns an SQL expression for when the run whose id is in `column` started. Removals not
+/// associated with any run are considered older than all others.
+fn started_at(column: &str) -> String {
+    format!("COALESCE((SELECT started_at FROM run WHERE id = {column}), '')")
+}
+
+/// Copies the feature set of the attached database `input` to the output, or verifies that it
+/// matches the one already there.
+fn merge_feature_set(connection: &mut SqliteConnection, input: &Path) -> Result<()> {
+    let recorded = feature_set::table
+        .select(feature_set::features)
+        .load::<String>(connection)?;
+    let features =
+        sql_query("SELECT features FROM input.feature_set").load::<FeatureSet>(connection)?;
+    for FeatureSet { features } in features {
+        if let Some(recorded_features) = recorded.first() {
+            if *recorded_features != features {
+                bail!(
+                    "Features {features} of {input:?} differ from those of the databases merged \
+                     before it ({recorded_features})"
+                );
+            }
+        } else {
+            insert_into(feature_set::table)
+                .values(&FeatureSet { features })
+                .execute(connection)
+                .with_context(|| "Failed to insert feature set")?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns `text`'s tokens separated by single spaces, with comments removed. `extension` is that
+/// of the source file from which `text` was removed, and determines how `text` is tokenized. Text
+/// that cannot be tokenized (e.g., because it contains an unterminated string) has its whitespace
+/// collapsed instead.
+fn normalize(extension: Option<&OsStr>, text: &str) -> String {
+    let normalized = if extension == Some(OsStr::new("rs")) {
+        text.parse::<proc_macro2::TokenStream>()
+            .ok()
+            .map(|token_stream| token_stream.to_string())
+    } else {
+        c_like_tokens(text).map(|tokens| tokens.join(" "))
+    };
+    normalized.unwrap_or_else(|| text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Splits `text` into tokens according to the lexical rules that Go, Solidity, and TypeScript
+/// share: comments begin with `//` or are enclosed in `/*` and `*/`, strings are enclosed in `"`,
+/// `'`, or `` ` `` and may contain `\`-escaped characters, and words consist of alphanumerics,
+/// `_`, and `$`. Every other character that is not whitespace is a token of its own. Returns `None`
+/// if a string or comment is unterminated.
+fn c_like_tokens(text: &str) -> Option<Vec<&str>> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '/' && chars.next_if(|&(_, next)| next == '/').is_some() {
+            chars.find(|&(_, c)| c == '\n');
+            continue;
+        }
+        if c == '/' && chars.next_if(|&(_, next)| next == '*').is_some() {
+            let mut prev = None;
+            chars.find(|&(_, c)| prev.replace(c) == Some('*') && c == '/')?;
+            continue;
+        }
+        let end = if matches!(c, '"' | '\'' | '`') {
+            let mut escaped = false;
+            let (i, _) = chars.find(|&(_, next)| {
+                let found = !escaped && next == c;
+                escaped = !escaped && next == '\\';
+                found
+            })?;
+            i + c.len_utf8()
+        } else if is_word_char(c) {
+            let mut end = start + c.len_utf8();
+            while let Some((i, next)) = chars.next_if(|&(_, next)| is_word_char(next)) {
+                end = i + next.len_utf8();
+            }
+            end
+        } else {
+            start + c.len_utf8()
+        };
+        tokens.push(&text[start..end]);
+    }
+    Some(tokens)
+}
+
+fn url_from_span(remote: &Remote, span: &Span) -> Option<String> {
     let base_url = remote.url.strip_suffix(".git").unwrap_or(&remote.url);
 
     let base_url = if let Some(captures) = SSH_RE.captures(base_url) {
@@ -192,20 +1480,80 @@ fn url_from_span(remote: &Remote, span: &Span) -> String {
         base_url.to_owned()
     };
 
-    #[allow(clippy::unwrap_used)]
-    let path = remote
-        .repository
-        .workdir()
-        .and_then(|path| util::strip_prefix(&span.source_file, path).ok())
-        .unwrap();
-
-    base_url
-        + "/blob/"
-        + &remote.oid.to_string()
-        + "/"
-        + &path.to_string_lossy()
-        + "#L"
-        + &span.start.line.to_string()
-        + "-L"
-        + &span.end.line.to_string()
+    // smoelius: A source file outside of the repository's working tree (e.g., one reached through
+    // a symlink) has no URL.
+    let path = util::strip_prefix(&span.source_file, &remote.workdir).ok()?;
+
+    // smoelius: URLs' path components are separated by `/` regardless of the platform.
+    let path = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    Some(
+        base_url
+            + "/blob/"
+            + &remote.oid.to_string()
+            + "/"
+            + &path
+            + "#L"
+            + &span.start.line.to_string()
+            + "-L"
+            + &span.end.line.to_string(),
+    )
+}
+
+#[test]
+fn normalize_rust() {
+    assert_eq!(
+        "assert_eq ! (x , 1) ;",
+        normalize(
+            Some(OsStr::new("rs")),
+            "assert_eq!(\n    x,\n    1 // comment\n);"
+        )
+    );
+}
+
+#[test]
+fn normalize_go() {
+    let extension = Some(OsStr::new("go"));
+    assert_eq!(
+        "u : = \"http://example.com\"",
+        normalize(extension, "u := \"http://example.com\" // comment")
+    );
+    assert_eq!(
+        "fmt . Println ( `raw  string` )",
+        normalize(extension, "fmt.Println(`raw  string`)")
+    );
+}
+
+#[test]
+fn normalize_solidity() {
+    let extension = Some(OsStr::new("sol"));
+    assert_eq!(
+        normalize(extension, "token.approve(spender, amount);"),
+        normalize(
+            extension,
+            "token.approve(\n    spender,\n    amount /* all */\n);"
+        )
+    );
+    assert_eq!(
+        "vm . expectRevert ( \"it's  \\\"bad\\\"\" ) ;",
+        normalize(extension, "vm.expectRevert(\"it's  \\\"bad\\\"\");")
+    );
+}
+
+#[test]
+fn normalize_typescript() {
+    let extension = Some(OsStr::new("ts"));
+    assert_eq!(
+        "this . # count + = 1 ;",
+        normalize(extension, "this.#count += 1; /* comment */")
+    );
+    assert_eq!(
+        "expect ( x ) . to . equal ( 'a  b' ) ;",
+        normalize(extension, "expect(x)\n  .to.equal('a  b');")
+    );
+    assert_eq!("foo( 'a b", normalize(extension, "foo(  'a   b"));
 }
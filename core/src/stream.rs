@@ -0,0 +1,81 @@
+use crate::Removal;
+use anyhow::{ensure, Context, Result};
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+};
+
+/// A destination for `--stream-results`
+pub(crate) enum ResultsStream {
+    File(File),
+    Url(String),
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    span: String,
+    text: &'a str,
+    outcome: String,
+}
+
+impl ResultsStream {
+    /// Interprets `target` as a URL if it begins with `http://` or `https://`, and as a file to
+    /// append to otherwise.
+    pub fn new(target: &str) -> Result<Self> {
+        if target.starts_with("http://") || target.starts_with("https://") {
+            return Ok(Self::Url(target.to_owned()));
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(target)
+            .with_context(|| format!("Failed to open {target:?}"))?;
+
+        Ok(Self::File(file))
+    }
+
+    /// Writes `removal` as a single line of JSON, or POSTs it as a JSON object.
+    pub fn send(&mut self, removal: &Removal) -> Result<()> {
+        let Removal {
+            span,
+            text,
+            outcome,
+        } = removal;
+
+        let json = serde_json::to_string(&Record {
+            span: span.to_string(),
+            text,
+            outcome: outcome.to_string(),
+        })?;
+
+        match self {
+            Self::File(file) => {
+                writeln!(file, "{json}")?;
+                file.flush().map_err(Into::into)
+            }
+            Self::Url(url) => post(url, &json),
+        }
+    }
+}
+
+fn post(url: &str, json: &str) -> Result<()> {
+    let mut headers = curl::easy::List::new();
+    headers.append("Content-Type: application/json")?;
+
+    let mut handle = curl::easy::Easy::new();
+    handle.url(url)?;
+    handle.http_headers(headers)?;
+    handle.post(true)?;
+    handle.post_fields_copy(json.as_bytes())?;
+    handle.perform()?;
+
+    let response_code = handle.response_code()?;
+    ensure!(
+        (200..300).contains(&response_code),
+        "POST to {url} returned {response_code}"
+    );
+
+    Ok(())
+}
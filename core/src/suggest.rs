@@ -0,0 +1,179 @@
+//! Support for `--suggest-ignores`, which looks for calls whose removals passed in every test that
+//! exercised them (e.g., calls to a project's logging helpers), and suggests configuration entries
+//! to ignore them.
+
+use crate::{LightContext, Outcome, Removal};
+use indexmap::IndexSet;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The minimum number of tests in which a call's removals must have passed for the call to be
+/// suggested
+const MIN_TESTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum List {
+    Functions,
+    Macros,
+    Methods,
+}
+
+impl List {
+    fn key(self) -> &'static str {
+        match self {
+            List::Functions => "ignored_functions",
+            List::Macros => "ignored_macros",
+            List::Methods => "ignored_methods",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    n_removals: usize,
+    all_passed: bool,
+    tests: BTreeSet<String>,
+}
+
+/// Outcomes tallied by `--suggest-ignores`, keyed by the list to which a call could be added and
+/// the call's name
+#[derive(Default)]
+pub(crate) struct Suggestions {
+    calls: BTreeMap<(List, String), Tally>,
+}
+
+impl Suggestions {
+    pub fn push(&mut self, test_names: &IndexSet<String>, removal: &Removal, diagnostic: bool) {
+        // smoelius: Diagnostic calls are expected to pass, and already have their own option.
+        if diagnostic {
+            return;
+        }
+        let Some((list, name)) = call(&removal.text) else {
+            return;
+        };
+        let tally = self.calls.entry((list, name)).or_insert_with(|| Tally {
+            all_passed: true,
+            ..Default::default()
+        });
+        tally.n_removals += 1;
+        tally.all_passed &= removal.outcome == Outcome::Passed;
+        tally.tests.extend(test_names.iter().cloned());
+    }
+
+    /// Outputs, for each list, the names of the calls whose removals passed in at least
+    /// `MIN_TESTS` tests and never produced another outcome, as a TOML array
+    pub fn print(self, context: &LightContext) {
+        let mut lists = BTreeMap::<List, Vec<String>>::new();
+        for ((list, name), tally) in self.calls {
+            if !tally.all_passed || tally.tests.len() < MIN_TESTS {
+                continue;
+            }
+            lists.entry(list).or_default().push(format!(
+                "    {name:?}, # {} passed removal{} in {} tests",
+                tally.n_removals,
+                if tally.n_removals == 1 { "" } else { "s" },
+                tally.tests.len()
+            ));
+        }
+
+        if lists.is_empty() {
+            return;
+        }
+
+        (context.println)(
+            &"Removing the following calls left every test that exercised them passing. If they \
+              are not meant to be tested (e.g., if they only log), consider adding these entries \
+              to necessist.toml:",
+        );
+        for (list, entries) in lists {
+            (context.println)(&format!("{} = [\n{}\n]", list.key(), entries.join("\n")));
+        }
+    }
+}
+
+/// If `text` is a call and nothing else (e.g., `log_step("...");`, or `.trace()` for a method
+/// call), returns the list to which the call could be added and the call's name
+///
+/// Rust paths containing `::` are not suggested, since patterns cannot contain `:`.
+fn call(text: &str) -> Option<(List, String)> {
+    let text = text.trim_end().trim_end_matches(';').trim_end();
+
+    let (list, rest) = if let Some(rest) = text.strip_prefix('.') {
+        (List::Methods, rest)
+    } else {
+        (List::Functions, text)
+    };
+
+    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))?;
+    let (name, args) = rest.split_at(end);
+    if name.is_empty() || (list == List::Methods && name.contains('.')) {
+        return None;
+    }
+
+    let (list, args) = match args.strip_prefix('!') {
+        Some(args) if list == List::Functions => (List::Macros, args),
+        Some(_) => return None,
+        None => (list, args),
+    };
+
+    if !is_delimited(args) {
+        return None;
+    }
+
+    Some((list, name.to_owned()))
+}
+
+/// Returns true if `s` starts with an opening delimiter whose matching closing delimiter ends `s`
+fn is_delimited(s: &str) -> bool {
+    if !s.starts_with(['(', '[', '{']) {
+        return false;
+    }
+
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, ch) in s.char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1 == s.len();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+#[test]
+fn calls() {
+    assert_eq!(
+        Some((List::Functions, "step".to_owned())),
+        call("step(\"a\");")
+    );
+    assert_eq!(
+        Some((List::Functions, "console.log".to_owned())),
+        call("console.log(\"(\", x);")
+    );
+    assert_eq!(
+        Some((List::Macros, "println".to_owned())),
+        call("println!();")
+    );
+    assert_eq!(Some((List::Methods, "trace".to_owned())), call(".trace()"));
+    assert_eq!(None, call("log::step(\"a\");"));
+    assert_eq!(None, call("step(\"a\").unwrap();"));
+    assert_eq!(None, call("n += 1;"));
+    assert_eq!(None, call(".a.b()"));
+}
@@ -1,3 +1,5 @@
-pub trait ToConsoleString {
+// smoelius: `Display` is used when a warning's source is recorded in the database. Like a removal's
+// span, it gives the path relative to the project root.
+pub trait ToConsoleString: std::fmt::Display {
     fn to_console_string(&self) -> String;
 }
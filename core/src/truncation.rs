@@ -0,0 +1,144 @@
+//! Counts of the command outputs truncated because they exceeded `max_output_bytes`, which are
+//! recorded in the database with the run, and the buffer that truncates them.
+
+use anyhow::{Context, Result};
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    process::{Command, Output, Stdio},
+    sync::Mutex,
+    thread,
+};
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Totals {
+    pub n_outputs: u64,
+    pub n_bytes: u64,
+}
+
+static TOTALS: Mutex<Totals> = Mutex::new(Totals {
+    n_outputs: 0,
+    n_bytes: 0,
+});
+
+/// Records that an output was truncated by `n_bytes` bytes
+pub fn record(n_bytes: u64) {
+    #[allow(clippy::unwrap_used)]
+    let mut totals = TOTALS.lock().unwrap();
+    totals.n_outputs += 1;
+    totals.n_bytes += n_bytes;
+}
+
+/// Returns the number of outputs truncated so far, and the number of bytes truncated from them
+pub(crate) fn totals() -> Totals {
+    #[allow(clippy::unwrap_used)]
+    *TOTALS.lock().unwrap()
+}
+
+/// Like [`Command::output`], but strips ANSI escapes from the command's standard output and error
+/// as they are read, and keeps at most `max_output_bytes` of each
+pub fn output(command: &mut Command, max_output_bytes: usize) -> Result<Output> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run command: {command:?}"))?;
+    #[allow(clippy::unwrap_used)]
+    let (stdout, stderr) = (child.stdout.take().unwrap(), child.stderr.take().unwrap());
+    let (stdout, stderr) = thread::scope(|scope| {
+        let stderr = scope.spawn(|| strip_and_cap(stderr, max_output_bytes));
+        let stdout = strip_and_cap(stdout, max_output_bytes);
+        #[allow(clippy::unwrap_used)]
+        (stdout, stderr.join().unwrap())
+    });
+    let status = child
+        .wait()
+        .with_context(|| format!("Failed to wait for command: {command:?}"))?;
+    Ok(Output {
+        status,
+        stdout: stdout?,
+        stderr: stderr?,
+    })
+}
+
+/// Reads `reader` to its end, and returns what was read stripped of ANSI escapes and capped at
+/// `max_output_bytes`
+pub fn strip_and_cap(mut reader: impl Read, max_output_bytes: usize) -> Result<Vec<u8>> {
+    let mut writer = strip_ansi_escapes::Writer::new(Capped::new(max_output_bytes));
+    io::copy(&mut reader, &mut writer)?;
+    let capped = writer
+        .into_inner()
+        .map_err(io::IntoInnerError::into_error)?;
+    Ok(capped.finish())
+}
+
+/// A buffer that keeps the first and last halves of its limit, and drops the bytes between them
+struct Capped {
+    head_limit: usize,
+    tail_limit: usize,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    n_dropped: u64,
+}
+
+impl Capped {
+    fn new(limit: usize) -> Self {
+        Self {
+            head_limit: limit / 2,
+            tail_limit: limit - limit / 2,
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            n_dropped: 0,
+        }
+    }
+
+    /// Returns the kept bytes. If any were dropped, a line saying how many is put in their place,
+    /// and the truncation is recorded.
+    fn finish(self) -> Vec<u8> {
+        let Self {
+            mut head,
+            tail,
+            n_dropped,
+            ..
+        } = self;
+        if n_dropped != 0 {
+            record(n_dropped);
+            head.extend_from_slice(format!("\n[{n_dropped} bytes truncated]\n").as_bytes());
+        }
+        head.extend(tail);
+        head
+    }
+}
+
+impl Write for Capped {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf
+            .len()
+            .min(self.head_limit.saturating_sub(self.head.len()));
+        self.head.extend_from_slice(&buf[..n]);
+        self.tail.extend(&buf[n..]);
+        let excess = self.tail.len().saturating_sub(self.tail_limit);
+        self.tail.drain(..excess);
+        self.n_dropped += excess as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn strip_and_cap_keeps_head_and_tail() {
+    let output = b"\x1b[32mok\x1b[0m\n".repeat(4);
+    assert_eq!(
+        b"ok\nok\nok\nok\n".as_slice(),
+        strip_and_cap(output.as_slice(), 12).unwrap()
+    );
+    assert_eq!(
+        b"ok\n\n[6 bytes truncated]\nok\n".as_slice(),
+        strip_and_cap(output.as_slice(), 6).unwrap()
+    );
+}
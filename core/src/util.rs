@@ -1,12 +1,15 @@
 //! This module is semver exempt and its contents could change at any time.
 
+use crate::Exec;
 use anyhow::{Context, Result};
 use std::{
+    collections::BTreeMap,
     env::current_dir,
     path::{Path, PathBuf},
     process::Command,
+    time::SystemTime,
 };
-use subprocess::Exec;
+use walkdir::WalkDir;
 
 pub struct RemoveFile(pub PathBuf);
 
@@ -18,7 +21,8 @@ impl Drop for RemoveFile {
     }
 }
 
-/// Constructs a [`subprocess::Exec`] from a [`std::process::Command`].
+/// Constructs an [`Exec`] from a [`std::process::Command`].
+#[must_use]
 pub fn exec_from_command(command: &Command) -> Exec {
     let mut exec = Exec::cmd(command.get_program()).args(&command.get_args().collect::<Vec<_>>());
     for (key, val) in command.get_envs() {
@@ -34,6 +38,25 @@ pub fn exec_from_command(command: &Command) -> Exec {
     exec
 }
 
+/// Maps each path beneath `dir` (including `dir` itself) to its modification time. Directories for
+/// which `skip` returns true are neither included nor descended into.
+///
+/// # Errors
+///
+/// If a directory cannot be read or a path's metadata cannot be obtained, an error is returned.
+pub fn mtimes(dir: &Path, skip: impl Fn(&Path) -> bool) -> Result<BTreeMap<PathBuf, SystemTime>> {
+    let mut mtimes = BTreeMap::new();
+    for result in WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| !(entry.file_type().is_dir() && skip(entry.path())))
+    {
+        let entry = result?;
+        let mtime = entry.metadata()?.modified()?;
+        mtimes.insert(entry.into_path(), mtime);
+    }
+    Ok(mtimes)
+}
+
 /// Strips the current directory from the given path.
 ///
 /// If the given path is not a child of the current directory, the path is
@@ -1,12 +1,13 @@
-use crate::{LightContext, __ToConsoleString as ToConsoleString};
-use ansi_term::{
-    Color::{Green, Yellow},
-    Style,
+use crate::{
+    color,
+    event::{self, Event},
+    LightContext, __ToConsoleString as ToConsoleString,
 };
+use ansi_term::Color::Green;
 use anyhow::{bail, Result};
 use bitflags::bitflags;
 use heck::ToKebabCase;
-use std::{collections::BTreeMap, io::IsTerminal, sync::Mutex};
+use std::{collections::BTreeMap, sync::Mutex};
 
 // smoelius: `Warning` is part of Necessist's public API. Please try to follow the naming convention
 // of `what` (e.g., `Output`) followed by `why` (e.g., `Invalid`).
@@ -19,17 +20,29 @@ pub enum Warning {
     DatabaseDoesNotExist,
     DryRunFailed,
     FilesChanged,
+    HarnessDisabled,
     IgnoredFunctionsUnsupported,
     IgnoredMacrosUnsupported,
     IgnoredMethodsUnsupported,
+    IgnoredTestSkipped,
     InstrumentationNonbuildable,
+    ItMessageAmbiguous,
     ItMessageNotFound,
     LocalFunctionAmbiguous,
     ModulePathUnknown,
+    NodeProcessOrphaned,
     OptionDeprecated,
     OutputInvalid,
     ParsingFailed,
     RunTestFailed,
+    ShouldPanicTestSkipped,
+    SideEffectsDetected,
+    StreamResultsFailed,
+    TestSkipped,
+    TestSuspicious,
+    UncoveredCallSkipped,
+    UnshareFailed,
+    WarningsDenied,
 }
 
 impl std::fmt::Display for Warning {
@@ -84,11 +97,29 @@ bitflags! {
         const ALLOW_MSG_EMITTED = 1 << 0;
         const BUG_MSG_EMITTED = 1 << 1;
         const WARNING_EMITTED = 1 << 2;
+        const WARNING_RECORDED = 1 << 3;
     }
 }
 
 static WARNING_STATE_MAP: Mutex<BTreeMap<Warning, State>> = Mutex::new(BTreeMap::new());
 
+/// A warning that has yet to be recorded in the database
+pub(crate) struct Record {
+    pub warning: Warning,
+    pub source: Option<String>,
+    pub msg: String,
+}
+
+// smoelius: Warnings can occur before the database is opened (e.g., while the database is being
+// opened). So they are buffered here and recorded by `sqlite::insert_warnings`.
+static RECORDS: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+
+/// Returns the warnings that have yet to be recorded in the database.
+pub(crate) fn take_records() -> Vec<Record> {
+    #[allow(clippy::unwrap_used)]
+    std::mem::take(&mut *RECORDS.lock().unwrap())
+}
+
 #[cfg_attr(dylint_lib = "general", allow(non_local_effect_before_error_return))]
 fn warn_internal(
     context: &LightContext,
@@ -106,6 +137,17 @@ fn warn_internal(
         .entry(warning)
         .or_insert_with(State::empty);
 
+    // smoelius: Warnings are recorded even if they are allowed, but `ONCE` is respected.
+    if !(flags.contains(Flags::ONCE) && state.contains(State::WARNING_RECORDED)) {
+        state.insert(State::WARNING_RECORDED);
+        #[allow(clippy::unwrap_used)]
+        RECORDS.lock().unwrap().push(Record {
+            warning,
+            source: source.map(ToString::to_string),
+            msg: msg.to_owned(),
+        });
+    }
+
     // smoelius: Append `BUG_MSG` to `msg` in case we have to `bail!`.
     let msg = msg.to_owned()
         + if may_be_bug(warning) && !state.contains(State::BUG_MSG_EMITTED) {
@@ -127,31 +169,18 @@ fn warn_internal(
         return Ok(());
     }
 
-    let allow_msg = if state.contains(State::ALLOW_MSG_EMITTED) {
-        String::new()
-    } else {
-        state.insert(State::ALLOW_MSG_EMITTED);
-        format!(
-            "
-Silence this warning with: --allow {warning}"
-        )
-    };
+    let first_of_kind = !state.contains(State::ALLOW_MSG_EMITTED);
+    state.insert(State::ALLOW_MSG_EMITTED);
 
-    (context.println)(&format!(
-        "{}{}: {}{}",
-        source.map_or(String::new(), |source| format!(
-            "{}: ",
-            source.to_console_string()
-        )),
-        if std::io::stdout().is_terminal() {
-            Yellow.bold()
-        } else {
-            Style::default()
-        }
-        .paint("Warning"),
-        msg,
-        allow_msg
-    ));
+    event::publish(
+        context,
+        &Event::Warning {
+            warning,
+            source: source.map(ToConsoleString::to_console_string),
+            msg: &msg,
+            first_of_kind,
+        },
+    )?;
 
     state.insert(State::WARNING_EMITTED);
 
@@ -165,12 +194,7 @@ pub(crate) fn note(context: &LightContext, msg: &str) {
 
     (context.println)(&format!(
         "{}: {}",
-        if std::io::stdout().is_terminal() {
-            Green.bold()
-        } else {
-            Style::default()
-        }
-        .paint("Note"),
+        color::style(context.opts, Green.bold()).paint("Note"),
         msg
     ));
 }
@@ -181,14 +205,26 @@ fn may_be_bug(warning: Warning) -> bool {
         Warning::DatabaseDoesNotExist
         | Warning::DryRunFailed
         | Warning::FilesChanged
+        | Warning::HarnessDisabled
         | Warning::IgnoredFunctionsUnsupported
         | Warning::IgnoredMacrosUnsupported
         | Warning::IgnoredMethodsUnsupported
+        | Warning::IgnoredTestSkipped
+        | Warning::ItMessageAmbiguous
         | Warning::ItMessageNotFound
         | Warning::LocalFunctionAmbiguous
+        | Warning::NodeProcessOrphaned
         | Warning::OptionDeprecated
         | Warning::OutputInvalid
-        | Warning::ParsingFailed => false,
+        | Warning::ParsingFailed
+        | Warning::ShouldPanicTestSkipped
+        | Warning::SideEffectsDetected
+        | Warning::StreamResultsFailed
+        | Warning::TestSkipped
+        | Warning::TestSuspicious
+        | Warning::UncoveredCallSkipped
+        | Warning::UnshareFailed
+        | Warning::WarningsDenied => false,
         Warning::InstrumentationNonbuildable
         | Warning::ModulePathUnknown
         | Warning::RunTestFailed => true,
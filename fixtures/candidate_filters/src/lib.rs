@@ -0,0 +1,23 @@
+#[cfg(test)]
+mod test {
+    use std::hint::black_box;
+
+    macro_rules! noop {
+        ($($tt:tt)*) => {};
+    }
+
+    #[test]
+    fn test() {
+        let mut n = 0;
+        n += 1;
+        black_box(n);
+        black_box(
+            n,
+        );
+        noop!(
+            n
+        );
+        n.to_string().push('x');
+        assert_eq!(n, 1);
+    }
+}
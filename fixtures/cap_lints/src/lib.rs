@@ -0,0 +1,13 @@
+#![deny(warnings)]
+
+#[cfg(test)]
+mod test {
+    // smoelius: Removing `.repeat(n)` leaves `n` unused, which `#![deny(warnings)]` would make an
+    // error were lints not capped.
+    #[test]
+    fn unused_variable() {
+        let n = 2;
+        let s = "x".repeat(n);
+        assert!(!s.is_empty());
+    }
+}
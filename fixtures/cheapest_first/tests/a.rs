@@ -0,0 +1,9 @@
+use std::{thread::sleep, time::Duration};
+
+#[test]
+fn a() {
+    let mut n = 0;
+    n += 1;
+    sleep(Duration::from_secs(2));
+    assert_eq!(n, 1);
+}
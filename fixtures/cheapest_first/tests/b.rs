@@ -0,0 +1,6 @@
+#[test]
+fn b() {
+    let mut n = 0;
+    n += 1;
+    assert_eq!(n, 1);
+}
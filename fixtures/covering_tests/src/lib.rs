@@ -0,0 +1,19 @@
+#[cfg(test)]
+mod tests {
+    fn increment(n: &mut u32) {
+        *n += 1;
+    }
+
+    #[test]
+    fn checked() {
+        let mut n = 0;
+        increment(&mut n);
+        assert_eq!(1, n);
+    }
+
+    #[test]
+    fn unchecked() {
+        let mut n = 0;
+        increment(&mut n);
+    }
+}
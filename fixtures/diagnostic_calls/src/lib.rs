@@ -0,0 +1,9 @@
+fn log_context(_: &str) {}
+
+#[test]
+fn test() {
+    let mut n = 0;
+    log_context("incrementing n");
+    n += 1;
+    assert_eq!(n, 1);
+}
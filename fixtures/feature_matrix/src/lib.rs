@@ -0,0 +1,7 @@
+#[test]
+fn test() {
+    let mut n = 0;
+    n += 1;
+    #[cfg(feature = "extra")]
+    assert_eq!(n, 1);
+}
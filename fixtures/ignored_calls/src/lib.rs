@@ -0,0 +1,12 @@
+fn check(_: bool) {}
+
+fn transfer() -> bool {
+    true
+}
+
+#[test]
+fn test() {
+    check(true);
+    check(transfer());
+    assert!(true);
+}
@@ -0,0 +1,20 @@
+#[cfg(test)]
+mod test {
+    use std::{process::Command, thread::sleep, time::Duration};
+
+    #[test]
+    fn lingering_process() {
+        let mut n = 0;
+        n += 1;
+        if n == 0 {
+            // smoelius: `sh` exits immediately, leaving `sleep` without a parent.
+            Command::new("sh")
+                .args(["-c", "sleep 4567 &"])
+                .status()
+                .unwrap();
+            loop {
+                sleep(Duration::from_secs(1));
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+mod nested;
+
+fn foo() {}
+
+fn bar() {}
+
+fn baz() {}
+
+#[test]
+fn test() {
+    foo();
+    bar();
+    baz();
+    assert!(true);
+}
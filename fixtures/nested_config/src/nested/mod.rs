@@ -0,0 +1,9 @@
+use super::{bar, baz, foo};
+
+#[test]
+fn test() {
+    foo();
+    bar();
+    baz();
+    assert!(true);
+}
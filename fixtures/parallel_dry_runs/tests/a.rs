@@ -0,0 +1,6 @@
+#[test]
+fn a() {
+    let mut n = 0;
+    n += 1;
+    assert_eq!(n, 1);
+}
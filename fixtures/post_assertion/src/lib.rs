@@ -0,0 +1,16 @@
+#[test]
+fn with_assertion() {
+    let mut n = 0;
+    n += 1;
+    assert_eq!(n, 1);
+    n += 1;
+    n.to_string().push('x');
+    let _ = n;
+}
+
+#[test]
+fn without_assertion() {
+    let mut n = 0;
+    n += 1;
+    let _ = n;
+}
@@ -0,0 +1,8 @@
+mod common;
+
+#[test]
+fn a() {
+    let mut n = 0;
+    common::increment(&mut n);
+    assert_eq!(n, 1);
+}
@@ -0,0 +1,9 @@
+mod common;
+use common::increment;
+
+#[test]
+fn b() {
+    let mut n = 0;
+    increment(&mut n);
+    assert!(n <= 1);
+}
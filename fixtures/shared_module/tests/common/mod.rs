@@ -0,0 +1,3 @@
+pub fn increment(n: &mut u32) {
+    *n += 1;
+}
@@ -0,0 +1,13 @@
+#[cfg(test)]
+mod test {
+    use std::fs::write;
+
+    #[test]
+    fn side_effect() {
+        let mut n = 0;
+        n += 1;
+        if n == 0 {
+            write("side_effect.txt", "").unwrap();
+        }
+    }
+}
@@ -0,0 +1,30 @@
+mod log;
+
+#[cfg(test)]
+mod tests {
+    use crate::log::step;
+
+    #[test]
+    fn a() {
+        step("a");
+        let mut n = 0;
+        n += 1;
+        assert_eq!(1, n);
+    }
+
+    #[test]
+    fn b() {
+        step("b");
+        let mut n = 0;
+        n += 1;
+        assert_eq!(1, n);
+    }
+
+    #[test]
+    fn c() {
+        step("c");
+        let mut n = 0;
+        n += 1;
+        assert_eq!(1, n);
+    }
+}
@@ -0,0 +1,19 @@
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::{read_to_string, write},
+        path::Path,
+    };
+
+    // smoelius: `flaky` passes the first two times it is run, i.e., during the dry run and with
+    // `n += 1;` removed, but fails thereafter.
+    #[test]
+    fn flaky() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("count.txt");
+        let count = read_to_string(&path).map_or(0, |s| s.parse::<u32>().unwrap()) + 1;
+        write(&path, count.to_string()).unwrap();
+        let mut n = 0;
+        n += 1;
+        assert!(count <= 2, "{n}");
+    }
+}
@@ -0,0 +1,109 @@
+//! `cargo necessist`: runs Necessist as a Cargo subcommand, with `--framework rust` and with the
+//! root directory of the current Cargo workspace as `--root`, unless they are given explicitly
+
+#![warn(clippy::expect_used)]
+#![warn(clippy::unwrap_used)]
+#![warn(clippy::panic)]
+
+use anyhow::{ensure, Context, Result};
+use clap::Parser;
+use necessist_backends::Identifier;
+use necessist_core::{cli, framework::Auto, necessist, Necessist};
+use std::{
+    env::{args, var},
+    path::Path,
+    process::Command,
+};
+
+#[path = "../init_tracing.rs"]
+mod init_tracing;
+use init_tracing::init_tracing;
+
+fn main() -> Result<()> {
+    let args = cargo_args(args().collect())?;
+
+    let (opts, framework): (Necessist, Auto<Identifier>) = cli::Opts::parse_from(args).into();
+
+    init_tracing(opts.log_json);
+
+    necessist(&opts, framework)
+}
+
+/// Converts the arguments with which Cargo runs `cargo-necessist` into ones for `necessist`
+fn cargo_args(mut args: Vec<String>) -> Result<Vec<String>> {
+    // smoelius: Cargo passes the subcommand's name as the first argument, i.e., `cargo necessist
+    // ...` runs `cargo-necessist necessist ...`.
+    if args.get(1).map(String::as_str) == Some("necessist") {
+        args.remove(1);
+    }
+
+    // smoelius: Arguments after `--` are passed to the test command.
+    let n = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let has_option = |name: &str| {
+        args[..n]
+            .iter()
+            .any(|arg| arg == name || arg.starts_with(&format!("{name}=")))
+    };
+
+    let mut inserted = Vec::new();
+    if !has_option("--framework") {
+        inserted.extend(["--framework".to_owned(), "rust".to_owned()]);
+    }
+    if !has_option("--root") {
+        inserted.extend(["--root".to_owned(), workspace_root()?]);
+    }
+
+    args.splice(1..1, inserted);
+
+    Ok(args)
+}
+
+/// Returns the root directory of the Cargo workspace containing the current directory
+fn workspace_root() -> Result<String> {
+    let cargo = var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    let output = Command::new(&cargo)
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .output()
+        .with_context(|| format!("Failed to run `{cargo} locate-project`"))?;
+    ensure!(
+        output.status.success(),
+        "Failed to locate Cargo workspace: {}",
+        String::from_utf8_lossy(&output.stderr).trim_end()
+    );
+    let manifest_path = String::from_utf8(output.stdout)?;
+    let root = Path::new(manifest_path.trim_end())
+        .parent()
+        .with_context(|| format!("Manifest path {manifest_path:?} has no parent"))?;
+    Ok(root.to_string_lossy().into_owned())
+}
+
+#[test]
+fn cargo_args_are_converted() {
+    let args = |args: &[&str]| args.iter().copied().map(ToOwned::to_owned).collect();
+
+    assert_eq!(
+        args(&[
+            "cargo-necessist",
+            "--framework",
+            "rust",
+            "--root",
+            ".",
+            "--verbose",
+            "--",
+            "--root",
+        ]),
+        cargo_args(args(&[
+            "cargo-necessist",
+            "necessist",
+            "--root",
+            ".",
+            "--verbose",
+            "--",
+            "--root",
+        ]))
+        .unwrap()
+    );
+}
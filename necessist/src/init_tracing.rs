@@ -0,0 +1,28 @@
+use tracing_subscriber::{filter::LevelFilter, fmt::format::FmtSpan, EnvFilter};
+
+pub fn init_tracing(log_json: bool) {
+    // smoelius: When `--log-json` is passed, span close events are emitted so that the time spent
+    // in each phase can be recovered from the log.
+    let filter = EnvFilter::builder()
+        .with_default_directive(if log_json {
+            LevelFilter::INFO.into()
+        } else {
+            LevelFilter::ERROR.into()
+        })
+        .from_env_lossy();
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    if log_json {
+        builder
+            .json()
+            .with_span_events(FmtSpan::CLOSE)
+            .with_current_span(true)
+            .with_span_list(true)
+            .init();
+    } else {
+        builder.init();
+    }
+}
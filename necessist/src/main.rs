@@ -10,10 +10,13 @@ use std::env::args;
 
 mod backends;
 
-fn main() -> Result<()> {
-    env_logger::init();
+mod init_tracing;
+use init_tracing::init_tracing;
 
+fn main() -> Result<()> {
     let (opts, framework): (Necessist, Auto<Identifier>) = cli::Opts::parse_from(args()).into();
 
+    init_tracing(opts.log_json);
+
     necessist(&opts, framework)
 }
@@ -1,7 +1,13 @@
 use assert_cmd::prelude::*;
 use necessist_core::util;
 use predicates::prelude::*;
-use std::{env::set_current_dir, path::PathBuf, process::Command, sync::Mutex};
+use std::{
+    env::set_current_dir,
+    fs::{create_dir, read_to_string, remove_file, write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
 
 mod tempfile_util;
 use tempfile_util::tempdir;
@@ -71,6 +77,21 @@ fixtures/dry_run_failure/tests/a.rs: Warning: dry run failed: code=101
         "{stdout_normalized:?}",
     );
 
+    // smoelius: The dry run failure is recorded in the database.
+    let assert = Command::cargo_bin("necessist")
+        .unwrap()
+        .args(["--root", DRF_ROOT, "show"])
+        .assert()
+        .success();
+    let stdout_normalized = std::str::from_utf8(&assert.get_output().stdout)
+        .unwrap()
+        .replace('\\', "/");
+    assert!(
+        stdout_normalized
+            .contains("\ntests/a.rs: Warning (dry-run-failed): dry run failed: code=101\n"),
+        "{stdout_normalized:?}",
+    );
+
     Command::cargo_bin("necessist")
         .unwrap()
         .args(["--root", DRF_ROOT, "--resume"])
@@ -120,6 +141,17 @@ fn resume_following_ctrl_c() {
 
         let _: subprocess::ExitStatus = popen.wait().unwrap();
 
+        // smoelius: The candidate whose test was running when the ctrl-c was sent is recorded as
+        // aborted.
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--dump", "--verbose"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "fixtures/basic/src/lib.rs:14:9-14:16: `n += 1;` aborted\n",
+            ));
+
         let assert = command().arg("--resume").assert().success();
 
         // smoelius: N.B. `stdout_expected` intentionally lacks the following line:
@@ -151,18 +183,2046 @@ fn kill() -> Command {
     command
 }
 
+#[cfg(not(windows))]
 #[test]
-fn tests_are_not_rebuilt() {
+fn timed_out_process_group_is_killed() {
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            "fixtures/lingering_process",
+            "--no-sqlite",
+            "--timeout",
+            TIMEOUT,
+            "--verbose",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`n += 1;` timed-out"));
+
+    Command::new("pgrep")
+        .args(["-f", "^sleep 4567$"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn features_are_recorded() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args(["-r", BASIC_ROOT, &tempdir.path().to_string_lossy()])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("basic");
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--timeout", TIMEOUT])
+        .assert()
+        .success();
+
+    std::fs::write(
+        root.join("necessist.toml"),
+        "[rust]\nfeatures = [\"--all-features\"]\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .arg("--resume")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            r#"Features ["--all-features"] differ from those with which"#,
+        ));
+}
+
+#[test]
+fn recurse_projects_share_database() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args([
+            "-rp",
+            "fixtures/recurse_projects",
+            &tempdir.path().to_string_lossy(),
+        ])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("recurse_projects");
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--recurse-projects", "--timeout", TIMEOUT])
+        .assert()
+        .success();
+
+    // smoelius: There is one database, in the directory that was searched.
+    assert!(root.join("necessist.db").try_exists().unwrap());
+    assert!(!root.join("a/necessist.db").try_exists().unwrap());
+    assert!(!root.join("b/necessist.db").try_exists().unwrap());
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--recurse-projects", "--resume"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "\
+project a
+1 candidates in 1 test in 1 source file
+project b
+1 candidates in 1 test in 1 source file
+",
+        ));
+}
+
+#[test]
+fn color_always() {
+    run_basic_test(|| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args([
+                "--root",
+                BASIC_ROOT,
+                "--no-sqlite",
+                "--timeout",
+                TIMEOUT,
+                "--color",
+                "always",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "fixtures/basic/src/lib.rs:4:5-4:12: `n += 1;` \u{1b}[1;31mpassed\u{1b}[0m\n",
+            ));
+    });
+}
+
+#[test]
+fn explain() {
+    run_basic_test(|| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args([
+                "--root",
+                BASIC_ROOT,
+                "--timeout",
+                TIMEOUT,
+                "explain",
+                "fixtures/basic/src/lib.rs:21:5-21:12",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "fixtures/basic/src/lib.rs:21:5-21:12: running `failed` with `n += 1;` removed\n",
+            ))
+            .stdout(predicate::str::contains("assertion failed: n >= 1"))
+            .stdout(predicate::str::contains(
+                "fixtures/basic/src/lib.rs:21:5-21:12: `n += 1;` failed\n",
+            ));
+
+        // smoelius: Explaining a span does not record anything in the database.
+        assert!(!PathBuf::from(BASIC_ROOT)
+            .join("necessist.db")
+            .try_exists()
+            .unwrap());
+    });
+}
+
+#[test]
+fn frameworks() {
+    let tempdir = tempdir().unwrap();
+
+    write(tempdir.path().join("Cargo.toml"), "").unwrap();
+    write(tempdir.path().join("foundry.toml"), "").unwrap();
+
+    let assert_selected = |expected: &str| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", &tempdir.path().to_string_lossy(), "frameworks"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "foundry: applicable (foundry.toml found)\n",
+            ))
+            .stdout(predicate::str::contains(
+                "go: not applicable (go.mod not found)\n",
+            ))
+            .stdout(predicate::str::contains(expected));
+    };
+
+    assert_selected("Selected: none (multiple frameworks are applicable: foundry, rust;");
+
+    write(
+        tempdir.path().join("necessist.toml"),
+        "framework_precedence = [\"rust\", \"foundry\"]\n",
+    )
+    .unwrap();
+
+    assert_selected("Selected: rust (the first applicable framework in `framework_precedence`)\n");
+}
+
+#[test]
+fn config_check() {
+    let tempdir = tempdir().unwrap();
+
+    write(tempdir.path().join("Cargo.toml"), "").unwrap();
+    write(
+        tempdir.path().join("necessist.toml"),
+        "timout = 10\n[foundry]\nfuzz_sed = 1\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            &tempdir.path().to_string_lossy(),
+            "config",
+            "check",
+        ])
+        .assert()
+        .failure()
+        .stdout(
+            "\
+{\"file\":\"necessist.toml\",\"severity\":\"error\",\"key\":\"foundry.fuzz_sed\",\"message\":\"\
+             unknown key\",\"suggestion\":\"foundry.fuzz_seed\"}
+{\"file\":\"necessist.toml\",\"severity\":\"error\",\"key\":\"timout\",\"message\":\"unknown \
+             key\",\"suggestion\":\"timeout\"}
+{\"file\":\"necessist.toml\",\"severity\":\"warning\",\"key\":\"foundry\",\"message\":\"not used \
+             by the selected framework (rust)\"}
+",
+        )
+        .stderr(predicate::str::contains(
+            "Found 2 errors in configuration files",
+        ));
+
+    write(
+        tempdir.path().join("necessist.toml"),
+        "timeout = 10\n[foundry]\nfuzz_seed = 1\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            &tempdir.path().to_string_lossy(),
+            "config",
+            "check",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "{\"file\":\"necessist.toml\",\"severity\":\"warning\",\"key\":\"foundry\",\"message\"\
+             :\"not used by the selected framework (rust)\"}\n",
+        );
+}
+
+#[test]
+fn patch() {
     run_basic_test(|| {
         Command::cargo_bin("necessist")
             .unwrap()
             .args(["--root", BASIC_ROOT, "--timeout", TIMEOUT])
-            .env("NECESSIST_CHECK_MTIMES", "1")
             .assert()
             .success();
+
+        let tempdir = tempdir().unwrap();
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args([
+                "--root",
+                BASIC_ROOT,
+                "patch",
+                "--out",
+                &tempdir.path().to_string_lossy(),
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("Wrote 1 patch to "));
+
+        let patch = read_to_string(tempdir.path().join("src-lib.rs-4-5-4-12.patch")).unwrap();
+        assert!(patch.starts_with(
+            "src/lib.rs:4:5-4:12: `n += 1;` passed
+--- a/src/lib.rs
++++ b/src/lib.rs
+"
+        ));
+        assert!(patch.contains("\n-    n += 1;\n"));
+    });
+}
+
+#[test]
+fn annotate() {
+    run_basic_test(|| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--timeout", TIMEOUT])
+            .assert()
+            .success();
+
+        let tempdir = tempdir().unwrap();
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args([
+                "--root",
+                BASIC_ROOT,
+                "annotate",
+                "--out",
+                &tempdir.path().to_string_lossy(),
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("Wrote 1 annotated file to "));
+
+        let contents = read_to_string(Path::new(BASIC_ROOT).join("src/lib.rs")).unwrap();
+        let annotated = read_to_string(tempdir.path().join("src/lib.rs")).unwrap();
+        assert_eq!(contents.lines().count(), annotated.lines().count());
+        let line = annotated.lines().nth(3).unwrap();
+        let date = line
+            .strip_prefix("    n += 1; // necessist: passed ")
+            .unwrap();
+        assert_eq!(10, date.len());
+        assert!(annotated.contains(" // necessist: timed-out "));
+    });
+}
+
+#[test]
+fn urls_for_subdirectory_root() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args(["-r", BASIC_ROOT, &tempdir.path().to_string_lossy()])
+        .assert()
+        .success();
+
+    write(tempdir.path().join(".gitignore"), "necessist.db\ntarget\n").unwrap();
+
+    for args in [
+        &["init", "--quiet"][..],
+        &["remote", "add", "origin", "git@github.com:owner/repo.git"],
+        &["add", "."],
+        &[
+            "-c",
+            "user.name=Necessist",
+            "-c",
+            "user.email=necessist@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "Initial commit",
+        ],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&tempdir)
+            .assert()
+            .success();
+    }
+
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(&tempdir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let oid = String::from_utf8(output.stdout).unwrap();
+
+    let root = tempdir.path().join("basic");
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--timeout", TIMEOUT])
+        .assert()
+        .success();
+
+    let output = Command::new("sqlite3")
+        .arg(root.join("necessist.db"))
+        .arg("SELECT url FROM removal WHERE span = 'src/lib.rs:4:5-4:12'")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        format!(
+            "https://github.com/owner/repo/blob/{}/basic/src/lib.rs#L4-L4\n",
+            oid.trim_end()
+        ),
+        String::from_utf8(output.stdout).unwrap()
+    );
+}
+
+#[test]
+fn skip_candidate_kinds() {
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            BASIC_ROOT,
+            "--no-sqlite",
+            "--dump-candidates",
+            "--skip-statements",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`n += 1;`").not())
+        .stdout(predicate::str::contains("`.join(\"\")`"));
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            BASIC_ROOT,
+            "--no-sqlite",
+            "--dump-candidates",
+            "--skip-method-calls",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`n += 1;`"))
+        .stdout(predicate::str::contains("`.join(\"\")`").not());
+}
+
+#[test]
+fn source_files_from_stdin() {
+    assert_cmd::Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            BASIC_ROOT,
+            "--no-sqlite",
+            "--dump-candidates",
+            "--source-files-from",
+            "-",
+        ])
+        .write_stdin("\nfixtures/basic/src/lib.rs\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`n += 1;`"));
+
+    // smoelius: An empty list is an error rather than a request to consider all test files.
+    assert_cmd::Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            BASIC_ROOT,
+            "--no-sqlite",
+            "--dump-candidates",
+            "--source-files-from",
+            "-",
+        ])
+        .write_stdin("\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("produced no test files"));
+}
+
+#[test]
+fn show_and_prune_runs() {
+    run_basic_test(|| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--timeout", TIMEOUT])
+            .assert()
+            .success();
+
+        // smoelius: The second run resumes from the first, and so produces no removals.
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--timeout", TIMEOUT, "--resume"])
+            .assert()
+            .success();
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "show", "--run", "1"])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("Run 1 started at "))
+            .stdout(predicate::str::contains("\nToolchain: cargo "))
+            .stdout(predicate::str::contains(
+                "fixtures/basic/src/lib.rs:4:5-4:12: `n += 1;` passed\n",
+            ));
+
+        // smoelius: A failed removal is shown with its probable cause.
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--verbose", "show", "--run", "1"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "fixtures/basic/src/lib.rs:21:5-21:12: `n += 1;` failed (assertion failed: n >= \
+                 1)\n",
+            ));
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "show"])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("Run 2 started at "))
+            .stdout(predicate::str::contains("passed").not());
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "db", "prune", "--keep", "1"])
+            .assert()
+            .success()
+            .stdout("Deleted 1 run and 4 removals\n");
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "show", "--run", "1"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Found no run with id 1"));
     });
 }
 
+#[test]
+fn cargo_necessist_infers_root() {
+    Command::cargo_bin("cargo-necessist")
+        .unwrap()
+        .args(["necessist", "--no-sqlite", "--dump-candidates"])
+        .current_dir(Path::new(BASIC_ROOT).join("src"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lib.rs:4:5-4:12: `n += 1;`\n"));
+}
+
+#[test]
+fn cap_lints() {
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            "fixtures/cap_lints",
+            "--no-sqlite",
+            "--timeout",
+            TIMEOUT,
+            "--verbose",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/lib.rs:10:20-10:30: `.repeat(n)` passed\n",
+        ))
+        .stdout(predicate::str::contains("Warning").not());
+}
+
+#[test]
+fn tolerate_unused() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args([
+            "-r",
+            "fixtures/cap_lints",
+            &tempdir.path().to_string_lossy(),
+        ])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("cap_lints");
+
+    // smoelius: Without `rust.cap_lints`, removing `.repeat(n)` leaves `n` unused, which
+    // `#![deny(warnings)]` makes an error.
+    remove_file(root.join("necessist.toml")).unwrap();
+
+    for (args, outcome) in [
+        (&[][..], "nonbuildable"),
+        (&["--tolerate-unused"][..], "passed"),
+    ] {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .arg("--root")
+            .arg(&root)
+            .args(["--no-sqlite", "--timeout", TIMEOUT, "--verbose"])
+            .args(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "src/lib.rs:10:20-10:30: `.repeat(n)` {outcome}\n"
+            )));
+    }
+}
+
+#[cfg(not(windows))]
+#[test]
+fn hooks_run_around_each_candidate() {
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "cheapest_first",
+        &["Cargo.toml", "src", "tests"],
+        tempdir.path(),
+    );
+    let log = tempdir.path().join("hooks.log");
+
+    write(
+        root.join("necessist.toml"),
+        format!(
+            r#"[hooks]
+before_each_candidate = ["sh", "-c", "echo before >> {0}"]
+after_each_candidate = ["sh", "-c", "echo after >> {0}"]
+reset = ["sh", "-c", "echo reset >> {0}"]
+"#,
+            log.display()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--no-sqlite", "--timeout", TIMEOUT])
+        .assert()
+        .success();
+
+    assert_eq!(
+        "before\nreset\nafter\n".repeat(3),
+        read_to_string(log).unwrap()
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn failing_hook_is_an_error() {
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "cheapest_first",
+        &["Cargo.toml", "src", "tests"],
+        tempdir.path(),
+    );
+
+    write(
+        root.join("necessist.toml"),
+        "[hooks]\nbefore_each_candidate = [\"false\"]\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--no-sqlite", "--timeout", TIMEOUT])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "`hooks.before_each_candidate` failed",
+        ));
+}
+
+#[test]
+fn dry_run_failure_skips_only_failing_tests() {
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "dry_run_failure",
+        &["Cargo.toml", "src", "tests"],
+        tempdir.path(),
+    );
+    let source_file = root.join("tests/a.rs");
+
+    write(
+        &source_file,
+        "\
+#[test]
+fn dry_run_failed() {
+    let mut n = 0;
+    n += 1;
+    assert!(n >= 2);
+}
+
+#[test]
+fn passed() {
+    let mut n = 0;
+    n += 1;
+    let _ = n;
+}
+",
+    )
+    .unwrap();
+
+    let necessist = |require_clean_dry_run: bool| {
+        let mut command = Command::cargo_bin("necessist").unwrap();
+        command
+            .arg("--root")
+            .arg(&root)
+            .args(["--no-sqlite", "--timeout", TIMEOUT]);
+        if require_clean_dry_run {
+            command.arg("--require-clean-dry-run");
+        }
+        command.arg(&source_file).assert().success()
+    };
+
+    necessist(false).stdout(
+        predicate::str::contains("Warning: dry run failed")
+            .and(predicate::str::contains(
+                "a.rs:11:5-11:12: `n += 1;` passed",
+            ))
+            .and(predicate::str::contains("a.rs:4:5-4:12: ").not()),
+    );
+
+    necessist(true).stdout(
+        predicate::str::contains("Warning: dry run failed")
+            .and(predicate::str::contains("mutilating").not()),
+    );
+}
+
+// smoelius: Warnings emitted while parsing a source file are not emitted again when its candidates
+// are reused. So the `should-panic-test-skipped` warning reveals whether the file was parsed.
+#[test]
+fn unchanged_source_files_are_not_reparsed() {
+    const WARNING: &str = "Skipping test `c`, which has `#[should_panic]`";
+
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "cheapest_first",
+        &["Cargo.toml", "src", "tests"],
+        tempdir.path(),
+    );
+    let source_file = root.join("tests/c.rs");
+    let contents = "#[test]\n#[should_panic]\nfn c() {\n    panic!();\n}\n";
+
+    write(&source_file, contents).unwrap();
+
+    let necessist = |resume: bool| {
+        let mut command = Command::cargo_bin("necessist").unwrap();
+        command.arg("--root").arg(&root);
+        if resume {
+            command.arg("--resume");
+        }
+        command.arg(&source_file).assert().success()
+    };
+
+    necessist(false).stdout(predicate::str::contains(WARNING));
+
+    necessist(true).stdout(predicate::str::contains(WARNING).not());
+
+    write(&source_file, format!("{contents}// changed\n")).unwrap();
+
+    necessist(true).stdout(predicate::str::contains(WARNING));
+}
+
+// smoelius: A shared module's candidates depend on the integration tests that call its helpers. So
+// changing a caller must cause the shared module to be reparsed, even though it is unchanged.
+#[test]
+fn shared_module_is_reparsed_when_caller_changes() {
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "shared_module",
+        &["Cargo.toml", "necessist.toml", "src", "tests"],
+        tempdir.path(),
+    );
+    let source_file = root.join("tests/common/mod.rs");
+
+    let necessist = |resume: bool| {
+        let mut command = Command::cargo_bin("necessist").unwrap();
+        command.args(["--root", &root.to_string_lossy(), "--timeout", TIMEOUT]);
+        if resume {
+            command.arg("--resume");
+        }
+        command.arg(&source_file).assert().success()
+    };
+
+    necessist(false).stdout(predicate::str::starts_with("2 candidates in "));
+
+    let caller = root.join("tests/b.rs");
+    let contents = read_to_string(&caller).unwrap();
+    write(
+        &caller,
+        contents.replace("    increment(&mut n);", "    n += 1;"),
+    )
+    .unwrap();
+
+    necessist(true).stdout(predicate::str::starts_with("1 candidates in "));
+}
+
+// smoelius: Removing `n += 1;` causes the test to fail, but only if the test is actually run.
+// Without `--include-ignored` in the test command, the test would be reported as ignored, and the
+// removal would appear to pass.
+#[test]
+fn ignored_tests_require_include_ignored() {
+    const WARNING: &str = "Skipping test `c`, which has `#[ignore]`";
+
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "cheapest_first",
+        &["Cargo.toml", "src", "tests"],
+        tempdir.path(),
+    );
+    let source_file = root.join("tests/c.rs");
+
+    write(
+        &source_file,
+        "#[test]
+#[ignore]
+fn c() {
+    let mut n = 0;
+    n += 1;
+    assert_eq!(n, 1);
+}
+",
+    )
+    .unwrap();
+
+    let necessist = |include_ignored: bool| {
+        let mut command = Command::cargo_bin("necessist").unwrap();
+        command.arg("--root").arg(&root).arg("--reset");
+        if include_ignored {
+            command.arg("--include-ignored");
+        }
+        command.arg(&source_file).assert().success()
+    };
+
+    necessist(false)
+        .stdout(predicate::str::contains(WARNING))
+        .stdout(predicate::str::contains("0 candidates in 0 tests"));
+
+    necessist(true)
+        .stdout(predicate::str::contains(WARNING).not())
+        .stdout(predicate::str::contains("in 1 test in 1 source file"))
+        .stdout(predicate::str::contains("`n += 1;` passed").not());
+}
+
+#[test]
+fn shards_can_be_merged() {
+    let tempdir = tempdir().unwrap();
+
+    let roots = ["1", "2", "merged"].map(|name| {
+        let dir = tempdir.path().join(name);
+        create_dir(&dir).unwrap();
+        copy_fixture("cheapest_first", &["Cargo.toml", "src", "tests"], &dir)
+    });
+
+    // smoelius: Each of the fixture's candidates should be run by exactly one shard.
+    let mut stdout = String::new();
+    for (i, root) in roots[..2].iter().enumerate() {
+        let assert = Command::cargo_bin("necessist")
+            .unwrap()
+            .arg("--root")
+            .arg(root)
+            .args([
+                "--shard",
+                &format!("{}/2", i + 1),
+                "--timeout",
+                TIMEOUT,
+                "--verbose",
+            ])
+            .assert()
+            .success();
+        stdout += std::str::from_utf8(&assert.get_output().stdout).unwrap();
+    }
+    for candidate in [
+        "tests/a.rs:6:5-6:12: `n += 1;` failed\n",
+        "tests/a.rs:7:5-7:35: `sleep(Duration::from_secs(2));` passed\n",
+        "tests/b.rs:4:5-4:12: `n += 1;` failed\n",
+    ] {
+        assert_eq!(1, stdout.matches(candidate).count(), "{candidate:?}");
+    }
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args(["db", "merge"])
+        .args(roots[..2].iter().map(|root| root.join("necessist.db")))
+        .arg("-o")
+        .arg(roots[2].join("necessist.db"))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "Merged 2 runs and 3 removals into ",
+        ));
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&roots[2])
+        .args(["--timeout", TIMEOUT, "--resume", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("failed").not())
+        .stdout(predicate::str::contains("passed").not());
+}
+
+// smoelius: Removing `n += 1;` from `b.rs` causes its test to fail. But if the test's assertion is
+// weakened, the removal passes. The merged database should keep the `passed` removal, even though
+// the `failed` one is more recent.
+#[test]
+fn merge_prefers_most_severe_outcome() {
+    let tempdir = tempdir().unwrap();
+
+    let roots = ["passed", "failed", "merged"].map(|name| {
+        let dir = tempdir.path().join(name);
+        create_dir(&dir).unwrap();
+        copy_fixture("cheapest_first", &["Cargo.toml", "src", "tests"], &dir)
+    });
+
+    let source_file = roots[0].join("tests/b.rs");
+    let contents = read_to_string(&source_file).unwrap();
+    write(
+        &source_file,
+        contents.replace("assert_eq!(n, 1);", "assert!(n <= 1);"),
+    )
+    .unwrap();
+
+    for root in &roots[..2] {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .arg("--root")
+            .arg(root)
+            .args(["--timeout", TIMEOUT])
+            .arg(root.join("tests/b.rs"))
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args(["db", "merge"])
+        .args(roots[..2].iter().map(|root| root.join("necessist.db")))
+        .arg("-o")
+        .arg(roots[2].join("necessist.db"))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "Merged 2 runs and 1 removal into ",
+        ));
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&roots[2])
+        .arg("--dump")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "tests/b.rs:4:5-4:12: `n += 1;` passed\n",
+        ));
+}
+
+#[test]
+fn progress_file() {
+    let tempdir = tempdir().unwrap();
+
+    let progress_file = tempdir.path().join("progress.json");
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args(["--root", BASIC_ROOT, "--no-sqlite", "--timeout", TIMEOUT])
+        .arg(format!("--progress-file={}", progress_file.display()))
+        .assert()
+        .success();
+
+    let contents = read_to_string(&progress_file).unwrap();
+    let value = serde_json::from_str::<serde_json::Value>(&contents).unwrap();
+
+    assert_eq!(4, value["n_candidates"]);
+    assert_eq!(4, value["n_done"]);
+    assert_eq!(
+        serde_json::json!({ "failed": 1, "nonbuildable": 1, "passed": 1, "timed-out": 1 }),
+        value["outcomes"]
+    );
+    assert_eq!("src/lib.rs", value["source_file"]);
+    assert!(value["candidate"].is_null());
+    assert_eq!(0.0, value["eta_secs"]);
+    assert_eq!(true, value["finished"]);
+}
+
+#[test]
+fn self_test() {
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("self-test")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`n += 1;` failed\n"))
+        .stdout(predicate::str::contains("`m += 1;` passed\n"))
+        .stdout(predicate::str::ends_with("Self-test passed\n"));
+}
+
+// smoelius: The fixture's `target` directory is not copied, since it could contain instrumented
+// test binaries that Cargo would consider fresh.
+fn copy_fixture(name: &str, paths: &[&str], dir: &Path) -> PathBuf {
+    let root = dir.join(name);
+    create_dir(&root).unwrap();
+    Command::new("cp")
+        .arg("-r")
+        .args(paths)
+        .arg(&root)
+        .current_dir(Path::new("fixtures").join(name))
+        .assert()
+        .success();
+    root
+}
+
+#[test]
+fn check_side_effects() {
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture("side_effects", &["Cargo.toml", "src"], tempdir.path());
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--no-sqlite", "--timeout", TIMEOUT, "--check-side-effects"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/lib.rs:8:9-8:16: Warning: Tests modified files outside of build directories: \
+             [\"side_effect.txt\"]",
+        ));
+}
+
+#[test]
+fn builtin_ignores_can_be_overridden() {
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture("basic", &["Cargo.toml", "src"], tempdir.path());
+
+    let lib_rs = root.join("src/lib.rs");
+    let contents = read_to_string(&lib_rs).unwrap();
+    write(
+        &lib_rs,
+        contents
+            + "
+#[test]
+fn printed() {
+    println!(\"printed\");
+    noop();
+}
+",
+    )
+    .unwrap();
+
+    let dump_candidates = || {
+        let mut command = Command::cargo_bin("necessist").unwrap();
+        command
+            .arg("--root")
+            .arg(&root)
+            .args(["--no-sqlite", "--dump-candidates"]);
+        command
+    };
+
+    dump_candidates()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`.join(\"\")`"))
+        .stdout(predicate::str::contains("`println!(\"printed\");`").not());
+
+    write(
+        root.join("necessist.toml"),
+        "\
+[rust]
+extra_ignored_methods = [\"join\"]
+removed_ignored_macros = [\"println\"]
+",
+    )
+    .unwrap();
+
+    dump_candidates()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`.join(\"\")`").not())
+        .stdout(predicate::str::contains("`println!(\"printed\");`"));
+
+    write(
+        root.join("necessist.toml"),
+        "\
+[rust]
+removed_ignored_methods = [\"join\"]
+",
+    )
+    .unwrap();
+
+    dump_candidates()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "`rust.removed_ignored_methods` contains `join`, which is not built into the rust \
+             framework",
+        ));
+}
+
+// smoelius: `ssh` and `rsync` are replaced with scripts that run commands and copy files locally.
+#[cfg(not(windows))]
+#[test]
+fn remote() {
+    const SSH: &str = r#"#!/bin/sh
+while [ "$1" = -o ]; do shift 2; done
+shift
+exec sh -c "$*"
+"#;
+
+    const RSYNC: &str = r#"#!/bin/sh
+src="$4"
+dest="${5#*:}"
+while IFS= read -r f || [ -n "$f" ]; do
+    mkdir -p "$dest/$(dirname "$f")" && cp "$src/$f" "$dest/$f"
+done
+"#;
+
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "cheapest_first",
+        &["Cargo.toml", "src", "tests"],
+        tempdir.path(),
+    );
+    let remote = tempdir.path().join("remote");
+    create_dir(&remote).unwrap();
+
+    let path = install_scripts(tempdir.path(), &[("ssh", SSH), ("rsync", RSYNC)]);
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .env("PATH", path)
+        .arg("--root")
+        .arg(&root)
+        .args(["--no-sqlite", "--timeout", TIMEOUT, "--verbose", "--remote"])
+        .arg(format!("user@host:{}", remote.display()))
+        .arg(root.join("tests/b.rs"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "tests/b.rs:4:5-4:12: `n += 1;` failed",
+        ));
+
+    // smoelius: The project was built remotely, but not locally.
+    assert!(remote.join("target").try_exists().unwrap());
+    assert!(!root.join("target").try_exists().unwrap());
+}
+
+// smoelius: `docker` is replaced with a script that logs the image and runs the command locally,
+// translating paths beneath the mount point back to paths beneath the project's root.
+#[cfg(not(windows))]
+#[test]
+fn docker() {
+    const DOCKER: &str = r#"#!/bin/sh
+shift
+while :; do
+    case "$1" in
+        --rm) shift ;;
+        --volume) mount="${2%%:*}"; shift 2 ;;
+        --workdir) workdir="$2"; shift 2 ;;
+        --entrypoint) shift 2 ;;
+        --env) export "$(echo "$2" | sed "s|=/repo|=$mount|")"; shift 2 ;;
+        *) break ;;
+    esac
+done
+echo "$1" >> "$DOCKER_LOG"
+shift
+cd "$mount${workdir#/repo}"
+for arg; do
+    shift
+    case "$arg" in
+        /repo*) set -- "$@" "$mount${arg#/repo}" ;;
+        *) set -- "$@" "$arg" ;;
+    esac
+done
+exec "$@"
+"#;
+
+    let tempdir = tempdir().unwrap();
+
+    let root = copy_fixture(
+        "cheapest_first",
+        &["Cargo.toml", "src", "tests"],
+        tempdir.path(),
+    );
+    let log = tempdir.path().join("docker.log");
+
+    let path = install_scripts(tempdir.path(), &[("docker", DOCKER)]);
+
+    write(
+        root.join("necessist.toml"),
+        "[docker]\nimages = { rust = \"rust:custom\" }\n",
+    )
+    .unwrap();
+
+    for (arg, image) in [
+        ("--docker", "rust:custom"),
+        ("--docker=rust:explicit", "rust:explicit"),
+    ] {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .env("PATH", &path)
+            .env("DOCKER_LOG", &log)
+            .arg("--root")
+            .arg(&root)
+            .args(["--no-sqlite", "--timeout", TIMEOUT, "--verbose", arg])
+            .arg(root.join("tests/b.rs"))
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "tests/b.rs:4:5-4:12: `n += 1;` failed",
+            ));
+
+        let contents = read_to_string(&log).unwrap();
+        assert!(!contents.is_empty());
+        assert!(contents.lines().all(|line| line == image), "{contents}");
+        remove_file(&log).unwrap();
+    }
+}
+
+/// Writes executable scripts to a `bin` directory beneath `dir`, and returns a `PATH` in which the
+/// scripts come first.
+#[cfg(not(windows))]
+fn install_scripts(dir: &Path, scripts: &[(&str, &str)]) -> std::ffi::OsString {
+    use std::{
+        env::{join_paths, split_paths, var_os},
+        fs::{set_permissions, Permissions},
+        os::unix::fs::PermissionsExt,
+    };
+
+    let bin = dir.join("bin");
+    create_dir(&bin).unwrap();
+
+    for (name, contents) in scripts {
+        let path = bin.join(name);
+        write(&path, contents).unwrap();
+        set_permissions(&path, Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let mut paths = vec![bin];
+    paths.extend(split_paths(&var_os("PATH").unwrap()));
+    join_paths(paths).unwrap()
+}
+
+#[test]
+fn diff_runs() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args(["-r", BASIC_ROOT, &tempdir.path().to_string_lossy()])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("basic");
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--timeout", TIMEOUT])
+        .assert()
+        .success();
+
+    // smoelius: Strengthen the `passed` test without changing any spans.
+    let lib_rs = root.join("src/lib.rs");
+    let contents = read_to_string(&lib_rs).unwrap();
+    std::fs::write(
+        &lib_rs,
+        contents.replacen("    noop();\n", "    assert!(n >= 1);\n", 1),
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--timeout", TIMEOUT, "--reset"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["diff", "--from", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/lib.rs:4:5-4:12: `n += 1;` passed -> failed\n",
+        ))
+        .stdout(predicate::str::ends_with(
+            "1 outcome changed from run 1 to run 2\n",
+        ));
+}
+
+#[test]
+fn verify_passed() {
+    let tempdir = tempdir().unwrap();
+
+    // smoelius: The fixture is copied so that its counter file starts out absent.
+    Command::new("cp")
+        .args([
+            "-r",
+            "fixtures/verify_passed",
+            &tempdir.path().to_string_lossy(),
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(tempdir.path().join("verify_passed"))
+        .args([
+            "--no-sqlite",
+            "--timeout",
+            TIMEOUT,
+            "--verbose",
+            "--verify-passed",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/lib.rs:16:9-16:16: `n += 1;` inconclusive\n",
+        ));
+}
+
+#[test]
+fn count_failing_tests() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args([
+            "-r",
+            "fixtures/covering_tests",
+            &tempdir.path().to_string_lossy(),
+        ])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("covering_tests");
+
+    // smoelius: `*n += 1;` is exercised by both tests, but only `checked` fails. By default,
+    // `unchecked` is not run once `checked` fails, so the number of failing tests is not recorded.
+    for (args, expected) in [
+        (
+            ["--timeout", TIMEOUT].as_slice(),
+            "`*n += 1;` failed (assertion",
+        ),
+        (
+            ["--timeout", TIMEOUT, "--reset", "--count-failing-tests"].as_slice(),
+            "; 1 of 2 tests failed)\n",
+        ),
+    ] {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .arg("--root")
+            .arg(&root)
+            .args(args)
+            .assert()
+            .success();
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .arg("--root")
+            .arg(&root)
+            .args(["--verbose", "show"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(expected))
+            .stdout(
+                predicate::str::contains("tests failed")
+                    .count(usize::from(args.contains(&"--count-failing-tests"))),
+            );
+    }
+}
+
+#[test]
+fn fail_fast_per_test() {
+    // smoelius: In each test, `step(..)`'s removal passes, so `n += 1;` is skipped.
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            "fixtures/suggest_ignores",
+            "--no-sqlite",
+            "--fail-fast-per-test",
+            "1",
+            "--timeout",
+            TIMEOUT,
+            "--verbose",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Warning: 1 consecutive removals passed in test `tests::a`; skipping its remaining \
+             candidates\n",
+        ))
+        .stdout(predicate::str::contains("`n += 1;`").not());
+}
+
+#[test]
+fn max_statements_per_test() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args([
+            "-r",
+            "fixtures/covering_tests",
+            &tempdir.path().to_string_lossy(),
+        ])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("covering_tests");
+
+    std::fs::write(
+        root.join("necessist.toml"),
+        "walkable_functions = [\"increment\"]\nmax_statements_per_test = 1\n",
+    )
+    .unwrap();
+
+    // smoelius: `increment` is walked for both tests, so `*n += 1;` is a statement of each.
+    // `checked` additionally has `n *= 2;`, which puts it over the limit.
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "\
+#[cfg(test)]
+mod tests {
+    fn increment(n: &mut u32) {
+        *n += 1;
+    }
+
+    #[test]
+    fn checked() {
+        let mut n = 0;
+        increment(&mut n);
+        n *= 2;
+        assert_eq!(2, n);
+    }
+
+    #[test]
+    fn unchecked() {
+        let mut n = 0;
+        increment(&mut n);
+        assert!(n <= 1);
+    }
+}
+",
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--no-sqlite", "--timeout", TIMEOUT, "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Warning: Skipping test `tests::checked`, which has 2 statements (more than \
+             `max_statements_per_test = 1`)\n",
+        ))
+        .stdout(predicate::str::contains("`n *= 2;`").not())
+        .stdout(predicate::str::contains("`*n += 1;` passed\n"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn no_network() {
+    // smoelius: Without `unshare`, only proxy environment variables are set, which do not affect
+    // the test below.
+    if !["-n", "-rn"].into_iter().any(|arg| {
+        Command::new("unshare")
+            .args([arg, "true"])
+            .status()
+            .is_ok_and(|status| status.success())
+    }) {
+        return;
+    }
+
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args([
+            "-r",
+            "fixtures/covering_tests",
+            &tempdir.path().to_string_lossy(),
+        ])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("covering_tests");
+
+    // smoelius: Connecting to a loopback address fails in a new network namespace, where the
+    // loopback device is down.
+    write(
+        root.join("src/lib.rs"),
+        "\
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn connect() {
+        let listener = std::net::TcpListener::bind(\"127.0.0.1:0\").unwrap();
+        let mut n = 0;
+        n += 1;
+        std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    }
+}
+",
+    )
+    .unwrap();
+
+    for (args, expected) in [
+        (["--no-sqlite", "--timeout", TIMEOUT].as_slice(), "passed"),
+        (
+            ["--no-sqlite", "--timeout", TIMEOUT, "--no-network"].as_slice(),
+            "failed",
+        ),
+    ] {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .arg("--root")
+            .arg(&root)
+            .args(args)
+            .arg("--verbose")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!("`n += 1;` {expected}\n")));
+    }
+}
+
+#[test]
+fn suggest_ignores() {
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            "fixtures/suggest_ignores",
+            "--no-sqlite",
+            "--suggest-ignores",
+            "--timeout",
+            TIMEOUT,
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "\
+ignored_functions = [
+    \"step\", # 3 passed removals in 3 tests
+]
+",
+        ));
+}
+
+#[test]
+fn shared_module() {
+    // smoelius: `*n += 1;` is exercised by the tests in both `a.rs` and `b.rs`, hence the four
+    // candidates.
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            "fixtures/shared_module",
+            "--no-sqlite",
+            "--timeout",
+            TIMEOUT,
+            "--verbose",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "4 candidates in 2 tests in 4 source files\n",
+        ))
+        .stdout(predicate::str::contains(
+            "fixtures/shared_module/tests/common/mod.rs:2:5-2:13: `*n += 1;` failed\n",
+        ));
+}
+
+#[test]
+fn build_only() {
+    run_basic_test(|| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--build-only", "--timeout", TIMEOUT])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("`n += 1;` buildable\n"))
+            .stdout(predicate::str::contains("`.join(\"\")` nonbuildable\n"))
+            .stdout(predicate::str::contains(" passed\n").not())
+            .stdout(predicate::str::contains(" failed\n").not())
+            .stdout(predicate::str::contains(" timed-out\n").not());
+
+        // smoelius: `--build-only` implies `--no-sqlite`.
+        assert!(!PathBuf::from(BASIC_ROOT)
+            .join("necessist.db")
+            .try_exists()
+            .unwrap());
+    });
+}
+
+#[test]
+fn group_by_test() {
+    run_basic_test(|| {
+        let assert = Command::cargo_bin("necessist")
+            .unwrap()
+            .args([
+                "--root",
+                BASIC_ROOT,
+                "--timeout",
+                TIMEOUT,
+                "--no-sqlite",
+                "--group-by",
+                "test",
+            ])
+            .assert()
+            .success();
+
+        let stdout_expected: &str = "\
+4 candidates in 4 tests in 1 source file
+fixtures/basic/src/lib.rs: dry running
+fixtures/basic/src/lib.rs: mutilating
+passed: 1 passed
+    fixtures/basic/src/lib.rs:4:5-4:12: `n += 1;` passed
+timed_out: 1 timed-out
+failed: 1 failed
+nonbuildable: 1 nonbuildable
+";
+
+        let stdout_actual = std::str::from_utf8(&assert.get_output().stdout).unwrap();
+
+        assert_eq!(stdout_expected, stdout_actual);
+    });
+}
+
+#[test]
+fn order_cheapest_first() {
+    let assert = Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            "fixtures/cheapest_first",
+            "--no-sqlite",
+            "--order",
+            "cheapest-first",
+            "--verbose",
+        ])
+        .assert()
+        .success();
+
+    // smoelius: `a.rs`'s test sleeps, so `b.rs`'s candidates should be run first.
+    let stdout_expected: &str = "\
+3 candidates in 2 tests in 3 source files
+fixtures/cheapest_first/tests/b.rs: dry running
+fixtures/cheapest_first/tests/b.rs: mutilating
+fixtures/cheapest_first/tests/b.rs:4:5-4:12: `n += 1;` failed
+fixtures/cheapest_first/tests/a.rs: dry running
+fixtures/cheapest_first/tests/a.rs: mutilating
+fixtures/cheapest_first/tests/a.rs:6:5-6:12: `n += 1;` failed
+fixtures/cheapest_first/tests/a.rs:7:5-7:35: `sleep(Duration::from_secs(2));` passed
+";
+
+    let stdout_actual = std::str::from_utf8(&assert.get_output().stdout).unwrap();
+
+    assert_eq!(stdout_expected, stdout_actual);
+}
+
+#[test]
+fn order_round_robin() {
+    let assert = Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            "fixtures/cheapest_first",
+            "--no-sqlite",
+            "--order",
+            "round-robin",
+            "--verbose",
+        ])
+        .assert()
+        .success();
+
+    // smoelius: Each source file's first candidate should be run before `a.rs`'s second.
+    let stdout_expected: &str = "\
+3 candidates in 2 tests in 3 source files
+fixtures/cheapest_first/tests/a.rs: dry running
+fixtures/cheapest_first/tests/a.rs: mutilating
+fixtures/cheapest_first/tests/a.rs:6:5-6:12: `n += 1;` failed
+fixtures/cheapest_first/tests/b.rs: dry running
+fixtures/cheapest_first/tests/b.rs: mutilating
+fixtures/cheapest_first/tests/b.rs:4:5-4:12: `n += 1;` failed
+fixtures/cheapest_first/tests/a.rs: mutilating
+fixtures/cheapest_first/tests/a.rs:7:5-7:35: `sleep(Duration::from_secs(2));` passed
+";
+
+    let stdout_actual = std::str::from_utf8(&assert.get_output().stdout).unwrap();
+
+    assert_eq!(stdout_expected, stdout_actual);
+}
+
+#[test]
+fn isolate_worktree() {
+    run_basic_test(|| {
+        let assert = Command::cargo_bin("necessist")
+            .unwrap()
+            .args([
+                "--root",
+                BASIC_ROOT,
+                "--timeout",
+                TIMEOUT,
+                "--verbose",
+                "--isolate",
+                "worktree",
+            ])
+            .assert()
+            .success();
+
+        let stdout_expected: &str = "\
+4 candidates in 4 tests in 1 source file
+fixtures/basic/src/lib.rs: dry running
+fixtures/basic/src/lib.rs: mutilating
+fixtures/basic/src/lib.rs:4:5-4:12: `n += 1;` passed
+fixtures/basic/src/lib.rs:14:9-14:16: `n += 1;` timed-out
+fixtures/basic/src/lib.rs:21:5-21:12: `n += 1;` failed
+fixtures/basic/src/lib.rs:28:18-28:27: `.join(\"\")` nonbuildable
+";
+
+        let stdout_actual = std::str::from_utf8(&assert.get_output().stdout).unwrap();
+
+        assert_eq!(stdout_expected, stdout_actual);
+
+        // smoelius: The database is written to the original checkout, and the worktree is removed.
+        assert!(PathBuf::from(BASIC_ROOT)
+            .join("necessist.db")
+            .try_exists()
+            .unwrap());
+
+        let assert = Command::new("git")
+            .args(["worktree", "list", "--porcelain"])
+            .assert()
+            .success();
+        let stdout = std::str::from_utf8(&assert.get_output().stdout).unwrap();
+        assert_eq!(1, stdout.matches("worktree ").count(), "{stdout:?}");
+    });
+}
+
+// smoelius: The fixture has one source file. So one job slot runs its candidates, and the other
+// has none.
+#[test]
+fn isolate_worktree_jobs() {
+    run_basic_test(|| {
+        let assert = Command::cargo_bin("necessist")
+            .unwrap()
+            .args([
+                "--root",
+                BASIC_ROOT,
+                "--timeout",
+                TIMEOUT,
+                "--verbose",
+                "--isolate",
+                "worktree",
+                "--jobs",
+                "2",
+            ])
+            .assert()
+            .success();
+
+        let stdout = std::str::from_utf8(&assert.get_output().stdout).unwrap();
+
+        for line in [
+            "0 candidates in 0 tests in 0 source files\n",
+            "4 candidates in 4 tests in 1 source file\n",
+            "fixtures/basic/src/lib.rs:4:5-4:12: `n += 1;` passed\n",
+            "fixtures/basic/src/lib.rs:14:9-14:16: `n += 1;` timed-out\n",
+            "fixtures/basic/src/lib.rs:21:5-21:12: `n += 1;` failed\n",
+            "fixtures/basic/src/lib.rs:28:18-28:27: `.join(\"\")` nonbuildable\n",
+        ] {
+            assert!(stdout.contains(line), "{stdout:?}");
+        }
+
+        let assert = Command::new("git")
+            .args(["worktree", "list", "--porcelain"])
+            .assert()
+            .success();
+        let stdout = std::str::from_utf8(&assert.get_output().stdout).unwrap();
+        assert_eq!(1, stdout.matches("worktree ").count(), "{stdout:?}");
+    });
+}
+
+#[test]
+fn isolate_worktree_jobs_incompatible_with_shard() {
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .args([
+            "--root",
+            BASIC_ROOT,
+            "--isolate",
+            "worktree",
+            "--jobs",
+            "2",
+            "--shard",
+            "1/2",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--shard cannot be used with --isolate worktree and --jobs greater than 1",
+        ));
+}
+
+#[test]
+fn batch() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args(["-r", BASIC_ROOT, &tempdir.path().to_string_lossy()])
+        .assert()
+        .success();
+
+    let repo = tempdir.path().join("basic");
+    for args in [
+        &["init", "--quiet"][..],
+        &["add", "."],
+        &[
+            "-c",
+            "user.name=necessist",
+            "-c",
+            "user.email=necessist@example.com",
+            "commit",
+            "--quiet",
+            "--message",
+            "Initial commit",
+        ],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo)
+            .assert()
+            .success();
+    }
+
+    let manifest = tempdir.path().join("manifest.toml");
+    std::fs::write(
+        &manifest,
+        format!(
+            "[[repo]]\nurl = {:?}\nargs = [\"--timeout\", \"{TIMEOUT}\"]\n",
+            repo.to_string_lossy()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("batch")
+        .arg(&manifest)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "basic: 1 failed, 1 nonbuildable, 1 passed, 1 timed-out",
+        ));
+
+    let summary = read_to_string(tempdir.path().join("necessist-batch/summary.json")).unwrap();
+    let summary = serde_json::from_str::<serde_json::Value>(&summary).unwrap();
+    assert_eq!(summary[0]["name"], "basic");
+    assert_eq!(summary[0]["success"], true);
+    assert_eq!(summary[0]["outcomes"]["passed"], 1);
+}
+
+#[test]
+fn batch_rejects_names_outside_output_dir() {
+    let tempdir = tempdir().unwrap();
+
+    let manifest = tempdir.path().join("manifest.toml");
+
+    for name in [".", "..", "/tmp/basic", "a/b", "a\\b"] {
+        std::fs::write(
+            &manifest,
+            format!(
+                "[[repo]]\nurl = \"https://github.com/trailofbits/necessist\"\nname = '{name}'\n"
+            ),
+        )
+        .unwrap();
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .arg("batch")
+            .arg(&manifest)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "Repository name `{name}` is invalid"
+            )));
+    }
+
+    assert!(!tempdir
+        .path()
+        .join("necessist-batch/summary.json")
+        .try_exists()
+        .unwrap());
+}
+
+#[test]
+fn stream_results_to_file() {
+    run_basic_test(|| {
+        let tempdir = tempdir().unwrap();
+        let results = tempdir.path().join("results.jsonl");
+
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--timeout", TIMEOUT, "--no-sqlite"])
+            .arg("--stream-results")
+            .arg(&results)
+            .assert()
+            .success();
+
+        let contents = read_to_string(results).unwrap();
+        let outcomes = contents
+            .lines()
+            .map(|line| {
+                let value = serde_json::from_str::<serde_json::Value>(line).unwrap();
+                value["outcome"].as_str().unwrap().to_owned()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ["passed", "timed-out", "failed", "nonbuildable"].as_slice(),
+            outcomes
+        );
+    });
+}
+
+#[test]
+fn audit_log_and_replay() {
+    const SPAN: &str = "src/lib.rs:4:5-4:12";
+
+    let tempdir = tempdir().unwrap();
+    let audit_log = tempdir.path().join("audit.jsonl");
+
+    run_basic_test(|| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--timeout", TIMEOUT, "--no-sqlite"])
+            .arg("--audit-log")
+            .arg(&audit_log)
+            .assert()
+            .success();
+    });
+
+    let contents = read_to_string(&audit_log).unwrap();
+    let events = contents
+        .lines()
+        .filter_map(|line| {
+            let value = serde_json::from_str::<serde_json::Value>(line).unwrap();
+            (value["span"] == SPAN).then(|| {
+                (
+                    value["event"].as_str().unwrap().to_owned(),
+                    value["outcome"].as_str().map(ToOwned::to_owned),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        [
+            (String::from("applied"), None),
+            (String::from("reverted"), Some(String::from("passed")))
+        ]
+        .as_slice(),
+        events
+    );
+
+    // smoelius: The mutation is replayed in a copy of the fixture, so that the fixture itself is
+    // not modified.
+    let root = copy_fixture("basic", &["Cargo.toml", "src"], tempdir.path());
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .arg("replay")
+        .arg(&audit_log)
+        .args(["--span", SPAN])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!(
+            "Removed `n += 1;` from {SPAN}"
+        )));
+
+    let lib_rs = read_to_string(root.join("src/lib.rs")).unwrap();
+    assert_eq!(Some("    "), lib_rs.lines().nth(3));
+
+    // smoelius: Replaying again fails because the span's text has changed.
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .arg("replay")
+        .arg(&audit_log)
+        .args(["--span", SPAN])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("has changed"));
+}
+
+#[test]
+fn tests_are_not_rebuilt() {
+    run_basic_test(|| {
+        Command::cargo_bin("necessist")
+            .unwrap()
+            .args(["--root", BASIC_ROOT, "--timeout", TIMEOUT])
+            .env("NECESSIST_CHECK_MTIMES", "1")
+            .assert()
+            .success();
+    });
+}
+
+#[test]
+fn timeout_is_failure() {
+    let tempdir = tempdir().unwrap();
+
+    Command::new("cp")
+        .args(["-r", BASIC_ROOT, &tempdir.path().to_string_lossy()])
+        .assert()
+        .success();
+
+    let root = tempdir.path().join("basic");
+
+    write(root.join("necessist.toml"), "timeout_is_failure = true\n").unwrap();
+
+    Command::cargo_bin("necessist")
+        .unwrap()
+        .arg("--root")
+        .arg(&root)
+        .args(["--timeout", TIMEOUT, "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/lib.rs:14:9-14:16: `n += 1;` failed\n",
+        ))
+        .stdout(predicate::str::contains("timed-out").not());
+
+    let output = Command::new("sqlite3")
+        .arg(root.join("necessist.db"))
+        .arg(
+            "SELECT span, outcome, raw_outcome FROM removal WHERE outcome = 'failed' ORDER BY span",
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        "\
+src/lib.rs:14:9-14:16|failed|timed-out
+src/lib.rs:21:5-21:12|failed|
+",
+        String::from_utf8(output.stdout).unwrap()
+    );
+}
+
 fn run_basic_test(f: impl FnOnce()) {
     // smoelius: Three tests use the `basic` fixture, but only one can run at a time.
     static BASIC_MUTEX: Mutex<()> = Mutex::new(());
@@ -506,8 +506,16 @@ fn run_test(tempdir: &Path, path: &Path, test: &Test) -> (String, Duration) {
             remove_file(necessist_toml).unwrap_or_default();
         }
 
+        // smoelius: A database left by an earlier run would cause Necessist to fail.
+        if test.check_sqlite_urls {
+            remove_file(root.join("necessist.db")).unwrap_or_default();
+        }
+
         let mut exec = Exec::cmd("../target/debug/necessist");
-        exec = exec.args(&["--no-sqlite", "--root", &root.to_string_lossy()]);
+        if !test.check_sqlite_urls {
+            exec = exec.arg("--no-sqlite");
+        }
+        exec = exec.args(&["--root", &root.to_string_lossy()]);
         if let Some(prefix) = &test.path_prefix {
             let prefix_in_tempdir = tempdir.join(prefix);
             let path = var("PATH").unwrap();